@@ -1,6 +1,3 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
 use paste::paste;
 
 use crate::disk::{
@@ -10,16 +7,17 @@ use crate::disk::{
 };
 
 use crate::errors::Error;
+use crate::shared::*;
 
 
 pub struct Block {
-    pub disk: Rc<RefCell<Disk>>,
+    pub disk: Shared<Disk>,
     pub address: LBAAddress,
 }
 
 impl Block {
     pub fn new(
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
         address: LBAAddress,
     ) -> Self {
         Self {
@@ -35,7 +33,7 @@ impl Block {
         offset: usize,
     ) -> Result<u8, Error> {
         let disk = self.disk.borrow();
-        let disk_data = disk.blocks(self.address, 1)?;
+        let disk_data = disk.block(self.address)?;
 
         if offset < disk_data.len() {
             Ok(disk_data[offset])
@@ -50,7 +48,7 @@ impl Block {
         data: &mut [u8],
     ) -> Result<(), Error> {
         let disk = self.disk.borrow();
-        let disk_data = disk.blocks(self.address, 1)?;
+        let disk_data = disk.block(self.address)?;
 
         if offset + data.len() <= disk_data.len() {
             data.copy_from_slice(&disk_data[offset..offset + data.len()]);
@@ -79,7 +77,7 @@ impl Block {
         value: u8,
     ) -> Result<(), Error> {
         let mut disk = self.disk.borrow_mut();
-        let disk_data = disk.blocks_mut(self.address, 1)?;
+        let disk_data = disk.block_mut(self.address)?;
 
         if offset < disk_data.len() {
             disk_data[offset] = value;
@@ -95,7 +93,7 @@ impl Block {
         values: &[u8],
     ) -> Result<(), Error> {
         let mut disk = self.disk.borrow_mut();
-        let disk_data = disk.blocks_mut(self.address, 1)?;
+        let disk_data = disk.block_mut(self.address)?;
         let size = values.len();
 
         if offset + size <= disk_data.len() {
@@ -116,7 +114,7 @@ macro_rules! generate_block_read_write_fns {
                 offset: usize,
             ) -> Result<$t, Error> {
                 let disk = self.disk.borrow();
-                let disk_data = disk.blocks(self.address, 1)?;
+                let disk_data = disk.block(self.address)?;
                 let size = std::mem::size_of::<$t>();
 
                 if let Ok(buf) = disk_data[offset..offset + size].try_into() {
@@ -157,7 +155,7 @@ macro_rules! generate_block_read_write_fns {
                 value: $t,
             ) -> Result<(), Error> {
                 let mut disk = self.disk.borrow_mut();
-                let disk_data = disk.blocks_mut(self.address, 1)?;
+                let disk_data = disk.block_mut(self.address)?;
 
                 let size = std::mem::size_of::<$t>();
                 let end = offset + size;
@@ -227,7 +225,7 @@ impl Block {
         to: usize,
     ) -> Result<(), Error> {
         let mut disk = self.disk.borrow_mut();
-        let disk_data = disk.blocks_mut(self.address, 1)?;
+        let disk_data = disk.block_mut(self.address)?;
 
         disk_data[from..to].fill(value);
         Ok(())
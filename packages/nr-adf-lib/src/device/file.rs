@@ -0,0 +1,424 @@
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use std::fmt;
+use std::fs;
+use std::io::{
+    self,
+    Write,
+};
+use std::path::Path;
+
+use crate::disk::*;
+use crate::errors::*;
+use crate::shared::*;
+
+use super::*;
+
+
+// Reads and writes at a given offset of a file, leaving its cursor alone so
+// that several threads can read at once
+#[cfg(unix)]
+fn read_exact_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &fs::File, buf: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &fs::File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            },
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &fs::File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match file.seek_write(buf, offset) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => {
+                buf = &buf[n..];
+                offset += n as u64;
+            },
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// The blocks of a file read so far.
+#[derive(Debug, Default)]
+struct BlockCache {
+    // runs of contiguous blocks, by address of their first block, which
+    // don't overlap
+    extents: BTreeMap<LBAAddress, Box<[u8]>>,
+    // copies of blocks spread over several extents, kept until the device
+    // is borrowed mutably as references to them may be alive
+    views: Vec<Box<[u8]>>,
+}
+
+impl BlockCache {
+    // Returns the address of the first block of the extent holding `addr`,
+    // and the extent
+    fn find(&self, addr: LBAAddress) -> Option<(LBAAddress, &[u8])> {
+        self.extents.range(..=addr)
+            .next_back()
+            .filter(|(start, data)| addr < **start + data.len()/BLOCK_SIZE)
+            .map(|(start, data)| (*start, &data[..]))
+    }
+
+    // Returns the runs of blocks of `blocks` which are not loaded yet
+    fn missing(&self, blocks: Range<LBAAddress>) -> Vec<Range<LBAAddress>> {
+        let mut missing = Vec::new();
+        let mut addr = blocks.start;
+
+        while addr < blocks.end {
+            if let Some((start, data)) = self.find(addr) {
+                addr = start + data.len()/BLOCK_SIZE;
+            } else {
+                let end = self.extents.range(addr..blocks.end)
+                    .next()
+                    .map_or(blocks.end, |(start, _)| *start);
+
+                missing.push(addr..end);
+                addr = end;
+            }
+        }
+        missing
+    }
+
+    // Returns the data of loaded blocks if they are held by a single extent
+    fn get(&self, blocks: &Range<LBAAddress>) -> Option<&[u8]> {
+        let (start, data) = self.find(blocks.start)?;
+
+        data.get(byte_range(blocks.start - start..blocks.end - start))
+    }
+
+    // Copies the data of loaded blocks
+    fn copy(&self, blocks: Range<LBAAddress>) -> Box<[u8]> {
+        let mut copy = Vec::with_capacity(blocks.len()*BLOCK_SIZE);
+        let mut addr = blocks.start;
+
+        while let Some((start, data)) = self.find(addr).filter(|_| addr < blocks.end) {
+            let end = blocks.end.min(start + data.len()/BLOCK_SIZE);
+
+            copy.extend_from_slice(&data[byte_range(addr - start..end - start)]);
+            addr = end;
+        }
+        copy.into_boxed_slice()
+    }
+
+    // Merges the extents holding loaded blocks into one, to be accessed
+    // mutably
+    fn merge(&mut self, blocks: Range<LBAAddress>) -> &mut [u8] {
+        if blocks.is_empty() {
+            return &mut [];
+        }
+
+        let first = self.find(blocks.start).map_or(blocks.start, |(start, _)| start);
+
+        if self.get(&blocks).is_none() {
+            let end = self.find(blocks.end - 1)
+                .map_or(blocks.end, |(start, data)| start + data.len()/BLOCK_SIZE);
+            let data = self.copy(first..end);
+            let starts = self.extents.range(first..end)
+                .map(|(start, _)| *start)
+                .collect::<Vec<_>>();
+
+            for start in starts {
+                self.extents.remove(&start);
+            }
+            self.extents.insert(first, data);
+        }
+
+        let data = self.extents.get_mut(&first).unwrap();
+
+        &mut data[byte_range(blocks.start - first..blocks.end - first)]
+    }
+}
+
+/// A block device reading its blocks from a file the first time they are
+/// accessed, only the blocks read so far being held in memory. Modified
+/// blocks are kept in memory until `flush` is called.
+pub struct FileBlockDevice {
+    file: fs::File,
+    read_only: bool,
+    block_count: usize,
+    cache: SharedCell<BlockCache>,
+    dirty_blocks: BTreeSet<LBAAddress>,
+}
+
+impl fmt::Debug for FileBlockDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileBlockDevice")
+            .field("file", &self.file)
+            .field("read_only", &self.read_only)
+            .field("block_count", &self.block_count)
+            .field("dirty_blocks", &self.dirty_blocks)
+            .finish()
+    }
+}
+
+impl FileBlockDevice {
+    /// Opens an image file for reading and writing.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = fs::File::options()
+            .read(true)
+            .write(true)
+            .open(path)?;
+
+        Self::try_from(file)
+    }
+
+    /// Opens an image file for reading only, `flush` failing if any block
+    /// was modified.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut device = Self::try_from(fs::File::open(path)?)?;
+
+        device.read_only = true;
+        Ok(device)
+    }
+
+    /// Reads the blocks not accessed yet of a range of blocks.
+    fn load(
+        file: &fs::File,
+        cache: &mut BlockCache,
+        blocks: Range<LBAAddress>,
+    ) -> Result<(), Error> {
+        for blocks in cache.missing(blocks) {
+            let mut data = vec![0; blocks.len()*BLOCK_SIZE].into_boxed_slice();
+
+            read_exact_at(file, &mut data, (blocks.start*BLOCK_SIZE) as u64)?;
+            cache.extents.insert(blocks.start, data);
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<fs::File> for FileBlockDevice {
+    type Error = Error;
+
+    fn try_from(file: fs::File) -> Result<Self, Self::Error> {
+        let block_count = check_size(file.metadata()?.len())?;
+
+        Ok(Self {
+            file,
+            read_only: false,
+            block_count,
+            cache: SharedCell::new(BlockCache::default()),
+            dirty_blocks: BTreeSet::new(),
+        })
+    }
+}
+
+impl BlockDevice for FileBlockDevice {
+    fn block_count(&self) -> usize {
+        self.block_count
+    }
+
+    fn blocks(&self, addr: LBAAddress, count: usize) -> Result<&[u8], Error> {
+        let blocks = check_range(addr, count, self.block_count())?;
+        let mut cache = self.cache.borrow_mut();
+
+        Self::load(&self.file, &mut cache, blocks.clone())?;
+
+        let data: *const [u8] = match cache.get(&blocks) {
+            Some(data) => data,
+            None => {
+                let view = cache.copy(blocks);
+
+                cache.views.push(view);
+                &**cache.views.last().unwrap()
+            },
+        };
+
+        // SAFETY: extents and views are neither modified nor dropped, their
+        // data staying in place, until the device is borrowed mutably.
+        Ok(unsafe { &*data })
+    }
+
+    fn blocks_mut(&mut self, addr: LBAAddress, count: usize) -> Result<&mut [u8], Error> {
+        let blocks = check_range(addr, count, self.block_count())?;
+        let cache = self.cache.get_mut();
+
+        cache.views.clear();
+        Self::load(&self.file, cache, blocks.clone())?;
+        self.dirty_blocks.extend(blocks.clone());
+
+        Ok(cache.merge(blocks))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.read_only && !self.dirty_blocks.is_empty() {
+            return Err(Error::DiskReadOnlyError);
+        }
+
+        let cache = self.cache.get_mut();
+
+        while let Some(addr) = self.dirty_blocks.first().copied() {
+            let data = cache.get(&(addr..addr + 1)).ok_or(Error::DiskInvalidLBAAddressError(addr))?;
+
+            write_all_at(&self.file, data, (addr*BLOCK_SIZE) as u64)?;
+            self.dirty_blocks.remove(&addr);
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn is_file_backed(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn file_device_is_ok() {
+        let file = write_image(8);
+        let device = FileBlockDevice::open(file.path()).unwrap();
+
+        assert!(device.is_file_backed());
+
+        let data = check_device(device);
+
+        assert_eq!(fs::read(file.path()).unwrap(), data);
+    }
+
+    #[test]
+    fn file_device_writes_dirty_blocks_only() {
+        let file = write_image(8);
+        let mut device = FileBlockDevice::open(file.path()).unwrap();
+
+        device.block_mut(2).unwrap().fill(0x42);
+        assert_eq!(device.dirty_blocks, BTreeSet::from([2]));
+
+        // blocks not modified through the device are left untouched
+        let mut data = fs::read(file.path()).unwrap();
+        data[7*BLOCK_SIZE] = 0xff;
+        fs::write(file.path(), &data).unwrap();
+
+        device.flush().unwrap();
+        assert!(device.dirty_blocks.is_empty());
+
+        let data = fs::read(file.path()).unwrap();
+
+        assert!(data[2*BLOCK_SIZE..3*BLOCK_SIZE].iter().all(|b| *b == 0x42));
+        assert_eq!(data[7*BLOCK_SIZE], 0xff);
+    }
+
+    #[test]
+    fn file_device_loads_accessed_blocks_only() {
+        let file = write_image(8);
+        let mut device = FileBlockDevice::open(file.path()).unwrap();
+        let extents = |device: &mut FileBlockDevice| {
+            device.cache.get_mut().extents.iter()
+                .map(|(start, data)| *start..*start + data.len()/BLOCK_SIZE)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(extents(&mut device).is_empty());
+
+        device.block(3).unwrap();
+        device.block(5).unwrap();
+        assert_eq!(extents(&mut device), [3..4, 5..6]);
+
+        // blocks spread over several extents are still contiguous
+        let blocks = device.blocks(2, 5).unwrap();
+
+        assert!(blocks.chunks(BLOCK_SIZE).zip(2..).all(|(block, i)| block.iter().all(|b| *b == i)));
+        assert_eq!(extents(&mut device), [2..3, 3..4, 4..5, 5..6, 6..7]);
+
+        device.blocks_mut(3, 3).unwrap()[BLOCK_SIZE..2*BLOCK_SIZE].fill(0x42);
+        assert_eq!(extents(&mut device), [2..3, 3..6, 6..7]);
+        assert!(device.block(4).unwrap().iter().all(|b| *b == 0x42));
+        assert!(device.block(5).unwrap().iter().all(|b| *b == 5));
+        assert_eq!(device.blocks_mut(0, 0).unwrap(), []);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn concurrent_reads_are_ok() {
+        let file = write_image(8);
+        let data = fs::read(file.path()).unwrap();
+
+        for _ in 0..20 {
+            let device = FileBlockDevice::open(file.path()).unwrap();
+
+            std::thread::scope(|scope| {
+                for thread in 0..8 {
+                    let (device, data) = (&device, &data);
+
+                    scope.spawn(move || {
+                        for addr in (0..8).map(|i| (i + thread)%8) {
+                            assert_eq!(device.block(addr).unwrap(), &data[byte_range(addr..addr + 1)]);
+                        }
+                    });
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn read_only_file_device_is_ok() {
+        let file = write_image(8);
+        let mut device = FileBlockDevice::open_read_only(file.path()).unwrap();
+
+        assert!(device.block(3).unwrap().iter().all(|b| *b == 3));
+        assert!(device.flush().is_ok());
+
+        device.block_mut(3).unwrap().fill(0x42);
+        assert_eq!(device.flush(), Err(Error::DiskReadOnlyError));
+    }
+
+    #[test]
+    fn file_device_open_fails_when_not_writable() {
+        let file = write_image(8);
+        let mut permissions = fs::metadata(file.path()).unwrap().permissions();
+
+        permissions.set_readonly(true);
+        fs::set_permissions(file.path(), permissions).unwrap();
+
+        // privileged users can write to any file
+        if fs::File::options().write(true).open(file.path()).is_ok() {
+            return;
+        }
+
+        assert!(matches!(
+            FileBlockDevice::open(file.path()).unwrap_err(),
+            Error::DiskIoError(_),
+        ));
+    }
+
+    #[test]
+    fn file_device_invalid_size_fails() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        fs::write(file.path(), [0; 1000]).unwrap();
+        assert_eq!(
+            FileBlockDevice::open(file.path()).unwrap_err(),
+            Error::DiskInvalidSizeError(1000),
+        );
+    }
+}
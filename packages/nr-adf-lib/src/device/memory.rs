@@ -0,0 +1,65 @@
+use crate::disk::*;
+use crate::errors::*;
+
+use super::*;
+
+
+/// A block device keeping all its data in memory.
+#[derive(Debug)]
+pub struct MemoryBlockDevice {
+    data: Vec<u8>,
+}
+
+impl MemoryBlockDevice {
+    pub fn try_create_with_data(data: Vec<u8>) -> Result<Self, Error> {
+        check_size(data.len() as u64)?;
+        Ok(Self {
+            data,
+        })
+    }
+}
+
+impl BlockDevice for MemoryBlockDevice {
+    fn block_count(&self) -> usize {
+        self.data.len()/BLOCK_SIZE
+    }
+
+    fn blocks(&self, addr: LBAAddress, count: usize) -> Result<&[u8], Error> {
+        let blocks = check_range(addr, count, self.block_count())?;
+        Ok(&self.data[byte_range(blocks)])
+    }
+
+    fn blocks_mut(&mut self, addr: LBAAddress, count: usize) -> Result<&mut [u8], Error> {
+        let blocks = check_range(addr, count, self.block_count())?;
+        Ok(&mut self.data[byte_range(blocks)])
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn is_file_backed(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn memory_device_is_ok() {
+        let data = (0..8*BLOCK_SIZE)
+            .map(|i| (i/BLOCK_SIZE) as u8)
+            .collect::<Vec<_>>();
+        let device = MemoryBlockDevice::try_create_with_data(data).unwrap();
+
+        assert!(!device.is_file_backed());
+        assert!(check_device(device)[5*BLOCK_SIZE..6*BLOCK_SIZE].iter().all(|b| *b == 0x42));
+        assert_eq!(
+            MemoryBlockDevice::try_create_with_data(vec![0; 100]).unwrap_err(),
+            Error::DiskInvalidSizeError(100),
+        );
+    }
+}
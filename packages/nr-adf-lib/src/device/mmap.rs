@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::disk::*;
+use crate::errors::*;
+
+use super::*;
+
+
+/// A block device mapping an image file in memory.
+/// Modified blocks are written back by the system at any time, `flush`
+/// makes sure they are actually written to the file.
+#[derive(Debug)]
+pub struct MmapBlockDevice {
+    mmap: MmapMut,
+    dirty_blocks: BTreeSet<LBAAddress>,
+}
+
+impl MmapBlockDevice {
+    /// Maps an image file, which has to be writable.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = fs::File::options()
+            .read(true)
+            .write(true)
+            .open(path)?;
+
+        Self::try_from(file)
+    }
+}
+
+impl TryFrom<fs::File> for MmapBlockDevice {
+    type Error = Error;
+
+    fn try_from(file: fs::File) -> Result<Self, Self::Error> {
+        check_size(file.metadata()?.len())?;
+
+        // SAFETY: the file is expected not to be modified by other processes
+        // while mapped.
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+
+        Ok(Self {
+            mmap,
+            dirty_blocks: BTreeSet::new(),
+        })
+    }
+}
+
+impl BlockDevice for MmapBlockDevice {
+    fn block_count(&self) -> usize {
+        self.mmap.len()/BLOCK_SIZE
+    }
+
+    fn blocks(&self, addr: LBAAddress, count: usize) -> Result<&[u8], Error> {
+        let blocks = check_range(addr, count, self.block_count())?;
+        Ok(&self.mmap[byte_range(blocks)])
+    }
+
+    fn blocks_mut(&mut self, addr: LBAAddress, count: usize) -> Result<&mut [u8], Error> {
+        let blocks = check_range(addr, count, self.block_count())?;
+
+        self.dirty_blocks.extend(blocks.clone());
+        Ok(&mut self.mmap[byte_range(blocks)])
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // flush runs of contiguous dirty blocks at once
+        while let Some(first) = self.dirty_blocks.pop_first() {
+            let mut end = first + 1;
+
+            while self.dirty_blocks.remove(&end) {
+                end += 1;
+            }
+            self.mmap.flush_range(first*BLOCK_SIZE, (end - first)*BLOCK_SIZE)?;
+        }
+        Ok(())
+    }
+
+    fn is_file_backed(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn mmap_device_is_ok() {
+        let file = write_image(8);
+        let device = MmapBlockDevice::open(file.path()).unwrap();
+
+        assert!(device.is_file_backed());
+
+        let data = check_device(device);
+
+        assert_eq!(fs::read(file.path()).unwrap(), data);
+    }
+
+    #[test]
+    fn mmap_device_tracks_dirty_blocks() {
+        let file = write_image(8);
+        let mut device = MmapBlockDevice::open(file.path()).unwrap();
+
+        device.block_mut(2).unwrap()[0] = 0x42;
+        device.block_mut(3).unwrap()[0] = 0x42;
+        device.block_mut(6).unwrap()[0] = 0x42;
+        assert_eq!(device.dirty_blocks, BTreeSet::from([2, 3, 6]));
+
+        device.flush().unwrap();
+        assert!(device.dirty_blocks.is_empty());
+        assert_eq!(fs::read(file.path()).unwrap()[6*BLOCK_SIZE], 0x42);
+    }
+}
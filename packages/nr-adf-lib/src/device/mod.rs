@@ -0,0 +1,118 @@
+//! Block devices holding the data of a disk.
+//!
+//! A `Disk` reads and writes its blocks through a `BlockDevice`. The data
+//! can live in memory, or in an image file, either read and written with
+//! `std::fs::File` or memory mapped. File backed devices keep track of the
+//! blocks modified since the last `flush`, so that only those are written
+//! back to the file.
+
+mod file;
+mod memory;
+mod mmap;
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::disk::*;
+use crate::errors::*;
+use crate::shared::*;
+
+pub use file::*;
+pub use memory::*;
+pub use mmap::*;
+
+
+pub trait BlockDevice: fmt::Debug + MaybeSync {
+    /// Returns the number of blocks of the device.
+    fn block_count(&self) -> usize;
+
+    /// Returns the data of `count` contiguous blocks starting at `addr`.
+    fn blocks(&self, addr: LBAAddress, count: usize) -> Result<&[u8], Error>;
+
+    /// Returns the data of `count` contiguous blocks starting at `addr`,
+    /// marking them as modified.
+    fn blocks_mut(&mut self, addr: LBAAddress, count: usize) -> Result<&mut [u8], Error>;
+
+    /// Returns the data of a block.
+    fn block(&self, addr: LBAAddress) -> Result<&[u8], Error> {
+        self.blocks(addr, 1)
+    }
+
+    /// Returns the data of a block, marking it as modified.
+    fn block_mut(&mut self, addr: LBAAddress) -> Result<&mut [u8], Error> {
+        self.blocks_mut(addr, 1)
+    }
+
+    /// Writes the modified blocks back to the storage of the device.
+    fn flush(&mut self) -> Result<(), Error>;
+
+    /// Tells if the device is backed by a file, in which case its blocks
+    /// are saved by `flush`.
+    fn is_file_backed(&self) -> bool;
+}
+
+// Returns the blocks from `addr` to `addr + count` if they are all within
+// the device
+fn check_range(
+    addr: LBAAddress,
+    count: usize,
+    block_count: usize,
+) -> Result<Range<LBAAddress>, Error> {
+    match addr.checked_add(count) {
+        Some(end) if addr < block_count && end <= block_count => Ok(addr..end),
+        _ => Err(Error::DiskInvalidLBAAddressError(addr)),
+    }
+}
+
+// Returns the bytes of a range of blocks
+fn byte_range(blocks: Range<LBAAddress>) -> Range<usize> {
+    blocks.start*BLOCK_SIZE..blocks.end*BLOCK_SIZE
+}
+
+fn check_size(size: u64) -> Result<usize, Error> {
+    let size = size as usize;
+
+    if size == 0 || !size.is_multiple_of(BLOCK_SIZE) {
+        Err(Error::DiskInvalidSizeError(size))
+    } else {
+        Ok(size/BLOCK_SIZE)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn write_image(block_count: usize) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let data = (0..block_count*BLOCK_SIZE)
+            .map(|i| (i/BLOCK_SIZE) as u8)
+            .collect::<Vec<_>>();
+
+        std::io::Write::write_all(&mut file, &data).unwrap();
+        file
+    }
+
+    pub(crate) fn check_device(mut device: impl BlockDevice) -> Vec<u8> {
+        assert_eq!(device.block_count(), 8);
+        assert!(device.block(3).unwrap().iter().all(|b| *b == 3));
+        assert!(device.block(8).is_err());
+
+        device.block_mut(5).unwrap().fill(0x42);
+        assert!(device.block(5).unwrap().iter().all(|b| *b == 0x42));
+
+        let blocks = device.blocks(2, 3).unwrap();
+
+        assert_eq!(blocks.len(), 3*BLOCK_SIZE);
+        assert!(blocks.chunks(BLOCK_SIZE).zip(2..).all(|(block, i)| block.iter().all(|b| *b == i)));
+        assert!(device.blocks(6, 3).is_err());
+        assert!(device.blocks(usize::MAX, 2).is_err());
+
+        device.blocks_mut(6, 2).unwrap()[BLOCK_SIZE..].fill(0x43);
+        assert!(device.block(7).unwrap().iter().all(|b| *b == 0x43));
+
+        device.flush().unwrap();
+
+        device.blocks(0, 8).unwrap().to_vec()
+    }
+}
@@ -1,49 +1,137 @@
+use std::path::Path;
+
+use crate::device::*;
 use crate::errors::*;
+use crate::ext_adf::*;
+use crate::flux::{self, TrackReport};
+use crate::image::*;
 
 pub const BLOCK_SIZE     : usize =  512;
 pub const DD_BLOCK_COUNT : usize = 1760;
 pub const HD_BLOCK_COUNT : usize = 3520;
 
+pub const FLOPPY_CYLINDERS  : usize = 80;
+pub const FLOPPY_HEADS      : usize =  2;
+pub const DD_TRACK_SECTORS  : usize = 11;
+pub const HD_TRACK_SECTORS  : usize = 22;
+
 pub type LBAAddress = usize;
 
-#[repr(usize)]
+/// Cylinders/heads/sectors geometry of a disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskGeometry {
+    pub cylinders: usize,
+    pub heads: usize,
+    pub sectors: usize,
+}
+
+impl DiskGeometry {
+    pub fn new(cylinders: usize, heads: usize, sectors: usize) -> Self {
+        Self {
+            cylinders,
+            heads,
+            sectors,
+        }
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.cylinders*self.heads*self.sectors
+    }
+
+    /// Returns the number of tracks, a track being the sectors under one
+    /// head on one cylinder.
+    pub fn track_count(&self) -> usize {
+        self.cylinders*self.heads
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DiskType {
-    DoubleDensity = DD_BLOCK_COUNT,
-    HighDensity   = HD_BLOCK_COUNT,
+    DoubleDensity,
+    HighDensity,
+    /// Any other disk, such as a hard disk partition, given its block count.
+    Custom(usize),
+}
+
+impl From<DiskGeometry> for DiskType {
+    fn from(geometry: DiskGeometry) -> Self {
+        Self::from_block_count(geometry.block_count())
+    }
 }
 
 impl DiskType {
+    /// Returns the disk type matching the given block count, floppy disk
+    /// block counts giving floppy disk types.
+    pub fn from_block_count(block_count: usize) -> Self {
+        match block_count {
+            DD_BLOCK_COUNT => Self::DoubleDensity,
+            HD_BLOCK_COUNT => Self::HighDensity,
+            _ => Self::Custom(block_count),
+        }
+    }
+
+    pub fn block_count(self) -> usize {
+        match self {
+            Self::DoubleDensity => DD_BLOCK_COUNT,
+            Self::HighDensity => HD_BLOCK_COUNT,
+            Self::Custom(block_count) => block_count,
+        }
+    }
+
     pub fn size(self) -> usize {
-        (self as usize)*BLOCK_SIZE
+        self.block_count()*BLOCK_SIZE
+    }
+
+    /// Returns the geometry of floppy disks, other disks have no known
+    /// geometry.
+    pub fn geometry(self) -> Option<DiskGeometry> {
+        match self {
+            Self::DoubleDensity => Some(DiskGeometry::new(
+                FLOPPY_CYLINDERS,
+                FLOPPY_HEADS,
+                DD_TRACK_SECTORS,
+            )),
+            Self::HighDensity => Some(DiskGeometry::new(
+                FLOPPY_CYLINDERS,
+                FLOPPY_HEADS,
+                HD_TRACK_SECTORS,
+            )),
+            Self::Custom(_) => None,
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct Disk {
-    disk_data: Vec<u8>,
+    device: Box<dyn BlockDevice>,
     disk_type: DiskType,
+    image_format: ImageFormat,
+    // first block of the selected blocks, see `select_blocks`
+    block_offset: LBAAddress,
+    // tracks of an extended ADF image, written back by `encode`
+    extended_adf: Option<ExtendedAdf>,
+    // decoded tracks of an SCP or IPF image
+    track_reports: Option<Vec<TrackReport>>,
 }
 
 impl Disk {
-    fn block_bounds(
+    fn device_address(
         &self,
         addr: LBAAddress,
         count: usize,
-    ) -> Result<std::ops::Range<usize>, Error> {
-        if addr >= self.block_count() || addr + count > self.block_count() {
-            Err(Error::DiskInvalidLBAAddressError(addr))
-        } else {
-            let begin = addr*BLOCK_SIZE;
-            let end = (addr + count)*BLOCK_SIZE;
-            Ok(begin..end)
+    ) -> Result<LBAAddress, Error> {
+        match addr.checked_add(count) {
+            Some(end) if addr < self.block_count() && end <= self.block_count() => {
+                Ok(self.block_offset + addr)
+            },
+            _ => Err(Error::DiskInvalidLBAAddressError(addr)),
         }
     }
 }
 
 impl Disk {
     pub fn block_count(&self) -> usize {
-        self.disk_type as usize
+        self.disk_type.block_count()
     }
 
     pub fn size(&self) -> usize {
@@ -53,38 +141,184 @@ impl Disk {
     pub fn disk_type(&self) -> DiskType {
         self.disk_type
     }
+
+    pub fn image_format(&self) -> ImageFormat {
+        self.image_format
+    }
+
+    pub fn set_image_format(&mut self, image_format: ImageFormat) {
+        self.image_format = image_format;
+    }
+
+    /// Returns the number of blocks of the whole image, regardless of the
+    /// selected blocks.
+    pub fn image_block_count(&self) -> usize {
+        self.device.block_count()
+    }
+
+    /// Returns the range of blocks of the image the disk gives access to.
+    pub fn selected_blocks(&self) -> std::ops::Range<LBAAddress> {
+        self.block_offset..self.block_offset + self.block_count()
+    }
+
+    /// Restricts the disk to a range of blocks of the image, e.g. a hard
+    /// disk partition. Block addresses are then relative to the start of
+    /// the range, while `encode` still gives back the whole image.
+    pub fn select_blocks(
+        &mut self,
+        blocks: std::ops::Range<LBAAddress>,
+    ) -> Result<(), Error> {
+        if blocks.is_empty() || blocks.end > self.image_block_count() {
+            return Err(Error::DiskInvalidLBAAddressError(blocks.end));
+        }
+
+        self.block_offset = blocks.start;
+        self.disk_type = DiskType::from_block_count(blocks.len());
+
+        Ok(())
+    }
+
+    /// Gives access back to all the blocks of the image.
+    pub fn select_all_blocks(&mut self) {
+        self.block_offset = 0;
+        self.disk_type = DiskType::from_block_count(self.image_block_count());
+    }
 }
 
 impl Disk {
     pub fn create(disk_type: DiskType) -> Self {
-        let disk_data = vec![0; disk_type.size()];
+        let device = MemoryBlockDevice::try_create_with_data(
+            vec![0; disk_type.size()]
+        ).unwrap();
 
         Self {
-            disk_data,
+            device: Box::new(device),
             disk_type,
+            image_format: ImageFormat::default(),
+            block_offset: 0,
+            extended_adf: None,
+            track_reports: None,
         }
     }
 
+    /// Creates a disk from the content of an image file.
+    /// Compressed images are transparently decompressed, their format is
+    /// kept so that `encode` gives back an image of the same format.
+    /// DMS archives are unpacked too, but cannot be encoded back.
+    /// Extended ADF images keep their tracks, see `extended_adf`.
+    /// SCP and IPF images are decoded read only, see `track_reports`.
     pub fn try_create_with_data(
-        disk_data: Vec<u8>,
+        data: Vec<u8>,
     ) -> Result<Self, Error> {
-        let disk_size = disk_data.len();
+        let mut extended_adf = None;
+        let mut track_reports = None;
+
+        let (disk_data, image_format) = match ImageFormat::detect(&data) {
+            ImageFormat::ExtendedADF => {
+                let (image, disk_data) = ExtendedAdf::decode(&data)?;
+
+                extended_adf = Some(image);
+                (disk_data, ImageFormat::ExtendedADF)
+            },
+            image_format@(ImageFormat::SCP|ImageFormat::IPF) => {
+                let image = flux::import(&data)?;
+
+                track_reports = Some(image.tracks);
+                (image.disk_data, image_format)
+            },
+            _ => decode(data)?,
+        };
+        let device = MemoryBlockDevice::try_create_with_data(disk_data)?;
+        let mut disk = Self::with_device(Box::new(device));
 
-        if disk_size == DiskType::DoubleDensity.size() {
-            return Ok(Disk {
-                disk_data,
-                disk_type: DiskType::DoubleDensity,
-            });
+        disk.set_image_format(image_format);
+        disk.extended_adf = extended_adf;
+        disk.track_reports = track_reports;
+        Ok(disk)
+    }
+
+    /// Returns the tracks of the extended ADF image the disk was created
+    /// from, telling which sectors of its raw MFM tracks were readable.
+    pub fn extended_adf(&self) -> Option<&ExtendedAdf> {
+        self.extended_adf.as_ref()
+    }
+
+    /// Returns the status of the sectors decoded from the SCP or IPF image
+    /// the disk was created from, along with the revolution they were read
+    /// from.
+    pub fn track_reports(&self) -> Option<&[TrackReport]> {
+        self.track_reports.as_deref()
+    }
+
+    /// Creates a disk reading and writing its blocks through the given
+    /// device. The image format defaults to ADF.
+    pub fn with_device(device: Box<dyn BlockDevice>) -> Self {
+        Self {
+            disk_type: DiskType::from_block_count(device.block_count()),
+            device,
+            image_format: ImageFormat::ADF,
+            block_offset: 0,
+            extended_adf: None,
+            track_reports: None,
         }
+    }
+
+    /// Opens a raw image file, blocks being read from the file when first
+    /// accessed. Modified blocks are written back by `flush`.
+    /// Errors:
+    /// - When the file can't be opened for writing, see `open_read_only`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::with_device(Box::new(FileBlockDevice::open(path)?)))
+    }
+
+    /// Opens a raw image file for reading only, `flush` failing once blocks
+    /// were modified.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::with_device(Box::new(FileBlockDevice::open_read_only(path)?)))
+    }
 
-        if disk_size == DiskType::HighDensity.size() {
-            return Ok(Disk {
-                disk_data,
-                disk_type: DiskType::HighDensity,
-            });
+    /// Opens a raw image file, mapping it in memory.
+    /// Modified blocks are written back by `flush`.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::with_device(Box::new(MmapBlockDevice::open(path)?)))
+    }
+
+    /// Tells if the disk blocks are stored in a file, saved by `flush`,
+    /// rather than in memory.
+    pub fn is_file_backed(&self) -> bool {
+        self.device.is_file_backed()
+    }
+
+    /// Writes the blocks modified since the last flush back to the image
+    /// file. Does nothing for a disk held in memory.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.device.flush()
+    }
+
+    /// Returns the content of the disk encoded in its image format.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let disk_data = self.device.blocks(0, self.image_block_count())?;
+
+        match (self.image_format, self.extended_adf.as_ref()) {
+            (ImageFormat::ExtendedADF, Some(extended_adf)) => {
+                extended_adf.encode(disk_data)
+            },
+            _ => encode(disk_data, self.image_format),
         }
+    }
+
+    pub fn block(
+        &self,
+        addr: LBAAddress,
+    ) -> Result<&[u8], Error> {
+        self.blocks(addr, 1)
+    }
 
-        Err(Error::DiskInvalidSizeError(disk_size))
+    pub fn block_mut(
+        &mut self,
+        addr: LBAAddress,
+    ) -> Result<&mut [u8], Error> {
+        self.blocks_mut(addr, 1)
     }
 
     pub fn blocks(
@@ -92,8 +326,7 @@ impl Disk {
         addr: LBAAddress,
         count: usize,
     ) -> Result<&[u8], Error> {
-        let r = self.block_bounds(addr, count)?;
-        Ok(&self.disk_data[r])
+        self.device.blocks(self.device_address(addr, count)?, count)
     }
 
     pub fn blocks_mut(
@@ -101,19 +334,125 @@ impl Disk {
         addr: LBAAddress,
         count: usize,
     ) -> Result<&mut [u8], Error> {
-        let r = self.block_bounds(addr, count)?;
-        Ok(&mut self.disk_data[r])
+        let addr = self.device_address(addr, count)?;
+        self.device.blocks_mut(addr, count)
+    }
+
+    /// Returns the selected blocks.
+    pub fn data(&self) -> Result<&[u8], Error> {
+        self.blocks(0, self.block_count())
     }
 
-    pub fn data(&self) -> &[u8] {
-        self.disk_data.as_slice()
+    /// Returns the selected blocks, marking them all as modified.
+    pub fn data_mut(&mut self) -> Result<&mut [u8], Error> {
+        self.blocks_mut(0, self.block_count())
     }
 
-    pub fn data_mut(&mut self) -> &mut [u8] {
-        self.disk_data.as_mut_slice()
+    /// Returns a copy of the selected blocks.
+    pub fn read_blocks(&self) -> Result<Vec<u8>, Error> {
+        self.read_blocks_at(0, self.block_count())
     }
 }
 
+/// Raw access to blocks and tracks, whatever the disk holds.
+impl Disk {
+    fn check_block_range(
+        &self,
+        addr: LBAAddress,
+        count: usize,
+    ) -> Result<(), Error> {
+        match addr.checked_add(count) {
+            Some(end) if end <= self.block_count() => Ok(()),
+            _ => Err(Error::DiskInvalidLBAAddressError(addr.saturating_add(count))),
+        }
+    }
+
+    /// Returns a copy of `count` blocks starting at `addr`.
+    pub fn read_blocks_at(
+        &self,
+        addr: LBAAddress,
+        count: usize,
+    ) -> Result<Vec<u8>, Error> {
+        self.check_block_range(addr, count)?;
+
+        let mut data = Vec::with_capacity(count*BLOCK_SIZE);
+
+        for addr in addr..addr + count {
+            data.extend_from_slice(self.block(addr)?);
+        }
+        Ok(data)
+    }
+
+    /// Writes `data` to the blocks starting at `addr`. Nothing is written
+    /// if any block is out of the disk.
+    /// Errors:
+    /// - When the size of `data` isn't a multiple of the block size.
+    pub fn write_blocks_at(
+        &mut self,
+        addr: LBAAddress,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        if !data.len().is_multiple_of(BLOCK_SIZE) {
+            return Err(Error::DiskInvalidSizeError(data.len()));
+        }
+
+        self.check_block_range(addr, data.len()/BLOCK_SIZE)?;
+
+        for (addr, chunk) in (addr..).zip(data.chunks_exact(BLOCK_SIZE)) {
+            self.block_mut(addr)?.copy_from_slice(chunk);
+        }
+        Ok(())
+    }
+
+    /// Returns the geometry of the disk, only known for floppy disks.
+    pub fn geometry(&self) -> Result<DiskGeometry, Error> {
+        self.disk_type.geometry().ok_or(Error::DiskUnknownGeometryError)
+    }
+
+    /// Returns the blocks of a track, tracks being numbered as by
+    /// trackdisk.device, i.e. `cylinder*heads + head`.
+    pub fn track_blocks(
+        &self,
+        track: usize,
+    ) -> Result<std::ops::Range<LBAAddress>, Error> {
+        let geometry = self.geometry()?;
+
+        if track >= geometry.track_count() {
+            return Err(Error::DiskInvalidTrackError(track));
+        }
+
+        let addr = track*geometry.sectors;
+
+        Ok(addr..addr + geometry.sectors)
+    }
+
+    /// Returns a copy of the sectors of a track.
+    pub fn read_track(
+        &self,
+        track: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let blocks = self.track_blocks(track)?;
+
+        self.read_blocks_at(blocks.start, blocks.len())
+    }
+
+    /// Overwrites the sectors of a track.
+    /// Errors:
+    /// - When the size of `data` isn't the size of a track.
+    pub fn write_track(
+        &mut self,
+        track: usize,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let blocks = self.track_blocks(track)?;
+
+        if data.len() != blocks.len()*BLOCK_SIZE {
+            return Err(Error::DiskInvalidSizeError(data.len()));
+        }
+
+        self.write_blocks_at(blocks.start, data)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -134,4 +473,182 @@ mod tests {
         assert_eq!(disk.block_count(), HD_BLOCK_COUNT);
         assert_eq!(disk.size(), HD_BLOCK_COUNT*BLOCK_SIZE);
     }
+
+    #[test]
+    fn custom_disk_is_ok() {
+        let disk_type = DiskType::from(DiskGeometry::new(100, 4, 32));
+        let disk = Disk::create(disk_type);
+
+        assert_eq!(disk_type, DiskType::Custom(12800));
+        assert_eq!(disk.block_count(), 12800);
+
+        let disk = Disk::try_create_with_data(disk.encode().unwrap()).unwrap();
+
+        assert_eq!(disk.disk_type(), DiskType::Custom(12800));
+        assert_eq!(
+            DiskType::from(DiskGeometry::new(80, 2, 11)),
+            DiskType::DoubleDensity,
+        );
+    }
+
+    #[test]
+    fn select_blocks_is_ok() {
+        let mut disk = Disk::create(DiskType::Custom(100));
+
+        disk.block_mut(10).unwrap().fill(0x42);
+        disk.select_blocks(10..30).unwrap();
+
+        assert_eq!(disk.block_count(), 20);
+        assert!(disk.block(0).unwrap().iter().all(|b| *b == 0x42));
+        assert!(disk.block(20).is_err());
+        assert!(disk.select_blocks(90..101).is_err());
+
+        disk.select_all_blocks();
+
+        assert_eq!(disk.block_count(), 100);
+        assert_eq!(disk.encode().unwrap().len(), 100*BLOCK_SIZE);
+    }
+
+    #[test]
+    fn file_backed_disk_is_ok() {
+        let file = crate::device::tests::write_image(100);
+        let mut disk = Disk::open(file.path()).unwrap();
+
+        assert!(disk.is_file_backed());
+        assert_eq!(disk.disk_type(), DiskType::Custom(100));
+
+        disk.select_blocks(10..30).unwrap();
+        disk.block_mut(1).unwrap().fill(0x42);
+        disk.flush().unwrap();
+
+        let data = std::fs::read(file.path()).unwrap();
+
+        assert!(data[11*BLOCK_SIZE..12*BLOCK_SIZE].iter().all(|b| *b == 0x42));
+        assert_eq!(Disk::open_mmap(file.path()).unwrap().encode().unwrap(), data);
+    }
+
+    #[test]
+    fn raw_access_is_ok() {
+        let mut disk = Disk::create(DiskType::DoubleDensity);
+
+        assert_eq!(disk.geometry().unwrap().track_count(), 160);
+        assert_eq!(disk.track_blocks(3).unwrap(), 33..44);
+
+        disk.write_track(3, &[0x42; 11*BLOCK_SIZE]).unwrap();
+
+        assert!(disk.block(33).unwrap().iter().all(|b| *b == 0x42));
+        assert!(disk.block(44).unwrap().iter().all(|b| *b == 0));
+        assert_eq!(disk.read_blocks_at(43, 2).unwrap()[BLOCK_SIZE - 1..BLOCK_SIZE + 1], [0x42, 0]);
+
+        disk.write_blocks_at(1758, &[0x24; 2*BLOCK_SIZE]).unwrap();
+
+        assert_eq!(disk.read_track(159).unwrap()[10*BLOCK_SIZE], 0x24);
+        assert_eq!(disk.read_track(160), Err(Error::DiskInvalidTrackError(160)));
+        assert_eq!(
+            disk.write_blocks_at(1759, &[0; 2*BLOCK_SIZE]),
+            Err(Error::DiskInvalidLBAAddressError(1761)),
+        );
+        assert_eq!(disk.block(1759).unwrap()[0], 0x24);
+        assert_eq!(
+            disk.write_blocks_at(0, &[0; 100]),
+            Err(Error::DiskInvalidSizeError(100)),
+        );
+        assert_eq!(
+            Disk::create(DiskType::Custom(100)).read_track(0),
+            Err(Error::DiskUnknownGeometryError),
+        );
+    }
+
+    #[test]
+    fn contiguous_blocks_are_ok() {
+        let file = crate::device::tests::write_image(100);
+
+        for mut disk in [
+            Disk::try_create_with_data(std::fs::read(file.path()).unwrap()).unwrap(),
+            Disk::open(file.path()).unwrap(),
+            Disk::open_mmap(file.path()).unwrap(),
+        ] {
+            disk.select_blocks(10..30).unwrap();
+
+            assert_eq!(disk.blocks(2, 3).unwrap()[BLOCK_SIZE], 13);
+            assert_eq!(disk.data().unwrap().len(), 20*BLOCK_SIZE);
+            assert_eq!(disk.data().unwrap()[19*BLOCK_SIZE], 29);
+            assert_eq!(disk.blocks(18, 3), Err(Error::DiskInvalidLBAAddressError(18)));
+
+            disk.blocks_mut(4, 2).unwrap().fill(0x42);
+            disk.data_mut().unwrap()[0] = 0x24;
+
+            assert_eq!(disk.read_blocks_at(0, 6).unwrap()[..1], [0x24]);
+            assert!(disk.block(5).unwrap().iter().all(|b| *b == 0x42));
+            assert_eq!(disk.block(6).unwrap()[0], 16);
+        }
+    }
+
+    #[test]
+    fn invalid_disk_size_fails() {
+        assert_eq!(
+            Disk::try_create_with_data(vec![0; 1000]).unwrap_err(),
+            Error::DiskInvalidSizeError(1000),
+        );
+    }
+
+    #[test]
+    fn adz_image_is_ok() {
+        let mut disk = Disk::create(DiskType::DoubleDensity);
+
+        disk.set_image_format(ImageFormat::ADZ);
+
+        let disk = Disk::try_create_with_data(disk.encode().unwrap()).unwrap();
+
+        assert_eq!(disk.image_format(), ImageFormat::ADZ);
+        assert_eq!(disk.block_count(), DD_BLOCK_COUNT);
+    }
+
+    #[test]
+    fn extended_adf_image_is_ok() {
+        let mut disk = Disk::create(DiskType::DoubleDensity);
+
+        disk.block_mut(100).unwrap().fill(0x42);
+        disk.set_image_format(ImageFormat::ExtendedADF);
+
+        let data = disk.encode().unwrap();
+        let disk = Disk::try_create_with_data(data.clone()).unwrap();
+
+        assert_eq!(disk.image_format(), ImageFormat::ExtendedADF);
+        assert_eq!(disk.block_count(), DD_BLOCK_COUNT);
+        assert_eq!(disk.block(100).unwrap()[0], 0x42);
+        assert_eq!(disk.extended_adf().unwrap().tracks().len(), 160);
+        assert_eq!(disk.encode().unwrap(), data);
+    }
+
+    #[test]
+    fn dms_image_is_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let archive = crate::dms::tests::pack(&disk.read_blocks().unwrap());
+
+        let disk = Disk::try_create_with_data(archive).unwrap();
+
+        assert_eq!(disk.image_format(), ImageFormat::DMS);
+        assert_eq!(disk.block_count(), DD_BLOCK_COUNT);
+        assert_eq!(disk.encode(), Err(Error::ReadOnlyImageFormatError));
+    }
+
+    #[test]
+    fn scp_image_is_ok() {
+        let mut disk = Disk::create(DiskType::DoubleDensity);
+
+        disk.block_mut(12).unwrap().fill(0x42);
+
+        let data = disk.read_blocks_at(0, 4*DD_TRACK_SECTORS).unwrap();
+        let scp = crate::flux::scp::tests::write_scp(&data, |track, _| track == 2);
+        let disk = Disk::try_create_with_data(scp).unwrap();
+        let reports = disk.track_reports().unwrap();
+
+        assert_eq!(disk.image_format(), ImageFormat::SCP);
+        assert_eq!(disk.block_count(), DD_BLOCK_COUNT);
+        assert_eq!(disk.block(12).unwrap()[0], 0x42);
+        assert!(reports[0].is_ok());
+        assert!(!reports[2].is_ok());
+        assert_eq!(disk.encode(), Err(Error::ReadOnlyImageFormatError));
+    }
 }
@@ -0,0 +1,102 @@
+/// MSB first bit reader with the same refill strategy as the original DMS
+/// decrunchers: at least 16 bits are always available.
+pub(super) struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        let mut reader = Self {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        };
+
+        reader.drop_bits(0);
+        reader
+    }
+
+    fn next_byte(&mut self) -> u32 {
+        let byte = self.data.get(self.pos).copied().unwrap_or(0);
+
+        self.pos += 1;
+        byte as u32
+    }
+
+    /// Returns the next `n` bits (n <= 16) without consuming them.
+    pub(super) fn get_bits(&self, n: u32) -> u16 {
+        (self.bit_buf >> (self.bit_count - n)) as u16
+    }
+
+    /// Consumes `n` bits (n <= 16).
+    pub(super) fn drop_bits(&mut self, n: u32) {
+        self.bit_count -= n;
+        self.bit_buf &= (1u32 << self.bit_count) - 1;
+
+        while self.bit_count < 16 {
+            self.bit_buf = (self.bit_buf << 8) | self.next_byte();
+            self.bit_count += 8;
+        }
+    }
+
+    pub(super) fn read_bits(&mut self, n: u32) -> u16 {
+        let v = self.get_bits(n);
+
+        self.drop_bits(n);
+        v
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// MSB first bit writer used to build test streams.
+    #[derive(Default)]
+    pub(in crate::dms) struct BitWriter {
+        data: Vec<u8>,
+        bit_buf: u64,
+        bit_count: u32,
+    }
+
+    impl BitWriter {
+        pub(in crate::dms) fn write_bits(&mut self, n: u32, v: u32) {
+            self.bit_buf = (self.bit_buf << n) | (v as u64 & ((1u64 << n) - 1));
+            self.bit_count += n;
+
+            while self.bit_count >= 8 {
+                self.bit_count -= 8;
+                self.data.push((self.bit_buf >> self.bit_count) as u8);
+            }
+        }
+
+        pub(in crate::dms) fn finish(mut self) -> Vec<u8> {
+            if self.bit_count > 0 {
+                self.write_bits(8 - self.bit_count, 0);
+            }
+            self.data
+        }
+    }
+
+    #[test]
+    fn read_bits_is_ok() {
+        let mut writer = BitWriter::default();
+
+        writer.write_bits(1, 1);
+        writer.write_bits(8, 0xa5);
+        writer.write_bits(12, 0x123);
+        writer.write_bits(16, 0xbeef);
+
+        let data = writer.finish();
+        let mut reader = BitReader::new(&data);
+
+        assert_eq!(reader.read_bits(1), 1);
+        assert_eq!(reader.read_bits(8), 0xa5);
+        assert_eq!(reader.read_bits(12), 0x123);
+        assert_eq!(reader.read_bits(16), 0xbeef);
+    }
+}
@@ -0,0 +1,45 @@
+const fn crc_table() -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+const CRC_TABLE: [u16; 256] = crc_table();
+
+/// CRC-16 (ARC variant) used for DMS headers and packed track data.
+pub(super) fn compute_crc(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        CRC_TABLE[((crc ^ *byte as u16) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Checksum of unpacked track data.
+pub(super) fn compute_checksum(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_crc_is_ok() {
+        assert_eq!(compute_crc(b"123456789"), 0xbb3d);
+    }
+}
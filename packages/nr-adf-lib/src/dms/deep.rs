@@ -0,0 +1,293 @@
+use super::bit_reader::*;
+use super::tables::*;
+use super::DecrunchError;
+
+const DEEP_BIT_MASK: usize = 0x3fff;
+
+const F: usize = 60;
+const THRESHOLD: usize = 2;
+const N_CHAR: usize = 256 - THRESHOLD + F;
+const T: usize = 2*N_CHAR - 1;
+const R: usize = T - 1;
+const MAX_FREQ: u16 = 0x8000;
+
+/// Adaptive Huffman tree of the LZHUF algorithm.
+pub(super) struct DeepTree {
+    freq: [u16; T + 1],
+    prnt: [u16; T + N_CHAR],
+    son: [u16; T],
+}
+
+impl Default for DeepTree {
+    fn default() -> Self {
+        let mut tree = Self {
+            freq: [0; T + 1],
+            prnt: [0; T + N_CHAR],
+            son: [0; T],
+        };
+
+        for i in 0..N_CHAR {
+            tree.freq[i] = 1;
+            tree.son[i] = (i + T) as u16;
+            tree.prnt[i + T] = i as u16;
+        }
+
+        let mut i = 0;
+
+        for j in N_CHAR..=R {
+            tree.freq[j] = tree.freq[i] + tree.freq[i + 1];
+            tree.son[j] = i as u16;
+            tree.prnt[i] = j as u16;
+            tree.prnt[i + 1] = j as u16;
+            i += 2;
+        }
+
+        tree.freq[T] = 0xffff;
+        tree.prnt[R] = 0;
+        tree
+    }
+}
+
+impl DeepTree {
+    fn reconst(&mut self) {
+        // collect leaf nodes in the first half of the table and halve their
+        // frequencies
+        let mut j = 0;
+
+        for i in 0..T {
+            if self.son[i] as usize >= T {
+                self.freq[j] = self.freq[i].div_ceil(2);
+                self.son[j] = self.son[i];
+                j += 1;
+            }
+        }
+
+        // rebuild the tree by connecting sons
+        let mut i = 0;
+
+        for j in N_CHAR..T {
+            let f = self.freq[i] + self.freq[i + 1];
+
+            self.freq[j] = f;
+
+            let mut k = j - 1;
+
+            while f < self.freq[k] {
+                k -= 1;
+            }
+            k += 1;
+
+            self.freq.copy_within(k..j, k + 1);
+            self.freq[k] = f;
+            self.son.copy_within(k..j, k + 1);
+            self.son[k] = i as u16;
+            i += 2;
+        }
+
+        // connect parents
+        for i in 0..T {
+            let k = self.son[i] as usize;
+
+            self.prnt[k] = i as u16;
+            if k < T {
+                self.prnt[k + 1] = i as u16;
+            }
+        }
+    }
+
+    fn update(&mut self, c: usize) {
+        if self.freq[R] == MAX_FREQ {
+            self.reconst();
+        }
+
+        let mut c = self.prnt[c + T] as usize;
+
+        loop {
+            self.freq[c] += 1;
+
+            let k = self.freq[c];
+
+            // if the order is disturbed, exchange nodes
+            if k > self.freq[c + 1] {
+                let mut l = c + 1;
+
+                while k > self.freq[l + 1] {
+                    l += 1;
+                }
+
+                self.freq[c] = self.freq[l];
+                self.freq[l] = k;
+
+                let i = self.son[c] as usize;
+
+                self.prnt[i] = l as u16;
+                if i < T {
+                    self.prnt[i + 1] = l as u16;
+                }
+
+                let j = self.son[l] as usize;
+
+                self.son[l] = i as u16;
+                self.prnt[j] = c as u16;
+                if j < T {
+                    self.prnt[j + 1] = c as u16;
+                }
+                self.son[c] = j as u16;
+
+                c = l;
+            }
+
+            c = self.prnt[c] as usize;
+            if c == 0 {
+                break;
+            }
+        }
+    }
+
+    fn decode_char(&mut self, reader: &mut BitReader) -> usize {
+        let mut c = self.son[R] as usize;
+
+        while c < T {
+            c = self.son[c + reader.read_bits(1) as usize] as usize;
+        }
+        c -= T;
+
+        self.update(c);
+        c
+    }
+}
+
+fn decode_position(reader: &mut BitReader) -> usize {
+    let i = reader.read_bits(8) as usize;
+    let c = (D_CODE[i] as usize) << 8;
+    let j = D_LEN[i] as u32;
+
+    c | (((i << j) | reader.read_bits(j) as usize) & 0xff)
+}
+
+pub(super) struct Deep {
+    text_loc: usize,
+    tree: Option<Box<DeepTree>>,
+}
+
+impl Default for Deep {
+    fn default() -> Self {
+        Self {
+            text_loc: 0x3fc4,
+            tree: None,
+        }
+    }
+}
+
+impl Deep {
+    pub(super) fn unpack(
+        &mut self,
+        text: &mut [u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecrunchError> {
+        let mut reader = BitReader::new(input);
+        let tree = self.tree.get_or_insert_with(Box::default);
+        let mut pos = 0;
+
+        while pos < output.len() {
+            let c = tree.decode_char(&mut reader);
+
+            if c < 256 {
+                text[self.text_loc & DEEP_BIT_MASK] = c as u8;
+                self.text_loc += 1;
+                output[pos] = c as u8;
+                pos += 1;
+            } else {
+                let count = c - 255 + THRESHOLD;
+                let position = decode_position(&mut reader);
+
+                if pos + count > output.len() {
+                    return Err(DecrunchError);
+                }
+
+                let mut i = self.text_loc.wrapping_sub(position + 1);
+
+                for _ in 0..count {
+                    let c = text[i & DEEP_BIT_MASK];
+
+                    text[self.text_loc & DEEP_BIT_MASK] = c;
+                    self.text_loc += 1;
+                    output[pos] = c;
+                    pos += 1;
+                    i = i.wrapping_add(1);
+                }
+            }
+        }
+
+        self.text_loc = (self.text_loc + 60) & DEEP_BIT_MASK;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dms::bit_reader::tests::BitWriter;
+    use crate::dms::tables::tests::write_position;
+
+    fn write_char(tree: &mut DeepTree, writer: &mut BitWriter, c: usize) {
+        let mut bits = vec![];
+        let mut node = tree.prnt[c + T] as usize;
+
+        while node != R {
+            let parent = tree.prnt[node] as usize;
+
+            bits.push((node - tree.son[parent] as usize) as u32);
+            node = parent;
+        }
+
+        for bit in bits.into_iter().rev() {
+            writer.write_bits(1, bit);
+        }
+
+        tree.update(c);
+    }
+
+    #[test]
+    fn unpack_deep_is_ok() {
+        let mut tree = DeepTree::default();
+        let mut writer = BitWriter::default();
+        let mut expected = vec![];
+        let mut seed = 12345u32;
+
+        // enough symbols to make the tree be rebuilt at least once
+        for _ in 0..40000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+
+            let r = (seed >> 16) as usize;
+
+            if expected.len() < 16 || !r.is_multiple_of(4) {
+                let c = r%256;
+
+                write_char(&mut tree, &mut writer, c);
+                expected.push(c as u8);
+            } else {
+                let count = 3 + r%(F - 2);
+                let position = (r >> 6)%expected.len().min(0x3000);
+                let start = expected.len() - position - 1;
+
+                write_char(&mut tree, &mut writer, count + 253);
+                write_position(&mut writer, position);
+
+                for i in 0..count {
+                    expected.push(expected[start + i]);
+                }
+            }
+        }
+
+        let input = writer.finish();
+        let mut text = vec![0u8; 0x4000];
+        let mut output = vec![0u8; expected.len()];
+
+        Deep::default().unpack(&mut text, &input, &mut output).unwrap();
+
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,457 @@
+use super::bit_reader::*;
+use super::DecrunchError;
+
+const NC: usize = 510;
+const NPT: usize = 20;
+const N1: usize = 510;
+const OFFSET: usize = 253;
+
+const C_TABLE_BITS: u32 = 12;
+const PT_TABLE_BITS: u32 = 8;
+
+/// Builds a lookup table (and the tree for the codes longer than the table
+/// bits) from the code lengths of a canonical Huffman code.
+struct TableBuilder<'a> {
+    bit_len: &'a [u8],
+    table: &'a mut [u16],
+    left: &'a mut [u16],
+    right: &'a mut [u16],
+    n: usize,
+    avail: usize,
+    table_size: usize,
+    bit: usize,
+    max_depth: usize,
+    depth: usize,
+    len: usize,
+    c: usize,
+    codeword: usize,
+}
+
+impl TableBuilder<'_> {
+    fn make_tree(&mut self) -> Result<u16, DecrunchError> {
+        if self.len == self.depth {
+            while self.c < self.n {
+                let c = self.c;
+
+                self.c += 1;
+                if self.bit_len[c] as usize == self.len {
+                    let i = self.codeword;
+
+                    self.codeword += self.bit;
+                    if self.codeword > self.table_size {
+                        return Err(DecrunchError);
+                    }
+
+                    self.table[i..self.codeword].fill(c as u16);
+                    return Ok(c as u16);
+                }
+            }
+
+            self.c = 0;
+            self.len += 1;
+            self.bit >>= 1;
+        }
+
+        let mut i = 0;
+
+        self.depth += 1;
+        if self.depth < self.max_depth {
+            self.make_tree()?;
+            self.make_tree()?;
+        } else if self.depth > 32 {
+            return Err(DecrunchError);
+        } else {
+            i = self.avail;
+            self.avail += 1;
+            if i >= 2*self.n - 1 {
+                return Err(DecrunchError);
+            }
+
+            self.left[i] = self.make_tree()?;
+            self.right[i] = self.make_tree()?;
+
+            if self.codeword >= self.table_size {
+                return Err(DecrunchError);
+            }
+
+            if self.depth == self.max_depth {
+                self.table[self.codeword] = i as u16;
+                self.codeword += 1;
+            }
+        }
+        self.depth -= 1;
+
+        Ok(i as u16)
+    }
+}
+
+fn make_table(
+    bit_len: &[u8],
+    table_bits: u32,
+    table: &mut [u16],
+    left: &mut [u16],
+    right: &mut [u16],
+) -> Result<(), DecrunchError> {
+    let table_size = 1usize << table_bits;
+    let mut builder = TableBuilder {
+        bit_len,
+        table,
+        left,
+        right,
+        n: bit_len.len(),
+        avail: bit_len.len(),
+        table_size,
+        bit: table_size/2,
+        max_depth: table_bits as usize + 1,
+        depth: 1,
+        len: 1,
+        c: 0,
+        codeword: 0,
+    };
+
+    // left subtree, then right subtree
+    builder.make_tree()?;
+    builder.make_tree()?;
+
+    if builder.codeword != table_size {
+        return Err(DecrunchError);
+    }
+
+    Ok(())
+}
+
+pub(super) struct Heavy {
+    text_loc: usize,
+    last_len: usize,
+    np: usize,
+    c_len: [u8; NC],
+    c_table: [u16; 1 << C_TABLE_BITS],
+    pt_len: [u8; NPT],
+    pt_table: [u16; 1 << PT_TABLE_BITS],
+    left: [u16; 2*NC - 1],
+    right: [u16; 2*NC - 1],
+}
+
+impl Default for Heavy {
+    fn default() -> Self {
+        Self {
+            text_loc: 0,
+            last_len: 0,
+            np: 0,
+            c_len: [0; NC],
+            c_table: [0; 1 << C_TABLE_BITS],
+            pt_len: [0; NPT],
+            pt_table: [0; 1 << PT_TABLE_BITS],
+            left: [0; 2*NC - 1],
+            right: [0; 2*NC - 1],
+        }
+    }
+}
+
+impl Heavy {
+    pub(super) fn reset(&mut self) {
+        self.text_loc = 0;
+    }
+
+    fn read_tree_c(&mut self, reader: &mut BitReader) -> Result<(), DecrunchError> {
+        let n = reader.read_bits(9) as usize;
+
+        self.c_len.fill(0);
+
+        if n > 0 {
+            for i in 0..n {
+                let len = reader.read_bits(5) as u8;
+
+                if i < NC {
+                    self.c_len[i] = len;
+                }
+            }
+
+            make_table(
+                &self.c_len,
+                C_TABLE_BITS,
+                &mut self.c_table,
+                &mut self.left,
+                &mut self.right,
+            )
+        } else {
+            let c = reader.read_bits(9);
+
+            self.c_table.fill(c);
+            Ok(())
+        }
+    }
+
+    fn read_tree_p(&mut self, reader: &mut BitReader) -> Result<(), DecrunchError> {
+        let n = reader.read_bits(5) as usize;
+
+        self.pt_len.fill(0);
+
+        if n > 0 {
+            for i in 0..n {
+                let len = reader.read_bits(4) as u8;
+
+                if i < NPT {
+                    self.pt_len[i] = len;
+                }
+            }
+
+            make_table(
+                &self.pt_len[..self.np],
+                PT_TABLE_BITS,
+                &mut self.pt_table,
+                &mut self.left,
+                &mut self.right,
+            )
+        } else {
+            let p = reader.read_bits(5);
+
+            self.pt_table.fill(p);
+            Ok(())
+        }
+    }
+
+    fn walk_tree(
+        &self,
+        reader: &mut BitReader,
+        mut j: usize,
+        n: usize,
+        table_bits: u32,
+    ) -> Result<usize, DecrunchError> {
+        reader.drop_bits(table_bits);
+
+        let bits = reader.get_bits(16);
+        let mut mask = 0x8000u16;
+
+        while j >= n {
+            if mask == 0 || j >= self.left.len() {
+                return Err(DecrunchError);
+            }
+
+            j = if bits & mask != 0 {
+                self.right[j]
+            } else {
+                self.left[j]
+            } as usize;
+            mask >>= 1;
+        }
+
+        Ok(j)
+    }
+
+    fn decode_c(&self, reader: &mut BitReader) -> Result<usize, DecrunchError> {
+        let j = self.c_table[reader.get_bits(C_TABLE_BITS) as usize] as usize;
+
+        if j < N1 {
+            reader.drop_bits(self.c_len[j] as u32);
+            Ok(j)
+        } else {
+            let j = self.walk_tree(reader, j, N1, C_TABLE_BITS)?;
+
+            reader.drop_bits((self.c_len[j] as u32).saturating_sub(C_TABLE_BITS));
+            Ok(j)
+        }
+    }
+
+    fn decode_p(&mut self, reader: &mut BitReader) -> Result<usize, DecrunchError> {
+        let mut j = self.pt_table[reader.get_bits(PT_TABLE_BITS) as usize] as usize;
+
+        if j < self.np {
+            reader.drop_bits(self.pt_len[j] as u32);
+        } else {
+            j = self.walk_tree(reader, j, self.np, PT_TABLE_BITS)?;
+            reader.drop_bits((self.pt_len[j] as u32).saturating_sub(PT_TABLE_BITS));
+        }
+
+        if j != self.np - 1 {
+            if j > 0 {
+                let n = j as u32 - 1;
+
+                j = reader.read_bits(n) as usize | (1 << n);
+            }
+            self.last_len = j;
+        }
+
+        Ok(self.last_len)
+    }
+
+    /// Unpacks data packed with HEAVY1 (4KB dictionary) or, if bit 3 of
+    /// `flags` is set, HEAVY2 (8KB dictionary). Bit 1 tells that new
+    /// Huffman tables come first in the stream.
+    pub(super) fn unpack(
+        &mut self,
+        text: &mut [u8],
+        input: &[u8],
+        output: &mut [u8],
+        flags: u8,
+    ) -> Result<(), DecrunchError> {
+        let bit_mask = if flags & 8 != 0 {
+            self.np = 15;
+            0x1fff
+        } else {
+            self.np = 14;
+            0x0fff
+        };
+
+        let mut reader = BitReader::new(input);
+
+        if flags & 2 != 0 {
+            self.read_tree_c(&mut reader)?;
+            self.read_tree_p(&mut reader)?;
+        }
+
+        let mut pos = 0;
+
+        while pos < output.len() {
+            let c = self.decode_c(&mut reader)?;
+
+            if c < 256 {
+                text[self.text_loc & bit_mask] = c as u8;
+                self.text_loc += 1;
+                output[pos] = c as u8;
+                pos += 1;
+            } else {
+                let count = c - OFFSET;
+                let position = self.decode_p(&mut reader)?;
+
+                if pos + count > output.len() {
+                    return Err(DecrunchError);
+                }
+
+                let mut i = self.text_loc.wrapping_sub(position + 1);
+
+                for _ in 0..count {
+                    let c = text[i & bit_mask];
+
+                    text[self.text_loc & bit_mask] = c;
+                    self.text_loc += 1;
+                    output[pos] = c;
+                    pos += 1;
+                    i = i.wrapping_add(1);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dms::bit_reader::tests::BitWriter;
+
+    /// Canonical codes matching the order in which `make_table` assigns
+    /// them.
+    fn canonical_codes(bit_len: &[u8]) -> Vec<(u32, u32)> {
+        let mut codes = vec![(0, 0); bit_len.len()];
+        let mut code = 0u32;
+
+        for len in 1..=16u32 {
+            for (c, l) in bit_len.iter().enumerate() {
+                if *l as u32 == len {
+                    codes[c] = (len, code);
+                    code += 1;
+                }
+            }
+            code <<= 1;
+        }
+        codes
+    }
+
+    fn write_code(writer: &mut BitWriter, codes: &[(u32, u32)], c: usize) {
+        let (len, code) = codes[c];
+
+        writer.write_bits(len, code);
+    }
+
+    fn write_position(
+        writer: &mut BitWriter,
+        codes: &[(u32, u32)],
+        position: usize,
+    ) {
+        let j = (usize::BITS - position.leading_zeros()) as usize;
+
+        write_code(writer, codes, j);
+        if j > 1 {
+            writer.write_bits(j as u32 - 1, position as u32);
+        }
+    }
+
+    #[test]
+    fn unpack_heavy_is_ok() {
+        let mut c_len = vec![0u8; NC];
+
+        // literals 'a'..'z' and match lengths 3..=10, with a few codes
+        // longer than the 12 bits lookup table
+        for (i, c) in (b'a'..=b'z').enumerate() {
+            c_len[c as usize] = 5 + (i%2) as u8;
+        }
+        for count in 3..=10 {
+            c_len[count + OFFSET] = 5;
+        }
+
+        // complete the code so that it is exhaustive: 13*2^-5 + 13*2^-6 +
+        // 8*2^-5 = 55/64, missing 2^-3 + 2^-7 + ... + 2^-13 + 2^-13
+        c_len[b' ' as usize] = 3;
+        for (i, c) in b".,;:-!?".iter().enumerate() {
+            c_len[*c as usize] = 7 + i as u8;
+        }
+        c_len[b'#' as usize] = 13;
+
+        let c_codes = canonical_codes(&c_len);
+
+        // code lengths of position bit counts, 0 to 13
+        let pt_len = [4u8, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 3, 3];
+        let pt_codes = canonical_codes(&pt_len);
+
+        let mut writer = BitWriter::default();
+        let n = c_len.iter().rposition(|l| *l != 0).unwrap() + 1;
+
+        writer.write_bits(9, n as u32);
+        for l in &c_len[..n] {
+            writer.write_bits(5, *l as u32);
+        }
+
+        writer.write_bits(5, pt_len.len() as u32);
+        for l in &pt_len {
+            writer.write_bits(4, *l as u32);
+        }
+
+        let mut expected = vec![];
+
+        for c in b"hello world.!?#, ok" {
+            write_code(&mut writer, &c_codes, *c as usize);
+            expected.push(*c);
+        }
+
+        for (count, position) in [(5, 13), (10, 0), (3, 2), (7, 6), (4, 1)] {
+            let start = expected.len() - position - 1;
+
+            write_code(&mut writer, &c_codes, count + OFFSET);
+            write_position(&mut writer, &pt_codes, position);
+
+            for i in 0..count {
+                expected.push(expected[start + i]);
+            }
+        }
+
+        let input = writer.finish();
+        let mut text = vec![0u8; 0x4000];
+        let mut output = vec![0u8; expected.len()];
+
+        Heavy::default().unpack(&mut text, &input, &mut output, 2).unwrap();
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn make_table_rejects_incomplete_codes() {
+        let bit_len = [1u8, 2, 0, 0];
+        let mut table = [0u16; 256];
+        let mut left = [0u16; 7];
+        let mut right = [0u16; 7];
+
+        assert!(make_table(&bit_len, 8, &mut table, &mut left, &mut right).is_err());
+    }
+}
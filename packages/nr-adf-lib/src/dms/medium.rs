@@ -0,0 +1,115 @@
+use super::bit_reader::*;
+use super::tables::*;
+use super::DecrunchError;
+
+const MEDIUM_BIT_MASK: usize = 0x3fff;
+
+pub(super) struct Medium {
+    text_loc: usize,
+}
+
+impl Default for Medium {
+    fn default() -> Self {
+        Self { text_loc: 0x3fbe }
+    }
+}
+
+impl Medium {
+    pub(super) fn unpack(
+        &mut self,
+        text: &mut [u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecrunchError> {
+        let mut reader = BitReader::new(input);
+        let mut pos = 0;
+
+        while pos < output.len() {
+            if reader.read_bits(1) != 0 {
+                let c = reader.read_bits(8) as u8;
+
+                text[self.text_loc & MEDIUM_BIT_MASK] = c;
+                self.text_loc += 1;
+                output[pos] = c;
+                pos += 1;
+            } else {
+                let mut c = reader.read_bits(8) as usize;
+                let count = D_CODE[c] as usize + 3;
+
+                let mut u = D_LEN[c] as u32;
+                c = ((c << u) | reader.read_bits(u) as usize) & 0xff;
+
+                u = D_LEN[c] as u32;
+                c = ((D_CODE[c] as usize) << 8)
+                    | (((c << u) | reader.read_bits(u) as usize) & 0xff);
+
+                if pos + count > output.len() {
+                    return Err(DecrunchError);
+                }
+
+                let mut i = self.text_loc.wrapping_sub(c + 1);
+
+                for _ in 0..count {
+                    let c = text[i & MEDIUM_BIT_MASK];
+
+                    text[self.text_loc & MEDIUM_BIT_MASK] = c;
+                    self.text_loc += 1;
+                    output[pos] = c;
+                    pos += 1;
+                    i = i.wrapping_add(1);
+                }
+            }
+        }
+
+        self.text_loc = (self.text_loc + 66) & MEDIUM_BIT_MASK;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dms::bit_reader::tests::BitWriter;
+    use crate::dms::tables::tests::write_position;
+
+    /// Encodes a match length (3 to 66) with the same prefix codes as the
+    /// upper bits of positions.
+    fn write_length(writer: &mut BitWriter, count: usize) {
+        let first = D_CODE.iter().position(|c| *c as usize == count - 3).unwrap();
+        let len = D_LEN[first] as u32;
+
+        writer.write_bits(len, first as u32 >> (8 - len));
+    }
+
+    #[test]
+    fn unpack_medium_is_ok() {
+        let mut writer = BitWriter::default();
+        let mut expected = vec![];
+
+        for c in b"hello " {
+            writer.write_bits(1, 1);
+            writer.write_bits(8, *c as u32);
+            expected.push(*c);
+        }
+
+        // repeat "hello " 5 times, then 20 bytes from far back in the window
+        writer.write_bits(1, 0);
+        write_length(&mut writer, 30);
+        write_position(&mut writer, 5);
+        expected.extend(b"hello ".repeat(5));
+
+        writer.write_bits(1, 0);
+        write_length(&mut writer, 40);
+        write_position(&mut writer, 0x1234);
+        expected.extend([0u8; 40]);
+
+        let input = writer.finish();
+        let mut text = vec![0u8; 0x4000];
+        let mut output = vec![0u8; expected.len()];
+
+        Medium::default().unpack(&mut text, &input, &mut output).unwrap();
+
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,466 @@
+//! Reader for DMS (Disk Masher System) archives.
+//!
+//! A DMS archive is a header followed by the disk tracks, each of them
+//! being packed with one of the NONE, SIMPLE, QUICK, MEDIUM, DEEP, HEAVY1
+//! or HEAVY2 compression modes. Decrunchers keep their state from one track
+//! to the next unless the track tells otherwise.
+
+mod bit_reader;
+mod crc;
+mod deep;
+mod heavy;
+mod medium;
+mod quick;
+mod rle;
+mod tables;
+
+use crate::disk::*;
+use crate::errors::*;
+
+use crc::*;
+use deep::*;
+use heavy::*;
+use medium::*;
+use quick::*;
+use rle::*;
+
+
+pub const DMS_MAGIC_NUMBER: &[u8; 4] = b"DMS!";
+
+const FILE_HEADER_SIZE: usize = 56;
+const TRACK_HEADER_SIZE: usize = 20;
+const TRACK_COUNT: usize = 80;
+const MAX_TRACK_DATA_SIZE: usize = 32000;
+
+const GENERAL_INFO_ENCRYPTED: u16 = 0x02;
+
+const TRACK_FLAG_KEEP_STATE: u8 = 0x01;
+
+const TEXT_SIZE: usize = 0x4000;
+
+/// Error raised by a decruncher on malformed packed data.
+#[derive(Debug)]
+pub(super) struct DecrunchError;
+
+/// Compression mode of a track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CompressionMode {
+    None,
+    Simple,
+    Quick,
+    Medium,
+    Deep,
+    Heavy1,
+    Heavy2,
+}
+
+impl TryFrom<u8> for CompressionMode {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CompressionMode::None),
+            1 => Ok(CompressionMode::Simple),
+            2 => Ok(CompressionMode::Quick),
+            3 => Ok(CompressionMode::Medium),
+            4 => Ok(CompressionMode::Deep),
+            5 => Ok(CompressionMode::Heavy1),
+            6 => Ok(CompressionMode::Heavy2),
+            _ => Err(Error::DmsUnknownCompressionModeError(value)),
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+/******************************************************************************
+* Track ***********************************************************************
+******************************************************************************/
+
+struct Track<'a> {
+    number: u16,
+    packed_len: usize,
+    unpacked_len: usize,
+    flags: u8,
+    mode: u8,
+    checksum: u16,
+    data: &'a [u8],
+}
+
+impl<'a> Track<'a> {
+    /// Parses the track at the beginning of `data`, returns it along with
+    /// the size it occupies in the archive.
+    fn parse(data: &'a [u8]) -> Result<(Self, usize), Error> {
+        if data.len() < TRACK_HEADER_SIZE || &data[..2] != b"TR" {
+            return Err(Error::DmsInvalidTrackHeaderError);
+        }
+
+        let header = &data[..TRACK_HEADER_SIZE];
+
+        if compute_crc(&header[..18]) != read_u16(header, 18) {
+            return Err(Error::DmsInvalidTrackHeaderError);
+        }
+
+        let number = read_u16(header, 2);
+        let data_len = read_u16(header, 6) as usize;
+        let packed_len = read_u16(header, 8) as usize;
+        let unpacked_len = read_u16(header, 10) as usize;
+
+        if data_len > MAX_TRACK_DATA_SIZE
+        || packed_len > MAX_TRACK_DATA_SIZE
+        || unpacked_len > MAX_TRACK_DATA_SIZE {
+            return Err(Error::DmsInvalidTrackHeaderError);
+        }
+
+        let data = data
+            .get(TRACK_HEADER_SIZE..TRACK_HEADER_SIZE + data_len)
+            .ok_or(Error::CorruptedImageFile)?;
+
+        if compute_crc(data) != read_u16(header, 16) {
+            return Err(Error::DmsTrackCrcError(number as usize));
+        }
+
+        let track = Track {
+            number,
+            packed_len,
+            unpacked_len,
+            flags: header[12],
+            mode: header[13],
+            checksum: read_u16(header, 14),
+            data,
+        };
+
+        Ok((track, TRACK_HEADER_SIZE + data_len))
+    }
+}
+
+/******************************************************************************
+* Decruncher ******************************************************************
+******************************************************************************/
+
+struct Decruncher {
+    text: Vec<u8>,
+    quick: Quick,
+    medium: Medium,
+    deep: Deep,
+    heavy: Heavy,
+}
+
+impl Default for Decruncher {
+    fn default() -> Self {
+        Self {
+            text: vec![0; TEXT_SIZE],
+            quick: Quick::default(),
+            medium: Medium::default(),
+            deep: Deep::default(),
+            heavy: Heavy::default(),
+        }
+    }
+}
+
+impl Decruncher {
+    fn reset(&mut self) {
+        self.text.fill(0);
+        self.quick = Quick::default();
+        self.medium = Medium::default();
+        self.deep = Deep::default();
+        self.heavy.reset();
+    }
+
+    fn unpack_track(
+        &mut self,
+        track: &Track,
+    ) -> Result<Vec<u8>, Error> {
+        let mode = CompressionMode::try_from(track.mode)?;
+        let mut output = vec![0u8; track.unpacked_len];
+        let mut packed = vec![0u8; track.packed_len];
+
+        let res = match mode {
+            CompressionMode::None => {
+                if track.data.len() < output.len() {
+                    Err(DecrunchError)
+                } else {
+                    output.copy_from_slice(&track.data[..track.unpacked_len]);
+                    Ok(())
+                }
+            },
+            CompressionMode::Simple => {
+                unpack_rle(track.data, &mut output)
+            },
+            CompressionMode::Quick => {
+                self.quick.unpack(&mut self.text, track.data, &mut packed)
+                    .and_then(|_| unpack_rle(&packed, &mut output))
+            },
+            CompressionMode::Medium => {
+                self.medium.unpack(&mut self.text, track.data, &mut packed)
+                    .and_then(|_| unpack_rle(&packed, &mut output))
+            },
+            CompressionMode::Deep => {
+                self.deep.unpack(&mut self.text, track.data, &mut packed)
+                    .and_then(|_| unpack_rle(&packed, &mut output))
+            },
+            CompressionMode::Heavy1 | CompressionMode::Heavy2 => {
+                let flags = if mode == CompressionMode::Heavy1 {
+                    track.flags & 0x07
+                } else {
+                    track.flags | 0x08
+                };
+
+                self.heavy.unpack(&mut self.text, track.data, &mut packed, flags)
+                    .and_then(|_| {
+                        if flags & 0x04 != 0 {
+                            unpack_rle(&packed, &mut output)
+                        } else if packed.len() < output.len() {
+                            Err(DecrunchError)
+                        } else {
+                            output.copy_from_slice(&packed[..track.unpacked_len]);
+                            Ok(())
+                        }
+                    })
+            },
+        };
+
+        if track.flags & TRACK_FLAG_KEEP_STATE == 0 {
+            self.reset();
+        }
+
+        res.map_err(|_| Error::DmsTrackDecrunchError(track.number as usize))?;
+
+        if compute_checksum(&output) != track.checksum {
+            return Err(Error::DmsTrackChecksumError(track.number as usize));
+        }
+
+        Ok(output)
+    }
+}
+
+/******************************************************************************
+* Archive *********************************************************************
+******************************************************************************/
+
+/// Tells if the given data looks like a DMS archive.
+pub fn is_dms(data: &[u8]) -> bool {
+    data.starts_with(DMS_MAGIC_NUMBER)
+}
+
+/// Unpacks a DMS archive, returning the raw disk data.
+/// Tracks missing from the archive are left zeroed.
+pub fn unpack(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !is_dms(data) || data.len() < FILE_HEADER_SIZE {
+        return Err(Error::DmsInvalidHeaderError);
+    }
+
+    let header = &data[..FILE_HEADER_SIZE];
+
+    if compute_crc(&header[4..54]) != read_u16(header, 54) {
+        return Err(Error::DmsInvalidHeaderError);
+    }
+
+    if read_u16(header, 10) & GENERAL_INFO_ENCRYPTED != 0 {
+        return Err(Error::DmsEncryptedArchiveError);
+    }
+
+    let mut decruncher = Decruncher::default();
+    let mut tracks = vec![];
+    let mut offset = FILE_HEADER_SIZE;
+
+    while offset < data.len() {
+        let (track, len) = Track::parse(&data[offset..])?;
+
+        offset += len;
+
+        // skip banners, FILE_ID.DIZ and fake boot blocks
+        if (track.number as usize) < TRACK_COUNT && track.unpacked_len > 2048 {
+            let track_data = decruncher.unpack_track(&track)?;
+
+            tracks.push((track.number as usize, track_data));
+        }
+    }
+
+    let track_size = tracks
+        .iter()
+        .map(|(_, track_data)| track_data.len())
+        .max()
+        .ok_or(Error::CorruptedImageFile)?;
+
+    let disk_size = TRACK_COUNT*track_size;
+
+    if disk_size != DiskType::DoubleDensity.size()
+    && disk_size != DiskType::HighDensity.size() {
+        return Err(Error::DiskInvalidSizeError(disk_size));
+    }
+
+    let mut disk_data = vec![0u8; disk_size];
+
+    for (number, track_data) in tracks {
+        let offset = number*track_size;
+
+        disk_data[offset..offset + track_data.len()].copy_from_slice(&track_data);
+    }
+
+    Ok(disk_data)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bit_reader::tests::BitWriter;
+
+    fn pack_rle(data: &[u8]) -> Vec<u8> {
+        let mut packed = vec![];
+        let mut i = 0;
+
+        while i < data.len() {
+            let a = data[i];
+            let n = data[i..].iter().take(0xffff).take_while(|b| **b == a).count();
+
+            if n > 3 {
+                packed.push(0x90);
+                if n < 0xff {
+                    packed.extend([n as u8, a]);
+                } else {
+                    packed.extend([0xff, a]);
+                    packed.extend((n as u16).to_be_bytes());
+                }
+                i += n;
+            } else {
+                packed.push(a);
+                if a == 0x90 {
+                    packed.push(0);
+                }
+                i += 1;
+            }
+        }
+        packed
+    }
+
+    fn pack_quick(data: &[u8]) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+
+        for c in data {
+            writer.write_bits(1, 1);
+            writer.write_bits(8, *c as u32);
+        }
+        writer.finish()
+    }
+
+    fn pack_track(number: u16, mode: u8, unpacked: &[u8]) -> Vec<u8> {
+        let (data, packed_len) = match mode {
+            0 => (Vec::from(unpacked), unpacked.len()),
+            1 => {
+                let data = pack_rle(unpacked);
+                let len = data.len();
+                (data, len)
+            },
+            _ => {
+                let rle = pack_rle(unpacked);
+                (pack_quick(&rle), rle.len())
+            },
+        };
+
+        let mut header = vec![0u8; TRACK_HEADER_SIZE];
+
+        header[..2].copy_from_slice(b"TR");
+        header[2..4].copy_from_slice(&number.to_be_bytes());
+        header[6..8].copy_from_slice(&(data.len() as u16).to_be_bytes());
+        header[8..10].copy_from_slice(&(packed_len as u16).to_be_bytes());
+        header[10..12].copy_from_slice(&(unpacked.len() as u16).to_be_bytes());
+        header[13] = mode;
+        header[14..16].copy_from_slice(&compute_checksum(unpacked).to_be_bytes());
+        header[16..18].copy_from_slice(&compute_crc(&data).to_be_bytes());
+
+        let crc = compute_crc(&header[..18]);
+
+        header[18..20].copy_from_slice(&crc.to_be_bytes());
+        header.extend(data);
+        header
+    }
+
+    /// Packs raw disk data in a DMS archive, cycling over the NONE, SIMPLE
+    /// and QUICK modes.
+    pub(crate) fn pack(disk_data: &[u8]) -> Vec<u8> {
+        let track_size = disk_data.len()/TRACK_COUNT;
+        let mut header = vec![0u8; FILE_HEADER_SIZE];
+
+        header[..4].copy_from_slice(DMS_MAGIC_NUMBER);
+        header[18..20].copy_from_slice(&(TRACK_COUNT as u16 - 1).to_be_bytes());
+
+        let crc = compute_crc(&header[4..54]);
+
+        header[54..56].copy_from_slice(&crc.to_be_bytes());
+
+        // a banner, which is not part of the disk
+        header.extend(pack_track(0xffff, 1, b"packed by nr-adf"));
+
+        for (number, track) in disk_data.chunks(track_size).enumerate() {
+            header.extend(pack_track(number as u16, (number%3) as u8, track));
+        }
+        header
+    }
+
+    fn disk_data() -> Vec<u8> {
+        (0..DiskType::DoubleDensity.size())
+            .map(|i| if (i/4096)%2 == 0 { 0 } else { (i%0x9b) as u8 })
+            .collect()
+    }
+
+    #[test]
+    fn unpack_is_ok() {
+        let data = disk_data();
+
+        assert_eq!(unpack(&pack(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn unpack_encrypted_archive_fails() {
+        let mut archive = pack(&disk_data());
+
+        archive[11] |= GENERAL_INFO_ENCRYPTED as u8;
+
+        let crc = compute_crc(&archive[4..54]);
+
+        archive[54..56].copy_from_slice(&crc.to_be_bytes());
+
+        assert_eq!(unpack(&archive), Err(Error::DmsEncryptedArchiveError));
+    }
+
+    #[test]
+    fn unpack_corrupted_header_fails() {
+        let mut archive = pack(&disk_data());
+
+        archive[20] ^= 0xff;
+
+        assert_eq!(unpack(&archive), Err(Error::DmsInvalidHeaderError));
+    }
+
+    #[test]
+    fn unpack_corrupted_track_fails() {
+        let mut archive = pack(&disk_data());
+        let len = archive.len();
+
+        archive[len - 1] ^= 0xff;
+
+        assert_eq!(unpack(&archive), Err(Error::DmsTrackCrcError(79)));
+    }
+
+    #[test]
+    fn unpack_bad_checksum_fails() {
+        let data = disk_data();
+        let mut archive = vec![];
+
+        archive.extend(&pack(&data)[..FILE_HEADER_SIZE]);
+
+        let mut track = pack_track(0, 0, &data[..11264]);
+
+        track[15] ^= 0xff;
+
+        let crc = compute_crc(&track[..18]);
+
+        track[18..20].copy_from_slice(&crc.to_be_bytes());
+        archive.extend(track);
+
+        assert_eq!(unpack(&archive), Err(Error::DmsTrackChecksumError(0)));
+    }
+}
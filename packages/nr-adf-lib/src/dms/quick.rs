@@ -0,0 +1,88 @@
+use super::bit_reader::*;
+use super::DecrunchError;
+
+const QUICK_BIT_MASK: usize = 0xff;
+
+pub(super) struct Quick {
+    text_loc: usize,
+}
+
+impl Default for Quick {
+    fn default() -> Self {
+        Self { text_loc: 251 }
+    }
+}
+
+impl Quick {
+    pub(super) fn unpack(
+        &mut self,
+        text: &mut [u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecrunchError> {
+        let mut reader = BitReader::new(input);
+        let mut pos = 0;
+
+        while pos < output.len() {
+            if reader.read_bits(1) != 0 {
+                let c = reader.read_bits(8) as u8;
+
+                text[self.text_loc & QUICK_BIT_MASK] = c;
+                self.text_loc += 1;
+                output[pos] = c;
+                pos += 1;
+            } else {
+                let count = reader.read_bits(2) as usize + 2;
+                let offset = reader.read_bits(8) as usize;
+
+                if pos + count > output.len() {
+                    return Err(DecrunchError);
+                }
+
+                let mut i = self.text_loc.wrapping_sub(offset + 1);
+
+                for _ in 0..count {
+                    let c = text[i & QUICK_BIT_MASK];
+
+                    text[self.text_loc & QUICK_BIT_MASK] = c;
+                    self.text_loc += 1;
+                    output[pos] = c;
+                    pos += 1;
+                    i = i.wrapping_add(1);
+                }
+            }
+        }
+
+        self.text_loc = (self.text_loc + 5) & QUICK_BIT_MASK;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dms::bit_reader::tests::BitWriter;
+
+    #[test]
+    fn unpack_quick_is_ok() {
+        let mut writer = BitWriter::default();
+
+        // "ab" then copy 5 bytes from 2 bytes back
+        writer.write_bits(1, 1);
+        writer.write_bits(8, b'a' as u32);
+        writer.write_bits(1, 1);
+        writer.write_bits(8, b'b' as u32);
+        writer.write_bits(1, 0);
+        writer.write_bits(2, 3);
+        writer.write_bits(8, 1);
+
+        let input = writer.finish();
+        let mut text = vec![0u8; 0x4000];
+        let mut output = [0u8; 7];
+
+        Quick::default().unpack(&mut text, &input, &mut output).unwrap();
+
+        assert_eq!(&output, b"abababa");
+    }
+}
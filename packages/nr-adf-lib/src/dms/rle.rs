@@ -0,0 +1,71 @@
+use super::DecrunchError;
+
+const RLE_MARKER: u8 = 0x90;
+
+pub(super) fn unpack_rle(
+    input: &[u8],
+    output: &mut [u8],
+) -> Result<(), DecrunchError> {
+    let mut input = input.iter().copied();
+    let mut next = || input.next().ok_or(DecrunchError);
+    let mut pos = 0;
+
+    while pos < output.len() {
+        let a = next()?;
+
+        if a != RLE_MARKER {
+            output[pos] = a;
+            pos += 1;
+            continue;
+        }
+
+        let b = next()?;
+
+        if b == 0 {
+            output[pos] = a;
+            pos += 1;
+            continue;
+        }
+
+        let a = next()?;
+        let n = if b == 0xff {
+            ((next()? as usize) << 8) | next()? as usize
+        } else {
+            b as usize
+        };
+
+        if pos + n > output.len() {
+            return Err(DecrunchError);
+        }
+
+        output[pos..pos + n].fill(a);
+        pos += n;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_rle_is_ok() {
+        let input = [1, 0x90, 0, 0x90, 4, 7, 0x90, 0xff, 0x42, 0x01, 0x00, 2];
+        let mut output = [0u8; 263];
+
+        unpack_rle(&input, &mut output).unwrap();
+
+        assert_eq!(&output[..6], &[1, 0x90, 7, 7, 7, 7]);
+        assert!(output[6..262].iter().all(|b| *b == 0x42));
+        assert_eq!(output[262], 2);
+    }
+
+    #[test]
+    fn unpack_rle_overflow_fails() {
+        let input = [0x90, 4, 7];
+        let mut output = [0u8; 3];
+
+        assert!(unpack_rle(&input, &mut output).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+// Static Huffman tables used to decode the upper 6 bits of match positions,
+// as found in LZHUF.
+
+pub(super) const D_CODE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        table[i] = match i {
+            0..=31 => 0,
+            32..=79 => 1 + (i - 32)/16,
+            80..=143 => 4 + (i - 80)/8,
+            144..=191 => 12 + (i - 144)/4,
+            192..=239 => 24 + (i - 192)/2,
+            _ => 48 + (i - 240),
+        } as u8;
+        i += 1;
+    }
+    table
+};
+
+pub(super) const D_LEN: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        table[i] = match i {
+            0..=31 => 3,
+            32..=79 => 4,
+            80..=143 => 5,
+            144..=191 => 6,
+            192..=239 => 7,
+            _ => 8,
+        };
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::dms::bit_reader::tests::BitWriter;
+
+    /// Encodes a 14 bits position the way `D_CODE`/`D_LEN` decode it.
+    pub(in crate::dms) fn write_position(writer: &mut BitWriter, position: usize) {
+        let upper = (position >> 8) as u8;
+        let lower = (position & 0xff) as u32;
+
+        let first = D_CODE.iter().position(|c| *c == upper).unwrap();
+        let len = D_LEN[first] as u32;
+
+        writer.write_bits(len, first as u32 >> (8 - len));
+        writer.write_bits(8, lower);
+    }
+}
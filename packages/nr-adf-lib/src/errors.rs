@@ -5,17 +5,30 @@ pub enum Error {
     BadFileDescriptor,
 
     CorruptedImageFile,
+    /// The boot block doesn't hold a DOS filesystem signature, e.g. a game
+    /// disk using its own trackloader. Carries the first 4 bytes of the disk.
+    NotADosDisk([u8; 4]),
 
     InvalidDataBlockIndexError(usize),
     InvalidHashError(usize),
     InvalidNameError,
     InvalidNameLengthError(usize),
+    InvalidCommentLengthError(usize),
     InvalidPathError,
+    InvalidRenameError,
+    InvalidSeekPositionError,
     InvalidStringError,
     InvalidFileModeError,
     InvalidFilesystemTypeError,
+    InvalidImageFormatError,
     InvalidCacheModeError,
     InvalidInternationalModeError,
+    InvalidLongNameModeError,
+    InvalidDosTypeError,
+    InvalidNameEncodingModeError,
+    InvalidProtectionFlagsError,
+    InvalidBootCodeError,
+    InvalidBootCodeSizeError(usize),
 
     FileEOF,
 
@@ -29,15 +42,48 @@ pub enum Error {
 
     NotADirectoryError,
     NotAFileError,
+    NotASoftLinkError,
     NotFoundError,
 
     NotEmptyError,
 
+    SoftLinkLoopError,
+
     NoSpaceLeft,
 
     DiskInvalidLBAAddressError(usize),
     DiskInvalidBlockOffsetError(usize),
     DiskInvalidSizeError(usize),
+    DiskInvalidTrackError(usize),
+    DiskUnknownGeometryError,
+    DiskIoError(std::io::ErrorKind),
+    /// The disk image was opened read only.
+    DiskReadOnlyError,
+
+    ReadOnlyImageFormatError,
+
+    DmsInvalidHeaderError,
+    DmsInvalidTrackHeaderError,
+    DmsEncryptedArchiveError,
+    DmsUnknownCompressionModeError(u8),
+    DmsTrackCrcError(usize),
+    DmsTrackChecksumError(usize),
+    DmsTrackDecrunchError(usize),
+
+    ExtAdfInvalidHeaderError,
+    ExtAdfInvalidTrackHeaderError(usize),
+
+    ScpInvalidHeaderError,
+    ScpInvalidTrackHeaderError(usize),
+
+    IpfInvalidHeaderError,
+    IpfInvalidRecordError(usize),
+    IpfInvalidTrackError(usize),
+
+    RdbNotFoundError,
+    RdbInvalidBlockError(usize),
+    RdbInvalidLayoutError,
+    RdbUnsupportedBlockSizeError(usize),
 }
 
 impl std::error::Error for Error {}
@@ -46,3 +92,90 @@ impl fmt::Display for Error {
         write!(f, "{:?}", self)
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::DiskIoError(err.kind())
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        use std::io::ErrorKind;
+
+        let kind = match err {
+            Error::NotFoundError => ErrorKind::NotFound,
+            Error::AlreadyExists => ErrorKind::AlreadyExists,
+
+            Error::BadFileDescriptor => ErrorKind::PermissionDenied,
+
+            Error::NotADirectoryError => ErrorKind::NotADirectory,
+            Error::NotAFileError => ErrorKind::IsADirectory,
+            Error::NotEmptyError => ErrorKind::DirectoryNotEmpty,
+
+            Error::NoSpaceLeft => ErrorKind::StorageFull,
+
+            Error::FileEOF => ErrorKind::UnexpectedEof,
+
+            Error::DiskIoError(kind) => kind,
+            Error::DiskReadOnlyError => ErrorKind::ReadOnlyFilesystem,
+
+            Error::ReadOnlyImageFormatError |
+            Error::DmsEncryptedArchiveError |
+            Error::RdbUnsupportedBlockSizeError(_) => ErrorKind::Unsupported,
+
+            Error::InvalidNameError |
+            Error::InvalidNameLengthError(_) |
+            Error::InvalidCommentLengthError(_) |
+            Error::InvalidPathError |
+            Error::InvalidRenameError |
+            Error::InvalidSeekPositionError |
+            Error::InvalidFileModeError |
+            Error::InvalidFilesystemTypeError |
+            Error::InvalidImageFormatError |
+            Error::InvalidCacheModeError |
+            Error::InvalidInternationalModeError |
+            Error::InvalidLongNameModeError |
+            Error::InvalidDosTypeError |
+            Error::InvalidNameEncodingModeError |
+            Error::InvalidProtectionFlagsError |
+            Error::InvalidBootCodeError |
+            Error::InvalidBootCodeSizeError(_) |
+            Error::NotASoftLinkError |
+            Error::SoftLinkLoopError |
+            Error::DiskInvalidTrackError(_) |
+            Error::DiskUnknownGeometryError |
+            Error::RdbInvalidLayoutError => ErrorKind::InvalidInput,
+
+            Error::CorruptedImageFile |
+            Error::NotADosDisk(_) |
+            Error::InvalidDataBlockIndexError(_) |
+            Error::InvalidHashError(_) |
+            Error::InvalidStringError |
+            Error::InvalidFilesystemBlockPrimaryTypeError(_) |
+            Error::UnexpectedFilesystemBlockPrimaryTypeError(_) |
+            Error::InvalidFilesystemBlockSecondaryTypeError(_) |
+            Error::UnexpectedFilesystemBlockSecondaryTypeError(_) |
+            Error::DiskInvalidLBAAddressError(_) |
+            Error::DiskInvalidBlockOffsetError(_) |
+            Error::DiskInvalidSizeError(_) |
+            Error::DmsInvalidHeaderError |
+            Error::DmsInvalidTrackHeaderError |
+            Error::DmsUnknownCompressionModeError(_) |
+            Error::DmsTrackCrcError(_) |
+            Error::DmsTrackChecksumError(_) |
+            Error::DmsTrackDecrunchError(_) |
+            Error::ExtAdfInvalidHeaderError |
+            Error::ExtAdfInvalidTrackHeaderError(_) |
+            Error::ScpInvalidHeaderError |
+            Error::ScpInvalidTrackHeaderError(_) |
+            Error::IpfInvalidHeaderError |
+            Error::IpfInvalidRecordError(_) |
+            Error::IpfInvalidTrackError(_) |
+            Error::RdbNotFoundError |
+            Error::RdbInvalidBlockError(_) => ErrorKind::InvalidData,
+        };
+
+        std::io::Error::new(kind, err)
+    }
+}
@@ -0,0 +1,357 @@
+//! Reader and writer for WinUAE extended ADF ("UAE-1ADF") images.
+//!
+//! An extended ADF is a header giving the number of tracks, followed by a
+//! 12 bytes descriptor per track and then the tracks data. A track is either
+//! stored as its decoded AmigaDOS sectors or as a raw MFM bitstream, which is
+//! what copy protected and long tracks need.
+//!
+//! Raw tracks holding AmigaDOS sectors are decoded into the disk data. Raw
+//! tracks are written back unchanged unless their sectors have been
+//! modified, in which case they are replaced by standard tracks.
+
+use crate::disk::*;
+use crate::errors::*;
+use crate::mfm::*;
+
+
+pub const EXT_ADF_MAGIC_NUMBER: &[u8; 8] = b"UAE-1ADF";
+
+const FILE_HEADER_SIZE: usize = 12;
+const TRACK_HEADER_SIZE: usize = 12;
+
+const TRACK_TYPE_AMIGADOS: u16 = 0;
+const TRACK_TYPE_RAW: u16 = 1;
+
+// raw tracks longer than this are high density ones
+const DD_MAX_RAW_TRACK_SIZE: usize = 16384;
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/******************************************************************************
+* Track ***********************************************************************
+******************************************************************************/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TrackData {
+    /// Decoded AmigaDOS sectors.
+    AmigaDos,
+    /// MFM bitstream, along with the sectors decoded from it.
+    Raw {
+        mfm: Vec<u8>,
+        bit_length: usize,
+        decoded: DecodedTrack,
+    },
+}
+
+/// A track of an extended ADF image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedAdfTrack {
+    data: TrackData,
+    sector_count: usize,
+}
+
+impl ExtendedAdfTrack {
+    /// Tells if the track is stored as a raw MFM bitstream.
+    pub fn is_raw(&self) -> bool {
+        matches!(self.data, TrackData::Raw { .. })
+    }
+
+    /// Returns the status of each sector of the track, as decoded when the
+    /// image was read.
+    pub fn sectors(&self) -> Vec<SectorStatus> {
+        match &self.data {
+            TrackData::AmigaDos => vec![SectorStatus::Ok; self.sector_count],
+            TrackData::Raw { decoded, .. } => decoded.sectors.clone(),
+        }
+    }
+
+    /// Tells if all the sectors of the track are readable AmigaDOS sectors.
+    pub fn is_ok(&self) -> bool {
+        match &self.data {
+            TrackData::AmigaDos => true,
+            TrackData::Raw { decoded, .. } => decoded.is_ok(),
+        }
+    }
+}
+
+/******************************************************************************
+* Image ***********************************************************************
+******************************************************************************/
+
+/// An extended ADF image, see the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedAdf {
+    tracks: Vec<ExtendedAdfTrack>,
+    sector_count: usize,
+}
+
+/// Tells if the given data looks like an extended ADF image.
+pub fn is_extended_adf(data: &[u8]) -> bool {
+    data.starts_with(EXT_ADF_MAGIC_NUMBER)
+}
+
+impl ExtendedAdf {
+    /// Parses an extended ADF image, returns its tracks along with the disk
+    /// data decoded from them, the sectors of damaged tracks which couldn't
+    /// be decoded being zeroed.
+    pub fn decode(data: &[u8]) -> Result<(Self, Vec<u8>), Error> {
+        if !is_extended_adf(data) || data.len() < FILE_HEADER_SIZE {
+            return Err(Error::ExtAdfInvalidHeaderError);
+        }
+
+        let track_count = read_u16(data, 10) as usize;
+        let mut offset = FILE_HEADER_SIZE + track_count*TRACK_HEADER_SIZE;
+
+        if track_count == 0 || data.len() < offset {
+            return Err(Error::ExtAdfInvalidHeaderError);
+        }
+
+        let mut raw_tracks = Vec::with_capacity(track_count);
+
+        for track in 0..track_count {
+            let header = &data[FILE_HEADER_SIZE + track*TRACK_HEADER_SIZE..];
+            let track_type = read_u16(header, 2);
+            let size = read_u32(header, 4) as usize;
+            let bit_length = read_u32(header, 8) as usize;
+
+            let track_data = data
+                .get(offset..offset + size)
+                .ok_or(Error::ExtAdfInvalidTrackHeaderError(track))?;
+
+            if track_type != TRACK_TYPE_AMIGADOS && track_type != TRACK_TYPE_RAW
+            || bit_length > 8*size {
+                return Err(Error::ExtAdfInvalidTrackHeaderError(track));
+            }
+
+            raw_tracks.push((track_type, track_data, bit_length));
+            offset += size;
+        }
+
+        let sector_count = Self::guess_sector_count(&raw_tracks);
+        let track_size = sector_count*BLOCK_SIZE;
+        let mut tracks = Vec::with_capacity(track_count);
+        let mut disk_data = Vec::with_capacity(track_count*track_size);
+
+        for (track, (track_type, track_data, bit_length)) in raw_tracks.into_iter().enumerate() {
+            let data = if track_type == TRACK_TYPE_AMIGADOS {
+                if track_data.len() != track_size {
+                    return Err(Error::ExtAdfInvalidTrackHeaderError(track));
+                }
+
+                disk_data.extend_from_slice(track_data);
+                TrackData::AmigaDos
+            } else {
+                let decoded = decode_track(track_data, bit_length, track, sector_count);
+
+                disk_data.extend_from_slice(&decoded.data);
+                TrackData::Raw {
+                    mfm: Vec::from(track_data),
+                    bit_length,
+                    decoded,
+                }
+            };
+
+            tracks.push(ExtendedAdfTrack {
+                data,
+                sector_count,
+            });
+        }
+
+        let image = Self {
+            tracks,
+            sector_count,
+        };
+
+        Ok((image, disk_data))
+    }
+
+    /// Creates an image made of AmigaDOS tracks only from raw disk data.
+    pub fn from_disk_data(disk_data: &[u8]) -> Result<Self, Error> {
+        let geometry = DiskType::from_block_count(disk_data.len()/BLOCK_SIZE)
+            .geometry()
+            .filter(|_| disk_data.len().is_multiple_of(BLOCK_SIZE))
+            .ok_or(Error::DiskInvalidSizeError(disk_data.len()))?;
+
+        let track = ExtendedAdfTrack {
+            data: TrackData::AmigaDos,
+            sector_count: geometry.sectors,
+        };
+
+        Ok(Self {
+            tracks: vec![track; geometry.track_count()],
+            sector_count: geometry.sectors,
+        })
+    }
+
+    pub fn tracks(&self) -> &[ExtendedAdfTrack] {
+        &self.tracks
+    }
+
+    /// Returns the size of the disk data the image holds.
+    pub fn disk_size(&self) -> usize {
+        self.tracks.len()*self.sector_count*BLOCK_SIZE
+    }
+
+    /// Encodes the given disk data as an extended ADF image. Raw tracks are
+    /// kept as long as their sectors are the ones decoded from them.
+    /// Errors:
+    /// - When the size of `disk_data` isn't the size of the image.
+    pub fn encode(&self, disk_data: &[u8]) -> Result<Vec<u8>, Error> {
+        if disk_data.len() != self.disk_size() {
+            return Err(Error::DiskInvalidSizeError(disk_data.len()));
+        }
+
+        let track_size = self.sector_count*BLOCK_SIZE;
+        let mut headers = Vec::with_capacity(FILE_HEADER_SIZE + self.tracks.len()*TRACK_HEADER_SIZE);
+        let mut data = Vec::with_capacity(disk_data.len());
+
+        headers.extend_from_slice(EXT_ADF_MAGIC_NUMBER);
+        headers.extend_from_slice(&[0, 0]);
+        headers.extend_from_slice(&(self.tracks.len() as u16).to_be_bytes());
+
+        for (track, sectors) in self.tracks.iter().zip(disk_data.chunks_exact(track_size)) {
+            let (track_type, track_data, bit_length) = match &track.data {
+                TrackData::Raw { mfm, bit_length, decoded } if decoded.data == sectors => {
+                    (TRACK_TYPE_RAW, mfm.as_slice(), *bit_length)
+                },
+                _ => (TRACK_TYPE_AMIGADOS, sectors, 8*sectors.len()),
+            };
+
+            headers.extend_from_slice(&[0, 0]);
+            headers.extend_from_slice(&track_type.to_be_bytes());
+            headers.extend_from_slice(&(track_data.len() as u32).to_be_bytes());
+            headers.extend_from_slice(&(bit_length as u32).to_be_bytes());
+            data.extend_from_slice(track_data);
+        }
+
+        headers.extend(data);
+        Ok(headers)
+    }
+
+    // AmigaDOS tracks tell the density, raw ones are twice as long on high
+    // density disks
+    fn guess_sector_count(tracks: &[(u16, &[u8], usize)]) -> usize {
+        let amigados_track_size = tracks.iter()
+            .find(|(track_type, ..)| *track_type == TRACK_TYPE_AMIGADOS)
+            .map(|(_, data, _)| data.len());
+
+        match amigados_track_size {
+            Some(size) if size == HD_TRACK_SECTORS*BLOCK_SIZE => HD_TRACK_SECTORS,
+            Some(_) => DD_TRACK_SECTORS,
+            None => {
+                let max_size = tracks.iter()
+                    .map(|(_, data, _)| data.len())
+                    .max()
+                    .unwrap_or_default();
+
+                if max_size > DD_MAX_RAW_TRACK_SIZE {
+                    HD_TRACK_SECTORS
+                } else {
+                    DD_TRACK_SECTORS
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk_data() -> Vec<u8> {
+        (0..DD_BLOCK_COUNT*BLOCK_SIZE).map(|i| (i%249) as u8).collect()
+    }
+
+    #[test]
+    fn amigados_tracks_round_trip() {
+        let data = disk_data();
+        let image = ExtendedAdf::from_disk_data(&data).unwrap();
+        let encoded = image.encode(&data).unwrap();
+
+        assert!(is_extended_adf(&encoded));
+        assert_eq!(encoded.len(), FILE_HEADER_SIZE + 160*TRACK_HEADER_SIZE + data.len());
+
+        let (decoded, decoded_data) = ExtendedAdf::decode(&encoded).unwrap();
+
+        assert_eq!(decoded, image);
+        assert_eq!(decoded_data, data);
+        assert!(decoded.tracks().iter().all(|track| track.is_ok() && !track.is_raw()));
+        assert_eq!(
+            ExtendedAdf::from_disk_data(&[0; 1000]),
+            Err(Error::DiskInvalidSizeError(1000)),
+        );
+    }
+
+    #[test]
+    fn raw_tracks_are_kept() {
+        let data = disk_data();
+        let image = ExtendedAdf::from_disk_data(&data).unwrap();
+        let mut encoded = image.encode(&data).unwrap();
+
+        // replace the two last tracks by raw ones, the last one being
+        // damaged
+        let track_size = DD_TRACK_SECTORS*BLOCK_SIZE;
+
+        encoded.truncate(encoded.len() - 2*track_size);
+
+        for track in [158, 159] {
+            let mut mfm = encode_track(track, &data[track*track_size..(track + 1)*track_size]).unwrap();
+
+            if track == 159 {
+                mfm[1088 + 600] ^= 0x10;
+            }
+
+            let header = &mut encoded[FILE_HEADER_SIZE + track*TRACK_HEADER_SIZE..];
+
+            header[2..4].copy_from_slice(&TRACK_TYPE_RAW.to_be_bytes());
+            header[4..8].copy_from_slice(&(mfm.len() as u32).to_be_bytes());
+            header[8..12].copy_from_slice(&(8*mfm.len() as u32).to_be_bytes());
+            encoded.extend(mfm);
+        }
+
+        let (image, decoded_data) = ExtendedAdf::decode(&encoded).unwrap();
+        let tracks = image.tracks();
+
+        assert!(tracks[158].is_raw() && tracks[158].is_ok());
+        assert!(tracks[159].is_raw() && !tracks[159].is_ok());
+        assert_eq!(tracks[159].sectors()[1], SectorStatus::DataChecksumError);
+        assert_eq!(decoded_data[..159*track_size], data[..159*track_size]);
+
+        // written back unchanged
+        assert_eq!(image.encode(&decoded_data).unwrap(), encoded);
+
+        // a modified raw track becomes an AmigaDOS track
+        let mut data = decoded_data;
+
+        data[158*track_size] ^= 0xff;
+
+        let (image, _) = ExtendedAdf::decode(&image.encode(&data).unwrap()).unwrap();
+
+        assert!(!image.tracks()[158].is_raw());
+        assert!(image.tracks()[159].is_raw());
+    }
+
+    #[test]
+    fn invalid_image_fails() {
+        assert_eq!(
+            ExtendedAdf::decode(b"UAE-1ADF").unwrap_err(),
+            Error::ExtAdfInvalidHeaderError,
+        );
+
+        let data = disk_data();
+        let mut encoded = ExtendedAdf::from_disk_data(&data).unwrap().encode(&data).unwrap();
+
+        encoded.truncate(encoded.len() - 1);
+
+        assert_eq!(
+            ExtendedAdf::decode(&encoded).unwrap_err(),
+            Error::ExtAdfInvalidTrackHeaderError(159),
+        );
+    }
+}
@@ -0,0 +1,412 @@
+//! IPF (Interchangeable Preservation Format) images of the Software
+//! Preservation Society.
+//!
+//! An IPF image is a sequence of records, each starting with its type, its
+//! length and its CRC-32. An IMGE record describes a track and refers to
+//! the DATA record holding its blocks. Each block is a stream of sync, data
+//! and raw elements followed by a gap, data elements being stored decoded
+//! and raw ones as MFM cells.
+
+use std::collections::HashMap;
+
+use flate2::Crc;
+
+use crate::errors::*;
+
+use super::*;
+
+
+pub const IPF_MAGIC_NUMBER: &[u8; 4] = b"CAPS";
+
+const RECORD_HEADER_SIZE: usize = 12;
+const IMGE_RECORD_SIZE: usize = 80;
+const DATA_RECORD_SIZE: usize = 28;
+const BLOCK_DESCRIPTOR_SIZE: usize = 32;
+
+const DENSITY_NOISE: u32 = 1;
+
+// the sizes of the block elements are given in bits rather than in bytes
+const BLOCK_FLAG_DATA_IN_BIT: u32 = 0x04;
+
+const ELEMENT_END: u8 = 0;
+const ELEMENT_SYNC: u8 = 1;
+const ELEMENT_DATA: u8 = 2;
+const ELEMENT_GAP: u8 = 3;
+const ELEMENT_RAW: u8 = 4;
+const ELEMENT_FUZZY: u8 = 5;
+
+// twice as long as a high density track, sizes read from the image are
+// checked against it before the bitstream is grown
+const MAX_TRACK_BIT_LENGTH: usize = 4*8*DD_MFM_TRACK_SIZE;
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn compute_crc(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+
+    crc.update(data);
+    crc.sum()
+}
+
+/// Tells if the given data looks like an IPF image.
+pub fn is_ipf(data: &[u8]) -> bool {
+    data.starts_with(IPF_MAGIC_NUMBER)
+}
+
+/******************************************************************************
+* Records *********************************************************************
+******************************************************************************/
+
+struct Record<'a> {
+    kind: [u8; 4],
+    body: &'a [u8],
+}
+
+/// Parses the record at `offset`, checking its CRC, returns it along with
+/// the offset of the next record.
+fn parse_record(data: &[u8], offset: usize) -> Result<(Record<'_>, usize), Error> {
+    let header = data
+        .get(offset..offset + RECORD_HEADER_SIZE)
+        .ok_or(Error::IpfInvalidRecordError(offset))?;
+    let len = read_u32(header, 4) as usize;
+
+    let record = data
+        .get(offset..offset + len)
+        .filter(|_| len >= RECORD_HEADER_SIZE)
+        .ok_or(Error::IpfInvalidRecordError(offset))?;
+
+    let mut crc = Crc::new();
+
+    crc.update(&record[..8]);
+    crc.update(&[0; 4]);
+    crc.update(&record[RECORD_HEADER_SIZE..]);
+
+    if crc.sum() != read_u32(header, 8) {
+        return Err(Error::IpfInvalidRecordError(offset));
+    }
+
+    let record = Record {
+        kind: header[..4].try_into().unwrap(),
+        body: &record[RECORD_HEADER_SIZE..],
+    };
+
+    Ok((record, offset + len))
+}
+
+struct TrackDescriptor {
+    cylinder: usize,
+    head: usize,
+    density: u32,
+    block_count: usize,
+    data_key: u32,
+}
+
+impl TrackDescriptor {
+    fn parse(body: &[u8], offset: usize) -> Result<Self, Error> {
+        if body.len() < IMGE_RECORD_SIZE - RECORD_HEADER_SIZE {
+            return Err(Error::IpfInvalidRecordError(offset));
+        }
+
+        Ok(Self {
+            cylinder: read_u32(body, 0) as usize,
+            head: read_u32(body, 4) as usize,
+            density: read_u32(body, 8),
+            block_count: read_u32(body, 40) as usize,
+            data_key: read_u32(body, 52),
+        })
+    }
+
+    fn track(&self) -> usize {
+        self.cylinder*FLOPPY_HEADS + self.head
+    }
+}
+
+/******************************************************************************
+* Blocks **********************************************************************
+******************************************************************************/
+
+/// Appends the elements of a block data stream to the bitstream of a track.
+fn read_stream(
+    bits: &mut TrackBits,
+    stream: &[u8],
+    sizes_in_bits: bool,
+) -> Option<()> {
+    let mut offset = 0;
+
+    loop {
+        let header = *stream.get(offset)?;
+        let kind = header & 0x1f;
+        let size_len = (header >> 5) as usize;
+
+        if kind == ELEMENT_END {
+            return Some(());
+        }
+
+        let size = stream
+            .get(offset + 1..offset + 1 + size_len)?
+            .iter()
+            .fold(0usize, |size, b| (size << 8) | *b as usize);
+
+        offset += 1 + size_len;
+
+        let (byte_count, bit_count) = if sizes_in_bits {
+            (size.div_ceil(8), size)
+        } else {
+            (size, size.checked_mul(8)?)
+        };
+
+        // data bits are MFM encoded along with their clock bits
+        let cell_count = match kind {
+            ELEMENT_SYNC|ELEMENT_RAW => bit_count,
+            _ => bit_count.checked_mul(2)?,
+        };
+
+        if bits.bit_length() + cell_count > MAX_TRACK_BIT_LENGTH {
+            return None;
+        }
+
+        match kind {
+            ELEMENT_SYNC|ELEMENT_RAW => {
+                bits.push_raw(stream.get(offset..offset + byte_count)?, bit_count);
+                offset += byte_count;
+            },
+            ELEMENT_DATA|ELEMENT_GAP => {
+                bits.push_data(stream.get(offset..offset + byte_count)?, bit_count);
+                offset += byte_count;
+            },
+            // weak bits, which aren't stored
+            ELEMENT_FUZZY => bits.push_data(&vec![0; byte_count], bit_count),
+            _ => return None,
+        }
+    }
+}
+
+/// Builds the MFM bitstream of a track from the data area of its DATA
+/// record, gaps being filled with MFM encoded zeros.
+fn read_blocks(
+    area: &[u8],
+    block_count: usize,
+) -> Option<TrackBits> {
+    let mut bits = TrackBits::default();
+
+    for block in 0..block_count {
+        let descriptor = area.get(block*BLOCK_DESCRIPTOR_SIZE..(block + 1)*BLOCK_DESCRIPTOR_SIZE)?;
+        let gap_bits = read_u32(descriptor, 4) as usize;
+        let flags = read_u32(descriptor, 20);
+        let data_offset = read_u32(descriptor, 28) as usize;
+
+        read_stream(&mut bits, area.get(data_offset..)?, flags & BLOCK_FLAG_DATA_IN_BIT != 0)?;
+
+        if bits.bit_length() + gap_bits > MAX_TRACK_BIT_LENGTH {
+            return None;
+        }
+        bits.push_data(&vec![0; gap_bits.div_ceil(16)], gap_bits/2);
+    }
+
+    Some(bits)
+}
+
+/******************************************************************************
+* Image ***********************************************************************
+******************************************************************************/
+
+pub(super) fn import(data: &[u8]) -> Result<ImportedImage, Error> {
+    let (record, mut offset) = parse_record(data, 0)
+        .map_err(|_| Error::IpfInvalidHeaderError)?;
+
+    if &record.kind != IPF_MAGIC_NUMBER {
+        return Err(Error::IpfInvalidHeaderError);
+    }
+
+    let mut tracks = Vec::new();
+    let mut data_areas = HashMap::new();
+
+    while offset < data.len() {
+        let record_offset = offset;
+        let (record, next) = parse_record(data, offset)?;
+
+        offset = next;
+
+        match &record.kind {
+            b"IMGE" => tracks.push(TrackDescriptor::parse(record.body, record_offset)?),
+            b"DATA" => {
+                if record.body.len() < DATA_RECORD_SIZE - RECORD_HEADER_SIZE {
+                    return Err(Error::IpfInvalidRecordError(record_offset));
+                }
+
+                let len = read_u32(record.body, 0) as usize;
+                let crc = read_u32(record.body, 8);
+                let key = read_u32(record.body, 12);
+
+                let area = data
+                    .get(offset..offset + len)
+                    .filter(|area| len == 0 || compute_crc(area) == crc)
+                    .ok_or(Error::IpfInvalidRecordError(record_offset))?;
+
+                data_areas.insert(key, area);
+                offset += len;
+            },
+            _ => (),
+        }
+    }
+
+    let mut high_density = false;
+    let mut bitstreams = Vec::with_capacity(tracks.len());
+
+    for descriptor in tracks.iter() {
+        if descriptor.density == DENSITY_NOISE || descriptor.block_count == 0 {
+            continue;
+        }
+
+        let area = data_areas
+            .get(&descriptor.data_key)
+            .ok_or(Error::IpfInvalidTrackError(descriptor.track()))?;
+        let bits = read_blocks(area, descriptor.block_count)
+            .ok_or(Error::IpfInvalidTrackError(descriptor.track()))?;
+
+        // tracks longer than a double density one are high density ones
+        high_density |= bits.bit_length() > 12*DD_MFM_TRACK_SIZE;
+        bitstreams.push((descriptor.track(), bits));
+    }
+
+    let mut image = ImportedImage::new(if high_density {
+        HD_TRACK_SECTORS
+    } else {
+        DD_TRACK_SECTORS
+    });
+
+    for (track, bits) in bitstreams {
+        image.decode_track(track, &[(None, bits)]);
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut record = Vec::from(kind);
+
+        record.extend(((RECORD_HEADER_SIZE + body.len()) as u32).to_be_bytes());
+        record.extend([0; 4]);
+        record.extend(body);
+
+        let crc = compute_crc(&record);
+
+        record[8..12].copy_from_slice(&crc.to_be_bytes());
+        record
+    }
+
+    fn mfm_to_data(mfm: &[u8]) -> Vec<u8> {
+        mfm.chunks(2)
+            .map(|chunk| {
+                let cells = u16::from_be_bytes([chunk[0], chunk[1]]);
+
+                (0..8).fold(0, |byte, i| (byte << 1) | ((cells >> (14 - 2*i)) & 1) as u8)
+            })
+            .collect()
+    }
+
+    // one block per sector, made of its sync words and of its data
+    fn track_area(track: usize, sectors: &[u8]) -> Vec<u8> {
+        let mfm = encode_track(track, sectors).unwrap();
+        let mut descriptors = Vec::new();
+        let mut streams = Vec::new();
+        let stream_offset = DD_TRACK_SECTORS*BLOCK_DESCRIPTOR_SIZE;
+
+        for sector in mfm.chunks_exact(MFM_SECTOR_SIZE).take(DD_TRACK_SECTORS) {
+            let data = mfm_to_data(&sector[8..]);
+            let mut descriptor = vec![0u8; BLOCK_DESCRIPTOR_SIZE];
+
+            descriptor[4..8].copy_from_slice(&16u32.to_be_bytes());
+            descriptor[28..32].copy_from_slice(&((stream_offset + streams.len()) as u32).to_be_bytes());
+            descriptors.extend(descriptor);
+
+            streams.push((1 << 5) | ELEMENT_SYNC);
+            streams.push(8);
+            streams.extend(&sector[..8]);
+            streams.push((2 << 5) | ELEMENT_DATA);
+            streams.extend((data.len() as u16).to_be_bytes());
+            streams.extend(data);
+            streams.push(ELEMENT_END);
+        }
+
+        descriptors.extend(streams);
+        descriptors
+    }
+
+    fn write_ipf(disk_data: &[u8], tracks: usize) -> Vec<u8> {
+        let track_size = DD_TRACK_SECTORS*BLOCK_SIZE;
+        let mut ipf = record(IPF_MAGIC_NUMBER, &[]);
+
+        ipf.extend(record(b"INFO", &[0; 84]));
+
+        for track in 0..tracks {
+            let mut imge = vec![0u8; IMGE_RECORD_SIZE - RECORD_HEADER_SIZE];
+
+            imge[0..4].copy_from_slice(&((track/2) as u32).to_be_bytes());
+            imge[4..8].copy_from_slice(&((track%2) as u32).to_be_bytes());
+            imge[8..12].copy_from_slice(&2u32.to_be_bytes());
+            imge[40..44].copy_from_slice(&(DD_TRACK_SECTORS as u32).to_be_bytes());
+            imge[52..56].copy_from_slice(&(track as u32 + 1).to_be_bytes());
+            ipf.extend(record(b"IMGE", &imge));
+        }
+
+        for (track, sectors) in disk_data.chunks(track_size).take(tracks).enumerate() {
+            let area = track_area(track, sectors);
+            let mut body = vec![0u8; DATA_RECORD_SIZE - RECORD_HEADER_SIZE];
+
+            body[0..4].copy_from_slice(&(area.len() as u32).to_be_bytes());
+            body[8..12].copy_from_slice(&compute_crc(&area).to_be_bytes());
+            body[12..16].copy_from_slice(&(track as u32 + 1).to_be_bytes());
+            ipf.extend(record(b"DATA", &body));
+            ipf.extend(area);
+        }
+        ipf
+    }
+
+    #[test]
+    fn import_ipf_is_ok() {
+        let disk_data = (0..DD_BLOCK_COUNT*BLOCK_SIZE).map(|i| (i%239) as u8).collect::<Vec<_>>();
+        let image = import(&write_ipf(&disk_data, 158)).unwrap();
+        let track_size = DD_TRACK_SECTORS*BLOCK_SIZE;
+
+        assert!(image.tracks[..158].iter().all(|track| track.is_ok()));
+        assert_eq!(image.tracks[0].sectors[0].revolution, None);
+        assert_eq!(image.tracks[158].sectors[0].status, SectorStatus::Missing);
+        assert_eq!(image.disk_data[..158*track_size], disk_data[..158*track_size]);
+        assert!(image.disk_data[158*track_size..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn oversized_stream_fails() {
+        let mut bits = TrackBits::default();
+
+        // weak bits, whose size isn't backed by the stream
+        let stream = [(7 << 5) | ELEMENT_FUZZY, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, ELEMENT_END];
+
+        assert_eq!(read_stream(&mut bits, &stream, false), None);
+        assert_eq!(read_stream(&mut bits, &stream, true), None);
+
+        let stream = [(2 << 5) | ELEMENT_FUZZY, 0x02, 0x00, ELEMENT_END];
+
+        assert_eq!(read_stream(&mut bits, &stream, false), Some(()));
+        assert_eq!(bits.bit_length(), 2*8*512);
+    }
+
+    #[test]
+    fn invalid_ipf_fails() {
+        let mut ipf = write_ipf(&vec![0x42; DD_BLOCK_COUNT*BLOCK_SIZE], 2);
+
+        assert_eq!(import(&ipf[..8]).unwrap_err(), Error::IpfInvalidHeaderError);
+
+        // the CRC of the first IMGE record
+        ipf[12 + 96 + 20] ^= 0x01;
+
+        assert_eq!(import(&ipf).unwrap_err(), Error::IpfInvalidRecordError(108));
+    }
+}
@@ -0,0 +1,287 @@
+//! Read only importers for preservation images, SuperCard Pro flux dumps
+//! and IPF track images.
+//!
+//! Both are turned into MFM bitstreams, one per track and revolution, whose
+//! AmigaDOS sectors are then decoded into raw disk data. The import report
+//! tells the status of each sector and the revolution it was read from.
+
+mod ipf;
+pub(crate) mod scp;
+
+pub use ipf::is_ipf;
+pub use scp::is_scp;
+
+use std::fmt;
+
+use crate::disk::*;
+use crate::errors::*;
+use crate::mfm::*;
+
+
+const TRACK_COUNT: usize = FLOPPY_CYLINDERS*FLOPPY_HEADS;
+
+/******************************************************************************
+* Bitstream *******************************************************************
+******************************************************************************/
+
+/// An MFM bitstream being assembled, bits being packed MSB first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TrackBits {
+    data: Vec<u8>,
+    bit_length: usize,
+}
+
+impl TrackBits {
+    fn previous_bit(&self) -> u8 {
+        match self.bit_length {
+            0 => 0,
+            len => (self.data[(len - 1)/8] >> (7 - (len - 1)%8)) & 1,
+        }
+    }
+
+    pub(crate) fn push_bit(&mut self, bit: u8) {
+        if self.bit_length.is_multiple_of(8) {
+            self.data.push(0);
+        }
+        self.data[self.bit_length/8] |= (bit & 1) << (7 - self.bit_length%8);
+        self.bit_length += 1;
+    }
+
+    /// Appends `bit_count` MFM cells from `mfm`.
+    pub(crate) fn push_raw(&mut self, mfm: &[u8], bit_count: usize) {
+        for pos in 0..bit_count.min(8*mfm.len()) {
+            self.push_bit((mfm[pos/8] >> (7 - pos%8)) & 1);
+        }
+    }
+
+    /// Appends `bit_count` data bits from `data`, each of them being MFM
+    /// encoded along with its clock bit.
+    pub(crate) fn push_data(&mut self, data: &[u8], bit_count: usize) {
+        for pos in 0..bit_count.min(8*data.len()) {
+            let bit = (data[pos/8] >> (7 - pos%8)) & 1;
+            let clock = (self.previous_bit() == 0 && bit == 0) as u8;
+
+            self.push_bit(clock);
+            self.push_bit(bit);
+        }
+    }
+
+    pub(crate) fn bit_length(&self) -> usize {
+        self.bit_length
+    }
+
+    fn decode(&self, track: usize, sectors: usize) -> DecodedTrack {
+        decode_track(&self.data, self.bit_length, track, sectors)
+    }
+}
+
+/******************************************************************************
+* Report **********************************************************************
+******************************************************************************/
+
+/// How a sector of a preservation image has been decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectorReport {
+    pub status: SectorStatus,
+    /// The revolution the sector was read from, for flux images holding
+    /// several of them.
+    pub revolution: Option<usize>,
+}
+
+/// How a track of a preservation image has been decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackReport {
+    pub track: usize,
+    pub sectors: Vec<SectorReport>,
+}
+
+impl TrackReport {
+    pub fn is_ok(&self) -> bool {
+        self.sectors.iter().all(|sector| sector.status == SectorStatus::Ok)
+    }
+}
+
+impl fmt::Display for TrackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "track {}", self.track)?;
+
+        let mut revolutions = self.sectors.iter()
+            .filter_map(|sector| sector.revolution)
+            .collect::<Vec<_>>();
+
+        revolutions.sort();
+        revolutions.dedup();
+
+        if !revolutions.is_empty() {
+            let revolutions = revolutions.iter()
+                .map(|revolution| revolution.to_string())
+                .collect::<Vec<_>>();
+
+            write!(f, " (revolution {})", revolutions.join(", "))?;
+        }
+
+        if self.sectors.iter().all(|sector| sector.status == SectorStatus::Missing) {
+            return write!(f, ": unformatted");
+        }
+
+        let mut errors = self.sectors.iter()
+            .enumerate()
+            .filter(|(_, sector)| sector.status != SectorStatus::Ok)
+            .peekable();
+
+        if errors.peek().is_none() {
+            return write!(f, ": ok");
+        }
+
+        for (index, (sector, report)) in errors.enumerate() {
+            let separator = if index == 0 { ":" } else { "," };
+
+            write!(f, "{separator} sector {sector} {}", report.status)?;
+        }
+        Ok(())
+    }
+}
+
+/******************************************************************************
+* Image ***********************************************************************
+******************************************************************************/
+
+/// The disk data decoded from a preservation image, along with the report
+/// of each track.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedImage {
+    pub disk_data: Vec<u8>,
+    pub tracks: Vec<TrackReport>,
+}
+
+impl ImportedImage {
+    fn new(sectors: usize) -> Self {
+        let sector = SectorReport {
+            status: SectorStatus::Missing,
+            revolution: None,
+        };
+        let tracks = (0..TRACK_COUNT)
+            .map(|track| TrackReport {
+                track,
+                sectors: vec![sector; sectors],
+            })
+            .collect();
+
+        Self {
+            disk_data: vec![0; TRACK_COUNT*sectors*BLOCK_SIZE],
+            tracks,
+        }
+    }
+
+    /// Decodes the revolutions of a track, each sector being taken from the
+    /// first revolution it was best read from. Tracks out of a floppy disk
+    /// are ignored.
+    fn decode_track(
+        &mut self,
+        track: usize,
+        revolutions: &[(Option<usize>, TrackBits)],
+    ) {
+        fn rank(status: SectorStatus) -> usize {
+            match status {
+                SectorStatus::Missing => 0,
+                SectorStatus::HeaderChecksumError => 1,
+                SectorStatus::DataChecksumError => 2,
+                SectorStatus::Ok => 3,
+            }
+        }
+
+        let Some(report) = self.tracks.get_mut(track) else {
+            return;
+        };
+
+        let sectors = report.sectors.len();
+        let track_offset = track*sectors*BLOCK_SIZE;
+
+        for (revolution, bits) in revolutions {
+            let decoded = bits.decode(track, sectors);
+
+            for (index, status) in decoded.sectors.iter().copied().enumerate() {
+                if rank(status) <= rank(report.sectors[index].status) {
+                    continue;
+                }
+
+                let offset = index*BLOCK_SIZE;
+
+                report.sectors[index] = SectorReport {
+                    status,
+                    revolution: *revolution,
+                };
+                self.disk_data[track_offset + offset..track_offset + offset + BLOCK_SIZE]
+                    .copy_from_slice(&decoded.data[offset..offset + BLOCK_SIZE]);
+            }
+        }
+    }
+}
+
+/// Decodes a SuperCard Pro flux image or an IPF image.
+pub fn import(data: &[u8]) -> Result<ImportedImage, Error> {
+    if is_scp(data) {
+        scp::import(data)
+    } else if is_ipf(data) {
+        ipf::import(data)
+    } else {
+        Err(Error::InvalidImageFormatError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_bits_are_ok() {
+        let mfm = encode_track(3, &[0x42; 11*BLOCK_SIZE]).unwrap();
+        let mut bits = TrackBits::default();
+
+        // the sync words of each sector are raw cells, the data bits of the
+        // other cells being encoded again
+        for (index, chunk) in mfm.chunks(2).enumerate() {
+            if index%(MFM_SECTOR_SIZE/2) < 4 && index < 11*MFM_SECTOR_SIZE/2 {
+                bits.push_raw(chunk, 16);
+            } else {
+                let cells = u16::from_be_bytes([chunk[0], chunk[1]]);
+                let byte = (0..8).fold(0, |byte, i| (byte << 1) | ((cells >> (14 - 2*i)) & 1) as u8);
+
+                bits.push_data(&[byte], 8);
+            }
+        }
+
+        assert_eq!(bits.bit_length(), 8*mfm.len());
+        assert_eq!(bits.data, mfm);
+        assert!(bits.decode(3, 11).is_ok());
+    }
+
+    #[test]
+    fn track_report_is_ok() {
+        let sector = SectorReport {
+            status: SectorStatus::Ok,
+            revolution: Some(1),
+        };
+        let mut report = TrackReport {
+            track: 3,
+            sectors: vec![sector; 11],
+        };
+
+        assert_eq!(report.to_string(), "track 3 (revolution 1): ok");
+
+        report.sectors[2] = SectorReport {
+            status: SectorStatus::DataChecksumError,
+            revolution: Some(0),
+        };
+        report.sectors[7] = SectorReport {
+            status: SectorStatus::Missing,
+            revolution: None,
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "track 3 (revolution 0, 1): sector 2 data checksum error, sector 7 missing",
+        );
+        assert!(!report.is_ok());
+    }
+}
@@ -0,0 +1,305 @@
+//! SuperCard Pro flux images.
+//!
+//! A header is followed by a table of offsets to the tracks, each track
+//! giving the flux transitions of one or more revolutions. A transition is
+//! the 16 bits big endian time since the previous one, zero meaning the
+//! time overflowed and carries on into the next value.
+
+use crate::errors::*;
+
+use super::*;
+
+
+pub const SCP_MAGIC_NUMBER: &[u8; 3] = b"SCP";
+
+const FILE_HEADER_SIZE: usize = 0x10;
+const MAX_TRACK_COUNT: usize = 168;
+const TRACK_HEADER_SIZE: usize = 4;
+const REVOLUTION_HEADER_SIZE: usize = 12;
+
+const REVOLUTION_COUNT_OFFSET: usize = 0x05;
+const BIT_CELL_WIDTH_OFFSET: usize = 0x09;
+const RESOLUTION_OFFSET: usize = 0x0b;
+
+// time unit of the flux transitions at the default resolution
+const BASE_RESOLUTION_NS: u64 = 25;
+
+// the most frequent flux transitions are two bit cells long, i.e. 4us on
+// double density disks and 2us on high density ones
+const MIN_TWO_CELLS_NS: u64 = 1500;
+const MAX_TWO_CELLS_NS: u64 = 6000;
+
+// 2us bit cells on double density disks, 1us on high density ones
+const HD_MAX_BIT_CELL_NS: f64 = 1500.;
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Tells if the given data looks like a SuperCard Pro image.
+pub fn is_scp(data: &[u8]) -> bool {
+    data.starts_with(SCP_MAGIC_NUMBER)
+}
+
+/// Returns the flux transition times of a revolution, in nanoseconds.
+fn read_flux(
+    data: &[u8],
+    resolution_ns: u64,
+) -> Vec<u64> {
+    let mut flux = Vec::with_capacity(data.len()/2);
+    let mut carry = 0u64;
+
+    for chunk in data.chunks_exact(2) {
+        match u16::from_be_bytes([chunk[0], chunk[1]]) {
+            0 => carry += 0x10000,
+            value => {
+                flux.push((carry + value as u64)*resolution_ns);
+                carry = 0;
+            },
+        }
+    }
+    flux
+}
+
+/// Guesses the bit cell time from the most frequent transition time.
+fn guess_bit_cell(flux: &[u64]) -> Option<f64> {
+    let mut histogram = vec![0usize; (MAX_TWO_CELLS_NS/100) as usize + 1];
+
+    for time in flux.iter().filter(|time| (MIN_TWO_CELLS_NS..=MAX_TWO_CELLS_NS).contains(*time)) {
+        histogram[(time/100) as usize] += 1;
+    }
+
+    histogram.iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)
+        .filter(|(_, count)| **count > 0)
+        .map(|(bucket, _)| (100*bucket + 50) as f64/2.)
+}
+
+/// Turns flux transitions into MFM cells, a transition being a set cell
+/// preceded by as many clear ones as bit cells elapsed since the previous
+/// transition. The bit cell time follows the drive speed variations.
+fn decode_flux(flux: &[u64], bit_cell: f64) -> TrackBits {
+    let mut bits = TrackBits::default();
+    let mut cell = bit_cell;
+
+    for time in flux.iter().map(|time| *time as f64) {
+        let cells = (time/cell).round().clamp(1., 8.);
+
+        for _ in 1..cells as usize {
+            bits.push_bit(0);
+        }
+        bits.push_bit(1);
+
+        // let the cell time drift slowly, within 10% of its nominal value
+        cell = (cell + (time/cells - cell)/16.).clamp(0.9*bit_cell, 1.1*bit_cell);
+    }
+    bits
+}
+
+pub(super) fn import(data: &[u8]) -> Result<ImportedImage, Error> {
+    let table_end = FILE_HEADER_SIZE + 4*MAX_TRACK_COUNT;
+
+    if !is_scp(data) || data.len() < table_end {
+        return Err(Error::ScpInvalidHeaderError);
+    }
+
+    // only 16 bits transitions are supported
+    if data[BIT_CELL_WIDTH_OFFSET] != 0 && data[BIT_CELL_WIDTH_OFFSET] != 16 {
+        return Err(Error::ScpInvalidHeaderError);
+    }
+
+    let revolution_count = data[REVOLUTION_COUNT_OFFSET] as usize;
+    let resolution_ns = BASE_RESOLUTION_NS*(data[RESOLUTION_OFFSET] as u64 + 1);
+    let mut tracks = Vec::new();
+
+    for track in 0..MAX_TRACK_COUNT {
+        let offset = read_u32(data, FILE_HEADER_SIZE + 4*track) as usize;
+
+        if offset == 0 {
+            continue;
+        }
+
+        let header = data
+            .get(offset..offset + TRACK_HEADER_SIZE + revolution_count*REVOLUTION_HEADER_SIZE)
+            .filter(|header| &header[..3] == b"TRK")
+            .ok_or(Error::ScpInvalidTrackHeaderError(track))?;
+
+        let mut revolutions = Vec::with_capacity(revolution_count);
+
+        for revolution in 0..revolution_count {
+            let header = &header[TRACK_HEADER_SIZE + revolution*REVOLUTION_HEADER_SIZE..];
+            let length = read_u32(header, 4) as usize;
+            let start = offset + read_u32(header, 8) as usize;
+
+            let flux_data = data
+                .get(start..start + 2*length)
+                .ok_or(Error::ScpInvalidTrackHeaderError(track))?;
+
+            revolutions.push(read_flux(flux_data, resolution_ns));
+        }
+
+        tracks.push((header[3] as usize, revolutions));
+    }
+
+    let bit_cell = tracks.iter()
+        .flat_map(|(_, revolutions)| revolutions.first())
+        .find_map(|flux| guess_bit_cell(flux))
+        .ok_or(Error::ScpInvalidHeaderError)?;
+
+    let sectors = if bit_cell < HD_MAX_BIT_CELL_NS {
+        HD_TRACK_SECTORS
+    } else {
+        DD_TRACK_SECTORS
+    };
+    let mut image = ImportedImage::new(sectors);
+
+    for (track, revolutions) in tracks {
+        // a revolution is decoded along with the beginning of the next one,
+        // for the sector written over the index to be readable
+        let revolutions = revolutions.iter()
+            .enumerate()
+            .map(|(revolution, flux)| {
+                let mut flux = flux.clone();
+
+                if let Some(next) = revolutions.get(revolution + 1) {
+                    let mut time = 0;
+
+                    flux.extend(next.iter().take_while(|t| {
+                        time += **t;
+                        time < 2*(MFM_SECTOR_SIZE as u64)*8*(bit_cell as u64)
+                    }));
+                }
+
+                (Some(revolution), decode_flux(&flux, bit_cell))
+            })
+            .collect::<Vec<_>>();
+
+        image.decode_track(track, &revolutions);
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // 2us bit cells at 25ns per tick
+    const BIT_CELL_TICKS: usize = 80;
+
+    /// Turns the MFM cells of consecutive revolutions into flux transitions,
+    /// the cells following the last transition of a revolution being
+    /// counted in the first transition of the next one.
+    fn flux_from_mfm(revolutions: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut cells = 0;
+
+        revolutions.iter()
+            .map(|mfm| {
+                let mut flux = Vec::new();
+
+                for pos in 0..8*mfm.len() {
+                    cells += 1;
+
+                    if (mfm[pos/8] >> (7 - pos%8)) & 1 == 1 {
+                        let jitter = pos%7;
+                        let ticks = (cells*BIT_CELL_TICKS + jitter - 3) as u16;
+
+                        flux.extend(ticks.to_be_bytes());
+                        cells = 0;
+                    }
+                }
+                flux
+            })
+            .collect()
+    }
+
+    /// Builds a double density SuperCard Pro image of 2 revolutions per
+    /// track, `damage` telling whether to damage a revolution of a track.
+    pub(crate) fn write_scp(
+        disk_data: &[u8],
+        damage: impl Fn(usize, usize) -> bool,
+    ) -> Vec<u8> {
+        let track_size = DD_TRACK_SECTORS*BLOCK_SIZE;
+        let mut data = vec![0u8; FILE_HEADER_SIZE + 4*MAX_TRACK_COUNT];
+
+        data[..3].copy_from_slice(SCP_MAGIC_NUMBER);
+        data[REVOLUTION_COUNT_OFFSET] = 2;
+
+        for (track, sectors) in disk_data.chunks(track_size).enumerate() {
+            let offset = data.len();
+            let mut mfm = encode_track(track, sectors).unwrap();
+
+            // the track isn't written from the index
+            mfm.rotate_left(300);
+
+            let revolutions = (0..2)
+                .map(|revolution| {
+                    let mut mfm = mfm.clone();
+
+                    if damage(track, revolution) {
+                        mfm[3*MFM_SECTOR_SIZE + 400] ^= 0x10;
+                    }
+                    mfm
+                })
+                .collect::<Vec<_>>();
+            let revolutions = flux_from_mfm(&revolutions);
+
+            data[FILE_HEADER_SIZE + 4*track..FILE_HEADER_SIZE + 4*track + 4]
+                .copy_from_slice(&(offset as u32).to_le_bytes());
+
+            let mut header = Vec::from(b"TRK");
+            let mut flux_offset = TRACK_HEADER_SIZE + 2*REVOLUTION_HEADER_SIZE;
+
+            header.push(track as u8);
+
+            for flux in revolutions.iter() {
+                header.extend(0u32.to_le_bytes());
+                header.extend(((flux.len()/2) as u32).to_le_bytes());
+                header.extend((flux_offset as u32).to_le_bytes());
+                flux_offset += flux.len();
+            }
+
+            data.extend(header);
+            data.extend(revolutions.concat());
+        }
+        data
+    }
+
+    #[test]
+    fn import_scp_is_ok() {
+        let track_size = DD_TRACK_SECTORS*BLOCK_SIZE;
+        let disk_data = (0..24*track_size).map(|i| (i%241) as u8).collect::<Vec<_>>();
+        let scp = write_scp(&disk_data, |track, revolution| {
+            track == 10 && revolution == 0 || track == 20
+        });
+
+        let image = import(&scp).unwrap();
+        let tracks = &image.tracks;
+
+        assert_eq!(tracks.len(), 160);
+        assert!(tracks[0].is_ok());
+        assert_eq!(tracks[0].sectors[5].revolution, Some(0));
+
+        // the damaged sector read from the second revolution
+        assert!(tracks[10].is_ok());
+        assert_eq!(tracks[10].sectors[3].revolution, Some(1));
+        assert_eq!(tracks[20].sectors[3].status, SectorStatus::DataChecksumError);
+        assert_eq!(tracks.iter().filter(|track| track.is_ok()).count(), 23);
+        assert!(tracks[24..].iter().all(|track| track.sectors[0].status == SectorStatus::Missing));
+
+        assert_eq!(image.disk_data[..20*track_size], disk_data[..20*track_size]);
+        assert_eq!(image.disk_data[21*track_size..24*track_size], disk_data[21*track_size..]);
+    }
+
+    #[test]
+    fn invalid_scp_fails() {
+        assert_eq!(import(b"SCP").unwrap_err(), Error::ScpInvalidHeaderError);
+
+        let mut scp = write_scp(&vec![0; 4*DD_TRACK_SECTORS*BLOCK_SIZE], |_, _| false);
+
+        scp.truncate(scp.len() - 1);
+
+        assert_eq!(import(&scp).unwrap_err(), Error::ScpInvalidTrackHeaderError(3));
+    }
+}
@@ -1,24 +1,26 @@
+use std::borrow::Cow;
 use std::path::Path;
-use std::cell::RefCell;
-use std::rc::Rc;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos_options::*;
 use super::boot_block::*;
+use super::name::*;
 
 
 #[derive(Debug)]
 pub(super) struct AmigaDosInner {
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
     bitmap_block_addresses: Box<[LBAAddress]>,
+    name_encoding_mode: NameEncodingMode,
     // root_block_address: LBAAddress,
 }
 
 impl AmigaDosInner {
-    pub(super) fn disk(&self) -> Rc<RefCell<Disk>> {
+    pub(super) fn disk(&self) -> Shared<Disk> {
         self.disk.clone()
     }
 
@@ -44,19 +46,20 @@ impl AmigaDosInner {
         &self,
         path: P,
     ) -> Result<(), std::io::Error> {
-        std::fs::write(path, self.disk.borrow().data())?;
+        std::fs::write(path, self.disk.borrow().encode()?)?;
         Ok(())
     }
 }
 
 pub struct AmigaDos {
-    pub(super) inner: Rc<RefCell<AmigaDosInner>>
+    pub(super) inner: Shared<AmigaDosInner>,
+    pub(super) lock: FsLock,
 }
 
-impl TryFrom<Rc<RefCell<Disk>>> for AmigaDos {
+impl TryFrom<Shared<Disk>> for AmigaDos {
     type Error = Error;
 
-    fn try_from(disk: Rc<RefCell<Disk>>) -> Result<Self, Self::Error> {
+    fn try_from(disk: Shared<Disk>) -> Result<Self, Self::Error> {
         let boot_block = BootBlockReader::try_from_disk(disk.clone())?;
 
         let root_block_address = boot_block.get_root_block_address();
@@ -68,17 +71,19 @@ impl TryFrom<Rc<RefCell<Disk>>> for AmigaDos {
         let bitmap_block_addresses = root_block.read_bitmap()?.into_boxed_slice();
 
         Ok(Self {
-            inner: Rc::new(RefCell::new(AmigaDosInner {
+            inner: shared(AmigaDosInner {
                 disk,
                 bitmap_block_addresses,
+                name_encoding_mode: NameEncodingMode::default(),
                 // root_block_address,
-            }))
+            }),
+            lock: FsLock::new(),
         })
     }
 }
 
 impl AmigaDos {
-    pub(super) fn disk(&self) -> Rc<RefCell<Disk>> {
+    pub(super) fn disk(&self) -> Shared<Disk> {
         self.inner.borrow().disk()
     }
 
@@ -89,6 +94,38 @@ impl AmigaDos {
     // pub(super) fn get_root_block_address(&self) -> LBAAddress {
     //     self.inner.borrow().get_root_block_address()
     // }
+
+    /// Prepares a path whose names are to be created on disk, according to
+    /// the name encoding mode.
+    pub(super) fn encode_path<'a>(
+        &self,
+        path: &'a Path,
+    ) -> Result<Cow<'a, Path>, Error> {
+        let s = path.to_str().ok_or(Error::InvalidPathError)?;
+        let mode = self.name_encoding_mode();
+
+        Ok(match encode_name(s, mode)? {
+            Cow::Borrowed(_) => Cow::Borrowed(path),
+            Cow::Owned(s) => Cow::Owned(s.into()),
+        })
+    }
+}
+
+impl AmigaDos {
+    /// Returns how names which can't be encoded in Latin-1 are handled.
+    pub fn name_encoding_mode(&self) -> NameEncodingMode {
+        self.inner.borrow().name_encoding_mode
+    }
+
+    /// Sets how names which can't be encoded in Latin-1 are handled when
+    /// entries are created or looked up. They are rejected by default.
+    pub fn with_name_encoding_mode(
+        &mut self,
+        mode: NameEncodingMode,
+    ) -> &mut Self {
+        self.inner.borrow_mut().name_encoding_mode = mode;
+        self
+    }
 }
 
 impl AmigaDos {
@@ -96,6 +133,8 @@ impl AmigaDos {
         &self,
         path: P,
     ) -> Result<(), std::io::Error> {
+        let _guard = self.lock.read();
+
         self.inner.borrow().dump(path)
     }
 }
@@ -90,3 +90,214 @@ impl fmt::Display for CacheMode {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LongNameMode {
+    #[default]
+    Off = 0,
+    On  = 0x06,
+}
+
+impl FromStr for LongNameMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "on"|"yes" => Ok(LongNameMode::On),
+            "off"|"no" => Ok(LongNameMode::Off),
+            _ => Err(Error::InvalidLongNameModeError)
+        }
+    }
+}
+
+impl fmt::Display for LongNameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LongNameMode::On => write!(f, "LNFS-ON"),
+            LongNameMode::Off =>  write!(f, "LNFS-OFF"),
+        }
+    }
+}
+
+/// The DOS type of a filesystem, `DOS\0` to `DOS\7`, as stored in the last
+/// byte of the boot block magic number. It combines the filesystem type with
+/// the international, directory cache and long name modes; some combinations
+/// don't exist, e.g. long name filesystems have no directory cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DosType {
+    #[default]
+    OFS         = 0,
+    FFS         = 1,
+    OFSIntl     = 2,
+    FFSIntl     = 3,
+    OFSDirCache = 4,
+    FFSDirCache = 5,
+    OFSLongName = 6,
+    FFSLongName = 7,
+}
+
+impl DosType {
+    /// Combines a filesystem type and modes into a DOS type.
+    /// Errors:
+    /// - When both the directory cache and long name modes are on.
+    pub fn try_from_modes(
+        filesystem_type: FilesystemType,
+        international_mode: InternationalMode,
+        cache_mode: CacheMode,
+        long_name_mode: LongNameMode,
+    ) -> Result<Self, Error> {
+        let flags = match (cache_mode, long_name_mode) {
+            (CacheMode::On, LongNameMode::On) => {
+                return Err(Error::InvalidDosTypeError);
+            },
+            (CacheMode::On, LongNameMode::Off) => CacheMode::On as u8,
+            (CacheMode::Off, LongNameMode::On) => LongNameMode::On as u8,
+            (CacheMode::Off, LongNameMode::Off) => international_mode as u8,
+        };
+
+        Self::try_from(flags | filesystem_type as u8)
+    }
+
+    pub fn filesystem_type(&self) -> FilesystemType {
+        if *self as u8 & FilesystemType::FFS as u8 != 0 {
+            FilesystemType::FFS
+        } else {
+            FilesystemType::OFS
+        }
+    }
+
+    // directory cache and long name filesystems always use the international
+    // mode
+    pub fn international_mode(&self) -> InternationalMode {
+        if *self as u8 >= DosType::OFSIntl as u8 {
+            InternationalMode::On
+        } else {
+            InternationalMode::Off
+        }
+    }
+
+    pub fn cache_mode(&self) -> CacheMode {
+        match self {
+            DosType::OFSDirCache|DosType::FFSDirCache => CacheMode::On,
+            _ => CacheMode::Off,
+        }
+    }
+
+    pub fn long_name_mode(&self) -> LongNameMode {
+        match self {
+            DosType::OFSLongName|DosType::FFSLongName => LongNameMode::On,
+            _ => LongNameMode::Off,
+        }
+    }
+}
+
+impl TryFrom<u8> for DosType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DosType::OFS),
+            1 => Ok(DosType::FFS),
+            2 => Ok(DosType::OFSIntl),
+            3 => Ok(DosType::FFSIntl),
+            4 => Ok(DosType::OFSDirCache),
+            5 => Ok(DosType::FFSDirCache),
+            6 => Ok(DosType::OFSLongName),
+            7 => Ok(DosType::FFSLongName),
+            _ => Err(Error::InvalidDosTypeError),
+        }
+    }
+}
+
+impl FromStr for DosType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let flags = s.strip_prefix("dos\\")
+            .or_else(|| s.strip_prefix("dos"))
+            .and_then(|flags| flags.parse::<u8>().ok())
+            .ok_or(Error::InvalidDosTypeError)?;
+
+        DosType::try_from(flags)
+    }
+}
+
+impl fmt::Display for DosType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DOS\\{}", *self as u8)
+    }
+}
+
+/// How names holding characters which can't be encoded in Latin-1, the
+/// character set of AmigaDOS names, are handled when entries are created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NameEncodingMode {
+    /// Such names are rejected with `Error::InvalidNameError`.
+    #[default]
+    Reject,
+    /// Unencodable characters are escaped by their code point, e.g.
+    /// `\u{20ac}` for `'€'`. Backslashes are left as they are, so that a
+    /// name spelling such an escape, e.g. `"a\\u{20ac}"`, and the name it
+    /// escapes, `"a€"`, refer to the same entry.
+    Escape,
+}
+
+impl FromStr for NameEncodingMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reject" => Ok(NameEncodingMode::Reject),
+            "escape" => Ok(NameEncodingMode::Escape),
+            _ => Err(Error::InvalidNameEncodingModeError)
+        }
+    }
+}
+
+impl fmt::Display for NameEncodingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameEncodingMode::Reject => write!(f, "REJECT"),
+            NameEncodingMode::Escape => write!(f, "ESCAPE"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dos_type_is_ok() {
+        for flags in 0..8 {
+            let dos_type = DosType::try_from(flags).unwrap();
+
+            assert_eq!(dos_type.to_string().parse(), Ok(dos_type));
+            assert_eq!(format!("dos{flags}").parse(), Ok(dos_type));
+            assert_eq!(
+                DosType::try_from_modes(
+                    dos_type.filesystem_type(),
+                    dos_type.international_mode(),
+                    dos_type.cache_mode(),
+                    dos_type.long_name_mode(),
+                ),
+                Ok(dos_type),
+            );
+        }
+
+        assert_eq!(DosType::FFSLongName.to_string(), "DOS\\7");
+        assert_eq!("DOS8".parse::<DosType>(), Err(Error::InvalidDosTypeError));
+        assert_eq!(
+            DosType::try_from_modes(
+                FilesystemType::FFS,
+                InternationalMode::Off,
+                CacheMode::On,
+                LongNameMode::On,
+            ),
+            Err(Error::InvalidDosTypeError),
+        );
+    }
+}
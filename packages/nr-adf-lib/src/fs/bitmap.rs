@@ -1,10 +1,9 @@
-use std::cell::RefCell;
 use std::ops::Range;
-use std::rc::Rc;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
 use super::checksum::*;
@@ -16,7 +15,7 @@ enum BitmapAction {
 }
 
 fn get_bitmap_block_count(
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
 ) -> usize {
     let block_count = disk.borrow().block_count() - 2; // 2 boot blocks
 
@@ -30,7 +29,7 @@ fn get_bitmap_block_count(
 struct BitmapBlock {
     address: LBAAddress,
     address_range: Range<LBAAddress>,
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
 }
 
 impl BitmapBlock {
@@ -38,7 +37,7 @@ impl BitmapBlock {
         let block_count = self.block_count();
 
         // bitmap dword aligned length
-        if block_count%32 > 0 {
+        if !block_count.is_multiple_of(32) {
             4*(block_count/32 + 1)
         } else {
             block_count/8
@@ -61,7 +60,7 @@ impl BitmapBlock {
     ) -> usize {
         let disk_ref = self.disk.borrow();
 
-        let block = disk_ref.blocks(self.address, 1).unwrap();
+        let block = disk_ref.block(self.address).unwrap();
         let bytes = &block[4 .. 4 + self.byte_len()];
 
         let mut remaining = self.block_count() as u32;
@@ -86,31 +85,23 @@ impl BitmapBlock {
     ) -> Option<LBAAddress> {
         let disk_ref = self.disk.borrow();
 
-        let block = disk_ref.blocks(self.address, 1).unwrap();
+        let block = disk_ref.block(self.address).unwrap();
         let bytes = &block[4 .. 4 + self.byte_len()];
 
         let mut address_offset = self.address_range.start;
         let mut remaining = self.block_count();
 
         for chunk in bytes.chunks(4) {
-            let mask = if remaining > 32 {
-                0xffff_ffffu32
-            } else {
-                0xffff_ffffu32.checked_shl(32 - remaining as u32).unwrap_or(0)
-            };
-
-            let dword = u32::from_be_bytes(chunk.try_into().unwrap());
+            let bit_count = remaining.min(32);
+            let mask = 0xffff_ffffu32 >> (32 - bit_count);
+            let dword = u32::from_be_bytes(chunk.try_into().unwrap()) & mask;
 
-            if (dword & mask) == 0 {
-                address_offset += 32.min(remaining);
-                remaining -= 32.min(remaining);
-            } else {
-                for bit in 0..remaining.min(32) {
-                    if dword & (1u32 << bit) != 0 {
-                        return Some(address_offset + bit);
-                    }
-                }
+            if dword != 0 {
+                return Some(address_offset + dword.trailing_zeros() as usize);
             }
+
+            address_offset += bit_count;
+            remaining -= bit_count;
         }
 
         None
@@ -123,7 +114,7 @@ impl BitmapBlock {
     ) -> Result<(), Error> {
         let mut disk_ref = self.disk.borrow_mut();
 
-        let block = disk_ref.blocks_mut(self.address, 1).unwrap();
+        let block = disk_ref.block_mut(self.address).unwrap();
         let bytes = &mut block[4 .. 4 + self.byte_len()];
 
         if !self.contains_block(addr) {
@@ -155,6 +146,30 @@ impl BitmapBlock {
         Ok(())
     }
 
+    fn is_block_free(
+        &self,
+        addr: LBAAddress,
+    ) -> Result<bool, Error> {
+        let disk_ref = self.disk.borrow();
+
+        let block = disk_ref.block(self.address)?;
+        let bytes = &block[4 .. 4 + self.byte_len()];
+
+        if !self.contains_block(addr) {
+            return Err(Error::DiskInvalidLBAAddressError(addr));
+        }
+
+        let bit_offset = (addr - 2)%BITMAP_BLOCK_BIT_COUNT;
+
+        let dword_index = bit_offset/32;
+        let dword_bit = bit_offset%32;
+
+        let chunk = &bytes[4*dword_index..4*(dword_index + 1)];
+        let dword = u32::from_be_bytes(chunk.try_into().unwrap());
+
+        Ok(dword & (1u32 << dword_bit) != 0)
+    }
+
     fn try_reserve_block(
         &mut self,
     ) -> Option<LBAAddress> {
@@ -169,19 +184,19 @@ impl BitmapBlock {
 
 #[derive(Clone, Debug)]
 struct BitmapBlockIterator {
-    disk: Rc<RefCell<Disk>>,
-    bitmap_block_addresses: Vec<LBAAddress>,
+    disk: Shared<Disk>,
+    bitmap_block_addresses: std::vec::IntoIter<LBAAddress>,
     block_address_offset: LBAAddress,
 }
 
 impl BitmapBlockIterator {
     fn new(
         bitmap_block_addresses: &[LBAAddress],
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
     ) -> BitmapBlockIterator {
         BitmapBlockIterator {
             disk,
-            bitmap_block_addresses: Vec::from(bitmap_block_addresses),
+            bitmap_block_addresses: Vec::from(bitmap_block_addresses).into_iter(),
             block_address_offset: 2,
         }
     }
@@ -192,24 +207,22 @@ impl Iterator for BitmapBlockIterator {
 
     fn next(&mut self) -> Option<Self::Item> {
         let block_count = self.disk.borrow().block_count();
+        let address = self.bitmap_block_addresses.next()?;
 
-        if let Some(address) = self.bitmap_block_addresses.pop() {
-            let first = self.block_address_offset;
-            let last =
-                if first + BITMAP_BLOCK_BIT_COUNT > block_count {
-                    block_count
-                } else {
-                    BITMAP_BLOCK_BIT_COUNT
-                };
+        let first = self.block_address_offset;
+        let last = block_count.min(first + BITMAP_BLOCK_BIT_COUNT);
 
-            Some(BitmapBlock {
-                address,
-                address_range: first..last,
-                disk: self.disk.clone(),
-            })
-        } else {
-            None
+        if first >= last {
+            return None;
         }
+
+        self.block_address_offset = last;
+
+        Some(BitmapBlock {
+            address,
+            address_range: first..last,
+            disk: self.disk.clone(),
+        })
     }
 }
 
@@ -233,7 +246,7 @@ impl BitmapInitializer {
 
     pub fn init(
         &self,
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
     ) -> Result<(), Error> {
         let root_block_address =
             self.root_block_address.unwrap_or_else(|| {
@@ -244,23 +257,84 @@ impl BitmapInitializer {
         let mut bitmap_blocks = vec![];
 
         let bitmap_block_count = get_bitmap_block_count(disk.clone());
+        let bitmap_ext_block_count = bitmap_block_count
+            .saturating_sub(ROOT_BLOCK_BITMAP_PAGES_SIZE)
+            .div_ceil(BITMAP_EXT_BLOCK_PAGES_SIZE);
+
+        let first_bitmap_block_address = root_block_address + 1;
+        let first_bitmap_ext_block_address = first_bitmap_block_address + bitmap_block_count;
+
+        if first_bitmap_ext_block_address + bitmap_ext_block_count > disk.borrow().block_count() {
+            return Err(Error::NoSpaceLeft);
+        }
+
+        for bitmap_ext_block_index in 0..bitmap_ext_block_count {
+            let bitmap_ext_block_address = first_bitmap_ext_block_address + bitmap_ext_block_index;
+            let mut bitmap_ext_block = Block::new(disk.clone(), bitmap_ext_block_address);
+
+            bitmap_ext_block.fill(0, 0, BLOCK_SIZE)?;
+
+            // chain the extension block to the root block or to the previous
+            // extension block
+            if bitmap_ext_block_index == 0 {
+                Block::new(
+                    disk.clone(),
+                    root_block_address,
+                ).write_u32(
+                    ROOT_BLOCK_BITMAP_EXT_OFFSET,
+                    bitmap_ext_block_address as u32,
+                )?;
+            } else {
+                Block::new(
+                    disk.clone(),
+                    bitmap_ext_block_address - 1,
+                ).write_u32(
+                    BITMAP_EXT_BLOCK_NEXT_OFFSET,
+                    bitmap_ext_block_address as u32,
+                )?;
+            }
+
+            reserved_blocks.push(bitmap_ext_block_address);
+        }
+
         for bitmap_block_index in 0..bitmap_block_count {
-            let bitmap_block_address = root_block_address + 1 + bitmap_block_index;
+            let bitmap_block_address = first_bitmap_block_address + bitmap_block_index;
 
             // init the bitmap_block
-            Block::new(
+            let mut bitmap_block = Block::new(
                 disk.clone(),
                 bitmap_block_address
-            ).fill(0xff, 0, BLOCK_SIZE)?;
+            );
+
+            bitmap_block.fill(0xff, 0, BLOCK_SIZE)?;
+
+            let checksum = compute_checksum(
+                disk.borrow().block(bitmap_block_address)?,
+                BITMAP_BLOCK_CHECKSUM_OFFSET,
+            );
+
+            bitmap_block.write_u32(BITMAP_BLOCK_CHECKSUM_OFFSET, checksum)?;
 
             // write bitmap block address in the root block bitmap index table
+            // or in the bitmap extension blocks for the following ones
+            let (address, offset) = if bitmap_block_index < ROOT_BLOCK_BITMAP_PAGES_SIZE {
+                (
+                    root_block_address,
+                    ROOT_BLOCK_BITMAP_PAGES_OFFSET + 4*bitmap_block_index,
+                )
+            } else {
+                let index = bitmap_block_index - ROOT_BLOCK_BITMAP_PAGES_SIZE;
+
+                (
+                    first_bitmap_ext_block_address + index/BITMAP_EXT_BLOCK_PAGES_SIZE,
+                    BITMAP_EXT_BLOCK_PAGES_OFFSET + 4*(index%BITMAP_EXT_BLOCK_PAGES_SIZE),
+                )
+            };
+
             Block::new(
                 disk.clone(),
-                root_block_address
-            ).write_u32(
-                ROOT_BLOCK_BITMAP_PAGES_OFFSET + 4*bitmap_block_index,
-                bitmap_block_address as u32,
-            )?;
+                address,
+            ).write_u32(offset, bitmap_block_address as u32)?;
 
             reserved_blocks.push(bitmap_block_address);
             bitmap_blocks.push(bitmap_block_address);
@@ -339,6 +413,44 @@ impl AmigaDosInner {
             })
     }
 
+    /// Rebuilds the allocation bitmap so that exactly the given blocks are
+    /// marked as allocated.
+    pub fn rebuild_bitmap(
+        &mut self,
+        used_blocks: &[bool],
+    ) -> Result<(), Error> {
+        for bitmap_block in self.bitmap_block_iter() {
+            Block::new(
+                self.disk(),
+                bitmap_block.address,
+            ).fill(0xff, 0, BLOCK_SIZE)?;
+        }
+
+        for bitmap_block in self.bitmap_block_iter() {
+            for address in bitmap_block.address_range.clone() {
+                let action = if used_blocks[address] {
+                    BitmapAction::Alloc
+                } else {
+                    BitmapAction::Free
+                };
+
+                bitmap_block.clone().update_block(address, action)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_block_free(
+        &self,
+        address: LBAAddress,
+    ) -> Result<bool, Error> {
+        self.bitmap_block_iter()
+            .find(|bitmap_block| bitmap_block.contains_block(address))
+            .ok_or(Error::DiskInvalidLBAAddressError(address))
+            .and_then(|bitmap_block| bitmap_block.is_block_free(address))
+    }
+
     pub fn total_block_count(
         &self,
     ) -> usize {
@@ -6,10 +6,12 @@ use crate::block::Block;
 use crate::errors::Error;
 
 use super::amiga_dos::*;
+use super::amiga_dos_options::*;
 use super::block_type::*;
 use super::checksum::*;
 use super::constants::*;
 use super::datetime::*;
+use super::metadata::Permissions;
 use super::name::*;
 
 
@@ -18,13 +20,116 @@ impl Block {
         &self,
     ) -> Result<u32, Error> {
         let disk = self.disk.borrow();
-        let disk_data = disk.blocks(self.address, 1)?;
+        let disk_data = disk.block(self.address)?;
 
         Ok(compute_checksum(disk_data, BLOCK_SIZE))
     }
 }
 
 
+// Entry headers of long name filesystems store their name in the comment
+// area, followed by their comment. The root block keeps the classic layout,
+// its comment area being used by the bitmap pages.
+impl Block {
+    pub fn has_long_name_layout(
+        &self,
+    ) -> Result<bool, Error> {
+        if self.read_block_secondary_type()? == BlockSecondaryType::Root {
+            return Ok(false);
+        }
+
+        let flags = self.disk.borrow().block(0)?[BOOT_BLOCK_FLAGS_OFFSET];
+
+        Ok(DosType::try_from(flags)
+            .is_ok_and(|dos_type| dos_type.long_name_mode() == LongNameMode::On))
+    }
+
+    // returns the offsets of the name size and of the name, and its maximum
+    // size
+    fn name_layout(
+        &self,
+    ) -> Result<(usize, usize, usize), Error> {
+        if self.has_long_name_layout()? {
+            Ok((
+                BLOCK_LONG_NAME_SIZE_OFFSET,
+                BLOCK_LONG_NAME_OFFSET,
+                BLOCK_LONG_NAME_MAX_SIZE,
+            ))
+        } else {
+            Ok((
+                BLOCK_NAME_SIZE_OFFSET,
+                BLOCK_NAME_OFFSET,
+                BLOCK_NAME_MAX_SIZE,
+            ))
+        }
+    }
+
+    // returns the offset of the alteration days, followed by the minutes and
+    // the ticks
+    fn alteration_date_offset(
+        &self,
+    ) -> Result<usize, Error> {
+        if self.has_long_name_layout()? {
+            Ok(BLOCK_LONG_NAME_DAYS_OFFSET)
+        } else {
+            Ok(BLOCK_ALTERATION_DAYS_OFFSET)
+        }
+    }
+
+    // returns the offset of the comment size and the maximum comment size
+    fn comment_layout(
+        &self,
+    ) -> Result<(usize, usize), Error> {
+        if self.has_long_name_layout()? {
+            let name_len = (self.read_u8(BLOCK_LONG_NAME_SIZE_OFFSET)? as usize)
+                .min(BLOCK_LONG_NAME_MAX_SIZE);
+
+            Ok((
+                BLOCK_LONG_NAME_OFFSET + name_len,
+                BLOCK_COMMENT_MAX_SIZE.min(BLOCK_LONG_NAME_AREA_SIZE - name_len - 2),
+            ))
+        } else {
+            Ok((BLOCK_COMMENT_SIZE_OFFSET, BLOCK_COMMENT_MAX_SIZE))
+        }
+    }
+
+    fn read_long_name_area_comment(
+        &self,
+    ) -> Result<Vec<u8>, Error> {
+        let (size_offset, max_size) = self.comment_layout()?;
+        let len = (self.read_u8(size_offset)? as usize).min(max_size);
+
+        self.read_u8_vector(size_offset + 1, len)
+    }
+
+    fn write_long_name_area(
+        &mut self,
+        name: &[u8],
+        comment: &[u8],
+    ) -> Result<(), Error> {
+        if name.len() > BLOCK_LONG_NAME_MAX_SIZE {
+            return Err(Error::InvalidNameLengthError(name.len()));
+        }
+
+        if name.len() + comment.len() + 2 > BLOCK_LONG_NAME_AREA_SIZE {
+            return Err(Error::InvalidCommentLengthError(comment.len()));
+        }
+
+        let comment_size_offset = BLOCK_LONG_NAME_OFFSET + name.len();
+
+        self.fill(
+            0,
+            BLOCK_LONG_NAME_SIZE_OFFSET,
+            BLOCK_LONG_NAME_SIZE_OFFSET + BLOCK_LONG_NAME_AREA_SIZE,
+        )?;
+        self.write_u8(BLOCK_LONG_NAME_SIZE_OFFSET, name.len() as u8)?;
+        self.write_u8_array(BLOCK_LONG_NAME_OFFSET, name)?;
+        self.write_u8(comment_size_offset, comment.len() as u8)?;
+        self.write_u8_array(comment_size_offset + 1, comment)
+    }
+}
+
+
 impl Block {
     pub fn read_block_primary_type(
         &self,
@@ -73,24 +178,48 @@ impl Block {
 
 
 impl Block {
-    pub fn read_bitmap(
+    /// Returns the addresses of the bitmap blocks and of the bitmap
+    /// extension blocks, following the bitmap extension chain.
+    pub fn read_bitmap_with_extensions(
         &self,
-    ) -> Result<Vec<LBAAddress>, Error> {
+    ) -> Result<(Vec<LBAAddress>, Vec<LBAAddress>), Error> {
         self.check_block_primary_type(&[BlockPrimaryType::Header])?;
         self.check_block_secondary_type(&[BlockSecondaryType::Root])?;
 
-        let v = self.read_u32_vector(
+        let block_count = self.disk.borrow().block_count();
+
+        let mut pages = self.read_u32_vector(
             ROOT_BLOCK_BITMAP_PAGES_OFFSET,
             ROOT_BLOCK_BITMAP_PAGES_SIZE,
         )?;
+        let mut extensions = Vec::new();
+        let mut next = AmigaDos::to_address(self.read_u32(ROOT_BLOCK_BITMAP_EXT_OFFSET)?);
 
-        Ok(v.iter().copied().filter_map(|addr| {
-            if addr != 0 {
-                Some(addr as LBAAddress)
-            } else {
-                None
+        while let Some(addr) = next {
+            // a chain longer than the disk is a loop
+            if extensions.len() >= block_count {
+                return Err(Error::CorruptedImageFile);
             }
-        }).collect())
+
+            let block = Block::new(self.disk.clone(), addr);
+
+            pages.extend(block.read_u32_vector(
+                BITMAP_EXT_BLOCK_PAGES_OFFSET,
+                BITMAP_EXT_BLOCK_PAGES_SIZE,
+            )?);
+            extensions.push(addr);
+            next = AmigaDos::to_address(block.read_u32(BITMAP_EXT_BLOCK_NEXT_OFFSET)?);
+        }
+
+        let pages = pages.iter().copied().filter_map(AmigaDos::to_address).collect();
+
+        Ok((pages, extensions))
+    }
+
+    pub fn read_bitmap(
+        &self,
+    ) -> Result<Vec<LBAAddress>, Error> {
+        Ok(self.read_bitmap_with_extensions()?.0)
     }
 
     pub fn read_block_table_address(
@@ -139,10 +268,11 @@ impl Block {
             BlockSecondaryType::SoftLink,
         ])?;
 
-        let len = self.read_u8(BLOCK_NAME_SIZE_OFFSET)? as usize;
+        let (size_offset, offset, max_size) = self.name_layout()?;
+        let len = self.read_u8(size_offset)? as usize;
 
-        if len <= BLOCK_NAME_MAX_SIZE {
-            let name = self.read_string(BLOCK_NAME_OFFSET, len)?;
+        if len <= max_size {
+            let name = decode_latin1(&self.read_u8_vector(offset, len)?);
 
             check_name(&name)?;
             Ok(name)
@@ -151,6 +281,70 @@ impl Block {
         }
     }
 
+    /// Reads the comment of an entry header block, if any. The root block
+    /// has no comment.
+    pub fn read_comment(
+        &self,
+    ) -> Result<Option<String>, Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+
+        if self.read_block_secondary_type()? == BlockSecondaryType::Root {
+            return Ok(None);
+        }
+
+        if let Some(addr) = self.read_comment_block_address()? {
+            return Block::new(self.disk.clone(), addr)
+                .read_comment_block_comment()
+                .map(Some);
+        }
+
+        let (size_offset, max_size) = self.comment_layout()?;
+        let len = self.read_u8(size_offset)? as usize;
+
+        if len == 0 {
+            Ok(None)
+        } else if len <= max_size {
+            let comment = decode_latin1(&self.read_u8_vector(size_offset + 1, len)?);
+
+            check_name(&comment)?;
+            Ok(Some(comment))
+        } else {
+            Err(Error::InvalidCommentLengthError(len))
+        }
+    }
+
+    /// Reads the address of the comment block of an entry header block, long
+    /// name entries store there a comment which doesn't fit along with their
+    /// name.
+    pub fn read_comment_block_address(
+        &self,
+    ) -> Result<Option<LBAAddress>, Error> {
+        if self.has_long_name_layout()? {
+            Ok(AmigaDos::to_address(self.read_u32(BLOCK_COMMENT_BLOCK_OFFSET)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn read_comment_block_comment(
+        &self,
+    ) -> Result<String, Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Comment])?;
+
+        let len = self.read_u8(COMMENT_BLOCK_COMMENT_SIZE_OFFSET)? as usize;
+
+        if len <= BLOCK_COMMENT_MAX_SIZE {
+            let comment = decode_latin1(
+                &self.read_u8_vector(COMMENT_BLOCK_COMMENT_OFFSET, len)?
+            );
+
+            check_name(&comment)?;
+            Ok(comment)
+        } else {
+            Err(Error::InvalidCommentLengthError(len))
+        }
+    }
+
     pub fn read_file_size(
         &self,
     ) -> Result<usize, Error> {
@@ -162,6 +356,20 @@ impl Block {
         Ok(file_size)
     }
 
+    /// Reads the protection bits of an entry header block. The root block
+    /// has none, default permissions are returned for it.
+    pub fn read_permissions(
+        &self,
+    ) -> Result<Permissions, Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+
+        if self.read_block_secondary_type()? == BlockSecondaryType::Root {
+            return Ok(Permissions::default());
+        }
+
+        Ok(Permissions::from_bits(self.read_u32(BLOCK_PROTECT_OFFSET)?))
+    }
+
     pub fn read_alteration_date(
         &self,
     ) -> Result<SystemTime, Error> {
@@ -172,15 +380,26 @@ impl Block {
             BlockSecondaryType::HardLinkFile,
             BlockSecondaryType::HardLinkDirectory,
             BlockSecondaryType::Root,
+            BlockSecondaryType::SoftLink,
         ])?;
 
-        let days = self.read_u32(BLOCK_ALTERATION_DAYS_OFFSET)?;
-        let mins = self.read_u32(BLOCK_ALTERATION_MINS_OFFSET)?;
-        let ticks = self.read_u32(BLOCK_ALTERATION_TICKS_OFFSET)?;
+        let (days, mins, ticks) = self.read_alteration_date_triplet()?;
 
         Ok(date_triplet_to_system_time(days, mins, ticks))
     }
 
+    pub fn read_alteration_date_triplet(
+        &self,
+    ) -> Result<(u32, u32, u32), Error> {
+        let offset = self.alteration_date_offset()?;
+
+        Ok((
+            self.read_u32(offset)?,
+            self.read_u32(offset + 4)?,
+            self.read_u32(offset + 8)?,
+        ))
+    }
+
     pub fn read_disk_alteration_date(
         &self,
     ) -> Result<SystemTime, Error> {
@@ -207,6 +426,47 @@ impl Block {
         Ok(date_triplet_to_system_time(days, mins, ticks))
     }
 
+    pub fn read_soft_link_target(
+        &self,
+    ) -> Result<String, Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+        self.check_block_secondary_type(&[BlockSecondaryType::SoftLink])?;
+
+        let bytes = self.read_u8_vector(
+            SOFT_LINK_BLOCK_TARGET_OFFSET,
+            SOFT_LINK_BLOCK_TARGET_MAX_SIZE,
+        )?;
+        let len = bytes.iter().position(|c| *c == 0).unwrap_or(bytes.len());
+
+        Ok(decode_latin1(&bytes[..len]))
+    }
+
+    pub fn read_real_entry_address(
+        &self,
+    ) -> Result<Option<LBAAddress>, Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+        self.check_block_secondary_type(&[
+            BlockSecondaryType::HardLinkDirectory,
+            BlockSecondaryType::HardLinkFile,
+        ])?;
+
+        Ok(AmigaDos::to_address(self.read_u32(BLOCK_REAL_ENTRY_OFFSET)?))
+    }
+
+    pub fn read_next_link_address(
+        &self,
+    ) -> Result<Option<LBAAddress>, Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+        self.check_block_secondary_type(&[
+            BlockSecondaryType::Directory,
+            BlockSecondaryType::File,
+            BlockSecondaryType::HardLinkDirectory,
+            BlockSecondaryType::HardLinkFile,
+        ])?;
+
+        Ok(AmigaDos::to_address(self.read_u32(BLOCK_NEXT_LINK_OFFSET)?))
+    }
+
     pub fn read_parent_block_address(
         &self,
     ) -> Result<Option<LBAAddress>, Error> {
@@ -230,9 +490,11 @@ impl Block {
             ticks,
         ) = date_triplet_from_system_time(datetime);
 
-        self.write_u32(BLOCK_ALTERATION_DAYS_OFFSET, days)?;
-        self.write_u32(BLOCK_ALTERATION_MINS_OFFSET, mins)?;
-        self.write_u32(BLOCK_ALTERATION_TICKS_OFFSET, ticks)?;
+        let offset = self.alteration_date_offset()?;
+
+        self.write_u32(offset, days)?;
+        self.write_u32(offset + 4, mins)?;
+        self.write_u32(offset + 8, ticks)?;
 
         Ok(())
     }
@@ -287,24 +549,161 @@ impl Block {
         Ok(())
     }
 
+    /// Checks that a name can be written to the block, without writing it.
+    pub fn check_writable_name(
+        &self,
+        name: &str,
+    ) -> Result<(), Error> {
+        check_name(name)?;
+
+        let len = encode_latin1(name)?.len();
+        let max_len = if self.has_long_name_layout()? {
+            BLOCK_LONG_NAME_MAX_SIZE
+        } else {
+            BLOCK_NAME_MAX_SIZE
+        };
+
+        if len <= max_len {
+            Ok(())
+        } else {
+            Err(Error::InvalidNameLengthError(len))
+        }
+    }
+
     pub fn write_name(
         &mut self,
         name: &str,
     ) -> Result<(), Error> {
         check_name(name)?;
 
-        let bytes = name.as_bytes();
+        let bytes = encode_latin1(name)?;
         let len = bytes.len();
 
+        if self.has_long_name_layout()? {
+            let comment = self.read_long_name_area_comment()?;
+
+            return self.write_long_name_area(&bytes, &comment);
+        }
+
         if len <= BLOCK_NAME_MAX_SIZE {
+            self.fill(
+                0,
+                BLOCK_NAME_OFFSET,
+                BLOCK_NAME_OFFSET + BLOCK_NAME_MAX_SIZE,
+            )?;
             self.write_u8(BLOCK_NAME_SIZE_OFFSET, len as u8)?;
-            self.write_u8_array(BLOCK_NAME_OFFSET, bytes)?;
+            self.write_u8_array(BLOCK_NAME_OFFSET, &bytes)?;
             Ok(())
         } else {
             Err(Error::InvalidNameLengthError(len))
         }
     }
 
+    pub fn write_comment(
+        &mut self,
+        comment: Option<&str>,
+    ) -> Result<(), Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+        self.check_block_secondary_type(&[
+            BlockSecondaryType::Directory,
+            BlockSecondaryType::File,
+            BlockSecondaryType::HardLinkDirectory,
+            BlockSecondaryType::HardLinkFile,
+            BlockSecondaryType::SoftLink,
+        ])?;
+
+        let bytes = encode_latin1(comment.unwrap_or_default())?;
+        let len = bytes.len();
+
+        if len > BLOCK_COMMENT_MAX_SIZE {
+            return Err(Error::InvalidCommentLengthError(len));
+        }
+
+        if let Some(comment) = comment {
+            check_name(comment)?;
+        }
+
+        if self.has_long_name_layout()? {
+            let (size_offset, offset, _) = self.name_layout()?;
+            let name = self.read_u8_vector(offset, self.read_u8(size_offset)? as usize)?;
+
+            return self.write_long_name_area(&name, &bytes);
+        }
+
+        self.fill(
+            0,
+            BLOCK_COMMENT_OFFSET,
+            BLOCK_COMMENT_OFFSET + BLOCK_COMMENT_MAX_SIZE,
+        )?;
+        self.write_u8(BLOCK_COMMENT_SIZE_OFFSET, len as u8)?;
+        self.write_u8_array(BLOCK_COMMENT_OFFSET, &bytes)
+    }
+
+    pub fn write_comment_block_address(
+        &mut self,
+        addr: Option<LBAAddress>,
+    ) -> Result<(), Error> {
+        self.write_u32(BLOCK_COMMENT_BLOCK_OFFSET, addr.unwrap_or(0) as u32)
+    }
+
+    pub fn write_comment_block_comment(
+        &mut self,
+        comment: &str,
+    ) -> Result<(), Error> {
+        check_name(comment)?;
+
+        let bytes = encode_latin1(comment)?;
+        let len = bytes.len();
+
+        if len > BLOCK_COMMENT_MAX_SIZE {
+            return Err(Error::InvalidCommentLengthError(len));
+        }
+
+        self.fill(
+            0,
+            COMMENT_BLOCK_COMMENT_OFFSET,
+            COMMENT_BLOCK_COMMENT_OFFSET + BLOCK_COMMENT_MAX_SIZE,
+        )?;
+        self.write_u8(COMMENT_BLOCK_COMMENT_SIZE_OFFSET, len as u8)?;
+        self.write_u8_array(COMMENT_BLOCK_COMMENT_OFFSET, &bytes)
+    }
+
+    pub fn write_soft_link_target(
+        &mut self,
+        target: &str,
+    ) -> Result<(), Error> {
+        let bytes = encode_latin1(target).map_err(|_| Error::InvalidPathError)?;
+
+        if bytes.is_empty()
+        || bytes.len() > SOFT_LINK_BLOCK_TARGET_MAX_SIZE
+        || bytes.contains(&0) {
+            return Err(Error::InvalidPathError);
+        }
+
+        self.fill(
+            0,
+            SOFT_LINK_BLOCK_TARGET_OFFSET,
+            SOFT_LINK_BLOCK_TARGET_OFFSET + SOFT_LINK_BLOCK_TARGET_MAX_SIZE + 1,
+        )?;
+        self.write_u8_array(SOFT_LINK_BLOCK_TARGET_OFFSET, &bytes)
+    }
+
+    pub fn write_permissions(
+        &mut self,
+        permissions: Permissions,
+    ) -> Result<(), Error> {
+        self.check_block_primary_type(&[BlockPrimaryType::Header])?;
+        self.check_block_secondary_type(&[
+            BlockSecondaryType::Directory,
+            BlockSecondaryType::File,
+            BlockSecondaryType::HardLinkFile,
+            BlockSecondaryType::HardLinkDirectory,
+            BlockSecondaryType::SoftLink,
+        ])?;
+
+        self.write_u32(BLOCK_PROTECT_OFFSET, permissions.bits())
+    }
+
     pub fn write_file_size(
         &mut self,
         file_size: usize,
@@ -320,7 +719,7 @@ impl Block {
     ) -> Result<(), Error> {
         let chksum = {
             let disk = self.disk.borrow();
-            let disk_data = disk.blocks(self.address, 1)?;
+            let disk_data = disk.block(self.address)?;
 
             compute_checksum(disk_data, BLOCK_CHECKSUM_OFFSET)
         };
@@ -350,6 +749,20 @@ impl Block {
         }
     }
 
+    pub fn write_real_entry_address(
+        &mut self,
+        address: LBAAddress,
+    ) -> Result<(), Error> {
+        self.write_u32(BLOCK_REAL_ENTRY_OFFSET, address as u32)
+    }
+
+    pub fn write_next_link_address(
+        &mut self,
+        address: LBAAddress,
+    ) -> Result<(), Error> {
+        self.write_u32(BLOCK_NEXT_LINK_OFFSET, address as u32)
+    }
+
     pub fn write_hash_chain_next_address(
         &mut self,
         address: LBAAddress,
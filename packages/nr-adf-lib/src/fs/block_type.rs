@@ -3,9 +3,11 @@ use crate::errors::Error;
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BlockPrimaryType {
-    Header = 2,
-    Data   = 8,
-    List   = 16,
+    Header   = 2,
+    Data     = 8,
+    List     = 16,
+    DirCache = 33,
+    Comment  = 64,
 }
 
 impl From<BlockPrimaryType> for u32 {
@@ -28,6 +30,12 @@ impl TryFrom<u32> for BlockPrimaryType {
             _ if value == BlockPrimaryType::List.into() => {
                 Ok(BlockPrimaryType::List)
             },
+            _ if value == BlockPrimaryType::DirCache.into() => {
+                Ok(BlockPrimaryType::DirCache)
+            },
+            _ if value == BlockPrimaryType::Comment.into() => {
+                Ok(BlockPrimaryType::Comment)
+            },
             _  => Err(Error::InvalidFilesystemBlockPrimaryTypeError(value)),
         }
     }
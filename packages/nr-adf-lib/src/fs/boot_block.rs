@@ -1,8 +1,6 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos_options::*;
 use super::constants::*;
@@ -27,20 +25,36 @@ fn compute_checksum(data: &[u8]) -> u32 {
     !checksum
 }
 
+// the boot block spans the two first blocks of the disk
+pub(super) fn read_boot_block(disk: &Disk) -> Result<[u8; 2*BLOCK_SIZE], Error> {
+    let mut data = [0; 2*BLOCK_SIZE];
+
+    data[..BLOCK_SIZE].copy_from_slice(disk.block(0)?);
+    data[BLOCK_SIZE..].copy_from_slice(disk.block(1)?);
+    Ok(data)
+}
+
+fn write_boot_block(disk: &mut Disk, data: &[u8; 2*BLOCK_SIZE]) -> Result<(), Error> {
+    disk.block_mut(0)?.copy_from_slice(&data[..BLOCK_SIZE]);
+    disk.block_mut(1)?.copy_from_slice(&data[BLOCK_SIZE..]);
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct BootBlockReader {
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
 }
 
 impl BootBlockReader {
     fn check(disk: &Disk) -> Result<(), Error> {
-        let data = disk.blocks(0, 2)?;
+        let data = read_boot_block(disk)?;
 
-        if &data[0..3] != BOOT_BLOCK_MAGIC_NUMBER {
-            return Err(Error::CorruptedImageFile);
+        if &data[BOOT_BLOCK_MAGIC_NUMBER_SLICE] != BOOT_BLOCK_MAGIC_NUMBER
+        || DosType::try_from(data[BOOT_BLOCK_FLAGS_OFFSET]).is_err() {
+            return Err(Error::NotADosDisk(data[..4].try_into().unwrap()));
         }
 
-        let checksum = compute_checksum(data);
+        let checksum = compute_checksum(&data);
         let expected = u32::from_be_bytes(data[4..8].try_into().unwrap());
 
         if expected != 0
@@ -51,7 +65,7 @@ impl BootBlockReader {
         Ok(())
     }
 
-    pub fn try_from_disk(disk: Rc<RefCell<Disk>>) -> Result<Self, Error> {
+    pub fn try_from_disk(disk: Shared<Disk>) -> Result<Self, Error> {
         BootBlockReader::check(&disk.borrow())?;
         Ok(Self {
             disk,
@@ -60,60 +74,47 @@ impl BootBlockReader {
 }
 
 impl BootBlockReader {
-    pub fn get_filesystem_type(&self) -> FilesystemType {
-        let flags = self.disk.borrow().data()[3];
+    pub fn get_dos_type(&self) -> DosType {
+        let flags = self.disk.borrow().block(0).unwrap()[BOOT_BLOCK_FLAGS_OFFSET];
 
-        if flags & (FilesystemType::FFS as u8) != 0 {
-            FilesystemType::FFS
-        } else {
-            FilesystemType::OFS
-        }
+        DosType::try_from(flags).unwrap_or_default()
     }
 
-    pub fn get_international_mode(&self) -> InternationalMode {
-        let flags = self.disk.borrow().data()[3];
+    pub fn get_filesystem_type(&self) -> FilesystemType {
+        self.get_dos_type().filesystem_type()
+    }
 
-        if flags & (InternationalMode::On as u8) != 0 {
-            InternationalMode::On
-        } else {
-            InternationalMode::Off
-        }
+    pub fn get_international_mode(&self) -> InternationalMode {
+        self.get_dos_type().international_mode()
     }
 
     pub fn get_cache_mode(&self) -> CacheMode {
-        let flags = self.disk.borrow().data()[3];
+        self.get_dos_type().cache_mode()
+    }
 
-        if flags & (CacheMode::On as u8) != 0 {
-            CacheMode::On
-        } else {
-            CacheMode::Off
-        }
+    pub fn get_long_name_mode(&self) -> LongNameMode {
+        self.get_dos_type().long_name_mode()
     }
 
     pub fn get_root_block_address(&self) -> LBAAddress {
         u32::from_be_bytes(
-            self.disk.borrow().data()[BOOT_BLOCK_ROOT_BLOCK_SLICE].try_into().unwrap()
+            self.disk.borrow().block(0).unwrap()[BOOT_BLOCK_ROOT_BLOCK_SLICE].try_into().unwrap()
         ) as usize
     }
 
-    // pub fn get_boot_code(&self) -> &[u8] {
-    //     &self.data[BOOT_BLOCK_BOOT_CODE_SLICE]
-    // }
+    pub fn get_boot_code(&self) -> [u8; BOOT_BLOCK_BOOT_CODE_SIZE] {
+        let data = read_boot_block(&self.disk.borrow()).unwrap();
+
+        data[BOOT_BLOCK_BOOT_CODE_SLICE].try_into().unwrap()
+    }
 
-    // pub fn get_checksum(&self) -> u32 {
-    //     u32::from_be_bytes(
-    //         self.data[BOOT_BLOCK_CHECKSUM_SLICE].try_into().unwrap()
-    //     )
-    // }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct BootBlockInitializer {
     boot_code: [u8; BOOT_BLOCK_BOOT_CODE_SIZE],
     root_block_address: Option<LBAAddress>,
-    filesystem_type: FilesystemType,
-    international_mode: InternationalMode,
-    cache_mode: CacheMode,
+    dos_type: DosType,
 }
 
 impl Default for BootBlockInitializer {
@@ -121,70 +122,47 @@ impl Default for BootBlockInitializer {
         Self {
             boot_code: [0u8; BOOT_BLOCK_BOOT_CODE_SIZE],
             root_block_address: None,
-            filesystem_type: FilesystemType::OFS,
-            cache_mode: CacheMode::Off,
-            international_mode: InternationalMode::Off,
+            dos_type: DosType::OFS,
         }
     }
 }
 
 impl BootBlockInitializer {
-    // pub fn with_boot_code(
-    //     &mut self,
-    //     boot_code: &[u8; BOOT_BLOCK_BOOT_CODE_SIZE],
-    // ) -> &mut Self {
-    //     self.boot_code.copy_from_slice(boot_code);
-    //     self
-    // }
-
-    pub fn with_root_block_address(
+    pub fn with_boot_code(
         &mut self,
-        addr: Option<LBAAddress>,
+        boot_code: &[u8; BOOT_BLOCK_BOOT_CODE_SIZE],
     ) -> &mut Self {
-        self.root_block_address = addr;
+        self.boot_code.copy_from_slice(boot_code);
         self
     }
 
-    pub fn with_filesystem_type(
+    pub fn with_root_block_address(
         &mut self,
-        filesystem_type: FilesystemType,
+        addr: Option<LBAAddress>,
     ) -> &mut Self {
-        self.filesystem_type = filesystem_type;
-        self
-    }
-
-    pub fn with_international_mode(
-        &mut self,
-        international_mode: InternationalMode,
-    )-> &mut Self {
-        self.international_mode = international_mode;
+        self.root_block_address = addr;
         self
     }
 
-    pub fn with_cache_mode(
+    pub fn with_dos_type(
         &mut self,
-        cache_mode: CacheMode,
+        dos_type: DosType,
     ) -> &mut Self {
-        self.cache_mode = cache_mode;
+        self.dos_type = dos_type;
         self
     }
 
     pub fn init(
         &self,
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
     ) -> Result<(), Error> {
         let mut disk = disk.borrow_mut();
         let root_block_address =
             self.root_block_address.unwrap_or_else(|| disk.block_count()/2) as u32;
 
-        let data = disk.blocks_mut(0, 2)?;
-
-        let flags: u8 =
-            self.cache_mode as u8
-            | self.filesystem_type as u8
-            | self.international_mode as u8;
+        let mut data = read_boot_block(&disk)?;
 
-        data[BOOT_BLOCK_FLAGS_OFFSET] = flags;
+        data[BOOT_BLOCK_FLAGS_OFFSET] = self.dos_type as u8;
         data[BOOT_BLOCK_MAGIC_NUMBER_SLICE].copy_from_slice(
             BOOT_BLOCK_MAGIC_NUMBER
         );
@@ -196,12 +174,12 @@ impl BootBlockInitializer {
             &self.boot_code,
         );
 
-        let checksum = compute_checksum(data);
+        let checksum = compute_checksum(&data);
 
         data[BOOT_BLOCK_CHECKSUM_SLICE].copy_from_slice(
             &checksum.to_be_bytes(),
         );
 
-        Ok(())
+        write_boot_block(&mut disk, &data)
     }
 }
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::*;
+
+use super::amiga_dos::*;
+use super::amiga_dos_options::*;
+use super::boot_block::*;
+use super::constants::*;
+
+
+// Boot code written by the Install command of Workbench 1.3, it finds the
+// dos.library resident module and returns its init function
+const OS13_BOOT_CODE: &[u8] = &[
+    0x43, 0xfa, 0x00, 0x18, // lea     dos_name(pc),a1
+    0x4e, 0xae, 0xff, 0xa0, // jsr     _LVOFindResident(a6)
+    0x4a, 0x80,             // tst.l   d0
+    0x67, 0x0a,             // beq.s   fail
+    0x20, 0x40,             // move.l  d0,a0
+    0x20, 0x68, 0x00, 0x16, // move.l  RT_INIT(a0),a0
+    0x70, 0x00,             // moveq   #0,d0
+    0x4e, 0x75,             // rts
+    0x70, 0xff,             // fail: moveq #-1,d0
+    0x60, 0xfa,             // bra.s   rts
+    b'd', b'o', b's', b'.', b'l', b'i', b'b', b'r', b'a', b'r', b'y', 0,
+];
+
+// Boot code written by the Install command of Workbench 2.0 and later, it
+// also tells expansion.library that the boot happened from a floppy disk
+const OS20_BOOT_CODE: &[u8] = &[
+    0x43, 0xfa, 0x00, 0x3e, // lea     expansion_name(pc),a1
+    0x70, 0x25,             // moveq   #37,d0
+    0x4e, 0xae, 0xfd, 0xd8, // jsr     _LVOOpenLibrary(a6)
+    0x4a, 0x80,             // tst.l   d0
+    0x67, 0x0c,             // beq.s   no_expansion
+    0x22, 0x40,             // move.l  d0,a1
+    0x08, 0xe9, 0x00, 0x06, // bset    #EBB_SILENTSTART,eb_Flags(a1)
+    0x00, 0x22,
+    0x4e, 0xae, 0xfe, 0x62, // jsr     _LVOCloseLibrary(a6)
+    0x43, 0xfa, 0x00, 0x18, // no_expansion: lea dos_name(pc),a1
+    0x4e, 0xae, 0xff, 0xa0, // jsr     _LVOFindResident(a6)
+    0x4a, 0x80,             // tst.l   d0
+    0x67, 0x0a,             // beq.s   fail
+    0x20, 0x40,             // move.l  d0,a0
+    0x20, 0x68, 0x00, 0x16, // move.l  RT_INIT(a0),a0
+    0x70, 0x00,             // moveq   #0,d0
+    0x4e, 0x75,             // rts
+    0x70, 0xff,             // fail: moveq #-1,d0
+    0x4e, 0x75,             // rts
+    b'd', b'o', b's', b'.', b'l', b'i', b'b', b'r', b'a', b'r', b'y', 0,
+    b'e', b'x', b'p', b'a', b'n', b's', b'i', b'o', b'n', b'.',
+    b'l', b'i', b'b', b'r', b'a', b'r', b'y', 0,
+];
+
+/// The boot codes written by the AmigaOS Install command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StandardBootCode {
+    /// Workbench 1.3, which only boots OFS and FFS disks.
+    OS13,
+    /// Workbench 2.0 and later.
+    OS20,
+}
+
+impl StandardBootCode {
+    /// Returns the boot code able to boot a filesystem of the given DOS type,
+    /// the oldest one being preferred.
+    pub fn for_dos_type(dos_type: DosType) -> Self {
+        match dos_type {
+            DosType::OFS|DosType::FFS => StandardBootCode::OS13,
+            _ => StandardBootCode::OS20,
+        }
+    }
+
+    pub fn boot_code(&self) -> [u8; BOOT_BLOCK_BOOT_CODE_SIZE] {
+        let code = match self {
+            StandardBootCode::OS13 => OS13_BOOT_CODE,
+            StandardBootCode::OS20 => OS20_BOOT_CODE,
+        };
+        let mut boot_code = [0u8; BOOT_BLOCK_BOOT_CODE_SIZE];
+
+        boot_code[..code.len()].copy_from_slice(code);
+        boot_code
+    }
+}
+
+impl FromStr for StandardBootCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "1.3"|"os13" => Ok(StandardBootCode::OS13),
+            "2.0"|"os20" => Ok(StandardBootCode::OS20),
+            _ => Err(Error::InvalidBootCodeError),
+        }
+    }
+}
+
+impl fmt::Display for StandardBootCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StandardBootCode::OS13 => write!(f, "OS 1.3"),
+            StandardBootCode::OS20 => write!(f, "OS 2.0+"),
+        }
+    }
+}
+
+/// What the boot code of a disk is, as identified from its signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootCodeKind {
+    /// The boot code is zeroed, the disk isn't bootable.
+    Empty,
+    Standard(StandardBootCode),
+    /// A known boot block virus.
+    Virus(&'static str),
+    /// A known trackloader or custom boot code, e.g. of a game or a demo.
+    Loader(&'static str),
+    Unknown,
+}
+
+impl fmt::Display for BootCodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootCodeKind::Empty => write!(f, "not bootable"),
+            BootCodeKind::Standard(code) => write!(f, "standard {code} boot code"),
+            BootCodeKind::Virus(name) => write!(f, "{name} virus"),
+            BootCodeKind::Loader(name) => write!(f, "{name} loader"),
+            BootCodeKind::Unknown => write!(f, "unknown boot code"),
+        }
+    }
+}
+
+impl AmigaDos {
+    /// Returns the boot code of the disk.
+    pub fn boot_code(&self) -> Result<[u8; BOOT_BLOCK_BOOT_CODE_SIZE], Error> {
+        let _guard = self.lock.read();
+
+        Ok(self.inner.borrow().get_boot_block()?.get_boot_code())
+    }
+
+    /// Writes a boot code into the boot block and updates its checksum. A
+    /// boot code shorter than the boot code area is padded with zeros.
+    /// Errors:
+    /// - When `boot_code` is larger than 1012 bytes.
+    pub fn install_boot_code(
+        &mut self,
+        boot_code: &[u8],
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        if boot_code.len() > BOOT_BLOCK_BOOT_CODE_SIZE {
+            return Err(Error::InvalidBootCodeSizeError(boot_code.len()));
+        }
+
+        let mut data = [0u8; BOOT_BLOCK_BOOT_CODE_SIZE];
+        let boot_block = self.inner.borrow().get_boot_block()?;
+
+        data[..boot_code.len()].copy_from_slice(boot_code);
+
+        BootBlockInitializer::default()
+            .with_root_block_address(Some(boot_block.get_root_block_address()))
+            .with_dos_type(boot_block.get_dos_type())
+            .with_boot_code(&data)
+            .init(self.disk())
+    }
+
+    /// Writes the standard boot code matching the DOS type of the disk.
+    pub fn install_standard_boot_code(&mut self) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let dos_type = self.inner.borrow().get_boot_block()?.get_dos_type();
+
+        self.install_boot_code(&StandardBootCode::for_dos_type(dos_type).boot_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs(dos_type: DosType) -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+
+        AmigaDosFormater::default()
+            .with_dos_type(dos_type)
+            .format(shared(disk), "TEST")
+            .unwrap()
+    }
+
+    #[test]
+    fn identify_boot_code_is_ok() {
+        for code in [StandardBootCode::OS13, StandardBootCode::OS20] {
+            assert_eq!(
+                identify_boot_code(&code.boot_code()),
+                BootCodeKind::Standard(code),
+            );
+        }
+
+        let mut boot_code = StandardBootCode::OS13.boot_code();
+
+        boot_code[100] = 0x4e;
+
+        assert_eq!(identify_boot_code(&boot_code), BootCodeKind::Unknown);
+        assert_eq!(identify_boot_code(&[0; 1012]), BootCodeKind::Empty);
+    }
+
+    #[test]
+    fn identify_loader_boot_code_is_ok() {
+        let mut boot_code = [0u8; 1012];
+
+        // move.b #0x7f,0xbfd100
+        boot_code[..8].copy_from_slice(b"\x13\xfc\x00\x7f\x00\xbf\xd1\x00");
+        assert_eq!(identify_boot_code(&boot_code), BootCodeKind::Loader("hardware"));
+
+        // jsr _LVODoIO(a6)
+        boot_code[..8].copy_from_slice(b"\x4e\xae\xfe\x38\x4e\x75\x00\x00");
+        assert_eq!(identify_boot_code(&boot_code), BootCodeKind::Loader("trackdisk.device"));
+        assert_eq!(BootCodeKind::Loader("hardware").to_string(), "hardware loader");
+    }
+
+    #[test]
+    fn install_boot_code_is_ok() {
+        let mut fs = init_fs(DosType::FFSIntl);
+
+        assert_eq!(identify_boot_code(&fs.boot_code().unwrap()), BootCodeKind::Empty);
+
+        fs.install_standard_boot_code().unwrap();
+
+        // the boot block checksum is checked when the filesystem is mounted
+        let fs = AmigaDos::try_from(fs.disk()).unwrap();
+
+        assert_eq!(
+            identify_boot_code(&fs.boot_code().unwrap()),
+            BootCodeKind::Standard(StandardBootCode::OS20),
+        );
+        assert_eq!(fs.info().unwrap().dos_type, DosType::FFSIntl);
+        assert!(fs.check().unwrap().is_empty());
+
+        let mut fs = init_fs(DosType::OFS);
+
+        fs.install_standard_boot_code().unwrap();
+        assert_eq!(
+            identify_boot_code(&fs.boot_code().unwrap()),
+            BootCodeKind::Standard(StandardBootCode::OS13),
+        );
+
+        fs.install_boot_code(b"\x70\xff\x4e\x75").unwrap();
+        assert_eq!(&fs.boot_code().unwrap()[..6], b"\x70\xff\x4e\x75\0\0");
+        assert_eq!(
+            fs.install_boot_code(&[0; 1013]),
+            Err(Error::InvalidBootCodeSizeError(1013)),
+        );
+        assert!(AmigaDos::try_from(fs.disk()).is_ok());
+    }
+}
@@ -0,0 +1,422 @@
+use std::fmt;
+
+use flate2::Crc;
+
+use crate::disk::*;
+use crate::errors::*;
+use crate::shared::*;
+
+use super::amiga_dos::*;
+use super::amiga_dos_options::*;
+use super::boot_block::*;
+use super::boot_code::*;
+use super::constants::*;
+
+
+/// How a signature is matched against a boot code.
+#[derive(Clone, Copy, Debug)]
+pub enum BootCodeMatcher {
+    /// The CRC-32 of the whole boot code area.
+    Crc(u32),
+    /// A byte sequence found anywhere in the boot code, e.g. the message of
+    /// a virus which isn't encrypted.
+    Bytes(&'static [u8]),
+    /// A byte sequence with wildcards, e.g. the decryption loop of a virus
+    /// whose key and body length change with each infection.
+    Pattern(&'static [Option<u8>]),
+}
+
+/// A known boot code.
+#[derive(Clone, Copy, Debug)]
+pub struct BootCodeSignature {
+    pub matcher: BootCodeMatcher,
+    pub kind: BootCodeKind,
+}
+
+impl BootCodeSignature {
+    fn matches(
+        &self,
+        boot_code: &[u8],
+        crc: u32,
+    ) -> bool {
+        match self.matcher {
+            BootCodeMatcher::Crc(expected) => expected == crc,
+            BootCodeMatcher::Bytes(bytes) => {
+                boot_code.windows(bytes.len()).any(|window| window == bytes)
+            },
+            BootCodeMatcher::Pattern(pattern) => {
+                boot_code.windows(pattern.len()).any(|window| {
+                    window.iter().zip(pattern).all(|(b, p)| p.is_none_or(|p| p == *b))
+                })
+            },
+        }
+    }
+}
+
+const LAMER_EXTERMINATOR_DECRYPTOR: &[Option<u8>] = &[
+    Some(0x41), Some(0xfa), None, None,
+    Some(0x30), Some(0x3c), None, None,
+    Some(0xb3), Some(0x18),
+    Some(0x51), Some(0xc8), Some(0xff), Some(0xfc),
+];
+
+/// The signature database, the first matching signature wins.
+pub const BOOT_CODE_SIGNATURES: &[BootCodeSignature] = &[
+    BootCodeSignature {
+        matcher: BootCodeMatcher::Crc(0x2c9f6d94),
+        kind: BootCodeKind::Standard(StandardBootCode::OS13),
+    },
+    BootCodeSignature {
+        matcher: BootCodeMatcher::Crc(0x9942406e),
+        kind: BootCodeKind::Standard(StandardBootCode::OS20),
+    },
+    BootCodeSignature {
+        matcher: BootCodeMatcher::Bytes(b"Something wonderful has happened"),
+        kind: BootCodeKind::Virus("SCA"),
+    },
+    BootCodeSignature {
+        matcher: BootCodeMatcher::Bytes(b"Virus by Byte Bandit"),
+        kind: BootCodeKind::Virus("Byte Bandit"),
+    },
+    BootCodeSignature {
+        // the body, message included, is encrypted with a random key at each
+        // infection, only the decryption loop stays the same:
+        //     lea body(pc),a0
+        //     move.w #length,d0
+        // 1:  eor.b d1,(a0)+
+        //     dbf d0,1b
+        matcher: BootCodeMatcher::Pattern(LAMER_EXTERMINATOR_DECRYPTOR),
+        kind: BootCodeKind::Virus("Lamer Exterminator"),
+    },
+    // loaders come after the viruses, which also read and write the disk
+    BootCodeSignature {
+        // jsr _LVODoIO(a6), reading tracks with the IORequest the boot code
+        // is given
+        matcher: BootCodeMatcher::Bytes(b"\x4e\xae\xfe\x38"),
+        kind: BootCodeKind::Loader("trackdisk.device"),
+    },
+    BootCodeSignature {
+        // the absolute address of the CIA-B port driving the floppy drives,
+        // banged directly by the trackloaders of games and demos
+        matcher: BootCodeMatcher::Bytes(b"\x00\xbf\xd1\x00"),
+        kind: BootCodeKind::Loader("hardware"),
+    },
+];
+
+/// Computes the CRC-32 of a boot code, as matched by
+/// `BootCodeMatcher::Crc`.
+pub fn boot_code_signature(boot_code: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+
+    crc.update(boot_code);
+    crc.sum()
+}
+
+/// Identifies a boot code from the signature database.
+pub fn identify_boot_code(boot_code: &[u8]) -> BootCodeKind {
+    if boot_code.iter().all(|b| *b == 0) {
+        return BootCodeKind::Empty;
+    }
+
+    let crc = boot_code_signature(boot_code);
+
+    BOOT_CODE_SIGNATURES.iter()
+        .find(|signature| signature.matches(boot_code, crc))
+        .map(|signature| signature.kind)
+        .unwrap_or(BootCodeKind::Unknown)
+}
+
+/// Suspicious operations found in a boot code, which usually betray a virus
+/// staying resident across resets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootCodeHint {
+    /// Writes to ColdCapture, CoolCapture or WarmCapture of ExecBase.
+    PatchesCaptureVector,
+    /// Writes to KickMemPtr, KickTagPtr or KickCheckSum of ExecBase.
+    PatchesKickTags,
+    /// Calls exec.library SetFunction to patch a library function.
+    CallsSetFunction,
+}
+
+impl fmt::Display for BootCodeHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PatchesCaptureVector => write!(f, "patches a reset capture vector"),
+            Self::PatchesKickTags => write!(f, "patches the resident kick tags"),
+            Self::CallsSetFunction => write!(f, "calls SetFunction"),
+        }
+    }
+}
+
+// ExecBase offsets of the fields a resident virus hooks into
+const EXEC_CAPTURE_VECTOR_OFFSETS: &[u16] = &[0x2a, 0x2e, 0x32];
+const EXEC_KICK_TAGS_OFFSETS: &[u16] = &[0x222, 0x226, 0x22a];
+
+// jsr _LVOSetFunction(a6)
+const SET_FUNCTION_CALL: u32 = 0x4eaefe5c;
+
+fn find_hints(boot_code: &[u8]) -> Vec<BootCodeHint> {
+    let words = boot_code
+        .chunks_exact(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+        .collect::<Vec<_>>();
+
+    let mut hints = Vec::new();
+    let mut add_hint = |hint| {
+        if !hints.contains(&hint) {
+            hints.push(hint);
+        }
+    };
+
+    for (index, word) in words.iter().copied().enumerate() {
+        // move.l <ea>,d16(a6), the displacement follows the extension words
+        // of the source operand
+        if word & 0xffc0 == 0x2d40 {
+            for offset in words.iter().skip(index + 1).take(3) {
+                if EXEC_CAPTURE_VECTOR_OFFSETS.contains(offset) {
+                    add_hint(BootCodeHint::PatchesCaptureVector);
+                }
+                if EXEC_KICK_TAGS_OFFSETS.contains(offset) {
+                    add_hint(BootCodeHint::PatchesKickTags);
+                }
+            }
+        }
+
+        if let Some(next) = words.get(index + 1) {
+            if (word as u32) << 16 | *next as u32 == SET_FUNCTION_CALL {
+                add_hint(BootCodeHint::CallsSetFunction);
+            }
+        }
+    }
+
+    hints
+}
+
+/// The result of scanning a boot code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BootCodeScan {
+    pub kind: BootCodeKind,
+    /// Suspicious operations, reported for unknown boot codes and loaders,
+    /// to help spotting unknown viruses.
+    pub hints: Vec<BootCodeHint>,
+}
+
+impl BootCodeScan {
+    pub fn is_infected(&self) -> bool {
+        matches!(self.kind, BootCodeKind::Virus(_))
+    }
+}
+
+impl fmt::Display for BootCodeScan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        for hint in self.hints.iter() {
+            write!(f, ", {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Scans a boot code for viruses.
+pub fn scan_boot_code(boot_code: &[u8]) -> BootCodeScan {
+    let kind = identify_boot_code(boot_code);
+    let hints = match kind {
+        BootCodeKind::Unknown|BootCodeKind::Loader(_) => find_hints(boot_code),
+        _ => Vec::new(),
+    };
+
+    BootCodeScan {
+        kind,
+        hints,
+    }
+}
+
+/// Scans the boot code of a disk for viruses. The filesystem isn't mounted,
+/// so that damaged or non-DOS disks can be scanned as well.
+pub fn scan_disk_boot_code(disk: &Disk) -> Result<BootCodeScan, Error> {
+    let data = read_boot_block(disk)?;
+
+    Ok(scan_boot_code(&data[BOOT_BLOCK_BOOT_CODE_SLICE]))
+}
+
+/// Replaces the boot code of a disk by the standard one if it carries a
+/// known virus, without mounting its filesystem. Returns true when the disk
+/// has been cleaned.
+pub fn clean_disk_boot_code(disk: Shared<Disk>) -> Result<bool, Error> {
+    let data = read_boot_block(&disk.borrow())?;
+
+    if !scan_boot_code(&data[BOOT_BLOCK_BOOT_CODE_SLICE]).is_infected() {
+        return Ok(false);
+    }
+
+    let dos_type = DosType::try_from(data[BOOT_BLOCK_FLAGS_OFFSET]).unwrap_or_default();
+    let root_block_address = u32::from_be_bytes(
+        data[BOOT_BLOCK_ROOT_BLOCK_SLICE].try_into().unwrap()
+    ) as usize;
+
+    BootBlockInitializer::default()
+        .with_root_block_address(Some(root_block_address))
+        .with_dos_type(dos_type)
+        .with_boot_code(&StandardBootCode::for_dos_type(dos_type).boot_code())
+        .init(disk)?;
+    Ok(true)
+}
+
+impl AmigaDos {
+    /// Scans the boot code of the disk for viruses.
+    pub fn scan_boot_code(&self) -> Result<BootCodeScan, Error> {
+        let _guard = self.lock.read();
+
+        Ok(scan_boot_code(&self.boot_code()?))
+    }
+
+    /// Replaces the boot code of the disk by the standard one if it carries
+    /// a known virus. Returns true when the disk has been cleaned.
+    pub fn clean_boot_code(&mut self) -> Result<bool, Error> {
+        let _guard = self.lock.write();
+
+        if self.scan_boot_code()?.is_infected() {
+            self.install_standard_boot_code()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn infected_boot_code() -> Vec<u8> {
+        let mut boot_code = vec![0u8; 1012];
+        let message = b"Something wonderful has happened";
+
+        // move.l #0x00fc0000,CoolCapture(a6)
+        boot_code[..8].copy_from_slice(b"\x2d\x7c\x00\xfc\x00\x00\x00\x2e");
+        boot_code[200..200 + message.len()].copy_from_slice(message);
+        boot_code
+    }
+
+    #[test]
+    fn scan_boot_code_is_ok() {
+        let scan = scan_boot_code(&infected_boot_code());
+
+        assert_eq!(scan.kind, BootCodeKind::Virus("SCA"));
+        assert!(scan.is_infected());
+        assert!(scan.hints.is_empty());
+
+        let mut boot_code = infected_boot_code();
+
+        boot_code[200] = b's';
+        boot_code[100..104].copy_from_slice(b"\x4e\xae\xfe\x5c");
+
+        let scan = scan_boot_code(&boot_code);
+
+        assert_eq!(scan.kind, BootCodeKind::Unknown);
+        assert_eq!(
+            scan.hints,
+            [BootCodeHint::PatchesCaptureVector, BootCodeHint::CallsSetFunction],
+        );
+
+        for code in [StandardBootCode::OS13, StandardBootCode::OS20] {
+            assert_eq!(scan_boot_code(&code.boot_code()).hints, []);
+        }
+    }
+
+    fn boot_code_with(offset: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut boot_code = StandardBootCode::OS13.boot_code().to_vec();
+
+        boot_code[offset..offset + bytes.len()].copy_from_slice(bytes);
+        boot_code
+    }
+
+    #[test]
+    fn virus_signatures_are_ok() {
+        for (bytes, name) in [
+            (&b"Something wonderful has happened"[..], "SCA"),
+            (b"Virus by Byte Bandit", "Byte Bandit"),
+            (b"\x41\xfa\x00\x10\x30\x3c\x01\xbf\xb3\x18\x51\xc8\xff\xfc", "Lamer Exterminator"),
+        ] {
+            assert_eq!(identify_boot_code(&boot_code_with(300, bytes)), BootCodeKind::Virus(name));
+        }
+
+        // the plain message of an encrypted virus isn't matched
+        assert_eq!(
+            identify_boot_code(&boot_code_with(300, b"LAMER Exterminator")),
+            BootCodeKind::Unknown,
+        );
+
+        // whatever its key and length
+        let decryptor = b"\x41\xfa\x00\x2a\x30\x3c\x00\x80\xb3\x18\x51\xc8\xff\xfc";
+
+        // a virus also reading the disk is not taken for a loader
+        let mut boot_code = boot_code_with(300, decryptor);
+
+        boot_code[100..104].copy_from_slice(b"\x4e\xae\xfe\x38");
+        assert_eq!(identify_boot_code(&boot_code), BootCodeKind::Virus("Lamer Exterminator"));
+    }
+
+    #[test]
+    fn loader_signatures_are_ok() {
+        // jsr _LVODoIO(a6)
+        let boot_code = boot_code_with(100, b"\x4e\xae\xfe\x38");
+        let scan = scan_boot_code(&boot_code);
+
+        assert_eq!(scan.kind, BootCodeKind::Loader("trackdisk.device"));
+        assert!(!scan.is_infected());
+        assert!(scan.hints.is_empty());
+
+        // a loader patching the capture vectors is still reported
+        let mut boot_code = boot_code;
+
+        boot_code[200..208].copy_from_slice(b"\x2d\x7c\x00\xfc\x00\x00\x00\x2e");
+        assert_eq!(scan_boot_code(&boot_code).hints, [BootCodeHint::PatchesCaptureVector]);
+    }
+
+    #[test]
+    fn clean_disk_boot_code_is_ok() {
+        let disk = shared(Disk::create(DiskType::DoubleDensity));
+
+        assert_eq!(scan_disk_boot_code(&disk.borrow()).unwrap().kind, BootCodeKind::Empty);
+        assert!(!clean_disk_boot_code(disk.clone()).unwrap());
+
+        // a damaged disk, without a root block, is still cleaned
+        let mut data = vec![0u8; 1024];
+
+        data[..4].copy_from_slice(b"DOS\x01");
+        data[12..].copy_from_slice(&infected_boot_code());
+        disk.borrow_mut().block_mut(0).unwrap().copy_from_slice(&data[..512]);
+        disk.borrow_mut().block_mut(1).unwrap().copy_from_slice(&data[512..]);
+
+        assert!(AmigaDos::try_from(disk.clone()).is_err());
+        assert!(scan_disk_boot_code(&disk.borrow()).unwrap().is_infected());
+        assert!(clean_disk_boot_code(disk.clone()).unwrap());
+        assert_eq!(
+            scan_disk_boot_code(&disk.borrow()).unwrap().kind,
+            BootCodeKind::Standard(StandardBootCode::OS13),
+        );
+    }
+
+    #[test]
+    fn clean_boot_code_is_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        assert!(!fs.clean_boot_code().unwrap());
+
+        fs.install_boot_code(&infected_boot_code()).unwrap();
+
+        assert!(fs.scan_boot_code().unwrap().is_infected());
+        assert!(fs.clean_boot_code().unwrap());
+        assert_eq!(
+            fs.scan_boot_code().unwrap().kind,
+            BootCodeKind::Standard(StandardBootCode::OS13),
+        );
+    }
+}
@@ -0,0 +1,933 @@
+use std::fmt;
+
+use crate::block::*;
+use crate::disk::*;
+use crate::errors::*;
+use crate::shared::*;
+
+use super::amiga_dos::*;
+use super::amiga_dos_options::*;
+use super::block_type::*;
+use super::boot_block::*;
+use super::checksum::*;
+use super::constants::*;
+use super::dir_cache::*;
+use super::file::*;
+use super::name::*;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckSeverity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for CheckSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckSeverity::Warning => write!(f, "warning"),
+            CheckSeverity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckFindingKind {
+    /// The block refers to an address out of the disk bounds.
+    InvalidBlockAddress(LBAAddress),
+    /// The block is referred to more than once.
+    CrossLinkedBlock,
+    InvalidChecksum { expected: u32, found: u32 },
+    InvalidPrimaryType(u32),
+    InvalidSecondaryType(u32),
+    InvalidHeaderKey(u32),
+    InvalidParent { expected: LBAAddress, found: u32 },
+    InvalidName,
+    InvalidHashTableSize(u32),
+    /// The entry is not chained at the index its name hashes to.
+    MisplacedHashEntry { expected: usize, found: usize },
+    InvalidHighSeq { expected: usize, found: u32 },
+    InvalidFirstDataBlock { expected: LBAAddress, found: u32 },
+    InvalidSequenceNumber { expected: usize, found: u32 },
+    InvalidDataSize { expected: usize, found: u32 },
+    InvalidNextDataBlock { expected: LBAAddress, found: u32 },
+    /// The file size does not match the number of data blocks.
+    InvalidFileSize { size: usize, data_block_count: usize },
+    InvalidBitmapFlag(u32),
+    /// The block is in use but marked as free in the bitmap.
+    UsedBlockMarkedFree,
+    /// The block is not in use but marked as allocated in the bitmap.
+    FreeBlockMarkedUsed,
+    /// The directory has no directory cache block.
+    MissingDirCache,
+    /// The directory cache does not match the entries of the directory.
+    InvalidDirCache,
+}
+
+impl CheckFindingKind {
+    pub fn severity(&self) -> CheckSeverity {
+        match self {
+            Self::InvalidBitmapFlag(_) |
+            Self::FreeBlockMarkedUsed => CheckSeverity::Warning,
+            _ => CheckSeverity::Error,
+        }
+    }
+}
+
+impl fmt::Display for CheckFindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidBlockAddress(addr) => {
+                write!(f, "invalid block address {addr}")
+            },
+            Self::CrossLinkedBlock => {
+                write!(f, "block is cross-linked")
+            },
+            Self::InvalidChecksum { expected, found } => {
+                write!(f, "invalid checksum {found:#010x}, expected {expected:#010x}")
+            },
+            Self::InvalidPrimaryType(v) => {
+                write!(f, "invalid primary type {v}")
+            },
+            Self::InvalidSecondaryType(v) => {
+                write!(f, "invalid secondary type {}", *v as i32)
+            },
+            Self::InvalidHeaderKey(v) => {
+                write!(f, "invalid header key {v}")
+            },
+            Self::InvalidParent { expected, found } => {
+                write!(f, "invalid parent {found}, expected {expected}")
+            },
+            Self::InvalidName => {
+                write!(f, "invalid name")
+            },
+            Self::InvalidHashTableSize(v) => {
+                write!(f, "invalid hash table size {v}")
+            },
+            Self::MisplacedHashEntry { expected, found } => {
+                write!(f, "entry chained at hash index {found}, expected {expected}")
+            },
+            Self::InvalidHighSeq { expected, found } => {
+                write!(f, "invalid high seq {found}, expected {expected}")
+            },
+            Self::InvalidFirstDataBlock { expected, found } => {
+                write!(f, "invalid first data block {found}, expected {expected}")
+            },
+            Self::InvalidSequenceNumber { expected, found } => {
+                write!(f, "invalid sequence number {found}, expected {expected}")
+            },
+            Self::InvalidDataSize { expected, found } => {
+                write!(f, "invalid data size {found}, expected {expected}")
+            },
+            Self::InvalidNextDataBlock { expected, found } => {
+                write!(f, "invalid next data block {found}, expected {expected}")
+            },
+            Self::InvalidFileSize { size, data_block_count } => {
+                write!(f, "file size {size} does not match {data_block_count} data block(s)")
+            },
+            Self::InvalidBitmapFlag(v) => {
+                write!(f, "invalid bitmap flag {v:#010x}")
+            },
+            Self::UsedBlockMarkedFree => {
+                write!(f, "block is used but marked as free")
+            },
+            Self::FreeBlockMarkedUsed => {
+                write!(f, "block is not used but marked as allocated")
+            },
+            Self::MissingDirCache => {
+                write!(f, "directory has no directory cache")
+            },
+            Self::InvalidDirCache => {
+                write!(f, "directory cache does not match the directory content")
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckFinding {
+    pub address: LBAAddress,
+    pub severity: CheckSeverity,
+    pub kind: CheckFindingKind,
+}
+
+impl CheckFinding {
+    pub fn new(
+        address: LBAAddress,
+        kind: CheckFindingKind,
+    ) -> Self {
+        Self {
+            address,
+            severity: kind.severity(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for CheckFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {}: {}: {}", self.address, self.severity, self.kind)
+    }
+}
+
+/******************************************************************************
+* Checker *********************************************************************
+******************************************************************************/
+
+pub(super) struct Checker {
+    disk: Shared<Disk>,
+    filesystem_type: FilesystemType,
+    international_mode: InternationalMode,
+    cache_mode: CacheMode,
+    pub(super) used_blocks: Vec<bool>,
+    pub(super) findings: Vec<CheckFinding>,
+}
+
+impl Checker {
+    pub(super) fn try_new(
+        disk: Shared<Disk>,
+    ) -> Result<Self, Error> {
+        let boot_block = BootBlockReader::try_from_disk(disk.clone())?;
+        let block_count = disk.borrow().block_count();
+
+        let mut used_blocks = vec![false; block_count];
+
+        // boot blocks are never tracked by the bitmap
+        used_blocks[0] = true;
+        used_blocks[1] = true;
+
+        Ok(Self {
+            disk,
+            filesystem_type: boot_block.get_filesystem_type(),
+            international_mode: boot_block.get_international_mode(),
+            cache_mode: boot_block.get_cache_mode(),
+            used_blocks,
+            findings: Vec::new(),
+        })
+    }
+
+    fn report(
+        &mut self,
+        address: LBAAddress,
+        kind: CheckFindingKind,
+    ) {
+        self.findings.push(CheckFinding::new(address, kind));
+    }
+
+    fn block(
+        &self,
+        address: LBAAddress,
+    ) -> Block {
+        Block::new(self.disk.clone(), address)
+    }
+
+    fn mark_used(
+        &mut self,
+        referrer: LBAAddress,
+        address: LBAAddress,
+    ) -> bool {
+        if address < 2 || address >= self.used_blocks.len() {
+            self.report(referrer, CheckFindingKind::InvalidBlockAddress(address));
+            false
+        } else if self.used_blocks[address] {
+            self.report(address, CheckFindingKind::CrossLinkedBlock);
+            false
+        } else {
+            self.used_blocks[address] = true;
+            true
+        }
+    }
+
+    fn check_checksum(
+        &mut self,
+        address: LBAAddress,
+        offset: usize,
+    ) -> Result<(), Error> {
+        let (expected, found) = {
+            let disk = self.disk.borrow();
+            let data = disk.block(address)?;
+
+            (
+                compute_checksum(data, offset),
+                u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()),
+            )
+        };
+
+        if expected != found {
+            self.report(address, CheckFindingKind::InvalidChecksum {
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_block_types(
+        &mut self,
+        address: LBAAddress,
+        primary_type: BlockPrimaryType,
+        secondary_types: &[BlockSecondaryType],
+    ) -> Result<Option<BlockSecondaryType>, Error> {
+        let block = self.block(address);
+        let v = block.read_u32(BLOCK_PRIMARY_TYPE_OFFSET)?;
+
+        if v != primary_type as u32 {
+            self.report(address, CheckFindingKind::InvalidPrimaryType(v));
+            return Ok(None);
+        }
+
+        let v = block.read_u32(BLOCK_SECONDARY_TYPE_OFFSET)?;
+
+        match BlockSecondaryType::try_from(v) {
+            Ok(secondary_type) if secondary_types.contains(&secondary_type) => {
+                Ok(Some(secondary_type))
+            },
+            _ => {
+                self.report(address, CheckFindingKind::InvalidSecondaryType(v));
+                Ok(None)
+            },
+        }
+    }
+
+    fn check_u32(
+        &mut self,
+        address: LBAAddress,
+        offset: usize,
+        expected: LBAAddress,
+        kind: impl FnOnce(u32) -> CheckFindingKind,
+    ) -> Result<(), Error> {
+        let found = self.block(address).read_u32(offset)?;
+
+        if found as LBAAddress != expected {
+            self.report(address, kind(found));
+        }
+        Ok(())
+    }
+
+    pub(super) fn check_root(
+        &mut self,
+        root_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.mark_used(0, root_block_address) {
+            return Ok(());
+        }
+
+        self.check_checksum(root_block_address, BLOCK_CHECKSUM_OFFSET)?;
+
+        if self.check_block_types(
+            root_block_address,
+            BlockPrimaryType::Header,
+            &[BlockSecondaryType::Root],
+        )?.is_none() {
+            return Ok(());
+        }
+
+        let root_block = self.block(root_block_address);
+
+        let hash_table_size = root_block.read_u32(ROOT_BLOCK_HASH_TABLE_SIZE_OFFSET)?;
+        if hash_table_size as usize != BLOCK_TABLE_SIZE {
+            self.report(
+                root_block_address,
+                CheckFindingKind::InvalidHashTableSize(hash_table_size),
+            );
+        }
+
+        let bitmap_flag = root_block.read_u32(ROOT_BLOCK_BITMAP_FLAG_OFFSET)?;
+        if bitmap_flag != 0xffffffff {
+            self.report(
+                root_block_address,
+                CheckFindingKind::InvalidBitmapFlag(bitmap_flag),
+            );
+        }
+
+        if root_block.read_name().is_err() {
+            self.report(root_block_address, CheckFindingKind::InvalidName);
+        }
+
+        let mut bitmap_block_addresses = root_block.read_u32_vector(
+            ROOT_BLOCK_BITMAP_PAGES_OFFSET,
+            ROOT_BLOCK_BITMAP_PAGES_SIZE,
+        )?;
+
+        let mut referrer = root_block_address;
+        let mut next = AmigaDos::to_address(
+            root_block.read_u32(ROOT_BLOCK_BITMAP_EXT_OFFSET)?
+        );
+
+        while let Some(addr) = next {
+            if !self.mark_used(referrer, addr) {
+                break;
+            }
+
+            let bitmap_ext_block = self.block(addr);
+
+            bitmap_block_addresses.extend(bitmap_ext_block.read_u32_vector(
+                BITMAP_EXT_BLOCK_PAGES_OFFSET,
+                BITMAP_EXT_BLOCK_PAGES_SIZE,
+            )?);
+
+            referrer = addr;
+            next = AmigaDos::to_address(
+                bitmap_ext_block.read_u32(BITMAP_EXT_BLOCK_NEXT_OFFSET)?
+            );
+        }
+
+        for addr in bitmap_block_addresses.iter().copied().filter(|addr| *addr != 0) {
+            if self.mark_used(root_block_address, addr as LBAAddress) {
+                self.check_checksum(addr as LBAAddress, BITMAP_BLOCK_CHECKSUM_OFFSET)?;
+            }
+        }
+
+        self.check_directory(root_block_address)
+    }
+
+    fn check_directory(
+        &mut self,
+        dir_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        for hash_index in 0..BLOCK_TABLE_SIZE {
+            let mut referrer = dir_block_address;
+            let mut next = AmigaDos::to_address(self.block(dir_block_address).read_u32(
+                BLOCK_TABLE_OFFSET + 4*hash_index
+            )?);
+
+            while let Some(addr) = next {
+                if !self.mark_used(referrer, addr) {
+                    break;
+                }
+                next = self.check_entry(dir_block_address, hash_index, addr)?;
+                referrer = addr;
+            }
+        }
+
+        if let CacheMode::On = self.cache_mode {
+            self.check_dir_cache(dir_block_address)?;
+        }
+        Ok(())
+    }
+
+    fn check_dir_cache(
+        &mut self,
+        dir_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        let mut records = Vec::new();
+        let mut referrer = dir_block_address;
+        let mut next = AmigaDos::to_address(
+            self.block(dir_block_address).read_u32(BLOCK_DIR_CACHE_OFFSET)?
+        );
+
+        if next.is_none() {
+            self.report(dir_block_address, CheckFindingKind::MissingDirCache);
+            return Ok(());
+        }
+
+        while let Some(addr) = next {
+            if !self.mark_used(referrer, addr) {
+                return Ok(());
+            }
+
+            self.check_checksum(addr, BLOCK_CHECKSUM_OFFSET)?;
+
+            let block = self.block(addr);
+            let primary_type = block.read_u32(BLOCK_PRIMARY_TYPE_OFFSET)?;
+
+            if primary_type != BlockPrimaryType::DirCache as u32 {
+                self.report(addr, CheckFindingKind::InvalidPrimaryType(primary_type));
+                return Ok(());
+            }
+
+            self.check_u32(
+                addr,
+                BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+                addr,
+                CheckFindingKind::InvalidHeaderKey,
+            )?;
+            self.check_u32(
+                addr,
+                DIR_CACHE_BLOCK_PARENT_OFFSET,
+                dir_block_address,
+                |found| CheckFindingKind::InvalidParent {
+                    expected: dir_block_address,
+                    found,
+                },
+            )?;
+
+            match read_dir_cache_block(&block) {
+                Ok(block_records) => records.extend(block_records),
+                Err(_) => {
+                    self.report(addr, CheckFindingKind::InvalidDirCache);
+                    return Ok(());
+                },
+            }
+
+            referrer = addr;
+            next = AmigaDos::to_address(block.read_u32(DIR_CACHE_BLOCK_NEXT_OFFSET)?);
+        }
+
+        // entries which can't be read are reported on their own
+        if let Ok(mut expected) = read_dir_entries_records(self.disk.clone(), dir_block_address) {
+            expected.sort_by_key(|record| record.header_block_address);
+            records.sort_by_key(|record| record.header_block_address);
+
+            if expected != records {
+                self.report(dir_block_address, CheckFindingKind::InvalidDirCache);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_entry(
+        &mut self,
+        parent_block_address: LBAAddress,
+        hash_index: usize,
+        address: LBAAddress,
+    ) -> Result<Option<LBAAddress>, Error> {
+        self.check_checksum(address, BLOCK_CHECKSUM_OFFSET)?;
+
+        let secondary_type = match self.check_block_types(
+            address,
+            BlockPrimaryType::Header,
+            &[
+                BlockSecondaryType::Directory,
+                BlockSecondaryType::File,
+                BlockSecondaryType::SoftLink,
+                BlockSecondaryType::HardLinkDirectory,
+                BlockSecondaryType::HardLinkFile,
+            ],
+        )? {
+            Some(secondary_type) => secondary_type,
+            None => return Ok(None),
+        };
+
+        self.check_u32(
+            address,
+            BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+            address,
+            CheckFindingKind::InvalidHeaderKey,
+        )?;
+        self.check_u32(
+            address,
+            BLOCK_PARENT_OFFSET,
+            parent_block_address,
+            |found| CheckFindingKind::InvalidParent {
+                expected: parent_block_address,
+                found,
+            },
+        )?;
+
+        match self.block(address).read_name() {
+            Ok(name) => {
+                let expected = hash_name(&name, self.international_mode);
+
+                if expected != hash_index {
+                    self.report(address, CheckFindingKind::MisplacedHashEntry {
+                        expected,
+                        found: hash_index,
+                    });
+                }
+            },
+            Err(_) => {
+                self.report(address, CheckFindingKind::InvalidName);
+            },
+        }
+
+        if let Some(addr) = self.block(address).read_comment_block_address()? {
+            if self.mark_used(address, addr) {
+                self.check_comment_block(address, addr)?;
+            }
+        }
+
+        match secondary_type {
+            BlockSecondaryType::Directory => self.check_directory(address)?,
+            BlockSecondaryType::File => self.check_file(address)?,
+            _ => (),
+        }
+
+        Ok(AmigaDos::to_address(self.block(address).read_u32(BLOCK_HASH_CHAIN_NEXT_OFFSET)?))
+    }
+
+    fn check_comment_block(
+        &mut self,
+        header_block_address: LBAAddress,
+        address: LBAAddress,
+    ) -> Result<(), Error> {
+        self.check_checksum(address, BLOCK_CHECKSUM_OFFSET)?;
+
+        let primary_type = self.block(address).read_u32(BLOCK_PRIMARY_TYPE_OFFSET)?;
+
+        if primary_type != BlockPrimaryType::Comment as u32 {
+            self.report(address, CheckFindingKind::InvalidPrimaryType(primary_type));
+            return Ok(());
+        }
+
+        self.check_u32(
+            address,
+            BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+            address,
+            CheckFindingKind::InvalidHeaderKey,
+        )?;
+        self.check_u32(
+            address,
+            COMMENT_BLOCK_PARENT_OFFSET,
+            header_block_address,
+            |found| CheckFindingKind::InvalidParent {
+                expected: header_block_address,
+                found,
+            },
+        )
+    }
+
+    fn check_extension_block(
+        &mut self,
+        header_block_address: LBAAddress,
+        address: LBAAddress,
+    ) -> Result<bool, Error> {
+        self.check_checksum(address, BLOCK_CHECKSUM_OFFSET)?;
+
+        if self.check_block_types(
+            address,
+            BlockPrimaryType::List,
+            &[BlockSecondaryType::File],
+        )?.is_none() {
+            return Ok(false);
+        }
+
+        self.check_u32(
+            address,
+            BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+            address,
+            CheckFindingKind::InvalidHeaderKey,
+        )?;
+        self.check_u32(
+            address,
+            BLOCK_PARENT_OFFSET,
+            header_block_address,
+            |found| CheckFindingKind::InvalidParent {
+                expected: header_block_address,
+                found,
+            },
+        )?;
+
+        Ok(true)
+    }
+
+    /// Collects the data block addresses referred to by a file header block
+    /// and its extension blocks.
+    fn check_data_block_list(
+        &mut self,
+        header_block_address: LBAAddress,
+    ) -> Result<Vec<Option<LBAAddress>>, Error> {
+        let mut data_block_addresses = Vec::new();
+        let mut table_block_address = header_block_address;
+
+        loop {
+            let table_block = self.block(table_block_address);
+            let high_seq = table_block.read_u32(BLOCK_DATA_LIST_HIGH_SEQ_OFFSET)?;
+
+            let mut count = 0;
+
+            for index in (0..BLOCK_DATA_LIST_SIZE).rev() {
+                let addr = table_block.read_u32(BLOCK_TABLE_OFFSET + 4*index)?;
+
+                if addr == 0 {
+                    break;
+                }
+
+                count += 1;
+                data_block_addresses.push(
+                    if self.mark_used(table_block_address, addr as LBAAddress) {
+                        Some(addr as LBAAddress)
+                    } else {
+                        None
+                    }
+                );
+            }
+
+            if count != high_seq as usize {
+                self.report(table_block_address, CheckFindingKind::InvalidHighSeq {
+                    expected: count,
+                    found: high_seq,
+                });
+            }
+
+            let next = AmigaDos::to_address(table_block.read_u32(
+                BLOCK_DATA_LIST_EXTENSION_OFFSET
+            )?);
+
+            match next {
+                Some(addr) if self.mark_used(table_block_address, addr) => {
+                    if !self.check_extension_block(header_block_address, addr)? {
+                        break;
+                    }
+                    table_block_address = addr;
+                },
+                _ => break,
+            }
+        }
+
+        Ok(data_block_addresses)
+    }
+
+    fn check_ofs_data_block(
+        &mut self,
+        header_block_address: LBAAddress,
+        address: LBAAddress,
+        seq_num: usize,
+        data_size: usize,
+        next_data_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        self.check_checksum(address, BLOCK_CHECKSUM_OFFSET)?;
+
+        let primary_type = self.block(address).read_u32(BLOCK_PRIMARY_TYPE_OFFSET)?;
+
+        if primary_type != BlockPrimaryType::Data as u32 {
+            self.report(address, CheckFindingKind::InvalidPrimaryType(primary_type));
+            return Ok(());
+        }
+
+        self.check_u32(
+            address,
+            BLOCK_DATA_OFS_HEADER_KEY_OFFSET,
+            header_block_address,
+            CheckFindingKind::InvalidHeaderKey,
+        )?;
+        self.check_u32(
+            address,
+            BLOCK_DATA_OFS_SEQ_NUM_OFFSET,
+            seq_num,
+            |found| CheckFindingKind::InvalidSequenceNumber {
+                expected: seq_num,
+                found,
+            },
+        )?;
+        self.check_u32(
+            address,
+            BLOCK_DATA_OFS_SIZE_OFFSET,
+            data_size,
+            |found| CheckFindingKind::InvalidDataSize {
+                expected: data_size,
+                found,
+            },
+        )?;
+        self.check_u32(
+            address,
+            BLOCK_DATA_OFS_NEXT_DATA_OFFSET,
+            next_data_block_address,
+            |found| CheckFindingKind::InvalidNextDataBlock {
+                expected: next_data_block_address,
+                found,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn check_file(
+        &mut self,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        let (_, block_data_size) = get_data_block_info(self.filesystem_type);
+
+        let size = self.block(header_block_address).read_u32(BLOCK_FILE_SIZE)? as usize;
+        let data_block_addresses = self.check_data_block_list(header_block_address)?;
+        let data_block_count = data_block_addresses.len();
+
+        if size.div_ceil(block_data_size) != data_block_count {
+            self.report(header_block_address, CheckFindingKind::InvalidFileSize {
+                size,
+                data_block_count,
+            });
+        }
+
+        let first_data_block_address = data_block_addresses
+            .first()
+            .copied()
+            .flatten()
+            .unwrap_or(0);
+
+        self.check_u32(
+            header_block_address,
+            BLOCK_FIRST_DATA_OFFSET,
+            first_data_block_address,
+            |found| CheckFindingKind::InvalidFirstDataBlock {
+                expected: first_data_block_address,
+                found,
+            },
+        )?;
+
+        if let FilesystemType::OFS = self.filesystem_type {
+            for (index, addr) in data_block_addresses.iter().copied().enumerate() {
+                if let Some(addr) = addr {
+                    let data_size = size
+                        .saturating_sub(index*block_data_size)
+                        .min(block_data_size);
+                    let next_data_block_address = data_block_addresses
+                        .get(index + 1)
+                        .copied()
+                        .flatten()
+                        .unwrap_or(0);
+
+                    self.check_ofs_data_block(
+                        header_block_address,
+                        addr,
+                        index + 1,
+                        data_size,
+                        next_data_block_address,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn check_bitmap(
+        &mut self,
+        fs: &AmigaDosInner,
+        root_block_address: LBAAddress,
+    ) {
+        for address in 2..self.used_blocks.len() {
+            match fs.is_block_free(address) {
+                Ok(true) if self.used_blocks[address] => {
+                    self.report(address, CheckFindingKind::UsedBlockMarkedFree);
+                },
+                Ok(false) if !self.used_blocks[address] => {
+                    self.report(address, CheckFindingKind::FreeBlockMarkedUsed);
+                },
+                Err(_) => {
+                    self.report(
+                        root_block_address,
+                        CheckFindingKind::InvalidBlockAddress(address),
+                    );
+                    break;
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+/******************************************************************************
+* AmigaDos ********************************************************************
+******************************************************************************/
+
+impl AmigaDos {
+    /// Checks the consistency of the filesystem.
+    /// Walks all the blocks reachable from the root block and reports each
+    /// problem found. An empty list means the filesystem is consistent.
+    pub fn check(&self) -> Result<Vec<CheckFinding>, Error> {
+        let _guard = self.lock.read();
+
+        let disk = self.disk();
+        let root_block_address =
+            BootBlockReader::try_from_disk(disk.clone())?.get_root_block_address();
+
+        let mut checker = Checker::try_new(disk)?;
+
+        checker.check_root(root_block_address)?;
+        checker.check_bitmap(&self.inner.borrow(), root_block_address);
+
+        Ok(checker.findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::*;
+    use crate::fs::constants::*;
+    use crate::fs::*;
+
+    use super::*;
+
+    fn init_fs(filesystem_type: FilesystemType) -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        AmigaDosFormater::default()
+            .with_filesystem_type(filesystem_type)
+            .format(shared(disk), "TEST")
+            .unwrap()
+    }
+
+    fn populate(fs: &mut AmigaDos) {
+        fs.create_dir_all("/a/b/c").unwrap();
+        fs.write("/a/foo", vec![42u8; 100]).unwrap();
+        fs.write("/a/b/bar", vec![42u8; 40000]).unwrap();
+        fs.write("/empty", []).unwrap();
+        fs.remove_file("/a/foo").unwrap();
+        fs.rename("/a/b/bar", "/bar").unwrap();
+    }
+
+    #[test]
+    fn check_ofs_is_ok() {
+        let mut fs = init_fs(FilesystemType::OFS);
+
+        populate(&mut fs);
+        assert_eq!(fs.check().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn check_ffs_is_ok() {
+        let mut fs = init_fs(FilesystemType::FFS);
+
+        populate(&mut fs);
+        assert_eq!(fs.check().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn check_reports_invalid_checksum() {
+        let fs = init_fs(FilesystemType::OFS);
+
+        fs.write("/foo", vec![42u8; 100]).unwrap();
+
+        let addr = fs.metadata("/foo").unwrap().header_block_address();
+
+        Block::new(fs.disk(), addr).write_u32(BLOCK_FILE_SIZE, 10).unwrap();
+
+        let findings = fs.check().unwrap();
+
+        assert!(findings.iter().any(|finding| {
+            finding.address == addr
+            && matches!(finding.kind, CheckFindingKind::InvalidChecksum { .. })
+        }));
+    }
+
+    #[test]
+    fn check_reports_bitmap_mismatch() {
+        let fs = init_fs(FilesystemType::FFS);
+
+        fs.write("/foo", vec![42u8; 100]).unwrap();
+
+        let addr = fs.metadata("/foo").unwrap().header_block_address();
+
+        fs.inner.borrow_mut().free_block(addr).unwrap();
+
+        assert_eq!(fs.check().unwrap(), vec![
+            CheckFinding::new(addr, CheckFindingKind::UsedBlockMarkedFree),
+        ]);
+    }
+
+    #[test]
+    fn check_reports_broken_ofs_chain() {
+        let fs = init_fs(FilesystemType::OFS);
+
+        fs.write("/foo", vec![42u8; 1000]).unwrap();
+
+        let data_block_address = File::options()
+            .read(true)
+            .open(&fs, "/foo")
+            .unwrap()
+            .block_data_list[0]
+            .data_block_address;
+
+        let mut block = Block::new(fs.disk(), data_block_address);
+
+        block.write_u32(BLOCK_DATA_OFS_SEQ_NUM_OFFSET, 3).unwrap();
+        block.write_checksum().unwrap();
+
+        assert_eq!(fs.check().unwrap(), vec![
+            CheckFinding::new(
+                data_block_address,
+                CheckFindingKind::InvalidSequenceNumber { expected: 1, found: 3 },
+            ),
+        ]);
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use crate::block::*;
+use crate::disk::*;
+use crate::errors::*;
+
+use super::amiga_dos::*;
+use super::block_type::*;
+use super::constants::*;
+
+
+impl AmigaDos {
+    /// Sets the comment (filenote) of a file or a directory, or removes it
+    /// when `comment` is `None`. Soft and hard links are followed.
+    /// Errors:
+    /// - When `path` doesn't exist.
+    /// - When `path` is the root directory, which has no comment.
+    /// - When `comment` is longer than 79 characters or contains invalid
+    ///   characters.
+    pub fn set_comment<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        comment: Option<&str>,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let addr = self.lookup(path)?;
+        let block = Block::new(self.disk(), addr);
+
+        if block.read_block_secondary_type()? == BlockSecondaryType::Root {
+            return Err(Error::InvalidPathError);
+        }
+
+        let mut inner = self.inner.borrow_mut();
+
+        inner.write_entry_comment(addr, comment)?;
+        inner.update_parent_dir_cache(addr)
+    }
+}
+
+impl AmigaDosInner {
+    /// Writes the comment of an entry header block. On long name filesystems
+    /// a comment which doesn't fit along with the name is written to a
+    /// comment block, reserved or freed as needed.
+    pub(super) fn write_entry_comment(
+        &mut self,
+        header_block_address: LBAAddress,
+        comment: Option<&str>,
+    ) -> Result<(), Error> {
+        let mut block = Block::new(self.disk(), header_block_address);
+        let comment_block_address = block.read_comment_block_address()?;
+
+        match (block.write_comment(comment), comment) {
+            (Err(Error::InvalidCommentLengthError(len)), Some(comment))
+            if len <= BLOCK_COMMENT_MAX_SIZE && block.has_long_name_layout()? => {
+                let addr = match comment_block_address {
+                    Some(addr) => addr,
+                    None => self.reserve_block()?,
+                };
+                let mut comment_block = Block::new(self.disk(), addr);
+
+                comment_block.clear()?;
+                comment_block.write_block_primary_type(BlockPrimaryType::Comment)?;
+                comment_block.write_u32(BLOCK_DATA_LIST_HEADER_KEY_OFFSET, addr as u32)?;
+                comment_block.write_u32(COMMENT_BLOCK_PARENT_OFFSET, header_block_address as u32)?;
+                comment_block.write_comment_block_comment(comment)?;
+                comment_block.write_checksum()?;
+
+                block.write_comment(None)?;
+                block.write_comment_block_address(Some(addr))?;
+            },
+            (Err(err), _) => return Err(err),
+            (Ok(()), _) => {
+                if let Some(addr) = comment_block_address {
+                    block.write_comment_block_address(None)?;
+                    self.free_block(addr)?;
+                }
+            },
+        }
+
+        block.write_checksum()
+    }
+
+    /// Writes the name of an entry header block. On long name filesystems the
+    /// comment is moved to a comment block when it doesn't fit along with the
+    /// new name anymore.
+    pub(super) fn write_entry_name(
+        &mut self,
+        header_block_address: LBAAddress,
+        name: &str,
+    ) -> Result<(), Error> {
+        let mut block = Block::new(self.disk(), header_block_address);
+
+        match block.write_name(name) {
+            Err(Error::InvalidCommentLengthError(_)) => {
+                let comment = block.read_comment()?;
+
+                block.write_comment(None)?;
+                block.write_name(name)?;
+                self.write_entry_comment(header_block_address, comment.as_deref())
+            },
+            result => {
+                result?;
+                block.write_checksum()
+            },
+        }
+    }
+
+    /// Frees the comment block of an entry header block about to be removed.
+    pub(super) fn free_comment_block(
+        &mut self,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        let block = Block::new(self.disk(), header_block_address);
+
+        match block.read_comment_block_address()? {
+            Some(addr) => self.free_block(addr),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs() -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/file", b"hello").unwrap();
+        fs
+    }
+
+    #[test]
+    fn set_comment_is_ok() {
+        let mut fs = init_fs();
+
+        assert_eq!(fs.metadata("dir/file").unwrap().comment(), None);
+        assert_eq!(fs.metadata("/").unwrap().comment(), None);
+
+        fs.set_comment("dir/file", Some("v1.2 by someone")).unwrap();
+        fs.set_comment("dir", Some(&"x".repeat(79))).unwrap();
+
+        assert_eq!(
+            fs.metadata("dir/file").unwrap().comment(),
+            Some("v1.2 by someone"),
+        );
+        assert_eq!(
+            fs.metadata("dir").unwrap().comment(),
+            Some("x".repeat(79).as_str()),
+        );
+        assert_eq!(fs.read("dir/file").unwrap(), b"hello");
+
+        fs.set_comment("dir/file", None).unwrap();
+        assert_eq!(fs.metadata("dir/file").unwrap().comment(), None);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn set_comment_fails_with_an_invalid_comment() {
+        let mut fs = init_fs();
+
+        assert_eq!(
+            fs.set_comment("dir", Some(&"x".repeat(80))),
+            Err(Error::InvalidCommentLengthError(80)),
+        );
+        assert_eq!(
+            fs.set_comment("dir", Some("a:b")),
+            Err(Error::InvalidNameError),
+        );
+        assert_eq!(
+            fs.set_comment("/", Some("root")),
+            Err(Error::InvalidPathError),
+        );
+        assert_eq!(fs.metadata("dir").unwrap().comment(), None);
+    }
+
+    #[test]
+    fn long_name_comments_are_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .with_dos_type(DosType::OFSLongName)
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        let name = "n".repeat(60);
+        let comment = "c".repeat(79);
+
+        fs.write(&name, b"hello").unwrap();
+        let free_block_count = fs.info().unwrap().free_block_count;
+
+        // the comment shares the header block with the name while it fits
+        fs.set_comment(&name, Some("short")).unwrap();
+        assert_eq!(fs.metadata(&name).unwrap().comment(), Some("short"));
+        assert_eq!(fs.info().unwrap().free_block_count, free_block_count);
+
+        fs.set_comment(&name, Some(&comment)).unwrap();
+        assert_eq!(fs.metadata(&name).unwrap().comment(), Some(comment.as_str()));
+        assert_eq!(fs.info().unwrap().free_block_count, free_block_count - 1);
+        assert!(fs.check().unwrap().is_empty());
+
+        fs.rename(&name, "short").unwrap();
+        fs.set_comment("short", Some(&comment)).unwrap();
+        assert_eq!(fs.info().unwrap().free_block_count, free_block_count);
+
+        // a longer name pushes the comment out to a comment block
+        fs.rename("short", &name).unwrap();
+        assert_eq!(fs.metadata(&name).unwrap().comment(), Some(comment.as_str()));
+        assert_eq!(fs.info().unwrap().free_block_count, free_block_count - 1);
+        assert_eq!(fs.read(&name).unwrap(), b"hello");
+        assert!(fs.check().unwrap().is_empty());
+
+        fs.remove_file(&name).unwrap();
+        assert_eq!(fs.info().unwrap().free_block_count, free_block_count + 2);
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
@@ -24,10 +24,29 @@ pub const BLOCK_NAME_SIZE_OFFSET            : usize = BLOCK_SIZE - 0x50;
 pub const BLOCK_NAME_OFFSET                 : usize = BLOCK_SIZE - 0x4f;
 pub const BLOCK_NAME_MAX_SIZE               : usize = 30;
 
+pub const BLOCK_COMMENT_SIZE_OFFSET         : usize = BLOCK_SIZE - 0xb8;
+pub const BLOCK_COMMENT_OFFSET              : usize = BLOCK_SIZE - 0xb7;
+pub const BLOCK_COMMENT_MAX_SIZE            : usize = 79;
+
+// long name filesystems store the name followed by the comment in the comment
+// area, a comment which doesn't fit goes to a comment block, and the alteration
+// date moves to the former name area
+pub const BLOCK_LONG_NAME_SIZE_OFFSET       : usize = BLOCK_SIZE - 0xb8;
+pub const BLOCK_LONG_NAME_OFFSET            : usize = BLOCK_SIZE - 0xb7;
+pub const BLOCK_LONG_NAME_MAX_SIZE          : usize = 107;
+pub const BLOCK_LONG_NAME_AREA_SIZE         : usize = 112;
+pub const BLOCK_COMMENT_BLOCK_OFFSET        : usize = BLOCK_SIZE - 0x48;
+pub const BLOCK_LONG_NAME_DAYS_OFFSET       : usize = BLOCK_SIZE - 0x44;
+
 pub const BLOCK_FILE_SIZE                   : usize = BLOCK_SIZE - 0xbc;
 
 pub const BLOCK_PARENT_OFFSET               : usize = BLOCK_SIZE - 0x0c;
 
+pub const BLOCK_REAL_ENTRY_OFFSET           : usize = BLOCK_SIZE - 0x2c;
+pub const BLOCK_NEXT_LINK_OFFSET            : usize = BLOCK_SIZE - 0x28;
+
+pub const BLOCK_DIR_CACHE_OFFSET            : usize = BLOCK_SIZE - 0x08;
+
 pub const BLOCK_DATA_LIST_HEADER_KEY_OFFSET : usize = 0x04;
 pub const BLOCK_DATA_LIST_HIGH_SEQ_OFFSET   : usize = 0x08;
 pub const BLOCK_DATA_LIST_SIZE              : usize = BLOCK_SIZE/4 - 56;
@@ -44,6 +63,22 @@ pub const BLOCK_DATA_OFS_SEQ_NUM_OFFSET     : usize = 0x08;
 pub const BLOCK_DATA_OFS_SIZE_OFFSET        : usize = 0x0c;
 pub const BLOCK_DATA_OFS_NEXT_DATA_OFFSET   : usize = 0x10;
 
+// Soft link block ////////////////////////////////////////////////////////////
+pub const SOFT_LINK_BLOCK_TARGET_OFFSET     : usize = 0x18;
+pub const SOFT_LINK_BLOCK_TARGET_MAX_SIZE   : usize = BLOCK_SIZE - 224 - 1;
+
+// Directory cache block //////////////////////////////////////////////////////
+pub const DIR_CACHE_BLOCK_PARENT_OFFSET     : usize = 0x08;
+pub const DIR_CACHE_BLOCK_RECORDS_NB_OFFSET : usize = 0x0c;
+pub const DIR_CACHE_BLOCK_NEXT_OFFSET       : usize = 0x10;
+pub const DIR_CACHE_BLOCK_RECORDS_OFFSET    : usize = 0x18;
+pub const DIR_CACHE_BLOCK_RECORDS_SIZE      : usize = BLOCK_SIZE - 0x18;
+
+// Comment block //////////////////////////////////////////////////////////////
+pub const COMMENT_BLOCK_PARENT_OFFSET       : usize = 0x08;
+pub const COMMENT_BLOCK_COMMENT_SIZE_OFFSET : usize = 0x18;
+pub const COMMENT_BLOCK_COMMENT_OFFSET      : usize = 0x19;
+
 // Boot block /////////////////////////////////////////////////////////////////
 pub const BOOT_BLOCK_MAGIC_NUMBER           : &[u8; 3] = b"DOS";
 pub const BOOT_BLOCK_MAGIC_NUMBER_SLICE     : std::ops::Range<usize>
@@ -76,7 +111,7 @@ pub const ROOT_BLOCK_HASH_TABLE_SIZE_OFFSET : usize = 0x0c;
 pub const ROOT_BLOCK_BITMAP_FLAG_OFFSET     : usize = BLOCK_SIZE - 0xc8;
 pub const ROOT_BLOCK_BITMAP_PAGES_OFFSET    : usize = BLOCK_SIZE - 0xc4;
 pub const ROOT_BLOCK_BITMAP_PAGES_SIZE      : usize = 25;
-// pub const ROOT_BLOCK_BITMAP_PAGES_EXT_OFFSET : usize = BLOCK_SIZE - 0x60;
+pub const ROOT_BLOCK_BITMAP_EXT_OFFSET      : usize = BLOCK_SIZE - 0x60;
 
 pub const ROOT_BLOCK_V_DAYS_OFFSET          : usize = BLOCK_SIZE - 0x28;
 pub const ROOT_BLOCK_V_MINS_OFFSET          : usize = BLOCK_SIZE - 0x24;
@@ -91,3 +126,8 @@ pub const ROOT_BLOCK_EXTENSION_OFFSET       : usize = BLOCK_SIZE - 0x08;
 // Bitmap block ///////////////////////////////////////////////////////////////
 pub const BITMAP_BLOCK_CHECKSUM_OFFSET      : usize = 0;
 pub const BITMAP_BLOCK_BIT_COUNT            : usize = (BLOCK_SIZE - 4)*8;
+
+// Bitmap extension block /////////////////////////////////////////////////////
+pub const BITMAP_EXT_BLOCK_PAGES_OFFSET     : usize = 0;
+pub const BITMAP_EXT_BLOCK_PAGES_SIZE       : usize = BLOCK_SIZE/4 - 1;
+pub const BITMAP_EXT_BLOCK_NEXT_OFFSET      : usize = BLOCK_SIZE - 4;
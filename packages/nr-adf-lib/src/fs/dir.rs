@@ -1,14 +1,13 @@
-use std::cell::RefCell;
 use std::path::{
     Path,
     PathBuf,
 };
-use std::rc::Rc;
 use std::time::SystemTime;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
 use super::block_type::*;
@@ -18,7 +17,7 @@ use super::name::*;
 
 
 pub(super) fn check_directory(
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
     addr: LBAAddress,
 ) -> Result<(), Error> {
     let block = Block::new(disk.clone(), addr);
@@ -36,7 +35,7 @@ pub(super) fn check_directory(
 }
 
 pub(super) fn find_in_hash_chain(
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
     name: &str,
     mut addr: Option<LBAAddress>,
 ) -> Result<Option<LBAAddress>, Error> {
@@ -56,7 +55,7 @@ pub(super) fn find_in_hash_chain(
 
 #[derive(Clone, Debug)]
 pub(super) struct Dir {
-    fs: Rc<RefCell<AmigaDosInner>>,
+    fs: Shared<AmigaDosInner>,
     pub(super) header_block_address: LBAAddress,
     pub(super) path: PathBuf,
 }
@@ -148,11 +147,12 @@ impl Dir {
         entry_block.write_hash_chain_next_address(hash_chain_head.unwrap_or(0))?;
         entry_block.write_u32(
             BLOCK_PARENT_OFFSET,
-            entry_block_address as u32,
+            self.header_block_address as u32,
         )?;
         entry_block.write_checksum()?;
 
-        Ok(())
+        self.fs.borrow_mut().insert_dir_cache_record(self.header_block_address, entry_block_address)?;
+        self.update_parent_dir_cache()
     }
 
     pub(super) fn remove_entry(
@@ -188,23 +188,39 @@ impl Dir {
             curr_addr = next_addr;
         }
 
+        if curr_addr.is_none() {
+            return Err(Error::NotFoundError);
+        }
+
         let mut dir_block = Block::new(
             self.fs.borrow().disk(),
             self.header_block_address,
         );
 
-        if prev_addr.is_some() {
-            Block::new(
-                disk.clone(),
-                prev_addr.unwrap(),
-            ).write_hash_chain_next_address(next_addr.unwrap_or(0))?;
+        if let Some(prev_addr) = prev_addr {
+            let mut prev_block = Block::new(disk.clone(), prev_addr);
+
+            prev_block.write_hash_chain_next_address(next_addr.unwrap_or(0))?;
+            prev_block.write_checksum()?;
         } else {
-            dir_block.write_hash_table_block_address(hash_index, 0)?;
+            dir_block.write_hash_table_block_address(
+                hash_index,
+                next_addr.unwrap_or(0),
+            )?;
         }
 
         dir_block.write_alteration_date(&SystemTime::now())?;
         dir_block.write_checksum()?;
 
-        Ok(())
+        self.fs.borrow_mut().remove_dir_cache_record(self.header_block_address, curr_addr.unwrap())?;
+        self.update_parent_dir_cache()
+    }
+
+    // the record of the directory in the cache of its parent is updated as
+    // well, its alteration date having changed
+    fn update_parent_dir_cache(
+        &self,
+    ) -> Result<(), Error> {
+        self.fs.borrow_mut().update_parent_dir_cache(self.header_block_address)
     }
 }
@@ -0,0 +1,719 @@
+use crate::block::*;
+use crate::disk::*;
+use crate::errors::*;
+use crate::shared::*;
+
+use super::amiga_dos::*;
+use super::amiga_dos_options::*;
+use super::block_type::*;
+use super::constants::*;
+use super::name::*;
+
+
+// A record starts with the header block address, the file size and the
+// protection bits, then come the owner UID and GID, unused here
+const RECORD_HEADER_OFFSET  : usize = 0;
+const RECORD_SIZE_OFFSET    : usize = 4;
+const RECORD_PROTECT_OFFSET : usize = 8;
+const RECORD_DAYS_OFFSET    : usize = 16;
+const RECORD_MINS_OFFSET    : usize = 18;
+const RECORD_TICKS_OFFSET   : usize = 20;
+const RECORD_TYPE_OFFSET    : usize = 22;
+const RECORD_NAME_OFFSET    : usize = 23;
+
+/// An entry of a directory cache block (DCFS, `DOS\4` and `DOS\5`), which
+/// duplicates what is needed to list a directory without reading the
+/// header blocks of its entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct DirCacheRecord {
+    pub(super) header_block_address: LBAAddress,
+    pub(super) size: u32,
+    pub(super) protect: u32,
+    pub(super) days: u16,
+    pub(super) mins: u16,
+    pub(super) ticks: u16,
+    pub(super) secondary_type: BlockSecondaryType,
+    pub(super) name: String,
+    pub(super) comment: String,
+}
+
+impl DirCacheRecord {
+    fn try_from_header(block: &Block) -> Result<Self, Error> {
+        let secondary_type = block.read_block_secondary_type()?;
+
+        let size = if secondary_type == BlockSecondaryType::File {
+            block.read_u32(BLOCK_FILE_SIZE)?
+        } else {
+            0
+        };
+
+        Ok(Self {
+            header_block_address: block.address,
+            size,
+            protect: block.read_permissions()?.bits(),
+            days: block.read_u32(BLOCK_ALTERATION_DAYS_OFFSET)? as u16,
+            mins: block.read_u32(BLOCK_ALTERATION_MINS_OFFSET)? as u16,
+            ticks: block.read_u32(BLOCK_ALTERATION_TICKS_OFFSET)? as u16,
+            secondary_type,
+            name: block.read_name()?,
+            comment: block.read_comment()?.unwrap_or_default(),
+        })
+    }
+
+    // records are aligned on 16-bit words
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let name = encode_latin1(&self.name)?;
+        let comment = encode_latin1(&self.comment)?;
+
+        let mut data = Vec::with_capacity(RECORD_NAME_OFFSET + name.len() + comment.len() + 3);
+
+        data.extend_from_slice(&(self.header_block_address as u32).to_be_bytes());
+        data.extend_from_slice(&self.size.to_be_bytes());
+        data.extend_from_slice(&self.protect.to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&self.days.to_be_bytes());
+        data.extend_from_slice(&self.mins.to_be_bytes());
+        data.extend_from_slice(&self.ticks.to_be_bytes());
+        data.push(u32::from(self.secondary_type) as u8);
+        data.push(name.len() as u8);
+        data.extend_from_slice(&name);
+        data.push(comment.len() as u8);
+        data.extend_from_slice(&comment);
+
+        if data.len() % 2 != 0 {
+            data.push(0);
+        }
+
+        Ok(data)
+    }
+
+    // decodes a record, returning it along with its encoded size
+    fn decode(data: &[u8]) -> Result<(Self, usize), Error> {
+        let u16_at = |offset: usize| -> Result<u16, Error> {
+            data.get(offset..offset + 2)
+                .map(|bytes| u16::from_be_bytes(bytes.try_into().unwrap()))
+                .ok_or(Error::CorruptedImageFile)
+        };
+        let u32_at = |offset: usize| -> Result<u32, Error> {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+                .ok_or(Error::CorruptedImageFile)
+        };
+        let string_at = |offset: usize| -> Result<(String, usize), Error> {
+            let len = *data.get(offset).ok_or(Error::CorruptedImageFile)? as usize;
+            let bytes = data
+                .get(offset + 1..offset + 1 + len)
+                .ok_or(Error::CorruptedImageFile)?;
+
+            Ok((decode_latin1(bytes), offset + 1 + len))
+        };
+
+        let secondary_type = BlockSecondaryType::try_from(
+            *data.get(RECORD_TYPE_OFFSET).ok_or(Error::CorruptedImageFile)? as i8 as u32
+        )?;
+        let (name, offset) = string_at(RECORD_NAME_OFFSET)?;
+        let (comment, offset) = string_at(offset)?;
+
+        check_name(&name)?;
+
+        Ok((Self {
+            header_block_address: u32_at(RECORD_HEADER_OFFSET)? as LBAAddress,
+            size: u32_at(RECORD_SIZE_OFFSET)?,
+            protect: u32_at(RECORD_PROTECT_OFFSET)?,
+            days: u16_at(RECORD_DAYS_OFFSET)?,
+            mins: u16_at(RECORD_MINS_OFFSET)?,
+            ticks: u16_at(RECORD_TICKS_OFFSET)?,
+            secondary_type,
+            name,
+            comment,
+        }, offset + offset % 2))
+    }
+}
+
+/// Reads the records of a directory cache block.
+pub(super) fn read_dir_cache_block(
+    block: &Block,
+) -> Result<Vec<DirCacheRecord>, Error> {
+    let count = block.read_u32(DIR_CACHE_BLOCK_RECORDS_NB_OFFSET)? as usize;
+    let data = block.read_u8_vector(
+        DIR_CACHE_BLOCK_RECORDS_OFFSET,
+        DIR_CACHE_BLOCK_RECORDS_SIZE,
+    )?;
+
+    let mut records = Vec::new();
+    let mut offset = 0;
+
+    for _ in 0..count {
+        let (record, size) = DirCacheRecord::decode(
+            data.get(offset..).ok_or(Error::CorruptedImageFile)?
+        )?;
+
+        records.push(record);
+        offset += size;
+    }
+
+    Ok(records)
+}
+
+/// Reads the addresses of the directory cache blocks of a directory.
+fn read_dir_cache_chain(
+    disk: Shared<Disk>,
+    dir_block_address: LBAAddress,
+) -> Result<Vec<LBAAddress>, Error> {
+    let mut addresses = Vec::new();
+    let mut next = AmigaDos::to_address(
+        Block::new(disk.clone(), dir_block_address).read_u32(BLOCK_DIR_CACHE_OFFSET)?
+    );
+
+    while let Some(addr) = next {
+        if addresses.contains(&addr) {
+            return Err(Error::CorruptedImageFile);
+        }
+
+        let block = Block::new(disk.clone(), addr);
+
+        block.check_block_primary_type(&[BlockPrimaryType::DirCache])?;
+        addresses.push(addr);
+        next = AmigaDos::to_address(block.read_u32(DIR_CACHE_BLOCK_NEXT_OFFSET)?);
+    }
+
+    Ok(addresses)
+}
+
+/// Reads all the records of the directory cache of a directory, if it has
+/// one.
+pub(super) fn read_dir_cache(
+    disk: Shared<Disk>,
+    dir_block_address: LBAAddress,
+) -> Result<Option<Vec<DirCacheRecord>>, Error> {
+    let dir_block = Block::new(disk.clone(), dir_block_address);
+
+    if dir_block.read_u32(BLOCK_DIR_CACHE_OFFSET)? == 0 {
+        return Ok(None);
+    }
+
+    let mut records = Vec::new();
+
+    for addr in read_dir_cache_chain(disk.clone(), dir_block_address)? {
+        records.extend(read_dir_cache_block(&Block::new(disk.clone(), addr))?);
+    }
+
+    Ok(Some(records))
+}
+
+/// Builds the records a directory cache should hold from the hash table of
+/// a directory.
+pub(super) fn read_dir_entries_records(
+    disk: Shared<Disk>,
+    dir_block_address: LBAAddress,
+) -> Result<Vec<DirCacheRecord>, Error> {
+    let dir_block = Block::new(disk.clone(), dir_block_address);
+    let mut records = Vec::new();
+
+    for index in 0..BLOCK_TABLE_SIZE {
+        let mut next = dir_block.read_block_table_address(index)?;
+
+        while let Some(addr) = next {
+            let block = Block::new(disk.clone(), addr);
+
+            records.push(DirCacheRecord::try_from_header(&block)?);
+            next = block.read_hash_chain_next_address()?;
+        }
+    }
+
+    Ok(records)
+}
+
+// Packs records into the data of directory cache blocks, a directory
+// having at least one, possibly empty, cache block.
+fn pack_records(
+    records: &[DirCacheRecord],
+) -> Result<Vec<(usize, Vec<u8>)>, Error> {
+    let mut blocks = vec![(0, Vec::new())];
+
+    for record in records {
+        let data = record.encode()?;
+        let (mut count, mut block_data) = blocks.pop().unwrap();
+
+        if block_data.len() + data.len() > DIR_CACHE_BLOCK_RECORDS_SIZE {
+            blocks.push((count, block_data));
+            (count, block_data) = (0, Vec::new());
+        }
+
+        block_data.extend(data);
+        blocks.push((count + 1, block_data));
+    }
+
+    Ok(blocks)
+}
+
+// Writes a directory cache block
+fn write_dir_cache_block(
+    disk: Shared<Disk>,
+    dir_block_address: LBAAddress,
+    addr: LBAAddress,
+    next: LBAAddress,
+    count: usize,
+    data: &[u8],
+) -> Result<(), Error> {
+    let mut block = Block::new(disk, addr);
+
+    block.clear()?;
+    block.write_block_primary_type(BlockPrimaryType::DirCache)?;
+    block.write_u32(BLOCK_DATA_LIST_HEADER_KEY_OFFSET, addr as u32)?;
+    block.write_u32(DIR_CACHE_BLOCK_PARENT_OFFSET, dir_block_address as u32)?;
+    block.write_u32(DIR_CACHE_BLOCK_RECORDS_NB_OFFSET, count as u32)?;
+    block.write_u32(DIR_CACHE_BLOCK_NEXT_OFFSET, next as u32)?;
+    block.write_u8_array(DIR_CACHE_BLOCK_RECORDS_OFFSET, data)?;
+    block.write_checksum()
+}
+
+/// A directory cache block and its records.
+struct DirCacheBlock {
+    address: LBAAddress,
+    next: LBAAddress,
+    records: Vec<DirCacheRecord>,
+}
+
+impl DirCacheBlock {
+    fn read_chain(
+        disk: Shared<Disk>,
+        dir_block_address: LBAAddress,
+    ) -> Result<Vec<Self>, Error> {
+        read_dir_cache_chain(disk.clone(), dir_block_address)?
+            .into_iter()
+            .map(|address| {
+                let block = Block::new(disk.clone(), address);
+
+                Ok(Self {
+                    address,
+                    next: block.read_u32(DIR_CACHE_BLOCK_NEXT_OFFSET)? as LBAAddress,
+                    records: read_dir_cache_block(&block)?,
+                })
+            })
+            .collect()
+    }
+
+    // Writes the records if they fit in the block
+    fn write(
+        &self,
+        disk: Shared<Disk>,
+        dir_block_address: LBAAddress,
+    ) -> Result<bool, Error> {
+        let mut data = Vec::new();
+
+        for record in self.records.iter() {
+            data.extend(record.encode()?);
+        }
+
+        if data.len() > DIR_CACHE_BLOCK_RECORDS_SIZE {
+            return Ok(false);
+        }
+
+        write_dir_cache_block(
+            disk,
+            dir_block_address,
+            self.address,
+            self.next,
+            self.records.len(),
+            &data,
+        )?;
+        Ok(true)
+    }
+}
+
+impl AmigaDosInner {
+    pub(super) fn is_dir_cache_enabled(&self) -> Result<bool, Error> {
+        Ok(matches!(self.get_boot_block()?.get_cache_mode(), CacheMode::On))
+    }
+
+    /// Rewrites the directory cache of a directory from its hash table,
+    /// reserving or freeing cache blocks as needed. Nothing is done unless
+    /// the filesystem has directory caches.
+    pub(super) fn update_dir_cache(
+        &mut self,
+        dir_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_dir_cache_enabled()? {
+            return Ok(());
+        }
+
+        let disk = self.disk();
+        let records = read_dir_entries_records(disk.clone(), dir_block_address)?;
+        let blocks = pack_records(&records)?;
+
+        let mut addresses = read_dir_cache_chain(disk.clone(), dir_block_address)?;
+
+        while addresses.len() < blocks.len() {
+            addresses.push(self.reserve_block()?);
+        }
+
+        for addr in addresses.split_off(blocks.len()) {
+            self.free_block(addr)?;
+        }
+
+        for (index, (count, data)) in blocks.iter().enumerate() {
+            let next = addresses.get(index + 1).copied().unwrap_or(0);
+
+            write_dir_cache_block(disk.clone(), dir_block_address, addresses[index], next, *count, data)?;
+        }
+
+        let mut dir_block = Block::new(disk, dir_block_address);
+
+        dir_block.write_u32(BLOCK_DIR_CACHE_OFFSET, addresses[0] as u32)?;
+        dir_block.write_checksum()
+    }
+
+    /// Adds the record of an entry to the directory cache of a directory,
+    /// into the first cache block with room enough for it or into a new one.
+    pub(super) fn insert_dir_cache_record(
+        &mut self,
+        dir_block_address: LBAAddress,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_dir_cache_enabled()? {
+            return Ok(());
+        }
+
+        let disk = self.disk();
+        let record = DirCacheRecord::try_from_header(&Block::new(disk.clone(), header_block_address))?;
+        let mut blocks = DirCacheBlock::read_chain(disk.clone(), dir_block_address)?;
+
+        for block in blocks.iter_mut() {
+            block.records.push(record.clone());
+
+            if block.write(disk.clone(), dir_block_address)? {
+                return Ok(());
+            }
+            block.records.pop();
+        }
+
+        let address = self.reserve_block()?;
+        let block = DirCacheBlock {
+            address,
+            next: 0,
+            records: vec![record],
+        };
+
+        block.write(disk.clone(), dir_block_address)?;
+
+        // the new block is linked at the end of the chain
+        let (mut last, offset) = match blocks.last() {
+            Some(last) => (Block::new(disk, last.address), DIR_CACHE_BLOCK_NEXT_OFFSET),
+            None => (Block::new(disk, dir_block_address), BLOCK_DIR_CACHE_OFFSET),
+        };
+
+        last.write_u32(offset, address as u32)?;
+        last.write_checksum()
+    }
+
+    /// Removes the record of an entry from the directory cache of a
+    /// directory, freeing its cache block if it gets empty.
+    pub(super) fn remove_dir_cache_record(
+        &mut self,
+        dir_block_address: LBAAddress,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_dir_cache_enabled()? {
+            return Ok(());
+        }
+
+        let disk = self.disk();
+        let mut blocks = DirCacheBlock::read_chain(disk.clone(), dir_block_address)?;
+        let found = blocks.iter().position(|block| {
+            block.records.iter().any(|record| record.header_block_address == header_block_address)
+        });
+
+        // a cache missing the record is stale, it is rebuilt
+        let Some(index) = found else {
+            return self.update_dir_cache(dir_block_address);
+        };
+
+        blocks[index].records.retain(|record| record.header_block_address != header_block_address);
+
+        // a directory keeps at least one, possibly empty, cache block
+        if !blocks[index].records.is_empty() || blocks.len() == 1 {
+            blocks[index].write(disk, dir_block_address)?;
+            return Ok(());
+        }
+
+        let (mut prev, offset) = match index.checked_sub(1) {
+            Some(prev) => (Block::new(disk, blocks[prev].address), DIR_CACHE_BLOCK_NEXT_OFFSET),
+            None => (Block::new(disk, dir_block_address), BLOCK_DIR_CACHE_OFFSET),
+        };
+
+        prev.write_u32(offset, blocks[index].next as u32)?;
+        prev.write_checksum()?;
+        self.free_block(blocks[index].address)
+    }
+
+    /// Rewrites the record of an entry in the directory cache of a
+    /// directory, after the entry header block has changed.
+    pub(super) fn update_dir_cache_record(
+        &mut self,
+        dir_block_address: LBAAddress,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_dir_cache_enabled()? {
+            return Ok(());
+        }
+
+        let disk = self.disk();
+        let record = DirCacheRecord::try_from_header(&Block::new(disk.clone(), header_block_address))?;
+        let mut blocks = DirCacheBlock::read_chain(disk.clone(), dir_block_address)?;
+
+        for block in blocks.iter_mut() {
+            let Some(old) = block.records.iter_mut()
+                .find(|old| old.header_block_address == header_block_address) else {
+                continue;
+            };
+
+            if *old == record {
+                return Ok(());
+            }
+
+            *old = record;
+
+            if block.write(disk.clone(), dir_block_address)? {
+                return Ok(());
+            }
+
+            // the record has grown out of its block
+            self.remove_dir_cache_record(dir_block_address, header_block_address)?;
+            return self.insert_dir_cache_record(dir_block_address, header_block_address);
+        }
+
+        // a cache missing the record is stale, it is rebuilt
+        self.update_dir_cache(dir_block_address)
+    }
+
+    /// Updates the record of an entry in the directory cache of the
+    /// directory holding it, after the entry header block has changed.
+    pub(super) fn update_parent_dir_cache(
+        &mut self,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_dir_cache_enabled()? {
+            return Ok(());
+        }
+
+        let block = Block::new(self.disk(), header_block_address);
+
+        match block.read_parent_block_address()? {
+            Some(parent_block_address) => {
+                self.update_dir_cache_record(parent_block_address, header_block_address)
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Frees the directory cache blocks of a directory about to be removed.
+    pub(super) fn free_dir_cache(
+        &mut self,
+        dir_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_dir_cache_enabled()? {
+            return Ok(());
+        }
+
+        for addr in read_dir_cache_chain(self.disk(), dir_block_address)? {
+            self.free_block(addr)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fs::*;
+
+    use super::*;
+
+    fn init_fs() -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .with_filesystem_type(FilesystemType::FFS)
+            .with_cache_mode(CacheMode::On)
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/file", b"hello").unwrap();
+        fs
+    }
+
+    fn dir_cache_names(fs: &AmigaDos, path: &str) -> Vec<String> {
+        let addr = fs.metadata(path).unwrap().header_block_address();
+        let mut names = read_dir_cache(fs.disk(), addr)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|record| record.name)
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn dir_cache_record_encoding_is_ok() {
+        let record = DirCacheRecord {
+            header_block_address: 882,
+            size: 1234,
+            protect: 0x10,
+            days: 17000,
+            mins: 600,
+            ticks: 42,
+            secondary_type: BlockSecondaryType::File,
+            name: String::from("caf\u{e9}"),
+            comment: String::from("note"),
+        };
+
+        let data = record.encode().unwrap();
+
+        assert_eq!(data.len(), 34);
+        assert_eq!(data[RECORD_TYPE_OFFSET], 0xfd);
+        assert_eq!(DirCacheRecord::decode(&data).unwrap(), (record, 34));
+    }
+
+    #[test]
+    fn dir_cache_is_ok() {
+        let mut fs = init_fs();
+
+        assert_eq!(dir_cache_names(&fs, "/"), ["dir"]);
+        assert_eq!(dir_cache_names(&fs, "dir"), ["file"]);
+
+        fs.rename("dir/file", "file").unwrap();
+        fs.symlink("file", "dir/link").unwrap();
+        fs.set_comment("file", Some("note")).unwrap();
+
+        assert_eq!(dir_cache_names(&fs, "/"), ["dir", "file"]);
+        assert_eq!(dir_cache_names(&fs, "dir"), ["link"]);
+        assert_eq!(fs.read_dir("/").unwrap().count(), 2);
+        assert_eq!(
+            fs.read_dir("/")
+                .unwrap()
+                .map(|entry| entry.unwrap())
+                .find(|entry| entry.name() == "file")
+                .unwrap()
+                .metadata()
+                .comment(),
+            Some("note"),
+        );
+        assert!(fs.check().unwrap().is_empty());
+
+        fs.remove_file("dir/link").unwrap();
+        fs.remove_dir("dir").unwrap();
+
+        assert_eq!(dir_cache_names(&fs, "/"), ["file"]);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dir_cache_spans_many_blocks() {
+        let fs = init_fs();
+
+        for i in 0..100 {
+            fs.write(format!("dir/file-with-a-long-name-{i:03}"), b"hello").unwrap();
+        }
+
+        let addr = fs.metadata("dir").unwrap().header_block_address();
+
+        assert!(read_dir_cache_chain(fs.disk(), addr).unwrap().len() > 1);
+        assert_eq!(dir_cache_names(&fs, "dir").len(), 101);
+        assert_eq!(fs.read_dir("dir").unwrap().count(), 101);
+        assert!(fs.check().unwrap().is_empty());
+
+        for i in 0..100 {
+            fs.remove_file(format!("dir/file-with-a-long-name-{i:03}")).unwrap();
+        }
+
+        assert_eq!(read_dir_cache_chain(fs.disk(), addr).unwrap().len(), 1);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dir_cache_records_are_updated_in_place() {
+        let mut fs = init_fs();
+
+        for i in 0..60 {
+            fs.write(format!("dir/file-with-a-long-name-{i:03}"), b"hello").unwrap();
+        }
+
+        let addr = fs.metadata("dir").unwrap().header_block_address();
+        let chain = read_dir_cache_chain(fs.disk(), addr).unwrap();
+
+        // a record growing out of its block is moved to another one
+        fs.set_comment("dir/file-with-a-long-name-000", Some(&"c".repeat(79))).unwrap();
+        fs.remove_file("dir/file-with-a-long-name-001").unwrap();
+
+        assert_eq!(read_dir_cache_chain(fs.disk(), addr).unwrap()[..chain.len()], chain);
+        assert_eq!(dir_cache_names(&fs, "dir").len(), 60);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dir_cache_is_updated_when_a_file_is_flushed() {
+        let fs = init_fs();
+        let size = |fs: &AmigaDos| {
+            let addr = fs.metadata("dir").unwrap().header_block_address();
+
+            read_dir_cache(fs.disk(), addr).unwrap().unwrap()
+                .into_iter()
+                .find(|record| record.name == "file")
+                .unwrap()
+                .size
+        };
+
+        let mut file = File::options().write(true).open(&fs, "dir/file").unwrap();
+
+        std::io::Write::write_all(&mut file, b"hello, world").unwrap();
+
+        assert_eq!(size(&fs), 5);
+        assert_eq!(fs.metadata("dir/file").unwrap().size(), 12);
+
+        std::io::Write::flush(&mut file).unwrap();
+        assert_eq!(size(&fs), 12);
+
+        std::io::Write::write_all(&mut file, b"!").unwrap();
+        drop(file);
+
+        assert_eq!(size(&fs), 13);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stale_dir_cache_is_reported() {
+        let fs = init_fs();
+        let addr = fs.metadata("dir").unwrap().header_block_address();
+        let cache_addr = read_dir_cache_chain(fs.disk(), addr).unwrap()[0];
+
+        let mut block = Block::new(fs.disk(), cache_addr);
+
+        block.write_u32(DIR_CACHE_BLOCK_RECORDS_NB_OFFSET, 0).unwrap();
+        block.write_checksum().unwrap();
+
+        let findings = fs.check().unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].address, addr);
+        assert_eq!(findings[0].kind, CheckFindingKind::InvalidDirCache);
+    }
+
+    #[test]
+    fn repair_rewrites_dir_cache() {
+        let mut fs = init_fs();
+        let addr = fs.metadata("dir").unwrap().header_block_address();
+        let cache_addr = read_dir_cache_chain(fs.disk(), addr).unwrap()[0];
+
+        Block::new(fs.disk(), cache_addr).clear().unwrap();
+
+        assert!(!fs.check().unwrap().is_empty());
+
+        fs.repair(&RepairOptions::default()).unwrap();
+
+        assert_eq!(dir_cache_names(&fs, "dir"), ["file"]);
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
@@ -28,12 +28,19 @@ fn init_dir_block_header(
     block.write_block_primary_type(BlockPrimaryType::Header)?;
     block.write_block_secondary_type(BlockSecondaryType::Directory)?;
     block.write_alteration_date(&SystemTime::now())?;
-    block.write_name(name)?;
+
+    if let Err(err) = block.write_name(name) {
+        fs.inner.borrow_mut().free_block(block_addr)?;
+        return Err(err);
+    }
+
     block.write_u32(
         BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
         block.address as u32,
     )?;
 
+    fs.inner.borrow_mut().update_dir_cache(block_addr)?;
+
     Ok(block_addr)
 }
 
@@ -47,6 +54,9 @@ impl AmigaDos {
         &mut self,
         path: P,
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let path = self.encode_path(path.as_ref())?;
         let path = path.as_ref();
 
         let parent_path = get_dirname(path)?;
@@ -70,6 +80,10 @@ impl AmigaDos {
         &mut self,
         path: P,
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let path = self.encode_path(path.as_ref())?;
+
         if let Some(fragments) = split(path) {
             let disk = self.inner.borrow().disk();
 
@@ -1,17 +1,18 @@
-use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::path::{
     Path,
     PathBuf,
 };
-use std::rc::Rc;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
 use super::constants::*;
 use super::dir::*;
+use super::dir_cache::*;
 use super::metadata::*;
 
 
@@ -44,9 +45,12 @@ impl DirEntry {
 pub struct DirIterator {
     current_table_index: usize,
     current_table_addr: Option<LBAAddress>,
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
+    lock: FsLock,
     header_block_address: LBAAddress,
     path: PathBuf,
+    // records of the directory cache, when the filesystem has one
+    dir_cache: Option<VecDeque<DirCacheRecord>>,
 }
 
 impl DirIterator {
@@ -83,6 +87,21 @@ impl Iterator for DirIterator {
     type Item = Result<DirEntry, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let _guard = self.lock.read();
+
+        if let Some(dir_cache) = self.dir_cache.as_mut() {
+            let record = dir_cache.pop_front()?;
+
+            return Some(
+                Metadata::try_from_dir_cache_record(self.disk.clone(), &record)
+                    .map(|metadata| DirEntry {
+                        metadata,
+                        path: self.path.join(&record.name),
+                        name: record.name,
+                    })
+            );
+        }
+
         if let Err(err) = self.block_table_next() {
             return Some(Err(err));
         }
@@ -120,14 +139,25 @@ impl AmigaDos {
         &self,
         path: P,
     ) -> Result<DirIterator, Error> {
+        let _guard = self.lock.read();
+
         let dir = Dir::try_with_path(self, path)?;
 
+        // the hash table is walked when there is no directory cache
+        let dir_cache = if self.inner.borrow().is_dir_cache_enabled()? {
+            read_dir_cache(self.disk(), dir.header_block_address)?.map(VecDeque::from)
+        } else {
+            None
+        };
+
         Ok(DirIterator {
             current_table_index: 0,
             current_table_addr: None,
             disk: self.disk(),
+            lock: self.lock.clone(),
             header_block_address: dir.header_block_address,
             path: dir.path.clone(),
+            dir_cache,
         })
     }
 }
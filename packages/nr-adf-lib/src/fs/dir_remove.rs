@@ -1,18 +1,19 @@
-use std::cell::RefCell;
+use std::borrow::Cow;
 use std::path::Path;
-use std::rc::Rc;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
 use crate::fs::path::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
+use super::block_type::*;
 use super::constants::*;
 use super::dir::*;
 
 pub(super) fn check_empty_directory(
-    disk: Rc<RefCell<Disk>>,
+    disk: Shared<Disk>,
     dir: &Dir,
 ) -> Result<bool, Error> {
     let block = Block::new(disk.clone(), dir.header_block_address);
@@ -27,21 +28,80 @@ pub(super) fn check_empty_directory(
 }
 
 impl AmigaDos {
-    /// Removes an empty directory.
+    /// Removes an empty directory, or a hard link to a directory without
+    /// removing the directory. A directory having hard links is moved to
+    /// one of them instead of being removed.
     pub fn remove_dir<P: AsRef<Path>>(
         &self,
         path: P,
     ) -> Result<(), Error> {
-        let dir = Dir::try_with_path(self, path.as_ref())?;
+        let _guard = self.lock.write();
 
-        check_empty_directory(self.disk(), &dir)?;
+        // names which can't be encoded are not found unless escaped
+        let path = path.as_ref();
+        let path = self.encode_path(path).unwrap_or(Cow::Borrowed(path));
+        let path = path.as_ref();
 
-        let name = get_basename(path.as_ref())?;
-        let parent_path = get_dirname(path.as_ref())?;
+        let header_block_address = self.lookup_with(path, false)?;
+
+        let name = get_basename(path)?;
+        let parent_path = get_dirname(path)?;
         let mut parent_dir = Dir::try_with_path(self, parent_path)?;
 
+        let block = Block::new(self.disk(), header_block_address);
+
+        if block.read_block_secondary_type()? == BlockSecondaryType::HardLinkDirectory {
+            self.unlink_hard_link(header_block_address)?;
+        } else {
+            let dir = Dir::try_with_block_address(
+                self,
+                header_block_address,
+                path,
+            )?;
+
+            if !check_empty_directory(self.disk(), &dir)? {
+                return Err(Error::NotEmptyError);
+            }
+
+            if self.move_to_hard_link(&mut parent_dir, name, header_block_address)? {
+                return Ok(());
+            }
+        }
+
         parent_dir.remove_entry(name)?;
 
-        self.inner.borrow_mut().free_block(dir.header_block_address)
+        let mut inner = self.inner.borrow_mut();
+
+        inner.free_dir_cache(header_block_address)?;
+        inner.free_comment_block(header_block_address)?;
+        inner.free_block(header_block_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    #[test]
+    fn remove_dir_with_an_escaped_name() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.with_name_encoding_mode(NameEncodingMode::Escape);
+        fs.create_dir_all("d\u{20ac}/sub").unwrap();
+
+        assert_eq!(fs.remove_dir("d\u{20ac}"), Err(Error::NotEmptyError));
+
+        fs.remove_dir("d\u{20ac}/sub").unwrap();
+        fs.remove_dir("d\u{20ac}").unwrap();
+
+        assert!(!fs.exists("d\u{20ac}").unwrap());
+        assert_eq!(fs.read_dir("/").unwrap().count(), 0);
+        assert!(fs.check().unwrap().is_empty());
     }
 }
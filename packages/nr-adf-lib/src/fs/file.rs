@@ -1,12 +1,11 @@
-use std::cell::RefCell;
 use std::ops;
 use std::path::Path;
-use std::rc::Rc;
 use std::time::SystemTime;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
 use super::amiga_dos_options::*;
@@ -18,9 +17,10 @@ use super::path::*;
 
 
 #[repr(usize)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum FileMode {
     /// This mode means that the file should be read-able whrn opened.
+    #[default]
     Read     = 0x01,
 
     /// This mode means that the file should be write-able when opened.
@@ -31,12 +31,6 @@ pub enum FileMode {
 
 type FileModeMask = usize;
 
-impl Default for FileMode {
-    fn default() -> Self {
-        Self::Read
-    }
-}
-
 impl ops::Not for FileMode {
     type Output = FileModeMask;
 
@@ -115,7 +109,7 @@ pub(super) struct FileDataBlockListEntry {
 
 impl FileDataBlockListEntry {
     pub(super) fn try_create(
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
         extension_block_addr: usize,
         extension_block_index: usize,
     ) -> Result<Option<Self>, Error> {
@@ -137,7 +131,7 @@ impl FileDataBlockListEntry {
     }
 
     pub(super) fn try_get_block_data_list(
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
         header_block_addr: LBAAddress,
     ) -> Result<Vec<FileDataBlockListEntry>, Error> {
         let mut entries = Vec::new();
@@ -165,17 +159,18 @@ impl FileDataBlockListEntry {
     }
 }
 
-fn get_data_block_info(
-    fs: &AmigaDos,
-) -> Result<(usize, usize), Error> {
-    Ok(match fs.get_filesystem_type()? {
+pub(super) fn get_data_block_info(
+    filesystem_type: FilesystemType,
+) -> (usize, usize) {
+    match filesystem_type {
         FilesystemType::FFS => (BLOCK_DATA_FFS_OFFSET, BLOCK_DATA_FFS_SIZE),
         FilesystemType::OFS => (BLOCK_DATA_OFS_OFFSET, BLOCK_DATA_OFS_SIZE),
-    })
+    }
 }
 
 pub struct File {
-    pub(super) fs: Rc<RefCell<AmigaDosInner>>,
+    pub(super) fs: Shared<AmigaDosInner>,
+    pub(super) lock: FsLock,
     pub(super) block_data_list: Vec<FileDataBlockListEntry>,
     pub(super) block_data_offset: usize,
     pub(super) block_data_size: usize,
@@ -183,6 +178,9 @@ pub struct File {
     pub(super) mode: usize,
     pub(super) pos: usize,
     pub(super) size: usize,
+    // the record of the file in the directory cache of its parent is
+    // updated when the file is flushed or closed
+    pub(super) dir_cache_dirty: bool,
 }
 
 impl File {
@@ -459,9 +457,13 @@ impl File {
         self.block_data_list.get(pos/self.block_data_size).copied()
     }
 
-    pub(super) fn sync_all(
+    /// Writes the size and the alteration date of the file to its header
+    /// block.
+    pub(super) fn write_header(
         &mut self,
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
         if test_file_mode(FileMode::Write, self.mode) {
             let mut block = Block::new(
                 self.fs.borrow().disk(),
@@ -471,9 +473,36 @@ impl File {
             block.write_file_size(self.size)?;
             block.write_alteration_date(&SystemTime::now())?;
             block.write_checksum()?;
+
+            self.dir_cache_dirty = true;
         }
         Ok(())
     }
+
+    pub(super) fn sync_all(
+        &mut self,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        self.write_header()?;
+
+        if self.dir_cache_dirty {
+            self.fs.borrow_mut().update_parent_dir_cache(self.header_block_address)?;
+            self.dir_cache_dirty = false;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // errors can't be reported when a file is closed, `flush` reports
+        // them
+        if self.dir_cache_dirty {
+            let _guard = self.lock.write();
+            let _ = self.fs.borrow_mut().update_parent_dir_cache(self.header_block_address);
+        }
+    }
 }
 
 fn init_file_block_header(
@@ -488,7 +517,12 @@ fn init_file_block_header(
     block.write_block_primary_type(BlockPrimaryType::Header)?;
     block.write_block_secondary_type(BlockSecondaryType::File)?;
     block.write_alteration_date(&SystemTime::now())?;
-    block.write_name(name)?;
+
+    if let Err(err) = block.write_name(name) {
+        fs.inner.borrow_mut().free_block(block_addr)?;
+        return Err(err);
+    }
+
     block.write_u32(
         BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
         block.address as u32,
@@ -520,10 +554,11 @@ impl File {
         let (
             block_data_offset,
             block_data_size,
-        ) = get_data_block_info(fs)?;
+        ) = get_data_block_info(fs.get_filesystem_type()?);
 
         let file = Self {
             fs: fs.inner.clone(),
+            lock: fs.lock.clone(),
             block_data_list,
             block_data_offset,
             block_data_size,
@@ -531,6 +566,7 @@ impl File {
             mode,
             pos,
             size,
+            dir_cache_dirty: false,
         };
 
         Ok(file)
@@ -542,6 +578,9 @@ impl File {
         mode: usize,
         create_new: bool,
     ) -> Result<File, Error> {
+        let path = fs.encode_path(path)?;
+        let path = path.as_ref();
+
         if fs.exists(path)? {
             if create_new {
                 return Err(Error::AlreadyExists);
@@ -560,7 +599,7 @@ impl File {
         let (
             block_data_offset,
             block_data_size,
-        ) = get_data_block_info(fs)?;
+        ) = get_data_block_info(fs.get_filesystem_type()?);
 
         let mut parent_dir = Dir::try_with_path(fs, parent_path)?;
 
@@ -570,6 +609,7 @@ impl File {
 
         Ok(Self {
             fs: fs.inner.clone(),
+            lock: fs.lock.clone(),
             block_data_list,
             block_data_offset,
             block_data_size,
@@ -577,6 +617,7 @@ impl File {
             mode,
             size: 0,
             pos: 0,
+            dir_cache_dirty: false,
         })
     }
 }
@@ -0,0 +1,136 @@
+use std::io;
+
+use super::file::*;
+
+
+impl io::Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(File::read(self, buf)?)
+    }
+}
+
+impl io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(File::write(self, buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(self.sync_all()?)
+    }
+}
+
+impl io::Seek for File {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        Ok(File::seek(self, pos)? as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    };
+
+    use crate::disk::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs(filesystem_type: FilesystemType) -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        AmigaDosFormater::default()
+            .with_filesystem_type(filesystem_type)
+            .format(shared(disk), "TEST")
+            .unwrap()
+    }
+
+    fn copy(filesystem_type: FilesystemType) {
+        let fs = init_fs(filesystem_type);
+        let data_in = (0..20000).map(|i| i as u8).collect::<Vec<u8>>();
+
+        let mut output = File::options()
+            .create(true)
+            .write(true)
+            .open(&fs, "/data")
+            .unwrap();
+
+        std::io::copy(&mut data_in.as_slice(), &mut output).unwrap();
+        output.flush().unwrap();
+
+        let mut data_out = Vec::new();
+
+        File::options()
+            .read(true)
+            .open(&fs, "/data")
+            .unwrap()
+            .read_to_end(&mut data_out)
+            .unwrap();
+
+        assert_eq!(data_in, data_out);
+    }
+
+    fn seek_past_eof(filesystem_type: FilesystemType) {
+        let fs = init_fs(filesystem_type);
+
+        let mut file = File::options()
+            .create(true)
+            .read(true)
+            .write(true)
+            .open(&fs, "/data")
+            .unwrap();
+
+        file.write_all(&[42u8; 10]).unwrap();
+        assert_eq!(Seek::seek(&mut file, SeekFrom::Current(1000)).unwrap(), 1010);
+        file.write_all(&[43u8; 10]).unwrap();
+
+        let data = fs.read("/data").unwrap();
+
+        assert_eq!(data.len(), 1020);
+        assert!(data[..10].iter().all(|b| *b == 42));
+        assert!(data[10..1010].iter().all(|b| *b == 0));
+        assert!(data[1010..].iter().all(|b| *b == 43));
+
+        let mut buf = [0u8; 4];
+
+        Seek::seek(&mut file, SeekFrom::End(-12)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 0, 43, 43]);
+    }
+
+    #[test]
+    fn copy_ofs() {
+        copy(FilesystemType::OFS)
+    }
+
+    #[test]
+    fn copy_ffs() {
+        copy(FilesystemType::FFS)
+    }
+
+    #[test]
+    fn seek_past_eof_ofs() {
+        seek_past_eof(FilesystemType::OFS)
+    }
+
+    #[test]
+    fn seek_past_eof_ffs() {
+        seek_past_eof(FilesystemType::FFS)
+    }
+
+    #[test]
+    fn seek_before_start_fails() {
+        let fs = init_fs(FilesystemType::OFS);
+
+        let mut file = File::options()
+            .create(true)
+            .write(true)
+            .open(&fs, "/data")
+            .unwrap();
+
+        let err = Seek::seek(&mut file, SeekFrom::Current(-1)).unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -112,6 +112,12 @@ impl OpenOptions {
             return Err(Error::InvalidFileModeError);
         }
 
+        let _guard = if self.create || self.create_new || self.truncate {
+            fs.lock.write()
+        } else {
+            fs.lock.read()
+        };
+
         let mut mode: usize = 0;
 
         if self.read {
@@ -30,6 +30,8 @@ impl File {
         &mut self,
         mut buf: &mut [u8],
     ) -> Result<usize, Error> {
+        let _guard = self.lock.read();
+
         check_file_mode(FileMode::Read, self.mode)?;
 
         if self.pos >= self.size {
@@ -63,6 +65,8 @@ impl AmigaDos {
         &self,
         path: P,
     ) -> Result<Vec<u8>, Error> {
+        let _guard = self.lock.read();
+
         let mut buf = [0; BLOCK_SIZE];
 
         let mut output = Vec::new();
@@ -1,38 +1,101 @@
+use std::borrow::Cow;
 use std::path::Path;
 
+use crate::block::*;
 use crate::errors::*;
 
 use super::amiga_dos::*;
+use super::block_type::*;
 use super::dir::*;
 use super::file::*;
 use super::path::*;
 
 
 impl AmigaDos {
-    /// Removes an empty directory.
+    /// Removes a file, or a soft or hard link without removing its target.
+    /// A file having hard links is moved to one of them instead of being
+    /// removed.
     pub fn remove_file<P: AsRef<Path>>(
         &self,
         path: P,
     ) -> Result<(), Error> {
-        let mut file = File::try_open(
-            self,
-            path.as_ref(),
-            0|FileMode::Write,
-        )?;
+        let _guard = self.lock.write();
+
+        // names which can't be encoded are not found unless escaped
+        let path = path.as_ref();
+        let path = self.encode_path(path).unwrap_or(Cow::Borrowed(path));
+        let path = path.as_ref();
+
+        let metadata = self.symlink_metadata(path)?;
 
-        file.set_len(0)?;
+        if metadata.is_dir() {
+            return Err(Error::NotAFileError);
+        }
 
-        let name = get_basename(path.as_ref())?;
-        let parent_path = get_dirname(path.as_ref())?;
+        let header_block_address = metadata.header_block_address();
+
+        let name = get_basename(path)?;
+        let parent_path = get_dirname(path)?;
         let mut dir = Dir::try_with_path(
             self,
             parent_path
         )?;
 
+        let block = Block::new(self.disk(), header_block_address);
+
+        match block.read_block_secondary_type()? {
+            BlockSecondaryType::SoftLink => (),
+            BlockSecondaryType::HardLinkFile => {
+                self.unlink_hard_link(header_block_address)?;
+            },
+            _ => {
+                if self.move_to_hard_link(&mut dir, name, header_block_address)? {
+                    return Ok(());
+                }
+
+                let mut file = File::try_open(
+                    self,
+                    path,
+                    0|FileMode::Write,
+                )?;
+
+                file.set_len(0)?;
+            },
+        }
+
         dir.remove_entry(name)?;
 
-        self.inner.borrow_mut().free_block(file.header_block_address)?;
+        let mut inner = self.inner.borrow_mut();
+
+        inner.free_comment_block(header_block_address)?;
+        inner.free_block(header_block_address)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    #[test]
+    fn remove_file_with_an_escaped_name() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.with_name_encoding_mode(NameEncodingMode::Escape);
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/a\u{20ac}", b"foo").unwrap();
+        fs.write("dir/b", b"bar").unwrap();
+        fs.remove_file("dir/a\u{20ac}").unwrap();
+
+        assert!(!fs.exists("dir/a\u{20ac}").unwrap());
+        assert_eq!(fs.read_dir("dir").unwrap().count(), 1);
+        assert_eq!(fs.read("dir/b").unwrap(), b"bar");
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,31 @@
+use std::io::SeekFrom;
+
+use crate::errors::*;
+
+use super::file::*;
+
+
+impl File {
+    /// Seek to an offset, in bytes, in the file.
+    /// Seeking beyond the end of the file is allowed. A subsequent write
+    /// will fill the gap with zeros.
+    pub fn seek(
+        &mut self,
+        pos: SeekFrom,
+    ) -> Result<usize, Error> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset as usize;
+                return Ok(self.pos);
+            },
+            SeekFrom::End(offset) => (self.size, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+
+        self.pos = base
+            .checked_add_signed(offset as isize)
+            .ok_or(Error::InvalidSeekPositionError)?;
+
+        Ok(self.pos)
+    }
+}
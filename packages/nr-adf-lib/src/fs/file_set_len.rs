@@ -32,13 +32,18 @@ impl File {
             entry.data_block_address,
         );
 
-        let block_offset = self.size%self.block_data_size;
+        let block_start = self.size - self.size%self.block_data_size;
+        let block_offset = self.size - block_start;
         let block_size = usize::min(
-            new_size - self.size/self.block_data_size, // ???
+            new_size - block_start,
             self.block_data_size,
         );
 
-        block.fill(0, block_offset, block_offset + block_size)?;
+        block.fill(
+            0,
+            self.block_data_offset + block_offset,
+            self.block_data_offset + block_size,
+        )?;
 
         self.sync_block(&mut block, block_size)?;
 
@@ -49,6 +54,8 @@ impl File {
         &mut self,
         new_size: usize,
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
         assert!(new_size > self.size, "internal error");
 
         if let Some(entry) = self.get_data_block_list_entry(self.size) {
@@ -80,7 +87,7 @@ impl File {
     ) -> usize {
         if size < self.block_data_size {
             0
-        } else if size%self.block_data_size > 0 {
+        } else if !size.is_multiple_of(self.block_data_size) {
             size/self.block_data_size
         } else {
             size/self.block_data_size -  1
@@ -126,6 +133,8 @@ impl File {
         &mut self,
         size: usize,
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
         check_file_mode(FileMode::Write, self.mode)?;
 
         if size > self.size {
@@ -142,12 +151,10 @@ impl File {
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
-    use std::cell::RefCell;
-
     use crate::disk::*;
     use crate::fs::constants::BLOCK_DATA_OFS_SIZE;
     use crate::fs::*;
+    use crate::shared::*;
 
     fn init_fs() -> AmigaDos {
         let disk = Disk::create(DiskType::DoubleDensity);
@@ -155,7 +162,7 @@ mod tests {
             .with_cache_mode(CacheMode::Off)
             .with_filesystem_type(FilesystemType::OFS)
             .with_international_mode(InternationalMode::Off)
-            .format(Rc::new(RefCell::new(disk)), "TEST")
+            .format(shared(disk), "TEST")
             .unwrap()
     }
 
@@ -1,8 +1,10 @@
+use std::path::Path;
 use std::time::SystemTime;
 
 use crate::block::*;
 use crate::errors::*;
 
+use super::amiga_dos::*;
 use super::file::*;
 
 
@@ -11,6 +13,8 @@ impl File {
         &mut self,
         datetime: &SystemTime,
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
         check_file_mode(FileMode::Write, self.mode)?;
 
         let mut block = Block::new(
@@ -21,6 +25,73 @@ impl File {
         block.write_alteration_date(datetime)?;
         block.write_checksum()?;
 
-        Ok(())
+        self.fs.borrow_mut().update_parent_dir_cache(self.header_block_address)
+    }
+}
+
+impl AmigaDos {
+    /// Changes the alteration date of a file or a directory, the root
+    /// directory included. Soft and hard links are followed.
+    /// As adding or removing an entry updates the date of its directory, the
+    /// date of a directory is to be set once its content is written.
+    /// Errors:
+    /// - When `path` doesn't exist.
+    pub fn set_modified<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        datetime: &SystemTime,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let addr = self.lookup(path)?;
+        let mut block = Block::new(self.disk(), addr);
+
+        block.write_alteration_date(datetime)?;
+        block.write_checksum()?;
+
+        self.inner.borrow_mut().update_parent_dir_cache(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        UNIX_EPOCH,
+    };
+
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    #[test]
+    fn set_modified_is_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .with_cache_mode(CacheMode::On)
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/file", b"hello").unwrap();
+
+        // dates are stored to the tick, i.e. 1/50th of a second
+        let date = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        for path in ["dir/file", "dir", "/"] {
+            fs.set_modified(path, &date).unwrap();
+
+            assert_eq!(fs.metadata(path).unwrap().alteration_date(), date);
+        }
+
+        let entry = fs.read_dir("dir").unwrap().next().unwrap().unwrap();
+
+        assert_eq!(entry.metadata().alteration_date(), date);
+        assert_eq!(
+            fs.set_modified("missing", &date),
+            Err(Error::NotFoundError),
+        );
+        assert!(fs.check().unwrap().is_empty());
     }
 }
@@ -41,8 +41,14 @@ impl File {
         &mut self,
         mut buf: &[u8],
     ) -> Result<usize, Error> {
+        let _guard = self.lock.write();
+
         check_file_mode(FileMode::Write, self.mode)?;
 
+        if self.pos > self.size && !buf.is_empty() {
+            self.grow(self.pos)?;
+        }
+
         let mut count = 0;
 
         while !buf.is_empty() {
@@ -68,7 +74,7 @@ impl File {
             self.size = self.pos.max(self.size);
         }
 
-        self.sync_all()?;
+        self.write_header()?;
 
         Ok(count)
     }
@@ -83,6 +89,8 @@ impl AmigaDos {
         path: P,
         data: C
     ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
         File::options()
             .create(true)
             .truncate(true)
@@ -95,10 +103,8 @@ impl AmigaDos {
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
-    use std::cell::RefCell;
-
     use crate::fs::*;
+    use crate::shared::*;
     use super::*;
 
     fn write_ofs(count: usize) {
@@ -109,7 +115,7 @@ mod tests {
             .with_cache_mode(CacheMode::Off)
             .with_filesystem_type(FilesystemType::OFS)
             .with_international_mode(InternationalMode::Off)
-            .format(Rc::new(RefCell::new(disk)), "TEST")
+            .format(shared(disk), "TEST")
             .unwrap();
 
         fs.write("/data", &data_in).unwrap();
@@ -1,8 +1,6 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
 use super::amiga_dos_options::*;
@@ -16,6 +14,7 @@ pub struct AmigaDosFormater {
     filesystem_type: FilesystemType,
     cache_mode: CacheMode,
     international_mode: InternationalMode,
+    long_name_mode: LongNameMode,
     root_block_address: Option<LBAAddress>,
 }
 
@@ -44,6 +43,26 @@ impl AmigaDosFormater {
         self
     }
 
+    pub fn with_long_name_mode(
+        &mut self,
+        long_name_mode: LongNameMode,
+    ) -> &mut Self {
+        self.long_name_mode = long_name_mode;
+        self
+    }
+
+    /// Sets the filesystem type and all the modes at once from a DOS type.
+    pub fn with_dos_type(
+        &mut self,
+        dos_type: DosType,
+    ) -> &mut Self {
+        self.filesystem_type = dos_type.filesystem_type();
+        self.international_mode = dos_type.international_mode();
+        self.cache_mode = dos_type.cache_mode();
+        self.long_name_mode = dos_type.long_name_mode();
+        self
+    }
+
     pub fn with_root_block_address(
         &mut self,
         addr: Option<LBAAddress>,
@@ -54,14 +73,19 @@ impl AmigaDosFormater {
 
     pub fn format(
         &self,
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
         volume_name: &str,
     ) -> Result<AmigaDos, Error> {
+        let dos_type = DosType::try_from_modes(
+            self.filesystem_type,
+            self.international_mode,
+            self.cache_mode,
+            self.long_name_mode,
+        )?;
+
         BootBlockInitializer::default()
             .with_root_block_address(self.root_block_address)
-            .with_filesystem_type(self.filesystem_type)
-            .with_cache_mode(self.cache_mode)
-            .with_international_mode(self.international_mode)
+            .with_dos_type(dos_type)
             .init(disk.clone())?;
 
         RootBlockInitializer::default()
@@ -74,15 +98,18 @@ impl AmigaDosFormater {
             .with_root_block_address(self.root_block_address)
             .init(disk.clone())?;
 
-        AmigaDos::try_from(disk.clone())
+        let fs = AmigaDos::try_from(disk.clone())?;
+        let root_block_address = BootBlockReader::try_from_disk(disk)?
+            .get_root_block_address();
+
+        fs.inner.borrow_mut().update_dir_cache(root_block_address)?;
+
+        Ok(fs)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
-    use std::cell::RefCell;
-
     use super::*;
 
     #[test]
@@ -94,7 +121,7 @@ mod tests {
             .with_cache_mode(CacheMode::Off)
             .with_filesystem_type(FilesystemType::OFS)
             .with_international_mode(InternationalMode::Off)
-            .format(Rc::new(RefCell::new(disk)), "TEST")
+            .format(shared(disk), "TEST")
             .unwrap();
 
         let info = fs.info().unwrap();
@@ -102,4 +129,50 @@ mod tests {
         assert_eq!(info.total_block_count, block_count);
         assert_eq!(info.free_block_count, block_count - 4);
     }
+
+    #[test]
+    fn format_hard_disk() {
+        // needs more bitmap blocks than the root block can reference
+        let disk = Disk::create(DiskType::Custom(110000));
+        let block_count = disk.block_count();
+
+        let fs = AmigaDosFormater::default()
+            .with_filesystem_type(FilesystemType::FFS)
+            .format(shared(disk), "HDF")
+            .unwrap();
+
+        let info = fs.info().unwrap();
+
+        // boot blocks, root block, 28 bitmap blocks and 1 extension block
+        assert_eq!(info.total_block_count, block_count);
+        assert_eq!(info.free_block_count, block_count - 32);
+        assert_eq!(fs.check().unwrap(), vec![]);
+
+        let fs = AmigaDos::try_from(fs.disk()).unwrap();
+
+        assert_eq!(fs.inner.borrow().get_bitmap_block_addresses().len(), 28);
+        assert!(fs.inner.borrow().is_block_free(block_count - 1).unwrap());
+        assert_eq!(fs.inner.borrow_mut().reserve_block().unwrap(), 2);
+    }
+
+    #[test]
+    fn non_dos_disk_fails() {
+        let mut disk = Disk::create(DiskType::DoubleDensity);
+
+        disk.block_mut(0).unwrap()[..4].copy_from_slice(b"NDOS");
+
+        let disk = shared(disk);
+
+        assert_eq!(
+            AmigaDos::try_from(disk.clone()).err(),
+            Some(Error::NotADosDisk(*b"NDOS")),
+        );
+
+        disk.borrow_mut().block_mut(0).unwrap()[..4].copy_from_slice(b"DOS\x08");
+
+        assert_eq!(
+            AmigaDos::try_from(disk).err(),
+            Some(Error::NotADosDisk(*b"DOS\x08")),
+        );
+    }
 }
@@ -0,0 +1,269 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::block::*;
+use crate::disk::*;
+use crate::errors::*;
+
+use super::amiga_dos::*;
+use super::block_type::*;
+use super::constants::*;
+use super::dir::*;
+use super::path::*;
+
+
+fn init_hard_link_block_header(
+    fs: &AmigaDos,
+    name: &str,
+    block_secondary_type: BlockSecondaryType,
+    real_entry_address: LBAAddress,
+) -> Result<LBAAddress, Error> {
+    let block_addr = fs.inner.borrow_mut().reserve_block()?;
+    let mut block = Block::new(fs.disk(), block_addr);
+
+    block.clear()?;
+
+    block.write_block_primary_type(BlockPrimaryType::Header)?;
+    block.write_block_secondary_type(block_secondary_type)?;
+    block.write_alteration_date(&SystemTime::now())?;
+
+    if let Err(err) = block.write_name(name) {
+        fs.inner.borrow_mut().free_block(block_addr)?;
+        return Err(err);
+    }
+
+    block.write_u32(
+        BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+        block.address as u32,
+    )?;
+    block.write_real_entry_address(real_entry_address)?;
+
+    Ok(block_addr)
+}
+
+impl AmigaDos {
+    /// Creates a new hard link at `link` to the `existing` file or
+    /// directory. Links to a link point to its real entry.
+    /// Errors:
+    /// - When `existing` doesn't exist or is the root directory.
+    /// - When the parent of `link` doesn't exist or is not a directory.
+    /// - When `link` already exists.
+    pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        existing: P,
+        link: Q,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let link = self.encode_path(link.as_ref())?;
+        let link = link.as_ref();
+        let real_entry_address = self.lookup(existing)?;
+        let mut real_entry = Block::new(self.disk(), real_entry_address);
+
+        let block_secondary_type = match real_entry.read_block_secondary_type()? {
+            BlockSecondaryType::File => BlockSecondaryType::HardLinkFile,
+            BlockSecondaryType::Directory => BlockSecondaryType::HardLinkDirectory,
+            _ => return Err(Error::InvalidPathError),
+        };
+
+        let parent_path = get_dirname(link)?;
+        let name = get_basename(link)?;
+
+        let mut dir = Dir::try_with_path(self, parent_path)?;
+
+        if dir.lookup(name)?.is_some() {
+            return Err(Error::AlreadyExists);
+        }
+
+        let addr = init_hard_link_block_header(
+            self,
+            name,
+            block_secondary_type,
+            real_entry_address,
+        )?;
+
+        // links are chained from the real entry, newest first
+        let mut block = Block::new(self.disk(), addr);
+
+        block.write_next_link_address(
+            real_entry.read_next_link_address()?.unwrap_or(0)
+        )?;
+        real_entry.write_next_link_address(addr)?;
+        real_entry.write_checksum()?;
+
+        dir.add_entry(name, addr)
+    }
+
+    /// Removes a hard link from the chain of links of its real entry.
+    pub(super) fn unlink_hard_link(
+        &self,
+        link_address: LBAAddress,
+    ) -> Result<(), Error> {
+        let disk = self.disk();
+        let link = Block::new(disk.clone(), link_address);
+
+        let next_link_address = link.read_next_link_address()?;
+        let block_count = disk.borrow().block_count();
+        let mut prev = Block::new(
+            disk.clone(),
+            link.read_real_entry_address()?.ok_or(Error::CorruptedImageFile)?,
+        );
+
+        for _ in 0..block_count {
+            match prev.read_next_link_address()? {
+                Some(addr) if addr == link_address => {
+                    prev.write_next_link_address(next_link_address.unwrap_or(0))?;
+                    return prev.write_checksum();
+                },
+                Some(addr) => {
+                    prev = Block::new(disk.clone(), addr);
+                },
+                None => break,
+            }
+        }
+
+        Err(Error::CorruptedImageFile)
+    }
+
+    /// Before a real entry having hard links is removed from its parent
+    /// directory, gives it to its first link: the entry takes the name and
+    /// the place of the link, whose block is released. The data of the
+    /// entry are left untouched and the other links still point to it.
+    /// Returns false when the entry has no link.
+    pub(super) fn move_to_hard_link(
+        &self,
+        parent_dir: &mut Dir,
+        name: &str,
+        real_entry_address: LBAAddress,
+    ) -> Result<bool, Error> {
+        let disk = self.disk();
+        let mut real_entry = Block::new(disk.clone(), real_entry_address);
+
+        let link_address = match real_entry.read_next_link_address()? {
+            Some(addr) => addr,
+            None => return Ok(false),
+        };
+
+        let link = Block::new(disk.clone(), link_address);
+        let link_name = link.read_name()?;
+        let link_date = link.read_alteration_date()?;
+        let next_link_address = link.read_next_link_address()?;
+
+        let mut link_dir = Dir::try_with_block_address(
+            self,
+            link.read_parent_block_address()?.ok_or(Error::CorruptedImageFile)?,
+            "",
+        )?;
+
+        parent_dir.remove_entry(name)?;
+        link_dir.remove_entry(&link_name)?;
+
+        self.inner.borrow_mut().write_entry_name(real_entry_address, &link_name)?;
+
+        real_entry.write_alteration_date(&link_date)?;
+        real_entry.write_next_link_address(next_link_address.unwrap_or(0))?;
+        real_entry.write_checksum()?;
+
+        link_dir.add_entry(&link_name, real_entry_address)?;
+
+        let mut inner = self.inner.borrow_mut();
+
+        inner.free_comment_block(link_address)?;
+        inner.free_block(link_address)?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs() -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .with_filesystem_type(FilesystemType::OFS)
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir_all("dir/sub").unwrap();
+        fs.write("dir/file", vec![42u8; 2000]).unwrap();
+        fs
+    }
+
+    #[test]
+    fn hard_link_is_ok() {
+        let mut fs = init_fs();
+
+        fs.hard_link("dir/file", "link").unwrap();
+        fs.hard_link("link", "dir/sub/link").unwrap();
+        fs.hard_link("dir/sub", "sub").unwrap();
+        fs.write("sub/new", b"hello").unwrap();
+
+        assert_eq!(fs.read("link").unwrap(), vec![42u8; 2000]);
+        assert_eq!(fs.read("dir/sub/link").unwrap(), vec![42u8; 2000]);
+        assert_eq!(fs.metadata("link").unwrap().size(), 2000);
+        assert!(fs.symlink_metadata("link").unwrap().is_hard_link());
+        assert!(fs.metadata("sub").unwrap().is_dir());
+        assert_eq!(fs.read("dir/sub/new").unwrap(), b"hello");
+        assert_eq!(fs.hard_link("dir", "link"), Err(Error::AlreadyExists));
+        assert_eq!(fs.hard_link("/", "root"), Err(Error::InvalidPathError));
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn remove_hard_link_is_ok() {
+        let mut fs = init_fs();
+        let free = fs.info().unwrap().free_block_count;
+
+        fs.hard_link("dir/file", "link1").unwrap();
+        fs.hard_link("dir/file", "link2").unwrap();
+        fs.hard_link("dir/sub", "sub").unwrap();
+
+        fs.remove_file("link2").unwrap();
+        fs.remove_dir("sub").unwrap();
+
+        assert!(!fs.exists("link2").unwrap());
+        assert!(fs.exists("dir/sub").unwrap());
+        assert_eq!(fs.read("link1").unwrap(), vec![42u8; 2000]);
+
+        fs.remove_file("link1").unwrap();
+
+        assert_eq!(fs.info().unwrap().free_block_count, free);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn remove_linked_entry_is_ok() {
+        let mut fs = init_fs();
+        let free = fs.info().unwrap().free_block_count;
+
+        fs.hard_link("dir/file", "link1").unwrap();
+        fs.hard_link("dir/file", "dir/sub/link2").unwrap();
+        fs.hard_link("dir/sub", "sub").unwrap();
+
+        fs.remove_file("dir/file").unwrap();
+
+        assert!(!fs.exists("dir/file").unwrap());
+        assert!(!fs.symlink_metadata("dir/sub/link2").unwrap().is_hard_link());
+        assert_eq!(fs.read("link1").unwrap(), vec![42u8; 2000]);
+        assert_eq!(fs.read("dir/sub/link2").unwrap(), vec![42u8; 2000]);
+
+        fs.remove_file("dir/sub/link2").unwrap();
+        fs.remove_dir("dir/sub").unwrap();
+
+        assert!(fs.metadata("sub").unwrap().is_dir());
+        assert!(!fs.symlink_metadata("sub").unwrap().is_hard_link());
+        assert!(fs.check().unwrap().is_empty());
+
+        fs.remove_file("link1").unwrap();
+        fs.remove_dir("sub").unwrap();
+
+        // dir/file header and data blocks, dir/sub header
+        assert_eq!(fs.info().unwrap().free_block_count, free + 7);
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
@@ -13,9 +13,11 @@ use super::boot_block::*;
 pub struct AmigaDosInfo {
     pub volume_name: String,
 
+    pub dos_type: DosType,
     pub filesystem_type: FilesystemType,
     pub cache_mode: CacheMode,
     pub international_mode: InternationalMode,
+    pub long_name_mode: LongNameMode,
 
     pub root_alteration_date: SystemTime,
     pub root_creation_date: SystemTime,
@@ -30,6 +32,8 @@ pub struct AmigaDosInfo {
 
 impl AmigaDos {
     pub fn info(&self) -> Result<AmigaDosInfo, Error> {
+        let _guard = self.lock.read();
+
         let fs = self.inner.borrow();
         let disk = fs.disk();
 
@@ -51,9 +55,11 @@ impl AmigaDos {
         let free_block_count = fs.free_block_count();
 
         Ok(AmigaDosInfo {
+            dos_type: boot_block.get_dos_type(),
             filesystem_type: boot_block.get_filesystem_type(),
             cache_mode: boot_block.get_cache_mode(),
             international_mode: boot_block.get_international_mode(),
+            long_name_mode: boot_block.get_long_name_mode(),
 
             root_alteration_date,
             root_creation_date,
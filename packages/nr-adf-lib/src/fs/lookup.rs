@@ -1,46 +1,257 @@
+use std::borrow::Cow;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
 
 use super::amiga_dos::*;
+use super::block_type::*;
 use super::boot_block::*;
 use super::dir::*;
 use super::path::*;
 
 
+// maximum number of soft links followed while resolving a path
+const MAX_SOFT_LINK_COUNT: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Component {
+    Parent,
+    Name(String),
+}
+
+// Splits a soft link target into its components, telling if the target is
+// relative to the root directory, i.e. starts with a volume name or ":".
+// As in AmigaDOS paths, a leading "/" and each "/" following another one
+// stand for the parent directory.
+fn split_soft_link_target(target: &str) -> (bool, Vec<Component>) {
+    let (absolute, path) = match target.split_once(':') {
+        Some((_, path)) => (true, path),
+        None => (false, target),
+    };
+
+    let mut parts = path.split('/').collect::<Vec<_>>();
+
+    // a trailing "/" only ends the last name
+    if parts.len() > 1 && parts.last() == Some(&"") {
+        parts.pop();
+    }
+
+    let components = parts.into_iter()
+        .filter(|part| !path.is_empty() || !part.is_empty())
+        .map(|part| match part {
+            "" => Component::Parent,
+            name => Component::Name(name.into()),
+        })
+        .collect();
+
+    (absolute, components)
+}
+
 impl AmigaDos {
-    pub(super) fn lookup<P: AsRef<Path>>(
+    /// Resolves a path to the address of its header block.
+    /// Soft and hard links met along the path are followed, the last
+    /// component being followed only when `follow_links` is set.
+    pub(super) fn lookup_with<P: AsRef<Path>>(
         &self,
         path: P,
+        follow_links: bool,
     ) -> Result<LBAAddress, Error> {
-        if let Some(path) = split(path) {
-            let disk = self.inner.borrow().disk();
+        // names which can't be encoded are not found unless escaped
+        let path = path.as_ref();
+        let path = self.encode_path(path).unwrap_or(Cow::Borrowed(path));
+
+        let mut names = split(path)
+            .ok_or(Error::InvalidPathError)?
+            .into_iter()
+            .map(Component::Name)
+            .collect::<Vec<_>>();
+        let disk = self.inner.borrow().disk();
+
+        let boot_block = BootBlockReader::try_from_disk(disk.clone())?;
+        let root_block_addr = boot_block.get_root_block_address();
 
-            let boot_block = BootBlockReader::try_from_disk(disk.clone())?;
+        let mut current_block_addr = root_block_addr;
+        let mut soft_link_count = 0;
 
-            let mut current_block_addr = boot_block.get_root_block_address();
-            let mut current_path = PathBuf::from("/");
+        names.reverse();
 
-            for name in path {
-                let dir = Dir::try_with_block_address(
-                    self,
-                    current_block_addr,
-                    &current_path,
-                )?;
+        while let Some(name) = names.pop() {
+            let dir = Dir::try_with_block_address(
+                self,
+                current_block_addr,
+                PathBuf::default(),
+            )?;
 
-                if let Some(addr) = dir.lookup(&name)? {
+            let addr = match name {
+                Component::Parent => {
+                    Block::new(disk.clone(), current_block_addr)
+                        .read_parent_block_address()?
+                        .unwrap_or(root_block_addr)
+                },
+                Component::Name(name) => dir.lookup(&name)?.ok_or(Error::NotFoundError)?,
+            };
+
+            let block = Block::new(disk.clone(), addr);
+            let follow = follow_links || !names.is_empty();
+
+            match block.read_block_secondary_type()? {
+                BlockSecondaryType::SoftLink if follow => {
+                    soft_link_count += 1;
+                    if soft_link_count > MAX_SOFT_LINK_COUNT {
+                        return Err(Error::SoftLinkLoopError);
+                    }
+
+                    let (absolute, target) = split_soft_link_target(
+                        &block.read_soft_link_target()?
+                    );
+
+                    if absolute {
+                        current_block_addr = root_block_addr;
+                    }
+                    names.extend(target.into_iter().rev());
+                },
+                BlockSecondaryType::HardLinkDirectory |
+                BlockSecondaryType::HardLinkFile if follow => {
+                    current_block_addr = block
+                        .read_real_entry_address()?
+                        .ok_or(Error::CorruptedImageFile)?;
+                },
+                _ => {
                     current_block_addr = addr;
-                    current_path = current_path.join(name);
-                } else {
-                    return Err(Error::NotFoundError);
-                }
+                },
             }
-
-            Ok(current_block_addr)
-        } else {
-            Err(Error::InvalidPathError)
         }
+
+        Ok(current_block_addr)
+    }
+
+    /// Resolves a path to the address of its header block, following soft
+    /// and hard links.
+    pub(super) fn lookup<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<LBAAddress, Error> {
+        self.lookup_with(path, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::*;
+
+    use super::super::*;
+    use super::*;
+
+    fn name(name: &str) -> Component {
+        Component::Name(name.into())
+    }
+
+    #[test]
+    fn split_soft_link_target_is_ok() {
+        assert_eq!(split_soft_link_target("a/b"), (false, vec![name("a"), name("b")]));
+        assert_eq!(split_soft_link_target("a/b/"), (false, vec![name("a"), name("b")]));
+        assert_eq!(split_soft_link_target("/a"), (false, vec![Component::Parent, name("a")]));
+        assert_eq!(split_soft_link_target("/"), (false, vec![Component::Parent]));
+        assert_eq!(
+            split_soft_link_target("//a"),
+            (false, vec![Component::Parent, Component::Parent, name("a")]),
+        );
+        assert_eq!(
+            split_soft_link_target("a//b"),
+            (false, vec![name("a"), Component::Parent, name("b")]),
+        );
+        assert_eq!(
+            split_soft_link_target("Workbench:c/dir"),
+            (true, vec![name("c"), name("dir")]),
+        );
+        assert_eq!(split_soft_link_target(":a"), (true, vec![name("a")]));
+        assert_eq!(split_soft_link_target("Workbench:"), (true, vec![]));
+        assert_eq!(split_soft_link_target(""), (false, vec![]));
+    }
+
+    #[test]
+    fn soft_link_targets_are_resolved_as_amigados_paths() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir_all("a/b").unwrap();
+        fs.write("x", b"root").unwrap();
+        fs.write("a/x", b"parent").unwrap();
+        fs.write("a/b/x", b"current").unwrap();
+
+        fs.symlink("/x", "a/b/parent").unwrap();
+        fs.symlink("//x", "a/b/grand_parent").unwrap();
+        fs.symlink("TEST:x", "a/b/volume").unwrap();
+        fs.symlink(":a/x", "a/b/root").unwrap();
+        fs.symlink("x", "a/b/relative").unwrap();
+
+        assert_eq!(fs.read("a/b/parent").unwrap(), b"parent");
+        assert_eq!(fs.read("a/b/grand_parent").unwrap(), b"root");
+        assert_eq!(fs.read("a/b/volume").unwrap(), b"root");
+        assert_eq!(fs.read("a/b/root").unwrap(), b"parent");
+        assert_eq!(fs.read("a/b/relative").unwrap(), b"current");
+    }
+
+    #[test]
+    fn dot_names_are_ordinary_names() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir("a").unwrap();
+        fs.create_dir("a/..").unwrap();
+        fs.write("a/../f", b"dot dot").unwrap();
+        fs.write("a/.", b"dot").unwrap();
+
+        assert_eq!(fs.read("a/../f").unwrap(), b"dot dot");
+        assert_eq!(fs.read("a/.").unwrap(), b"dot");
+        assert!(!fs.exists("f").unwrap());
+        assert_eq!(fs.read_dir("a").unwrap().count(), 2);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn long_names_are_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .with_dos_type(DosType::FFSLongName)
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        let dir_name = "d".repeat(107);
+        let file_name = "A rather long file name, longer than thirty characters";
+
+        fs.create_dir(&dir_name).unwrap();
+        fs.write(format!("{dir_name}/{file_name}"), b"hello").unwrap();
+
+        let names = fs.read_dir(&dir_name)
+            .unwrap()
+            .map(|entry| entry.unwrap().name().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, [file_name]);
+        assert_eq!(fs.read(format!("{dir_name}/{file_name}")).unwrap(), b"hello");
+        assert_eq!(
+            fs.create_dir("x".repeat(108)),
+            Err(Error::InvalidNameLengthError(108)),
+        );
+        assert_eq!(fs.info().unwrap().dos_type, DosType::FFSLongName);
+        assert!(fs.check().unwrap().is_empty());
+
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        assert_eq!(
+            fs.create_dir(&dir_name),
+            Err(Error::InvalidNameLengthError(107)),
+        );
     }
 }
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
 use std::time::SystemTime;
 
 use chrono::{
@@ -10,23 +11,22 @@ use chrono::{
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos::*;
 use super::block_type::*;
-use super::constants::*;
+use super::datetime::*;
+use super::dir_cache::*;
 
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum FileType {
+    #[default]
     File,
     Dir,
     Link,
 }
 
-impl Default for FileType {
-    fn default() -> Self { Self::File }
-}
-
 impl From<FileType> for BlockSecondaryType {
     fn from(value: FileType) -> Self {
         match value {
@@ -37,6 +37,19 @@ impl From<FileType> for BlockSecondaryType {
     }
 }
 
+impl From<BlockSecondaryType> for FileType {
+    fn from(value: BlockSecondaryType) -> Self {
+        match value {
+            BlockSecondaryType::Root |
+            BlockSecondaryType::Directory |
+            BlockSecondaryType::HardLinkDirectory => FileType::Dir,
+            BlockSecondaryType::File |
+            BlockSecondaryType::HardLinkFile => FileType::File,
+            BlockSecondaryType::SoftLink => FileType::Link,
+        }
+    }
+}
+
 impl fmt::Display for FileType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match self {
@@ -48,10 +61,22 @@ impl fmt::Display for FileType {
 }
 
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Protection bits of a file or directory header block.
+///
+/// The owner RWED bits are stored inverted (a set bit denies the access),
+/// whereas the HSPA flags and the group and other bits are set when granted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Permissions (u32);
 
 impl Permissions {
+    pub const fn from_bits(bits: u32) -> Self { Self(bits) }
+    pub const fn bits(&self) -> u32 { self.0 }
+
+    pub fn hold(&self) -> bool     { self.0 & 0x00000080 != 0 }
+    pub fn script(&self) -> bool   { self.0 & 0x00000040 != 0 }
+    pub fn pure(&self) -> bool     { self.0 & 0x00000020 != 0 }
+    pub fn archived(&self) -> bool { self.0 & 0x00000010 != 0 }
+
     pub fn owner_deletable(&self) -> bool  { self.0 & 0x00000001 == 0 }
     pub fn owner_executable(&self) -> bool { self.0 & 0x00000002 == 0 }
     pub fn owner_writable(&self) -> bool   { self.0 & 0x00000004 == 0 }
@@ -66,15 +91,26 @@ impl Permissions {
     pub fn other_executable(&self) -> bool { self.0 & 0x00002000 != 0 }
     pub fn other_writable(&self) -> bool   { self.0 & 0x00004000 != 0 }
     pub fn other_readable(&self) -> bool   { self.0 & 0x00008000 != 0 }
+
+    /// Returns the HSPARWED flags, as listed by the AmigaDOS `List` command.
+    pub fn flags(&self) -> ProtectionFlags {
+        ProtectionFlags::from_bits(
+            ((self.0 ^ PROTECTION_OWNER_RWED_MASK) & 0xff) as u8
+        )
+    }
+
+    /// Returns a copy of these permissions with the HSPARWED flags replaced
+    /// by the given ones, group and other bits being left untouched.
+    pub fn with_flags(&self, flags: ProtectionFlags) -> Self {
+        let bits = (flags.bits() as u32) ^ PROTECTION_OWNER_RWED_MASK;
+        Self((self.0 & !0xff) | bits)
+    }
 }
 
 impl fmt::Display for Permissions {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}{}{}{}{}{}{}{}{}{}{}{}",
-            if self.owner_readable()   { 'r' } else { '-' },
-            if self.owner_writable()   { 'w' } else { '-' },
-            if self.owner_executable() { 'e' } else { '-' },
-            if self.owner_deletable()  { 'd' } else { '-' },
+        write!(f, "{}{}{}{}{}{}{}{}{}",
+            self.flags(),
             if self.group_readable()   { 'r' } else { '-' },
             if self.group_writable()   { 'w' } else { '-' },
             if self.group_executable() { 'e' } else { '-' },
@@ -87,14 +123,155 @@ impl fmt::Display for Permissions {
     }
 }
 
+impl FromStr for Permissions {
+    type Err = Error;
+
+    /// Parses permissions as displayed, e.g. `"----rwed----r---"`: the
+    /// HSPARWED flags followed by the group and other RWED bits, a cleared
+    /// bit being given by a '-'.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars = s.as_bytes();
+
+        if chars.len() != 16 {
+            return Err(Error::InvalidProtectionFlagsError);
+        }
+
+        let expected = PROTECTION_FLAG_CHARS.iter()
+            .chain(PROTECTION_GROUP_OTHER_CHARS);
+        let mut flags = 0u8;
+        let mut bits = 0u32;
+
+        for (i, (&c, &expected)) in chars.iter().zip(expected).enumerate() {
+            if c == b'-' {
+                continue;
+            }
+            if c.to_ascii_lowercase() != expected {
+                return Err(Error::InvalidProtectionFlagsError);
+            }
+
+            match i {
+                0..8 => flags |= 0x80 >> i,
+                8..12 => bits |= 0x0800 >> (i - 8),
+                _ => bits |= 0x8000 >> (i - 12),
+            }
+        }
+
+        Ok(Self(bits).with_flags(ProtectionFlags(flags)))
+    }
+}
+
+const PROTECTION_OWNER_RWED_MASK: u32 = 0x0000000f;
+const PROTECTION_FLAG_CHARS: &[u8; 8] = b"hsparwed";
+const PROTECTION_GROUP_OTHER_CHARS: &[u8; 8] = b"rwedrwed";
+
+/// A set of HSPARWED protection flags, a set flag always granting the
+/// property whatever the way it is stored on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProtectionFlags (u8);
+
+impl ProtectionFlags {
+    pub const HOLD: Self    = Self(0x80);
+    pub const SCRIPT: Self  = Self(0x40);
+    pub const PURE: Self    = Self(0x20);
+    pub const ARCHIVE: Self = Self(0x10);
+    pub const READ: Self    = Self(0x08);
+    pub const WRITE: Self   = Self(0x04);
+    pub const EXECUTE: Self = Self(0x02);
+    pub const DELETE: Self  = Self(0x01);
+
+    pub const fn empty() -> Self { Self(0) }
+    pub const fn from_bits(bits: u8) -> Self { Self(bits) }
+    pub const fn bits(&self) -> u8 { self.0 }
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl FromStr for ProtectionFlags {
+    type Err = Error;
+
+    /// Parses flag letters among "hsparwed", in any order and case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().try_fold(Self::empty(), |flags, c| {
+            let c = c.to_ascii_lowercase();
+            let pos = PROTECTION_FLAG_CHARS
+                .iter()
+                .position(|&flag| flag as char == c)
+                .ok_or(Error::InvalidProtectionFlagsError)?;
+
+            Ok(Self(flags.0 | (0x80 >> pos)))
+        })
+    }
+}
+
+impl fmt::Display for ProtectionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, &c) in PROTECTION_FLAG_CHARS.iter().enumerate() {
+            let c = if self.0 & (0x80 >> i) != 0 { c as char } else { '-' };
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A change of protection flags in the AmigaDOS `Protect` syntax:
+/// `"rwed"` sets exactly the given flags, `"+s"` adds them and `"-a"`
+/// removes them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtectionChange {
+    Set(ProtectionFlags),
+    Add(ProtectionFlags),
+    Remove(ProtectionFlags),
+}
+
+impl ProtectionChange {
+    /// Applies the change to the given permissions.
+    pub fn apply(&self, permissions: Permissions) -> Permissions {
+        let mut flags = permissions.flags();
+
+        match *self {
+            Self::Set(other) => flags = other,
+            Self::Add(other) => flags.insert(other),
+            Self::Remove(other) => flags.remove(other),
+        }
+
+        permissions.with_flags(flags)
+    }
+}
+
+impl FromStr for ProtectionChange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(flags) = s.strip_prefix('+') {
+            Ok(Self::Add(flags.parse()?))
+        } else if let Some(flags) = s.strip_prefix('-') {
+            Ok(Self::Remove(flags.parse()?))
+        } else {
+            Ok(Self::Set(s.parse()?))
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Metadata {
     header_block_address: LBAAddress,
     file_type: FileType,
     file_size: usize,
+    hard_link: bool,
     permissions: Permissions,
     alteration_date: SystemTime,
     name: String,
+    comment: Option<String>,
 }
 
 impl TryFrom<&Block> for Metadata {
@@ -104,37 +281,69 @@ impl TryFrom<&Block> for Metadata {
         let header_block_address = block.address;
         let alteration_date = block.read_alteration_date()?;
         let name = block.read_name()?;
+        let comment = block.read_comment()?;
 
-        let file_type = match block.read_block_secondary_type()? {
-            BlockSecondaryType::Root |
-            BlockSecondaryType::Directory |
-            BlockSecondaryType::HardLinkDirectory => {
-                FileType::Dir
-            },
-            BlockSecondaryType::File |
-            BlockSecondaryType::HardLinkFile => {
-                FileType::File
-            },
-            BlockSecondaryType::SoftLink => {
-                FileType::Link
-            },
-        };
+        let file_type = FileType::from(block.read_block_secondary_type()?);
 
-        let permissions = Permissions(block.read_u32(BLOCK_PROTECT_OFFSET)?);
+        let permissions = block.read_permissions()?;
 
-        let file_size = if file_type == FileType::File {
-            block.read_file_size()?
-        } else {
+        let hard_link = matches!(
+            block.read_block_secondary_type()?,
+            BlockSecondaryType::HardLinkDirectory | BlockSecondaryType::HardLinkFile
+        );
+
+        let file_size = if file_type != FileType::File {
             0
+        } else if hard_link {
+            let real_entry_address = block
+                .read_real_entry_address()?
+                .ok_or(Error::CorruptedImageFile)?;
+
+            Block::new(block.disk.clone(), real_entry_address).read_file_size()?
+        } else {
+            block.read_file_size()?
         };
 
         Ok(Metadata {
             file_size,
             file_type,
+            hard_link,
             permissions,
             header_block_address,
             alteration_date,
             name,
+            comment,
+        })
+    }
+}
+
+impl Metadata {
+    /// Builds the metadata of an entry from its directory cache record,
+    /// hard links excepted as their size is the one of their real entry.
+    pub(super) fn try_from_dir_cache_record(
+        disk: Shared<Disk>,
+        record: &DirCacheRecord,
+    ) -> Result<Self, Error> {
+        if matches!(
+            record.secondary_type,
+            BlockSecondaryType::HardLinkDirectory | BlockSecondaryType::HardLinkFile
+        ) {
+            return Metadata::try_from(&Block::new(disk, record.header_block_address));
+        }
+
+        Ok(Metadata {
+            header_block_address: record.header_block_address,
+            file_type: FileType::from(record.secondary_type),
+            file_size: record.size as usize,
+            hard_link: false,
+            permissions: Permissions::from_bits(record.protect),
+            alteration_date: date_triplet_to_system_time(
+                record.days as u32,
+                record.mins as u32,
+                record.ticks as u32,
+            ),
+            name: record.name.clone(),
+            comment: Some(record.comment.clone()).filter(|comment| !comment.is_empty()),
         })
     }
 }
@@ -177,6 +386,11 @@ impl Metadata {
         self.file_type == FileType::Link
     }
 
+    /// Tells if the entry is a hard link to another file or directory.
+    pub fn is_hard_link(&self) -> bool {
+        self.hard_link
+    }
+
     pub fn size(&self) -> usize {
         self.file_size
     }
@@ -192,6 +406,11 @@ impl Metadata {
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
+
+    /// Returns the comment (filenote) of the entry, if any.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
 }
 
 impl AmigaDosInner {
@@ -213,15 +432,31 @@ impl AmigaDos {
         &self,
         path: P,
     ) -> Result<Metadata, Error> {
+        let _guard = self.lock.read();
+
         let header_block_address = self.lookup(path.as_ref())?;
         self.inner.borrow().metadata(header_block_address)
     }
 
+    /// Queries the file system to get information about a file, directory,
+    /// etc., without following the path if it is a soft link.
+    pub fn symlink_metadata<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Metadata, Error> {
+        let _guard = self.lock.read();
+
+        let header_block_address = self.lookup_with(path.as_ref(), false)?;
+        self.inner.borrow().metadata(header_block_address)
+    }
+
     /// Returns Ok(true) if the path points at an existing entity.
     pub fn exists<P: AsRef<Path>>(
         &self,
         path: P,
     ) -> Result<bool, Error> {
+        let _guard = self.lock.read();
+
         match self.metadata(path) {
             Err(Error::NotFoundError) => Ok(false),
             Err(err) => Err(err),
@@ -4,29 +4,44 @@ mod bitmap;
 mod block;
 mod block_type;
 mod boot_block;
+mod boot_code;
+mod boot_code_scan;
+mod check;
 mod checksum;
+mod comment;
 mod constants;
 mod datetime;
 mod dir;
+mod dir_cache;
 mod dir_create;
 mod dir_read;
 mod dir_remove;
 mod file;
+mod file_io;
 mod file_open;
 mod file_read;
 mod file_remove;
+mod file_seek;
 mod file_set_len;
 mod file_set_time;
 mod file_write;
 mod format;
+mod hard_link;
 mod info;
 mod lookup;
 mod metadata;
 mod name;
 mod path;
+mod protect;
+mod rename;
+mod repair;
 mod root_block;
+mod symlink;
 
 pub use amiga_dos::*;
+pub use boot_code::*;
+pub use boot_code_scan::*;
+pub use check::*;
 pub use dir_read::*;
 pub use file::*;
 pub use file_open::*;
@@ -34,3 +49,4 @@ pub use format::*;
 pub use info::*;
 pub use amiga_dos_options::*;
 pub use metadata::*;
+pub use repair::*;
@@ -1,24 +1,75 @@
+use std::borrow::Cow;
 use std::ops::Rem;
 
 use crate::disk::BLOCK_SIZE;
 use crate::errors::*;
 
-use super::InternationalMode;
+use super::{
+    InternationalMode,
+    NameEncodingMode,
+};
 
 
-fn valid_name_char(c: u8) -> bool {
-    c >= b' ' && c != b':' && c != b'/' && c != 127
+fn valid_name_char(c: char) -> bool {
+    c >= ' ' && c != ':' && c != '/' && c != '\x7f'
 }
 
 // Check if a (dir, file or volume) name is valid
 pub fn check_name(name: &str) -> Result<(), Error> {
-    if name.bytes().all(valid_name_char) {
+    if name.chars().all(valid_name_char) {
         Ok(())
     } else {
         Err(Error::InvalidNameError)
     }
 }
 
+fn to_latin1(c: char) -> Option<u8> {
+    u8::try_from(u32::from(c)).ok()
+}
+
+// Names are stored in Latin-1 (ISO-8859-1) on disk, whose code points are
+// the 256 first Unicode ones, decoding is thus lossless
+pub fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().copied().map(char::from).collect()
+}
+
+// Encode a string to Latin-1, failing on characters beyond U+00FF
+pub fn encode_latin1(s: &str) -> Result<Vec<u8>, Error> {
+    s.chars()
+        .map(|c| to_latin1(c).ok_or(Error::InvalidNameError))
+        .collect()
+}
+
+// Escape the characters of a name which can't be encoded to Latin-1 by
+// their code point, e.g. `\u{20ac}`, so that names differing by such
+// characters stay distinct
+fn escape_name(name: &str) -> Cow<'_, str> {
+    if name.chars().all(|c| to_latin1(c).is_some()) {
+        return Cow::Borrowed(name);
+    }
+
+    Cow::Owned(name
+        .chars()
+        .map(|c| match to_latin1(c) {
+            Some(_) => c.to_string(),
+            None => c.escape_unicode().to_string(),
+        })
+        .collect()
+    )
+}
+
+// Prepare a name to be encoded to Latin-1 according to the given mode
+pub fn encode_name(
+    name: &str,
+    mode: NameEncodingMode,
+) -> Result<Cow<'_, str>, Error> {
+    match mode {
+        _ if name.chars().all(|c| to_latin1(c).is_some()) => Ok(Cow::Borrowed(name)),
+        NameEncodingMode::Reject => Err(Error::InvalidNameError),
+        NameEncodingMode::Escape => Ok(escape_name(name)),
+    }
+}
+
 // adapted from https://github.com/lclevy/ADFlib/blob/master/src/adf_dir.c#L918
 fn to_upper(c: u8) -> u8 {
     if (0x61..=0x7a).contains(&c) {
@@ -42,16 +93,18 @@ pub fn hash_name(
     name: &str,
     international_mode: InternationalMode,
 ) -> usize {
-    name.as_bytes()
-        .iter()
-        .copied()
+    // names which can't be encoded are hashed the way they are escaped
+    let name = escape_name(name);
+
+    name.chars()
+        .filter_map(to_latin1)
         .map(|c| {
             match international_mode {
                 InternationalMode::On  => to_upper_intl(c),
                 InternationalMode::Off => to_upper(c),
             }
         })
-        .fold(name.len(), |mut hash, c| {
+        .fold(name.chars().count(), |mut hash, c| {
             (hash, _) = hash.overflowing_mul(13);
             (hash, _) = hash.overflowing_add(c as usize);
             hash & 0x07ff
@@ -61,6 +114,10 @@ pub fn hash_name(
 
 #[cfg(test)]
 mod tests {
+    use crate::disk::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
     use super::*;
 
     #[test]
@@ -80,4 +137,95 @@ mod tests {
         assert_eq!(hash_name("foo", InternationalMode::Off), 15);
         assert_eq!(hash_name("bar", InternationalMode::Off), 24);
     }
+
+    #[test]
+    fn hash_name_of_unencodable_name_is_ok() {
+        assert_eq!(
+            hash_name("a\u{20ac}", InternationalMode::Off),
+            hash_name("a\\u{20ac}", InternationalMode::Off),
+        );
+    }
+
+    #[test]
+    fn hash_name_intl_is_ok() {
+        assert_eq!(
+            hash_name("\u{e9}t\u{e9}", InternationalMode::On),
+            hash_name("\u{c9}T\u{c9}", InternationalMode::On),
+        );
+        assert_ne!(
+            hash_name("\u{e9}t\u{e9}", InternationalMode::Off),
+            hash_name("\u{c9}T\u{c9}", InternationalMode::Off),
+        );
+    }
+
+    #[test]
+    fn latin1_is_ok() {
+        let bytes = encode_latin1("Gr\u{fc}\u{df}e").unwrap();
+
+        assert_eq!(bytes, b"Gr\xfc\xdfe");
+        assert_eq!(decode_latin1(&bytes), "Gr\u{fc}\u{df}e");
+        assert_eq!(encode_latin1("\u{20ac}"), Err(Error::InvalidNameError));
+    }
+
+    #[test]
+    fn encode_name_is_ok() {
+        assert_eq!(
+            encode_name("caf\u{e9}", NameEncodingMode::Reject).unwrap(),
+            "caf\u{e9}",
+        );
+        assert_eq!(
+            encode_name("5\u{20ac}", NameEncodingMode::Reject),
+            Err(Error::InvalidNameError),
+        );
+        assert_eq!(
+            encode_name("5\u{20ac}", NameEncodingMode::Escape).unwrap(),
+            "5\\u{20ac}",
+        );
+        assert_ne!(
+            encode_name("a\u{20ac}", NameEncodingMode::Escape).unwrap(),
+            encode_name("a\u{2713}", NameEncodingMode::Escape).unwrap(),
+        );
+    }
+
+    #[test]
+    fn latin1_names_are_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .with_international_mode(InternationalMode::On)
+            .format(shared(disk), "\u{c9}t\u{e9}")
+            .unwrap();
+
+        fs.create_dir("Gr\u{fc}\u{df}e").unwrap();
+        fs.write("Gr\u{fc}\u{df}e/caf\u{e9}", b"hello").unwrap();
+
+        let names = fs.read_dir("Gr\u{fc}\u{df}e")
+            .unwrap()
+            .map(|entry| entry.unwrap().name().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["caf\u{e9}"]);
+        assert_eq!(fs.read("Gr\u{fc}\u{df}e/caf\u{e9}").unwrap(), b"hello");
+        assert_eq!(fs.info().unwrap().volume_name, "\u{c9}t\u{e9}");
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unencodable_names_are_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        assert_eq!(fs.create_dir("5\u{20ac}"), Err(Error::InvalidNameError));
+        assert_eq!(fs.exists("5\u{20ac}"), Ok(false));
+
+        fs.with_name_encoding_mode(NameEncodingMode::Escape);
+        fs.write("5\u{20ac}", b"hello").unwrap();
+        fs.write("5\u{2713}", b"world").unwrap();
+
+        assert_eq!(fs.read("5\\u{20ac}").unwrap(), b"hello");
+        assert_eq!(fs.read("5\u{20ac}").unwrap(), b"hello");
+        assert_eq!(fs.read("5\u{2713}").unwrap(), b"world");
+        assert_eq!(fs.read_dir("/").unwrap().count(), 2);
+    }
 }
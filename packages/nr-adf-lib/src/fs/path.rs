@@ -1,4 +1,3 @@
-use std::ffi::OsStr;
 use std::path::Path;
 
 use crate::errors::*;
@@ -19,16 +18,32 @@ pub(super) fn split<P: AsRef<Path>>(
         .map(|res| res.collect::<Vec<String>>())
 }
 
+// Splits a path into its parent and its last name. Unlike `Path`, "." and
+// ".." are names as any other, AmigaDOS having no such special names.
+fn split_last(
+    path: &Path,
+) -> Result<(&str, &str), Error> {
+    let path = path.to_str().ok_or(Error::InvalidPathError)?.trim_end_matches('/');
+
+    if path.is_empty() {
+        return Err(Error::InvalidPathError);
+    }
+
+    match path.rsplit_once('/') {
+        Some(("", name)) => Ok(("/", name)),
+        Some((dirname, name)) => Ok((dirname, name)),
+        None => Ok(("", path)),
+    }
+}
+
 pub(super) fn get_basename(
     path: &Path,
 ) -> Result<&str, Error> {
-    path.file_name()
-        .and_then(OsStr::to_str)
-        .ok_or(Error::InvalidPathError)
+    split_last(path).map(|(_, name)| name)
 }
 
 pub(super) fn get_dirname(
     path: &Path,
 ) -> Result<&Path, Error> {
-    path.parent().ok_or(Error::InvalidPathError)
+    split_last(path).map(|(dirname, _)| Path::new(dirname))
 }
@@ -0,0 +1,162 @@
+use std::path::Path;
+
+use crate::block::*;
+use crate::errors::*;
+
+use super::amiga_dos::*;
+use super::block_type::*;
+use super::metadata::*;
+
+
+impl AmigaDos {
+    /// Changes the protection bits of a file or a directory. Soft and hard
+    /// links are followed, the permissions of their target are changed.
+    /// Errors:
+    /// - When `path` doesn't exist.
+    /// - When `path` is the root directory, which has no protection bits.
+    pub fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        permissions: Permissions,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let addr = self.lookup(path)?;
+        let mut block = Block::new(self.disk(), addr);
+
+        if block.read_block_secondary_type()? == BlockSecondaryType::Root {
+            return Err(Error::InvalidPathError);
+        }
+
+        block.write_permissions(permissions)?;
+        block.write_checksum()?;
+
+        self.inner.borrow_mut().update_parent_dir_cache(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs() -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/file", b"hello").unwrap();
+        fs
+    }
+
+    #[test]
+    fn protection_change_parsing_is_ok() {
+        let rwed = ProtectionFlags::READ.bits()
+            | ProtectionFlags::WRITE.bits()
+            | ProtectionFlags::EXECUTE.bits()
+            | ProtectionFlags::DELETE.bits();
+
+        assert_eq!(
+            "rwed".parse(),
+            Ok(ProtectionChange::Set(ProtectionFlags::from_bits(rwed))),
+        );
+        assert_eq!(
+            "+S".parse(),
+            Ok(ProtectionChange::Add(ProtectionFlags::SCRIPT)),
+        );
+        assert_eq!(
+            "-a".parse(),
+            Ok(ProtectionChange::Remove(ProtectionFlags::ARCHIVE)),
+        );
+        assert_eq!(
+            "".parse(),
+            Ok(ProtectionChange::Set(ProtectionFlags::empty())),
+        );
+        assert_eq!(
+            "+x".parse::<ProtectionChange>(),
+            Err(Error::InvalidProtectionFlagsError),
+        );
+    }
+
+    #[test]
+    fn protection_change_apply_is_ok() {
+        let permissions = Permissions::default();
+
+        assert_eq!(permissions.flags().to_string(), "----rwed");
+
+        let permissions = "+sa".parse::<ProtectionChange>()
+            .unwrap()
+            .apply(permissions);
+
+        assert!(permissions.script() && permissions.archived());
+        assert_eq!(permissions.to_string(), "-s-arwed--------");
+
+        let permissions = "-wd".parse::<ProtectionChange>()
+            .unwrap()
+            .apply(permissions);
+
+        assert!(!permissions.owner_writable() && permissions.owner_readable());
+        assert_eq!(permissions.bits(), 0x55);
+
+        let permissions = "re".parse::<ProtectionChange>()
+            .unwrap()
+            .apply(Permissions::from_bits(0x0800));
+
+        assert!(permissions.group_readable());
+        assert_eq!(permissions.flags().to_string(), "----r-e-");
+    }
+
+    #[test]
+    fn permissions_parsing_is_ok() {
+        for bits in [0x0000, 0x000f, 0x00f0, 0x0855, 0xa5a0, 0xff0f] {
+            let permissions = Permissions::from_bits(bits);
+
+            assert_eq!(permissions.to_string().parse::<Permissions>(), Ok(permissions));
+        }
+
+        assert_eq!(
+            "-s-arwed-w--r--d".parse::<Permissions>().map(|p| p.bits()),
+            Ok(0x9450),
+        );
+        assert_eq!(
+            "----rwed".parse::<Permissions>(),
+            Err(Error::InvalidProtectionFlagsError),
+        );
+        assert_eq!(
+            "----rwedrwedrwex".parse::<Permissions>(),
+            Err(Error::InvalidProtectionFlagsError),
+        );
+        assert_eq!(
+            "----wred--------".parse::<Permissions>(),
+            Err(Error::InvalidProtectionFlagsError),
+        );
+    }
+
+    #[test]
+    fn set_permissions_is_ok() {
+        let mut fs = init_fs();
+
+        let permissions = Permissions::default()
+            .with_flags("hr".parse().unwrap());
+
+        fs.set_permissions("dir/file", permissions).unwrap();
+        fs.set_permissions("dir", permissions).unwrap();
+
+        assert_eq!(fs.metadata("dir/file").unwrap().permissions(), permissions);
+        assert_eq!(fs.metadata("dir").unwrap().permissions(), permissions);
+        assert_eq!(fs.read("dir/file").unwrap(), b"hello");
+        assert_eq!(
+            fs.set_permissions("/", permissions),
+            Err(Error::InvalidPathError),
+        );
+        assert_eq!(
+            fs.set_permissions("missing", permissions),
+            Err(Error::NotFoundError),
+        );
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
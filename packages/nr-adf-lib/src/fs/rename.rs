@@ -0,0 +1,221 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::block::Block;
+use crate::errors::*;
+
+use super::amiga_dos::*;
+use super::boot_block::*;
+use super::dir::*;
+use super::path::*;
+
+
+impl AmigaDos {
+    /// Renames a file or directory to a new name, moving it to another
+    /// directory if needed.
+    /// The entry keeps its header block, so its data, dates and protection
+    /// bits are preserved.
+    /// Errors:
+    /// - When `from` doesn't exist or is the root directory.
+    /// - When the parent of `to` doesn't exist or is not a directory.
+    /// - When `to` already exists.
+    /// - When `from` is a directory and `to` is inside it.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        // names which can't be encoded are not found unless escaped
+        let from = from.as_ref();
+        let from = self.encode_path(from).unwrap_or(Cow::Borrowed(from));
+        let from = from.as_ref();
+        let to = self.encode_path(to.as_ref())?;
+        let to = to.as_ref();
+
+        let root_block_address =
+            BootBlockReader::try_from_disk(self.disk())?.get_root_block_address();
+        let header_block_address = self.lookup_with(from, false)?;
+
+        if header_block_address == root_block_address {
+            return Err(Error::InvalidPathError);
+        }
+
+        let from_name = get_basename(from)?;
+        let from_parent_path = get_dirname(from)?;
+
+        let to_name = get_basename(to)?;
+        let to_parent_path = get_dirname(to)?;
+
+        let mut to_parent_dir = Dir::try_with_path(self, to_parent_path)?;
+
+        if let Some(addr) = to_parent_dir.lookup(to_name)? {
+            return if addr == header_block_address {
+                Ok(())
+            } else {
+                Err(Error::AlreadyExists)
+            };
+        }
+
+        if self.symlink_metadata(from)?.is_dir() {
+            for path in to_parent_path.ancestors() {
+                if self.lookup(path)? == header_block_address {
+                    return Err(Error::InvalidRenameError);
+                }
+            }
+        }
+
+        // the new name is validated before the entry is unlinked, so that an
+        // invalid name leaves the filesystem unchanged
+        Block::new(self.disk(), header_block_address).check_writable_name(to_name)?;
+
+        let mut from_parent_dir = Dir::try_with_path(self, from_parent_path)?;
+
+        from_parent_dir.remove_entry(from_name)?;
+
+        let result = self.inner.borrow_mut().write_entry_name(header_block_address, to_name);
+
+        if let Err(err) = result {
+            from_parent_dir.add_entry(from_name, header_block_address)?;
+            return Err(err);
+        }
+
+        to_parent_dir.add_entry(to_name, header_block_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs() -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap()
+    }
+
+    #[test]
+    fn rename_file() {
+        let fs = init_fs();
+
+        fs.write("/foo", b"foo").unwrap();
+
+        let before = fs.metadata("/foo").unwrap();
+
+        fs.rename("/foo", "/bar").unwrap();
+
+        let after = fs.metadata("/bar").unwrap();
+
+        assert!(!fs.exists("/foo").unwrap());
+        assert_eq!(fs.read("/bar").unwrap(), b"foo");
+        assert_eq!(after.name(), "bar");
+        assert_eq!(after.alteration_date(), before.alteration_date());
+        assert_eq!(after.header_block_address(), before.header_block_address());
+    }
+
+    #[test]
+    fn move_file_to_another_directory() {
+        let mut fs = init_fs();
+
+        fs.create_dir_all("/a/b").unwrap();
+        fs.write("/foo", b"foo").unwrap();
+        fs.rename("/foo", "/a/b/foo").unwrap();
+
+        assert!(!fs.exists("/foo").unwrap());
+        assert_eq!(fs.read("/a/b/foo").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn move_directory() {
+        let mut fs = init_fs();
+
+        fs.create_dir_all("/a/b").unwrap();
+        fs.create_dir("/c").unwrap();
+        fs.write("/a/b/foo", b"foo").unwrap();
+        fs.rename("/a", "/c/d").unwrap();
+
+        assert!(!fs.exists("/a").unwrap());
+        assert_eq!(fs.read("/c/d/b/foo").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn move_directory_into_its_own_subtree_fails() {
+        let mut fs = init_fs();
+
+        fs.create_dir_all("/a/b").unwrap();
+
+        assert_eq!(fs.rename("/a", "/a/b/c"), Err(Error::InvalidRenameError));
+        assert_eq!(fs.rename("/a", "/a/c"), Err(Error::InvalidRenameError));
+        assert!(fs.exists("/a/b").unwrap());
+    }
+
+    #[test]
+    fn rename_to_an_existing_name_fails() {
+        let fs = init_fs();
+
+        fs.write("/foo", b"foo").unwrap();
+        fs.write("/bar", b"bar").unwrap();
+
+        assert_eq!(fs.rename("/foo", "/bar"), Err(Error::AlreadyExists));
+        assert_eq!(fs.read("/foo").unwrap(), b"foo");
+        assert_eq!(fs.read("/bar").unwrap(), b"bar");
+    }
+
+    #[test]
+    fn rename_keeps_hash_chains_consistent() {
+        let fs = init_fs();
+        let names = (0..100).map(|i| format!("file{i}")).collect::<Vec<_>>();
+
+        for name in names.iter() {
+            fs.write(format!("/{name}"), name).unwrap();
+        }
+
+        for name in names.iter() {
+            fs.rename(format!("/{name}"), format!("/{name}.bak")).unwrap();
+        }
+
+        for name in names.iter() {
+            assert_eq!(fs.read(format!("/{name}.bak")).unwrap(), name.as_bytes());
+        }
+        assert_eq!(fs.read_dir("/").unwrap().count(), names.len());
+    }
+
+    #[test]
+    fn rename_to_an_invalid_name_leaves_fs_unchanged() {
+        let fs = init_fs();
+
+        fs.write("/foo", b"foo").unwrap();
+        fs.write("/bar", b"bar").unwrap();
+
+        assert_eq!(
+            fs.rename("/foo", format!("/{}", "a".repeat(41))),
+            Err(Error::InvalidNameLengthError(41)),
+        );
+        assert_eq!(fs.rename("/bar", "/b:r"), Err(Error::InvalidNameError));
+        assert_eq!(fs.rename("/bar", "/b\u{20ac}r"), Err(Error::InvalidNameError));
+
+        assert_eq!(fs.read("/foo").unwrap(), b"foo");
+        assert_eq!(fs.read("/bar").unwrap(), b"bar");
+        assert_eq!(fs.metadata("/bar").unwrap().name(), "bar");
+        assert_eq!(fs.read_dir("/").unwrap().count(), 2);
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rename_from_an_escaped_name() {
+        let mut fs = init_fs();
+
+        fs.with_name_encoding_mode(NameEncodingMode::Escape);
+        fs.write("/a\u{20ac}", b"foo").unwrap();
+        fs.rename("/a\u{20ac}", "/b").unwrap();
+
+        assert_eq!(fs.read("/b").unwrap(), b"foo");
+        assert_eq!(fs.read_dir("/").unwrap().count(), 1);
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
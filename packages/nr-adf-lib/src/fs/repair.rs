@@ -0,0 +1,893 @@
+use std::fmt;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use crate::block::*;
+use crate::disk::*;
+use crate::errors::*;
+use crate::shared::*;
+
+use super::amiga_dos::*;
+use super::amiga_dos_options::*;
+use super::block_type::*;
+use super::boot_block::*;
+use super::constants::*;
+use super::dir::*;
+use super::file::*;
+
+
+#[derive(Clone, Debug)]
+pub struct RepairOptions {
+    lost_and_found: Option<String>,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            lost_and_found: Some(String::from("lost+found")),
+        }
+    }
+}
+
+impl RepairOptions {
+    /// Sets the name of the root directory where unreachable entries and
+    /// data of truncated files are saved. When `None`, they are discarded.
+    pub fn with_lost_and_found(
+        &mut self,
+        name: Option<&str>,
+    ) -> &mut Self {
+        self.lost_and_found = name.map(String::from);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RepairAction {
+    /// The block content or checksum has been rewritten.
+    FixedBlock(LBAAddress),
+    /// An invalid link to `target` has been removed from a hash chain.
+    DroppedHashChainLink { address: LBAAddress, target: LBAAddress },
+    /// The file has been truncated to its readable data.
+    TruncatedFile { address: LBAAddress, size: usize },
+    /// An allocated but unreachable entry has been linked back to the
+    /// filesystem.
+    RecoveredEntry { address: LBAAddress, path: PathBuf },
+    /// The data following a broken file data chain has been saved.
+    SavedLostData { address: LBAAddress, path: PathBuf },
+    RebuiltBitmap,
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FixedBlock(address) => {
+                write!(f, "block {address}: fixed")
+            },
+            Self::DroppedHashChainLink { address, target } => {
+                write!(f, "block {address}: dropped link to block {target}")
+            },
+            Self::TruncatedFile { address, size } => {
+                write!(f, "block {address}: file truncated to {size} bytes")
+            },
+            Self::RecoveredEntry { address, path } => {
+                write!(f, "block {address}: recovered as {}", path.display())
+            },
+            Self::SavedLostData { address, path } => {
+                write!(f, "block {address}: lost data saved as {}", path.display())
+            },
+            Self::RebuiltBitmap => {
+                write!(f, "bitmap rebuilt")
+            },
+        }
+    }
+}
+
+/******************************************************************************
+* Repairer ********************************************************************
+******************************************************************************/
+
+struct LostData {
+    header_block_address: LBAAddress,
+    data: Vec<u8>,
+}
+
+struct Repairer {
+    disk: Shared<Disk>,
+    filesystem_type: FilesystemType,
+    cache_mode: CacheMode,
+    used_blocks: Vec<bool>,
+    // a block may be fixed by several passes, it is reported once
+    fixed_blocks: Vec<bool>,
+    directories: Vec<LBAAddress>,
+    lost_data: Vec<LostData>,
+    actions: Vec<RepairAction>,
+}
+
+impl Repairer {
+    fn try_new(
+        disk: Shared<Disk>,
+    ) -> Result<Self, Error> {
+        let boot_block = BootBlockReader::try_from_disk(disk.clone())?;
+        let block_count = disk.borrow().block_count();
+
+        let mut used_blocks = vec![false; block_count];
+
+        // boot blocks are never tracked by the bitmap
+        used_blocks[0] = true;
+        used_blocks[1] = true;
+
+        Ok(Self {
+            disk,
+            filesystem_type: boot_block.get_filesystem_type(),
+            cache_mode: boot_block.get_cache_mode(),
+            used_blocks,
+            fixed_blocks: vec![false; block_count],
+            directories: Vec::new(),
+            lost_data: Vec::new(),
+            actions: Vec::new(),
+        })
+    }
+
+    fn block(
+        &self,
+        address: LBAAddress,
+    ) -> Block {
+        Block::new(self.disk.clone(), address)
+    }
+
+    fn is_free(
+        &self,
+        address: LBAAddress,
+    ) -> bool {
+        address >= 2
+        && address < self.used_blocks.len()
+        && !self.used_blocks[address]
+    }
+
+    fn fix_block(
+        &mut self,
+        address: LBAAddress,
+        f: impl FnOnce(&mut Block) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut block = self.block(address);
+        let before = block.read_u8_vector(0, BLOCK_SIZE)?;
+
+        f(&mut block)?;
+        block.write_checksum()?;
+
+        if block.read_u8_vector(0, BLOCK_SIZE)? != before && !self.fixed_blocks[address] {
+            self.fixed_blocks[address] = true;
+            self.actions.push(RepairAction::FixedBlock(address));
+        }
+        Ok(())
+    }
+
+    fn is_valid_header(
+        &self,
+        address: LBAAddress,
+        secondary_types: &[BlockSecondaryType],
+    ) -> bool {
+        if !self.is_free(address) {
+            return false;
+        }
+
+        let block = self.block(address);
+
+        block.check_block_primary_type(&[BlockPrimaryType::Header]).is_ok()
+        && block.check_block_secondary_type(secondary_types).is_ok()
+        && block.read_name().is_ok()
+    }
+
+    fn is_valid_extension_block(
+        &self,
+        header_block_address: LBAAddress,
+        address: LBAAddress,
+    ) -> bool {
+        if !self.is_free(address) {
+            return false;
+        }
+
+        let block = self.block(address);
+
+        block.check_block_primary_type(&[BlockPrimaryType::List]).is_ok()
+        && block.read_u32(BLOCK_PARENT_OFFSET).ok() == Some(header_block_address as u32)
+    }
+
+    fn is_valid_comment_block(
+        &self,
+        header_block_address: LBAAddress,
+        address: LBAAddress,
+    ) -> bool {
+        if !self.is_free(address) {
+            return false;
+        }
+
+        let block = self.block(address);
+
+        block.read_comment_block_comment().is_ok()
+        && block.read_u32(COMMENT_BLOCK_PARENT_OFFSET).ok() == Some(header_block_address as u32)
+    }
+
+    fn is_valid_data_block(
+        &self,
+        header_block_address: LBAAddress,
+        address: LBAAddress,
+    ) -> bool {
+        if !self.is_free(address) {
+            return false;
+        }
+
+        match self.filesystem_type {
+            FilesystemType::OFS => {
+                let block = self.block(address);
+
+                block.check_block_primary_type(&[BlockPrimaryType::Data]).is_ok()
+                && block.read_u32(BLOCK_DATA_OFS_HEADER_KEY_OFFSET).ok() == Some(header_block_address as u32)
+            },
+            FilesystemType::FFS => true,
+        }
+    }
+
+    fn read_data_block(
+        &self,
+        address: LBAAddress,
+    ) -> Result<Vec<u8>, Error> {
+        let block = self.block(address);
+
+        match self.filesystem_type {
+            FilesystemType::OFS => {
+                let size = (block.read_u32(BLOCK_DATA_OFS_SIZE_OFFSET)? as usize)
+                    .min(BLOCK_DATA_OFS_SIZE);
+
+                block.read_u8_vector(BLOCK_DATA_OFS_OFFSET, size)
+            },
+            FilesystemType::FFS => {
+                block.read_u8_vector(BLOCK_DATA_FFS_OFFSET, BLOCK_DATA_FFS_SIZE)
+            },
+        }
+    }
+
+    fn repair_root(
+        &mut self,
+        root_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        if !self.is_valid_header(root_block_address, &[BlockSecondaryType::Root]) {
+            return Err(Error::CorruptedImageFile);
+        }
+
+        self.used_blocks[root_block_address] = true;
+
+        let (bitmap_block_addresses, bitmap_ext_block_addresses) =
+            self.block(root_block_address).read_bitmap_with_extensions()?;
+
+        for address in bitmap_block_addresses.into_iter().chain(bitmap_ext_block_addresses) {
+            if !self.is_free(address) {
+                return Err(Error::CorruptedImageFile);
+            }
+            self.used_blocks[address] = true;
+        }
+
+        self.repair_directory(root_block_address)?;
+        self.fix_block(root_block_address, |block| {
+            block.write_u32(
+                ROOT_BLOCK_HASH_TABLE_SIZE_OFFSET,
+                BLOCK_TABLE_SIZE as u32,
+            )?;
+            block.write_u32(ROOT_BLOCK_BITMAP_FLAG_OFFSET, 0xffffffff)
+        })
+    }
+
+    fn repair_entry(
+        &mut self,
+        parent_block_address: LBAAddress,
+        address: LBAAddress,
+    ) -> Result<(), Error> {
+        self.used_blocks[address] = true;
+
+        if let Some(addr) = self.block(address).read_comment_block_address()? {
+            if self.is_valid_comment_block(address, addr) {
+                self.used_blocks[addr] = true;
+                self.fix_block(addr, |block| {
+                    block.write_u32(BLOCK_DATA_LIST_HEADER_KEY_OFFSET, addr as u32)
+                })?;
+            } else {
+                self.fix_block(address, |block| block.write_comment_block_address(None))?;
+            }
+        }
+
+        match self.block(address).read_block_secondary_type()? {
+            BlockSecondaryType::Directory => self.repair_directory(address)?,
+            BlockSecondaryType::File => self.repair_file(address)?,
+            _ => (),
+        }
+
+        self.fix_block(address, |block| {
+            block.write_u32(BLOCK_DATA_LIST_HEADER_KEY_OFFSET, address as u32)?;
+            block.write_u32(BLOCK_PARENT_OFFSET, parent_block_address as u32)
+        })
+    }
+
+    /// Keeps the valid blocks of the directory cache of a directory, the
+    /// chain being cut at the first invalid one. The cache content is
+    /// rewritten once the bitmap is rebuilt.
+    fn repair_dir_cache(
+        &mut self,
+        dir_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        let mut prev = dir_block_address;
+        let mut next = AmigaDos::to_address(
+            self.block(dir_block_address).read_u32(BLOCK_DIR_CACHE_OFFSET)?
+        );
+
+        while let Some(addr) = next {
+            let block = self.block(addr);
+
+            if !self.is_free(addr)
+            || block.check_block_primary_type(&[BlockPrimaryType::DirCache]).is_err()
+            || block.read_u32(DIR_CACHE_BLOCK_PARENT_OFFSET)? != dir_block_address as u32 {
+                let offset = if prev == dir_block_address {
+                    BLOCK_DIR_CACHE_OFFSET
+                } else {
+                    DIR_CACHE_BLOCK_NEXT_OFFSET
+                };
+
+                self.fix_block(prev, |block| block.write_u32(offset, 0))?;
+                break;
+            }
+
+            self.used_blocks[addr] = true;
+            prev = addr;
+            next = AmigaDos::to_address(block.read_u32(DIR_CACHE_BLOCK_NEXT_OFFSET)?);
+        }
+        Ok(())
+    }
+
+    fn repair_directory(
+        &mut self,
+        dir_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        self.directories.push(dir_block_address);
+
+        if let CacheMode::On = self.cache_mode {
+            self.repair_dir_cache(dir_block_address)?;
+        }
+
+        for hash_index in 0..BLOCK_TABLE_SIZE {
+            let mut prev = dir_block_address;
+            let mut next = self.block(dir_block_address).read_block_table_address(hash_index)?;
+
+            while let Some(addr) = next {
+                if self.is_valid_header(addr, &[
+                    BlockSecondaryType::Directory,
+                    BlockSecondaryType::File,
+                    BlockSecondaryType::SoftLink,
+                    BlockSecondaryType::HardLinkDirectory,
+                    BlockSecondaryType::HardLinkFile,
+                ]) {
+                    next = self.block(addr).read_hash_chain_next_address()?;
+                    self.repair_entry(dir_block_address, addr)?;
+                    prev = addr;
+                } else {
+                    let mut block = self.block(prev);
+
+                    if prev == dir_block_address {
+                        block.write_hash_table_block_address(hash_index, 0)?;
+                    } else {
+                        block.write_hash_chain_next_address(0)?;
+                    }
+                    block.write_checksum()?;
+
+                    self.actions.push(RepairAction::DroppedHashChainLink {
+                        address: prev,
+                        target: addr,
+                    });
+                    break;
+                }
+            }
+        }
+
+        self.fix_block(dir_block_address, |_| Ok(()))
+    }
+
+    fn repair_file(
+        &mut self,
+        header_block_address: LBAAddress,
+    ) -> Result<(), Error> {
+        let (_, block_data_size) = get_data_block_info(self.filesystem_type);
+
+        let size = self.block(header_block_address).read_file_size()?;
+
+        let mut table_block_addresses = vec![header_block_address];
+        let mut data_block_addresses = Vec::new();
+        let mut lost_data_block_addresses = Vec::new();
+        let mut broken = false;
+
+        let mut table_block_address = header_block_address;
+
+        loop {
+            let table_block = self.block(table_block_address);
+
+            for index in (0..BLOCK_DATA_LIST_SIZE).rev() {
+                let addr = table_block.read_u32(BLOCK_TABLE_OFFSET + 4*index)? as LBAAddress;
+
+                if addr == 0 {
+                    break;
+                }
+
+                if self.is_valid_data_block(header_block_address, addr)
+                && !data_block_addresses.contains(&addr)
+                && !lost_data_block_addresses.contains(&addr) {
+                    if broken {
+                        lost_data_block_addresses.push(addr);
+                    } else {
+                        data_block_addresses.push(addr);
+                    }
+                } else {
+                    broken = true;
+                }
+            }
+
+            match table_block.read_data_list_extension_address()? {
+                Some(addr) if self.is_valid_extension_block(header_block_address, addr)
+                           && !table_block_addresses.contains(&addr) => {
+                    table_block_addresses.push(addr);
+                    table_block_address = addr;
+                },
+                Some(_) => {
+                    broken = true;
+                    break;
+                },
+                None => break,
+            }
+        }
+
+        let data_block_count = data_block_addresses.len().min(size.div_ceil(block_data_size));
+        let new_size = size.min(data_block_count*block_data_size);
+
+        data_block_addresses.truncate(data_block_count);
+
+        if broken && !lost_data_block_addresses.is_empty() {
+            let mut data = Vec::new();
+
+            for addr in lost_data_block_addresses.iter().copied() {
+                data.extend(self.read_data_block(addr)?);
+            }
+            self.lost_data.push(LostData {
+                header_block_address,
+                data,
+            });
+        }
+
+        if new_size != size {
+            self.actions.push(RepairAction::TruncatedFile {
+                address: header_block_address,
+                size: new_size,
+            });
+        }
+
+        self.rewrite_data_block_list(
+            header_block_address,
+            &table_block_addresses,
+            &data_block_addresses,
+            new_size,
+        )
+    }
+
+    fn rewrite_data_block_list(
+        &mut self,
+        header_block_address: LBAAddress,
+        table_block_addresses: &[LBAAddress],
+        data_block_addresses: &[LBAAddress],
+        size: usize,
+    ) -> Result<(), Error> {
+        let (_, block_data_size) = get_data_block_info(self.filesystem_type);
+
+        let table_block_count = usize::max(
+            1,
+            data_block_addresses.len().div_ceil(BLOCK_DATA_LIST_SIZE),
+        );
+
+        for (table_index, table_block_address) in table_block_addresses
+            .iter()
+            .copied()
+            .take(table_block_count)
+            .enumerate() {
+            let entries = data_block_addresses
+                .iter()
+                .copied()
+                .skip(table_index*BLOCK_DATA_LIST_SIZE)
+                .take(BLOCK_DATA_LIST_SIZE)
+                .collect::<Vec<_>>();
+            let next_table_block_address = if table_index + 1 < table_block_count {
+                table_block_addresses[table_index + 1]
+            } else {
+                0
+            };
+
+            self.used_blocks[table_block_address] = true;
+            self.fix_block(table_block_address, |block| {
+                for index in 0..BLOCK_DATA_LIST_SIZE {
+                    block.write_block_table_address(
+                        BLOCK_DATA_LIST_SIZE - index - 1,
+                        entries.get(index).copied().unwrap_or(0),
+                    )?;
+                }
+                block.write_u32(
+                    BLOCK_DATA_LIST_HIGH_SEQ_OFFSET,
+                    entries.len() as u32,
+                )?;
+                block.write_u32(
+                    BLOCK_DATA_LIST_EXTENSION_OFFSET,
+                    next_table_block_address as u32,
+                )?;
+                if table_block_address != header_block_address {
+                    block.write_u32(
+                        BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+                        table_block_address as u32,
+                    )?;
+                }
+                Ok(())
+            })?;
+        }
+
+        self.fix_block(header_block_address, |block| {
+            block.write_u32(
+                BLOCK_FIRST_DATA_OFFSET,
+                data_block_addresses.first().copied().unwrap_or(0) as u32,
+            )?;
+            block.write_file_size(size)
+        })?;
+
+        for (index, addr) in data_block_addresses.iter().copied().enumerate() {
+            self.used_blocks[addr] = true;
+
+            if let FilesystemType::OFS = self.filesystem_type {
+                let data_size = (size - index*block_data_size).min(block_data_size);
+                let next_data_block_address = data_block_addresses
+                    .get(index + 1)
+                    .copied()
+                    .unwrap_or(0);
+
+                self.fix_block(addr, |block| {
+                    block.write_u32(
+                        BLOCK_DATA_OFS_SEQ_NUM_OFFSET,
+                        index as u32 + 1,
+                    )?;
+                    block.write_u32(
+                        BLOCK_DATA_OFS_SIZE_OFFSET,
+                        data_size as u32,
+                    )?;
+                    block.write_u32(
+                        BLOCK_DATA_OFS_NEXT_DATA_OFFSET,
+                        next_data_block_address as u32,
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the header blocks which are allocated in the bitmap but can't be
+    /// reached from the root block. Directories come first so that their
+    /// content is recovered along with them.
+    fn find_unreachable_entries(
+        &self,
+        fs: &AmigaDosInner,
+    ) -> Vec<LBAAddress> {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+
+        for address in 2..self.used_blocks.len() {
+            if self.used_blocks[address]
+            || fs.is_block_free(address).unwrap_or(true) {
+                continue;
+            }
+
+            let block = self.block(address);
+
+            if block.read_u32(BLOCK_DATA_LIST_HEADER_KEY_OFFSET).ok() != Some(address as u32)
+            || block.check_block_primary_type(&[BlockPrimaryType::Header]).is_err()
+            || block.read_name().is_err() {
+                continue;
+            }
+
+            match block.read_block_secondary_type() {
+                Ok(BlockSecondaryType::Directory) => dirs.push(address),
+                Ok(BlockSecondaryType::File) => files.push(address),
+                _ => (),
+            }
+        }
+
+        dirs.extend(files);
+        dirs
+    }
+}
+
+/******************************************************************************
+* AmigaDos ********************************************************************
+******************************************************************************/
+
+fn lost_and_found_name(
+    fs: &AmigaDos,
+    dir: &Path,
+    name: &str,
+    address: LBAAddress,
+) -> Result<String, Error> {
+    if fs.exists(dir.join(name))? {
+        let suffix = format!(".{address}");
+        let len = name.len().min(BLOCK_NAME_MAX_SIZE - suffix.len());
+
+        Ok(format!("{}{}", &name[..name.floor_char_boundary(len)], suffix))
+    } else {
+        Ok(String::from(name))
+    }
+}
+
+impl AmigaDos {
+    /// Repairs the filesystem.
+    /// Invalid hash chain links are dropped, files with a broken data chain
+    /// are truncated and all the reachable blocks get their checksum
+    /// recomputed. The allocation bitmap is then rebuilt from the reachable
+    /// blocks.
+    /// Entries allocated in the bitmap but not reachable, and the data found
+    /// after a broken data chain, are saved in the lost and found directory.
+    pub fn repair(
+        &mut self,
+        options: &RepairOptions,
+    ) -> Result<Vec<RepairAction>, Error> {
+        let _guard = self.lock.write();
+
+        let disk = self.disk();
+        let root_block_address =
+            BootBlockReader::try_from_disk(disk.clone())?.get_root_block_address();
+
+        let mut repairer = Repairer::try_new(disk)?;
+
+        repairer.repair_root(root_block_address)?;
+
+        let mut recovered = Vec::new();
+
+        if options.lost_and_found.is_some() {
+            let addresses = repairer.find_unreachable_entries(&self.inner.borrow());
+
+            for address in addresses {
+                if !repairer.used_blocks[address] {
+                    repairer.repair_entry(root_block_address, address)?;
+                    recovered.push(address);
+                }
+            }
+        } else {
+            repairer.lost_data.clear();
+        }
+
+        self.inner.borrow_mut().rebuild_bitmap(&repairer.used_blocks)?;
+        repairer.actions.push(RepairAction::RebuiltBitmap);
+
+        for address in repairer.directories.iter().copied() {
+            self.inner.borrow_mut().update_dir_cache(address)?;
+        }
+
+        if let Some(lost_and_found) = options.lost_and_found.as_ref() {
+            if recovered.is_empty() && repairer.lost_data.is_empty() {
+                return Ok(repairer.actions);
+            }
+
+            let dir_path = PathBuf::from("/").join(lost_and_found);
+
+            self.create_dir(&dir_path)?;
+
+            let mut dir = Dir::try_with_path(self, &dir_path)?;
+
+            for address in recovered {
+                let mut block = Block::new(self.disk(), address);
+                let name = lost_and_found_name(
+                    self,
+                    &dir_path,
+                    &block.read_name()?,
+                    address,
+                )?;
+
+                self.inner.borrow_mut().write_entry_name(address, &name)?;
+
+                block.write_hash_chain_next_address(0)?;
+                block.write_checksum()?;
+                dir.add_entry(&name, address)?;
+
+                repairer.actions.push(RepairAction::RecoveredEntry {
+                    address,
+                    path: dir_path.join(name),
+                });
+            }
+
+            for lost_data in std::mem::take(&mut repairer.lost_data) {
+                let address = lost_data.header_block_address;
+                let name = lost_and_found_name(
+                    self,
+                    &dir_path,
+                    &format!("{address}.lost"),
+                    address,
+                )?;
+                let path = dir_path.join(name);
+
+                self.write(&path, lost_data.data)?;
+
+                repairer.actions.push(RepairAction::SavedLostData {
+                    address,
+                    path,
+                });
+            }
+        }
+
+        Ok(repairer.actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::*;
+    use crate::fs::constants::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    use super::*;
+
+    fn init_fs(filesystem_type: FilesystemType) -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        AmigaDosFormater::default()
+            .with_filesystem_type(filesystem_type)
+            .format(shared(disk), "TEST")
+            .unwrap()
+    }
+
+    fn data_block_address(
+        fs: &AmigaDos,
+        path: &str,
+        index: usize,
+    ) -> LBAAddress {
+        File::options()
+            .read(true)
+            .open(fs, path)
+            .unwrap()
+            .block_data_list[index]
+            .data_block_address
+    }
+
+    #[test]
+    fn repair_consistent_filesystem() {
+        let mut fs = init_fs(FilesystemType::OFS);
+
+        fs.create_dir_all("/a/b").unwrap();
+        fs.write("/a/b/foo", vec![42u8; 40000]).unwrap();
+
+        let actions = fs.repair(&RepairOptions::default()).unwrap();
+
+        assert_eq!(actions, vec![RepairAction::RebuiltBitmap]);
+        assert_eq!(fs.check().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn repair_checksum_and_bitmap() {
+        let mut fs = init_fs(FilesystemType::FFS);
+
+        fs.write("/foo", vec![42u8; 100]).unwrap();
+
+        let addr = fs.metadata("/foo").unwrap().header_block_address();
+
+        Block::new(fs.disk(), addr).write_u32(BLOCK_CHECKSUM_OFFSET, 0).unwrap();
+        fs.inner.borrow_mut().free_block(addr).unwrap();
+
+        let actions = fs.repair(&RepairOptions::default()).unwrap();
+
+        assert!(actions.contains(&RepairAction::FixedBlock(addr)));
+        assert_eq!(fs.check().unwrap(), vec![]);
+        assert_eq!(fs.read("/foo").unwrap(), vec![42u8; 100]);
+    }
+
+    #[test]
+    fn repair_reports_a_block_once() {
+        let mut fs = init_fs(FilesystemType::OFS);
+
+        fs.write("/foo", b"foo").unwrap();
+
+        let addr = fs.metadata("/foo").unwrap().header_block_address();
+        let mut block = Block::new(fs.disk(), addr);
+
+        // fixed by both the data chain and the entry passes
+        block.write_u32(BLOCK_FIRST_DATA_OFFSET, 0).unwrap();
+        block.write_u32(BLOCK_PARENT_OFFSET, 0).unwrap();
+        block.write_checksum().unwrap();
+
+        let actions = fs.repair(&RepairOptions::default()).unwrap();
+
+        assert_eq!(
+            actions.iter().filter(|action| **action == RepairAction::FixedBlock(addr)).count(),
+            1,
+        );
+        assert_eq!(fs.check().unwrap(), vec![]);
+        assert_eq!(fs.read("/foo").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn repair_dangling_hash_chain_link() {
+        let mut fs = init_fs(FilesystemType::OFS);
+
+        fs.write("/foo", b"foo").unwrap();
+
+        let addr = fs.metadata("/foo").unwrap().header_block_address();
+
+        Block::new(fs.disk(), addr).clear().unwrap();
+
+        fs.repair(&RepairOptions::default()).unwrap();
+
+        assert_eq!(fs.check().unwrap(), vec![]);
+        assert!(!fs.exists("/foo").unwrap());
+    }
+
+    #[test]
+    fn repair_broken_data_chain() {
+        let mut fs = init_fs(FilesystemType::OFS);
+        let data = (0..2000).map(|i| i as u8).collect::<Vec<_>>();
+
+        fs.write("/foo", &data).unwrap();
+
+        let header_block_address = fs.metadata("/foo").unwrap().header_block_address();
+        let addr = data_block_address(&fs, "/foo", 1);
+
+        Block::new(fs.disk(), addr).clear().unwrap();
+
+        let actions = fs.repair(&RepairOptions::default()).unwrap();
+        let lost_path = PathBuf::from(format!("/lost+found/{header_block_address}.lost"));
+
+        assert!(actions.contains(&RepairAction::TruncatedFile {
+            address: header_block_address,
+            size: BLOCK_DATA_OFS_SIZE,
+        }));
+        assert_eq!(fs.check().unwrap(), vec![]);
+        assert_eq!(fs.read("/foo").unwrap(), &data[..BLOCK_DATA_OFS_SIZE]);
+        assert_eq!(fs.read(lost_path).unwrap(), &data[2*BLOCK_DATA_OFS_SIZE..]);
+    }
+
+    #[test]
+    fn repair_unreachable_entries() {
+        let mut fs = init_fs(FilesystemType::OFS);
+
+        fs.create_dir("/a").unwrap();
+        fs.write("/a/foo", b"foo").unwrap();
+
+        let root_block_address = BootBlockReader::try_from_disk(fs.disk())
+            .unwrap()
+            .get_root_block_address();
+        let mut root_block = Block::new(fs.disk(), root_block_address);
+
+        for index in 0..BLOCK_TABLE_SIZE {
+            root_block.write_hash_table_block_address(index, 0).unwrap();
+        }
+        root_block.write_checksum().unwrap();
+
+        fs.repair(&RepairOptions::default()).unwrap();
+
+        assert_eq!(fs.check().unwrap(), vec![]);
+        assert_eq!(fs.read("/lost+found/a/foo").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn repair_without_lost_and_found() {
+        let mut fs = init_fs(FilesystemType::OFS);
+
+        fs.write("/foo", b"foo").unwrap();
+
+        let info = fs.info().unwrap();
+        let addr = fs.metadata("/foo").unwrap().header_block_address();
+
+        Block::new(fs.disk(), addr).clear().unwrap();
+
+        fs.repair(RepairOptions::default().with_lost_and_found(None)).unwrap();
+
+        assert_eq!(fs.check().unwrap(), vec![]);
+        assert_eq!(fs.info().unwrap().free_block_count, info.free_block_count + 2);
+        assert!(!fs.exists("/lost+found").unwrap());
+    }
+}
@@ -1,12 +1,11 @@
 use core::str;
 
-use std::cell::RefCell;
-use std::rc::Rc;
 use std::time::SystemTime;
 
 use crate::block::*;
 use crate::disk::*;
 use crate::errors::*;
+use crate::shared::*;
 
 use super::amiga_dos_options::*;
 use super::block_type::*;
@@ -57,7 +56,7 @@ impl RootBlockInitializer {
 
     pub fn init(
         &self,
-        disk: Rc<RefCell<Disk>>,
+        disk: Shared<Disk>,
     ) -> Result<(), Error> {
 
         let datetime = SystemTime::now();
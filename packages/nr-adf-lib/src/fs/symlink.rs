@@ -0,0 +1,166 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::SystemTime;
+
+use crate::block::*;
+use crate::disk::*;
+use crate::errors::*;
+
+use super::amiga_dos::*;
+use super::block_type::*;
+use super::constants::*;
+use super::dir::*;
+use super::path::*;
+
+
+fn init_soft_link_block_header(
+    fs: &AmigaDos,
+    name: &str,
+    target: &str,
+) -> Result<LBAAddress, Error> {
+    let block_addr = fs.inner.borrow_mut().reserve_block()?;
+    let mut block = Block::new(fs.disk(), block_addr);
+
+    block.clear()?;
+
+    block.write_block_primary_type(BlockPrimaryType::Header)?;
+    block.write_block_secondary_type(BlockSecondaryType::SoftLink)?;
+    block.write_alteration_date(&SystemTime::now())?;
+
+    if let Err(err) = block.write_name(name) {
+        fs.inner.borrow_mut().free_block(block_addr)?;
+        return Err(err);
+    }
+
+    block.write_u32(
+        BLOCK_DATA_LIST_HEADER_KEY_OFFSET,
+        block.address as u32,
+    )?;
+
+    if let Err(err) = block.write_soft_link_target(target) {
+        fs.inner.borrow_mut().free_block(block_addr)?;
+        return Err(err);
+    }
+
+    Ok(block_addr)
+}
+
+impl AmigaDos {
+    /// Creates a new soft link at `link` pointing to `target`.
+    /// The target is stored as is, it may be relative to the directory of
+    /// the link, absolute, or prefixed by a volume name (e.g.
+    /// `"Workbench:c/dir"`), and does not need to exist.
+    /// Errors:
+    /// - When the parent of `link` doesn't exist or is not a directory.
+    /// - When `link` already exists.
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        target: P,
+        link: Q,
+    ) -> Result<(), Error> {
+        let _guard = self.lock.write();
+
+        let link = self.encode_path(link.as_ref())?;
+        let link = link.as_ref();
+        let target = target.as_ref().to_str().ok_or(Error::InvalidPathError)?;
+
+        let parent_path = get_dirname(link)?;
+        let name = get_basename(link)?;
+
+        let mut dir = Dir::try_with_path(self, parent_path)?;
+
+        if dir.lookup(name)?.is_some() {
+            return Err(Error::AlreadyExists);
+        }
+
+        let addr = init_soft_link_block_header(self, name, target)?;
+
+        dir.add_entry(name, addr)
+    }
+
+    /// Reads the target of a soft link.
+    /// Errors:
+    /// - When `path` is not a soft link.
+    pub fn read_link<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<PathBuf, Error> {
+        let _guard = self.lock.read();
+
+        let addr = self.lookup_with(path, false)?;
+        let block = Block::new(self.disk(), addr);
+
+        if block.read_block_secondary_type()? != BlockSecondaryType::SoftLink {
+            return Err(Error::NotASoftLinkError);
+        }
+
+        Ok(PathBuf::from(block.read_soft_link_target()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disk::*;
+    use crate::errors::*;
+    use crate::fs::*;
+    use crate::shared::*;
+
+    fn init_fs() -> AmigaDos {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir_all("dir/sub").unwrap();
+        fs.write("dir/sub/file", b"hello").unwrap();
+        fs
+    }
+
+    #[test]
+    fn symlink_is_ok() {
+        let mut fs = init_fs();
+
+        fs.symlink("dir/sub", "abs").unwrap();
+        fs.symlink("sub/file", "dir/rel").unwrap();
+        fs.symlink("TEST:dir/sub/file", "vol").unwrap();
+        fs.symlink("/sub/file", "dir/sub/parent").unwrap();
+
+        assert_eq!(fs.read_link("abs").unwrap().to_str(), Some("dir/sub"));
+        assert!(fs.symlink_metadata("abs").unwrap().is_symlink());
+        assert!(fs.metadata("abs").unwrap().is_dir());
+        assert_eq!(fs.read("abs/file").unwrap(), b"hello");
+        assert_eq!(fs.read("dir/rel").unwrap(), b"hello");
+        assert_eq!(fs.read("vol").unwrap(), b"hello");
+        assert_eq!(fs.read("dir/sub/parent").unwrap(), b"hello");
+        assert_eq!(fs.read_link("dir"), Err(Error::NotASoftLinkError));
+        assert_eq!(fs.symlink("dir", "abs"), Err(Error::AlreadyExists));
+        assert!(fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn symlink_loop_fails() {
+        let mut fs = init_fs();
+
+        fs.symlink("b", "a").unwrap();
+        fs.symlink("a", "b").unwrap();
+
+        assert_eq!(fs.metadata("a").unwrap_err(), Error::SoftLinkLoopError);
+        assert!(fs.symlink_metadata("a").unwrap().is_symlink());
+    }
+
+    #[test]
+    fn dangling_symlink_is_ok() {
+        let mut fs = init_fs();
+
+        fs.symlink("missing", "dangling").unwrap();
+
+        assert_eq!(fs.metadata("dangling").unwrap_err(), Error::NotFoundError);
+        assert_eq!(fs.read_link("dangling").unwrap().to_str(), Some("missing"));
+
+        fs.remove_file("dangling").unwrap();
+        assert!(!fs.exists("dangling").unwrap());
+        assert!(fs.check().unwrap().is_empty());
+    }
+}
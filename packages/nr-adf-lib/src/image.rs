@@ -0,0 +1,209 @@
+use std::fmt;
+use std::io::{
+    Read,
+    Write,
+};
+use std::str::FromStr;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::dms;
+use crate::errors::*;
+use crate::ext_adf::*;
+use crate::flux;
+
+
+// gzip magic number followed by the deflate compression method, the only one
+// defined, so that raw data merely starting with the magic number is not
+// taken for a gzip stream
+const GZIP_MAGIC_NUMBER: &[u8; 3] = &[0x1f, 0x8b, 0x08];
+
+/// Container format of a disk image file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Raw Amiga disk file.
+    #[default]
+    ADF,
+    /// Gzip compressed Amiga disk file.
+    ADZ,
+    /// Disk Masher System archive, read only.
+    DMS,
+    /// WinUAE extended ADF, holding AmigaDOS or raw MFM tracks.
+    ExtendedADF,
+    /// SuperCard Pro flux image, read only.
+    SCP,
+    /// Interchangeable Preservation Format track image, read only.
+    IPF,
+}
+
+impl FromStr for ImageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "adf" => Ok(ImageFormat::ADF),
+            "adz" => Ok(ImageFormat::ADZ),
+            "dms" => Ok(ImageFormat::DMS),
+            "ext-adf"|"extadf" => Ok(ImageFormat::ExtendedADF),
+            "scp" => Ok(ImageFormat::SCP),
+            "ipf" => Ok(ImageFormat::IPF),
+            _ => Err(Error::InvalidImageFormatError),
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageFormat::ADF => write!(f, "ADF"),
+            ImageFormat::ADZ => write!(f, "ADZ"),
+            ImageFormat::DMS => write!(f, "DMS"),
+            ImageFormat::ExtendedADF => write!(f, "Extended ADF"),
+            ImageFormat::SCP => write!(f, "SCP"),
+            ImageFormat::IPF => write!(f, "IPF"),
+        }
+    }
+}
+
+impl ImageFormat {
+    /// Tells if images of this format can be read but not written.
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, ImageFormat::DMS | ImageFormat::SCP | ImageFormat::IPF)
+    }
+
+    /// Detects the format of the given image data.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(GZIP_MAGIC_NUMBER) {
+            ImageFormat::ADZ
+        } else if dms::is_dms(data) {
+            ImageFormat::DMS
+        } else if is_extended_adf(data) {
+            ImageFormat::ExtendedADF
+        } else if flux::is_scp(data) {
+            ImageFormat::SCP
+        } else if flux::is_ipf(data) {
+            ImageFormat::IPF
+        } else {
+            ImageFormat::ADF
+        }
+    }
+}
+
+/// Decodes image data, returning the raw disk data and the detected format.
+pub fn decode(
+    data: Vec<u8>,
+) -> Result<(Vec<u8>, ImageFormat), Error> {
+    match ImageFormat::detect(&data) {
+        ImageFormat::ADF => Ok((data, ImageFormat::ADF)),
+        ImageFormat::ADZ => {
+            let mut disk_data = Vec::new();
+
+            GzDecoder::new(data.as_slice())
+                .read_to_end(&mut disk_data)
+                .map_err(|_| Error::CorruptedImageFile)?;
+
+            Ok((disk_data, ImageFormat::ADZ))
+        },
+        ImageFormat::DMS => Ok((dms::unpack(&data)?, ImageFormat::DMS)),
+        ImageFormat::ExtendedADF => {
+            let (_, disk_data) = ExtendedAdf::decode(&data)?;
+
+            Ok((disk_data, ImageFormat::ExtendedADF))
+        },
+        image_format@(ImageFormat::SCP|ImageFormat::IPF) => {
+            Ok((flux::import(&data)?.disk_data, image_format))
+        },
+    }
+}
+
+/// Encodes raw disk data in the given format.
+/// DMS archives, SCP and IPF images can be read but not written. Extended ADF images are made of
+/// AmigaDOS tracks only, see `Disk::encode` to keep raw tracks.
+pub fn encode(
+    disk_data: &[u8],
+    image_format: ImageFormat,
+) -> Result<Vec<u8>, Error> {
+    match image_format {
+        ImageFormat::ADF => Ok(Vec::from(disk_data)),
+        ImageFormat::ADZ => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+
+            encoder.write_all(disk_data)
+                .and_then(|_| encoder.finish())
+                .map_err(|_| Error::CorruptedImageFile)
+        },
+        ImageFormat::DMS |
+        ImageFormat::SCP |
+        ImageFormat::IPF => Err(Error::ReadOnlyImageFormatError),
+        ImageFormat::ExtendedADF => {
+            ExtendedAdf::from_disk_data(disk_data)?.encode(disk_data)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adz_round_trip() {
+        let data = (0..901120).map(|i| (i%251) as u8).collect::<Vec<_>>();
+        let encoded = encode(&data, ImageFormat::ADZ).unwrap();
+
+        assert_eq!(ImageFormat::detect(&encoded), ImageFormat::ADZ);
+        assert_eq!(decode(encoded).unwrap(), (data, ImageFormat::ADZ));
+    }
+
+    #[test]
+    fn adf_is_left_untouched() {
+        let data = vec![0u8; 901120];
+        let encoded = encode(&data, ImageFormat::ADF).unwrap();
+
+        assert_eq!(decode(encoded).unwrap(), (data, ImageFormat::ADF));
+    }
+
+    #[test]
+    fn adf_starting_with_gzip_magic_number_is_ok() {
+        let mut data = vec![0u8; 901120];
+
+        data[..3].copy_from_slice(&[0x1f, 0x8b, 0x00]);
+
+        assert_eq!(ImageFormat::detect(&data), ImageFormat::ADF);
+        assert_eq!(decode(data.clone()).unwrap(), (data, ImageFormat::ADF));
+    }
+
+    #[test]
+    fn extended_adf_round_trip() {
+        let data = (0..901120).map(|i| (i%251) as u8).collect::<Vec<_>>();
+        let encoded = encode(&data, ImageFormat::ExtendedADF).unwrap();
+
+        assert_eq!(ImageFormat::detect(&encoded), ImageFormat::ExtendedADF);
+        assert_eq!(decode(encoded).unwrap(), (data, ImageFormat::ExtendedADF));
+    }
+
+    #[test]
+    fn dms_is_read_only() {
+        let data = vec![0x42u8; 901120];
+        let archive = crate::dms::tests::pack(&data);
+
+        assert_eq!(ImageFormat::detect(&archive), ImageFormat::DMS);
+        assert_eq!(decode(archive).unwrap(), (data.clone(), ImageFormat::DMS));
+        assert_eq!(encode(&data, ImageFormat::DMS), Err(Error::ReadOnlyImageFormatError));
+        assert!(ImageFormat::DMS.is_read_only());
+        assert!(!ImageFormat::ADZ.is_read_only());
+    }
+
+    #[test]
+    fn scp_is_read_only() {
+        let data = (0..4*5632).map(|i| (i%239) as u8).collect::<Vec<_>>();
+        let scp = crate::flux::scp::tests::write_scp(&data, |_, _| false);
+        let (disk_data, image_format) = decode(scp).unwrap();
+
+        assert_eq!(image_format, ImageFormat::SCP);
+        assert_eq!(disk_data.len(), 901120);
+        assert_eq!(disk_data[..data.len()], data);
+        assert_eq!(encode(&data, ImageFormat::SCP), Err(Error::ReadOnlyImageFormatError));
+    }
+}
@@ -1,6 +1,14 @@
 pub mod block;
+pub mod device;
 pub mod disk;
+pub mod dms;
 pub mod errors;
+pub mod ext_adf;
+pub mod flux;
+pub mod image;
+pub mod mfm;
+pub mod rdb;
+pub mod shared;
 
 pub mod fs;
 
@@ -0,0 +1,500 @@
+//! Encoder and decoder for AmigaDOS MFM tracks.
+//!
+//! Each sector of a track starts with two zero bytes and two 0x4489 sync
+//! words, followed by its header and data, every field being split into its
+//! odd bits and then its even bits. The track is read as a circular
+//! bitstream, so sectors may start at any bit and wrap around the index.
+//!
+//! Encoded tracks are written as by trackdisk.device, sectors in order from
+//! the index followed by a gap filling the track up to its nominal length.
+
+use std::fmt;
+
+use crate::disk::*;
+use crate::errors::*;
+
+
+const MFM_SYNC: u32 = 0x44894489;
+const MFM_SYNC_BYTES: [u8; 4] = [0x44, 0x89, 0x44, 0x89];
+const MFM_DATA_MASK: u32 = 0x55555555;
+
+const SECTOR_FORMAT: u8 = 0xff;
+
+// sizes of the MFM encoded fields following the sync words
+const SECTOR_INFO_SIZE: usize = 8;
+const SECTOR_LABEL_SIZE: usize = 32;
+const SECTOR_CHECKSUM_SIZE: usize = 8;
+const SECTOR_DATA_SIZE: usize = 2*BLOCK_SIZE;
+const SECTOR_SIZE: usize = SECTOR_INFO_SIZE
+    + SECTOR_LABEL_SIZE
+    + 2*SECTOR_CHECKSUM_SIZE
+    + SECTOR_DATA_SIZE;
+
+/// Size of an MFM encoded sector, from its leading zero bytes to the end of
+/// its data.
+pub const MFM_SECTOR_SIZE: usize = 4 + MFM_SYNC_BYTES.len() + SECTOR_SIZE;
+
+/// Nominal size of a double density MFM track, high density tracks being
+/// twice as long.
+pub const DD_MFM_TRACK_SIZE: usize = 12668;
+
+/// What became of a sector when decoding a track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectorStatus {
+    Ok,
+    /// The sector header is damaged, the sector couldn't be located.
+    HeaderChecksumError,
+    /// The sector data doesn't match its checksum, it has been decoded
+    /// anyway.
+    DataChecksumError,
+    /// No sector header with this sector number was found on the track.
+    Missing,
+}
+
+impl fmt::Display for SectorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::HeaderChecksumError => write!(f, "header checksum error"),
+            Self::DataChecksumError => write!(f, "data checksum error"),
+            Self::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+/// The sectors decoded from an MFM track.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedTrack {
+    /// The sectors data, missing sectors being zeroed.
+    pub data: Vec<u8>,
+    pub sectors: Vec<SectorStatus>,
+}
+
+impl DecodedTrack {
+    /// Tells if all the sectors have been decoded without error.
+    pub fn is_ok(&self) -> bool {
+        self.sectors.iter().all(|status| *status == SectorStatus::Ok)
+    }
+}
+
+/******************************************************************************
+* Bitstream *******************************************************************
+******************************************************************************/
+
+struct Bitstream<'a> {
+    data: &'a [u8],
+    bit_length: usize,
+}
+
+impl Bitstream<'_> {
+    fn bit(&self, pos: usize) -> u8 {
+        let pos = pos%self.bit_length;
+
+        (self.data[pos/8] >> (7 - pos%8)) & 1
+    }
+
+    /// Returns `len` bytes starting at the bit `pos`, wrapping around the
+    /// end of the track.
+    fn read_bytes(&self, pos: usize, len: usize) -> Vec<u8> {
+        let pos = pos%self.bit_length;
+
+        if pos + 8*len <= self.bit_length && pos/8 + len < self.data.len() {
+            let (index, shift) = (pos/8, pos%8);
+
+            return self.data[index..index + len + 1]
+                .windows(2)
+                .map(|w| ((((w[0] as u16) << 8) | w[1] as u16) >> (8 - shift)) as u8)
+                .collect();
+        }
+
+        (0..len)
+            .map(|i| (0..8).fold(0, |byte, bit| (byte << 1) | self.bit(pos + 8*i + bit)))
+            .collect()
+    }
+
+    /// Returns the bit positions following each sync words pair.
+    fn find_syncs(&self) -> Vec<usize> {
+        let mut syncs = Vec::new();
+        let mut window = 0u32;
+
+        // go around once more to catch a sync word pair split by the index
+        for pos in 0..self.bit_length + 31 {
+            window = (window << 1) | self.bit(pos) as u32;
+
+            if window == MFM_SYNC && pos >= 31 {
+                syncs.push((pos + 1)%self.bit_length);
+            }
+        }
+        syncs
+    }
+}
+
+/******************************************************************************
+* Sector **********************************************************************
+******************************************************************************/
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks_exact(4)
+        .fold(0, |checksum, chunk| checksum ^ u32::from_be_bytes(chunk.try_into().unwrap()))
+        & MFM_DATA_MASK
+}
+
+/// Merges odd and even bits split into two halves of `data`.
+fn decode_odd_even(data: &[u8]) -> Vec<u8> {
+    let (odd, even) = data.split_at(data.len()/2);
+
+    odd.iter()
+        .zip(even)
+        .map(|(odd, even)| ((odd & 0x55) << 1) | (even & 0x55))
+        .collect()
+}
+
+struct Sector {
+    track: u8,
+    sector: u8,
+    header_ok: bool,
+    data_ok: bool,
+    data: Vec<u8>,
+}
+
+impl Sector {
+    fn decode(raw: &[u8]) -> Option<Self> {
+        let info = decode_odd_even(&raw[..SECTOR_INFO_SIZE]);
+
+        if info[0] != SECTOR_FORMAT {
+            return None;
+        }
+
+        let header_end = SECTOR_INFO_SIZE + SECTOR_LABEL_SIZE;
+        let data_offset = header_end + 2*SECTOR_CHECKSUM_SIZE;
+
+        let header_checksum = read_u32(
+            &decode_odd_even(&raw[header_end..header_end + SECTOR_CHECKSUM_SIZE]),
+            0,
+        );
+        let data_checksum = read_u32(
+            &decode_odd_even(&raw[header_end + SECTOR_CHECKSUM_SIZE..data_offset]),
+            0,
+        );
+
+        Some(Self {
+            track: info[1],
+            sector: info[2],
+            header_ok: checksum(&raw[..header_end]) == header_checksum,
+            data_ok: checksum(&raw[data_offset..]) == data_checksum,
+            data: decode_odd_even(&raw[data_offset..]),
+        })
+    }
+}
+
+/******************************************************************************
+* Track ***********************************************************************
+******************************************************************************/
+
+/// Decodes the `sectors` sectors of the AmigaDOS track number `track` from
+/// its `bit_length` bits long MFM bitstream.
+pub fn decode_track(
+    mfm: &[u8],
+    bit_length: usize,
+    track: usize,
+    sectors: usize,
+) -> DecodedTrack {
+    let mut decoded = DecodedTrack {
+        data: vec![0; sectors*BLOCK_SIZE],
+        sectors: vec![SectorStatus::Missing; sectors],
+    };
+
+    let bit_length = bit_length.min(8*mfm.len());
+
+    if bit_length < 32 {
+        return decoded;
+    }
+
+    let bitstream = Bitstream {
+        data: mfm,
+        bit_length,
+    };
+
+    for pos in bitstream.find_syncs() {
+        let raw = bitstream.read_bytes(pos, SECTOR_SIZE);
+        let Some(sector) = Sector::decode(&raw) else {
+            continue;
+        };
+
+        let index = sector.sector as usize;
+
+        if sector.track as usize != track || index >= sectors {
+            continue;
+        }
+
+        let status = match (sector.header_ok, sector.data_ok) {
+            (false, _) => SectorStatus::HeaderChecksumError,
+            (true, false) => SectorStatus::DataChecksumError,
+            (true, true) => SectorStatus::Ok,
+        };
+
+        // keep the best copy of the sector
+        let current = decoded.sectors[index];
+
+        if current == SectorStatus::Ok
+        || (current == SectorStatus::DataChecksumError && status != SectorStatus::Ok) {
+            continue;
+        }
+
+        decoded.sectors[index] = status;
+
+        if status != SectorStatus::HeaderChecksumError {
+            decoded.data[index*BLOCK_SIZE..(index + 1)*BLOCK_SIZE]
+                .copy_from_slice(&sector.data);
+        }
+    }
+
+    decoded
+}
+
+/******************************************************************************
+* Encoder *********************************************************************
+******************************************************************************/
+
+/// Splits the odd bits and the even bits of `data` into two halves, each
+/// bit taking the data position of an MFM cell.
+fn encode_odd_even(data: &[u8]) -> Vec<u8> {
+    let odd = data.iter().map(|b| (b >> 1) & 0x55);
+    let even = data.iter().map(|b| b & 0x55);
+
+    odd.chain(even).collect()
+}
+
+#[derive(Default)]
+struct MfmWriter {
+    mfm: Vec<u8>,
+    // last data bit written, which the next clock bit depends on
+    previous: u8,
+}
+
+impl MfmWriter {
+    fn write_sync(&mut self) {
+        self.mfm.extend_from_slice(&MFM_SYNC_BYTES);
+        self.previous = 1;
+    }
+
+    /// Writes bytes holding data bits only, adding the clock bits, a clock
+    /// bit being set between two zero data bits.
+    fn write_cells(&mut self, cells: &[u8]) {
+        for byte in cells.iter().map(|byte| byte & 0x55) {
+            let mut clocked = byte;
+
+            for bit in [7, 5, 3, 1] {
+                let before = if bit == 7 { self.previous } else { (byte >> (bit + 1)) & 1 };
+
+                if before == 0 && (byte >> (bit - 1)) & 1 == 0 {
+                    clocked |= 1 << bit;
+                }
+            }
+            self.previous = byte & 1;
+            self.mfm.push(clocked);
+        }
+    }
+
+    fn write_sector(
+        &mut self,
+        track: usize,
+        sector: usize,
+        sectors: usize,
+        data: &[u8],
+    ) {
+        let info = [SECTOR_FORMAT, track as u8, sector as u8, (sectors - sector) as u8];
+        let mut header = encode_odd_even(&info);
+
+        header.extend(encode_odd_even(&[0; SECTOR_LABEL_SIZE/2]));
+
+        let data = encode_odd_even(data);
+        let mut checksums = encode_odd_even(&checksum(&header).to_be_bytes());
+
+        checksums.extend(encode_odd_even(&checksum(&data).to_be_bytes()));
+
+        self.write_cells(&[0; 4]);
+        self.write_sync();
+        self.write_cells(&header);
+        self.write_cells(&checksums);
+        self.write_cells(&data);
+    }
+}
+
+/// Returns the nominal size of an MFM track of `sectors` sectors.
+pub fn mfm_track_size(sectors: usize) -> usize {
+    DD_MFM_TRACK_SIZE*sectors.div_ceil(DD_TRACK_SECTORS)
+}
+
+/// Encodes the sectors of the AmigaDOS track number `track` in MFM, the
+/// track being padded with a gap up to its nominal size.
+/// Errors:
+/// - When the size of `data` isn't a multiple of the sector size.
+pub fn encode_track(
+    track: usize,
+    data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(Error::DiskInvalidSizeError(data.len()));
+    }
+
+    let sectors = data.len()/BLOCK_SIZE;
+    let track_size = mfm_track_size(sectors);
+    let mut writer = MfmWriter::default();
+
+    for (sector, sector_data) in data.chunks_exact(BLOCK_SIZE).enumerate() {
+        writer.write_sector(track, sector, sectors, sector_data);
+    }
+
+    let gap_size = track_size.saturating_sub(writer.mfm.len());
+
+    writer.write_cells(&vec![0; gap_size]);
+    Ok(writer.mfm)
+}
+
+/******************************************************************************
+* Disk ************************************************************************
+******************************************************************************/
+
+impl Disk {
+    /// Returns a track encoded in AmigaDOS MFM, see `Disk::track_blocks`.
+    pub fn read_mfm_track(
+        &self,
+        track: usize,
+    ) -> Result<Vec<u8>, Error> {
+        encode_track(track, &self.read_track(track)?)
+    }
+
+    /// Decodes an AmigaDOS MFM track of `bit_length` bits into the sectors
+    /// of the track, returning the status of each sector. Sectors with a
+    /// data checksum error are written as decoded, sectors whose header
+    /// couldn't be read are left untouched.
+    pub fn write_mfm_track(
+        &mut self,
+        track: usize,
+        mfm: &[u8],
+        bit_length: usize,
+    ) -> Result<Vec<SectorStatus>, Error> {
+        let blocks = self.track_blocks(track)?;
+        let decoded = decode_track(mfm, bit_length, track, blocks.len());
+
+        for (index, status) in decoded.sectors.iter().enumerate() {
+            if matches!(status, SectorStatus::Ok|SectorStatus::DataChecksumError) {
+                let offset = index*BLOCK_SIZE;
+
+                self.block_mut(blocks.start + index)?
+                    .copy_from_slice(&decoded.data[offset..offset + BLOCK_SIZE]);
+            }
+        }
+
+        Ok(decoded.sectors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_track_is_ok() {
+        let data = (0..11*BLOCK_SIZE).map(|i| (i%253) as u8).collect::<Vec<_>>();
+        let mfm = encode_track(7, &data).unwrap();
+        let decoded = decode_track(&mfm, 8*mfm.len(), 7, 11);
+
+        assert!(decoded.is_ok());
+        assert_eq!(decoded.data, data);
+
+        // not byte aligned, with the first sector wrapping around the index
+        let bit_length = 8*mfm.len() - 3;
+        let mut bits = (0..bit_length)
+            .map(|pos| (mfm[pos/8] >> (7 - pos%8)) & 1)
+            .collect::<Vec<_>>();
+
+        bits.rotate_left(8*100 + 5);
+
+        let mut rotated = vec![0u8; mfm.len()];
+
+        for (pos, bit) in bits.iter().enumerate() {
+            rotated[pos/8] |= bit << (7 - pos%8);
+        }
+
+        let decoded = decode_track(&rotated, bit_length, 7, 11);
+
+        assert!(decoded.is_ok());
+        assert_eq!(decoded.data, data);
+    }
+
+    #[test]
+    fn damaged_track_is_reported() {
+        let data = vec![0x42; 11*BLOCK_SIZE];
+        let mut mfm = encode_track(2, &data).unwrap();
+
+        // data of the sector 1, then header of the sector 3
+        mfm[1088 + 600] ^= 0x10;
+        mfm[3*1088 + 20] ^= 0x04;
+        // sector 5 gone
+        mfm[5*1088 + 4..5*1088 + 8].fill(0xaa);
+
+        let decoded = decode_track(&mfm, 8*mfm.len(), 2, 11);
+
+        assert!(!decoded.is_ok());
+        assert_eq!(decoded.sectors[0], SectorStatus::Ok);
+        assert_eq!(decoded.sectors[1], SectorStatus::DataChecksumError);
+        assert_eq!(decoded.sectors[3], SectorStatus::HeaderChecksumError);
+        assert_eq!(decoded.sectors[5], SectorStatus::Missing);
+        assert_eq!(decoded.data[3*BLOCK_SIZE], 0);
+        assert_eq!(decode_track(&mfm, 8*mfm.len(), 3, 11).sectors, [SectorStatus::Missing; 11]);
+    }
+
+    #[test]
+    fn encode_track_is_ok() {
+        let data = vec![0xff; 22*BLOCK_SIZE];
+        let mfm = encode_track(0, &data).unwrap();
+
+        assert_eq!(mfm.len(), 2*DD_MFM_TRACK_SIZE);
+        assert_eq!(&mfm[..8], b"\xaa\xaa\xaa\xaa\x44\x89\x44\x89");
+        // no clock bit after a set data bit
+        assert_eq!(mfm[MFM_SECTOR_SIZE], 0x2a);
+        assert_eq!(mfm[mfm.len() - 1], 0xaa);
+        assert_eq!(decode_track(&mfm, 8*mfm.len(), 0, 22).data, data);
+
+        // no two consecutive set bits, nor more than three zero bits
+        let bits = mfm.iter()
+            .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
+            .collect::<Vec<_>>();
+
+        assert!(bits.windows(2).all(|w| w != [1, 1]));
+        assert!(bits.windows(4).all(|w| w.contains(&1)));
+        assert_eq!(encode_track(0, &[0; 100]), Err(Error::DiskInvalidSizeError(100)));
+    }
+
+    #[test]
+    fn mfm_disk_tracks_are_ok() {
+        let mut disk = Disk::create(DiskType::DoubleDensity);
+
+        disk.write_track(5, &[0x42; 11*BLOCK_SIZE]).unwrap();
+
+        let mut mfm = disk.read_mfm_track(5).unwrap();
+
+        assert_eq!(mfm.len(), DD_MFM_TRACK_SIZE);
+
+        // damage the data of the sector 2, then zero it on the disk
+        mfm[2*MFM_SECTOR_SIZE + 600] ^= 0x10;
+        disk.block_mut(57).unwrap().fill(0);
+
+        let sectors = disk.write_mfm_track(5, &mfm, 8*mfm.len()).unwrap();
+
+        assert_eq!(sectors[2], SectorStatus::DataChecksumError);
+        assert_eq!(sectors.iter().filter(|s| **s == SectorStatus::Ok).count(), 10);
+        assert_eq!(disk.block(57).unwrap()[0], 0x42);
+        assert_eq!(
+            disk.write_mfm_track(6, &mfm, 8*mfm.len()).unwrap(),
+            [SectorStatus::Missing; 11],
+        );
+        assert!(disk.read_track(6).unwrap().iter().all(|b| *b == 0));
+    }
+}
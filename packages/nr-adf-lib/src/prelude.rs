@@ -1,3 +1,5 @@
 pub use crate::disk::*;
 pub use crate::errors::*;
 pub use crate::fs::*;
+pub use crate::image::ImageFormat;
+pub use crate::shared::*;
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use crate::block::Block;
+use crate::disk::*;
+use crate::errors::Error;
+use crate::shared::*;
+
+use super::constants::*;
+
+
+impl Block {
+    fn rdb_summed_longs(
+        &self,
+    ) -> Result<usize, Error> {
+        let summed_longs = self.read_u32(RDB_BLOCK_SUMMED_LONGS_OFFSET)? as usize;
+
+        if (RDB_BLOCK_CHECKSUM_OFFSET/4 + 1..=BLOCK_SIZE/4).contains(&summed_longs) {
+            Ok(summed_longs)
+        } else {
+            Err(Error::RdbInvalidBlockError(self.address))
+        }
+    }
+
+    fn compute_rdb_checksum(
+        &self,
+        summed_longs: usize,
+    ) -> Result<u32, Error> {
+        let sum = self
+            .read_u32_vector(0, summed_longs)?
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| 4*i != RDB_BLOCK_CHECKSUM_OFFSET)
+            .fold(0u32, |sum, (_, v)| sum.wrapping_add(*v));
+
+        Ok(sum.wrapping_neg())
+    }
+
+    /// Checks the id and the checksum of a RDB block.
+    pub(super) fn check_rdb_block(
+        &self,
+        id: u32,
+    ) -> Result<(), Error> {
+        if self.read_u32(RDB_BLOCK_ID_OFFSET)? != id {
+            return Err(Error::RdbInvalidBlockError(self.address));
+        }
+
+        let summed_longs = self.rdb_summed_longs()?;
+
+        if self.compute_rdb_checksum(summed_longs)? != self.read_u32(RDB_BLOCK_CHECKSUM_OFFSET)? {
+            return Err(Error::RdbInvalidBlockError(self.address));
+        }
+
+        Ok(())
+    }
+
+    /// Initializes a RDB block with the given id, the checksum has to be
+    /// written once the block content is complete.
+    pub(super) fn init_rdb_block(
+        &mut self,
+        id: u32,
+        summed_longs: usize,
+    ) -> Result<(), Error> {
+        self.fill(0, 0, BLOCK_SIZE)?;
+        self.write_u32(RDB_BLOCK_ID_OFFSET, id)?;
+        self.write_u32(RDB_BLOCK_SUMMED_LONGS_OFFSET, summed_longs as u32)?;
+        self.write_u32(RDB_BLOCK_HOST_ID_OFFSET, RDB_HOST_ID)
+    }
+
+    pub(super) fn write_rdb_checksum(
+        &mut self,
+    ) -> Result<(), Error> {
+        let checksum = self.compute_rdb_checksum(self.rdb_summed_longs()?)?;
+
+        self.write_u32(RDB_BLOCK_CHECKSUM_OFFSET, checksum)
+    }
+
+    pub(super) fn read_rdb_next(
+        &self,
+    ) -> Result<Option<LBAAddress>, Error> {
+        Ok(rdb_address(self.read_u32(RDB_BLOCK_NEXT_OFFSET)?))
+    }
+
+    /// Reads a fixed size, space or nul padded, string.
+    pub(super) fn read_rdb_string(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<String, Error> {
+        let bytes = self.read_u8_vector(offset, len)?;
+        let s = bytes
+            .iter()
+            .take_while(|b| **b != 0)
+            .map(|b| *b as char)
+            .collect::<String>();
+
+        Ok(s.trim_end().into())
+    }
+
+    /// Reads a BCPL string, i.e. a length byte followed by the characters.
+    pub(super) fn read_bcpl_string(
+        &self,
+        offset: usize,
+        size: usize,
+    ) -> Result<String, Error> {
+        let len = (self.read_u8(offset)? as usize).min(size - 1);
+        let bytes = self.read_u8_vector(offset + 1, len)?;
+
+        Ok(bytes.iter().map(|b| *b as char).collect())
+    }
+
+    pub(super) fn write_bcpl_string(
+        &mut self,
+        offset: usize,
+        size: usize,
+        s: &str,
+    ) -> Result<(), Error> {
+        if s.is_empty() || s.len() >= size || !s.is_ascii() {
+            return Err(Error::InvalidNameError);
+        }
+
+        self.write_u8(offset, s.len() as u8)?;
+        self.write_u8_array(offset + 1, s.as_bytes())
+    }
+}
+
+pub(super) fn rdb_address(addr: u32) -> Option<LBAAddress> {
+    if addr != RDB_END_OF_LIST {
+        Some(addr as LBAAddress)
+    } else {
+        None
+    }
+}
+
+/// Iterates over the blocks of a RDB list, failing on loops.
+pub(super) fn read_rdb_list<T>(
+    disk: Shared<Disk>,
+    first: Option<LBAAddress>,
+    id: u32,
+    mut f: impl FnMut(&Block) -> Result<T, Error>,
+) -> Result<Vec<T>, Error> {
+    let mut visited = HashSet::new();
+    let mut items = Vec::new();
+    let mut next = first;
+
+    while let Some(address) = next {
+        if !visited.insert(address) {
+            return Err(Error::RdbInvalidBlockError(address));
+        }
+
+        let block = Block::new(disk.clone(), address);
+
+        block.check_rdb_block(id)?;
+        items.push(f(&block)?);
+        next = block.read_rdb_next()?;
+    }
+
+    Ok(items)
+}
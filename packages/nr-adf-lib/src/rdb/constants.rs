@@ -0,0 +1,67 @@
+// RDB blocks are searched in the first blocks of the disk
+pub const RDB_LOCATION_LIMIT                : usize = 16;
+
+pub const RDB_END_OF_LIST                   : u32 = 0xffff_ffff;
+pub const RDB_HOST_ID                       : u32 = 7;
+
+pub const RDB_RIGID_DISK_BLOCK_ID           : u32 = 0x5244_534b; // RDSK
+pub const RDB_PARTITION_BLOCK_ID            : u32 = 0x5041_5254; // PART
+pub const RDB_FILESYSTEM_HEADER_BLOCK_ID    : u32 = 0x4653_4844; // FSHD
+pub const RDB_LOAD_SEG_BLOCK_ID             : u32 = 0x4c53_4547; // LSEG
+
+// Common /////////////////////////////////////////////////////////////////////
+pub const RDB_BLOCK_ID_OFFSET               : usize = 0x00;
+pub const RDB_BLOCK_SUMMED_LONGS_OFFSET     : usize = 0x04;
+pub const RDB_BLOCK_CHECKSUM_OFFSET         : usize = 0x08;
+pub const RDB_BLOCK_HOST_ID_OFFSET          : usize = 0x0c;
+pub const RDB_BLOCK_NEXT_OFFSET             : usize = 0x10;
+pub const RDB_BLOCK_SUMMED_LONGS            : usize = 64;
+
+// Rigid disk block ///////////////////////////////////////////////////////////
+pub const RDSK_BLOCK_BYTES_OFFSET           : usize = 0x10;
+pub const RDSK_FLAGS_OFFSET                 : usize = 0x14;
+pub const RDSK_BAD_BLOCK_LIST_OFFSET        : usize = 0x18;
+pub const RDSK_PARTITION_LIST_OFFSET        : usize = 0x1c;
+pub const RDSK_FILESYSTEM_HEADER_LIST_OFFSET: usize = 0x20;
+pub const RDSK_DRIVE_INIT_OFFSET            : usize = 0x24;
+pub const RDSK_RESERVED1_OFFSET             : usize = 0x28;
+pub const RDSK_RESERVED1_SIZE               : usize = 6;
+pub const RDSK_CYLINDERS_OFFSET             : usize = 0x40;
+pub const RDSK_SECTORS_OFFSET               : usize = 0x44;
+pub const RDSK_HEADS_OFFSET                 : usize = 0x48;
+pub const RDSK_INTERLEAVE_OFFSET            : usize = 0x4c;
+pub const RDSK_PARK_OFFSET                  : usize = 0x50;
+pub const RDSK_WRITE_PRE_COMP_OFFSET        : usize = 0x60;
+pub const RDSK_REDUCED_WRITE_OFFSET         : usize = 0x64;
+pub const RDSK_STEP_RATE_OFFSET             : usize = 0x68;
+pub const RDSK_RDB_BLOCKS_LO_OFFSET         : usize = 0x80;
+pub const RDSK_RDB_BLOCKS_HI_OFFSET         : usize = 0x84;
+pub const RDSK_LO_CYLINDER_OFFSET           : usize = 0x88;
+pub const RDSK_HI_CYLINDER_OFFSET           : usize = 0x8c;
+pub const RDSK_CYL_BLOCKS_OFFSET            : usize = 0x90;
+pub const RDSK_HIGH_RDSK_BLOCK_OFFSET       : usize = 0x98;
+pub const RDSK_DISK_VENDOR_OFFSET           : usize = 0xa0;
+pub const RDSK_DISK_VENDOR_SIZE             : usize = 8;
+pub const RDSK_DISK_PRODUCT_OFFSET          : usize = 0xa8;
+pub const RDSK_DISK_PRODUCT_SIZE            : usize = 16;
+pub const RDSK_DISK_REVISION_OFFSET         : usize = 0xb8;
+pub const RDSK_DISK_REVISION_SIZE           : usize = 4;
+
+// Partition block ////////////////////////////////////////////////////////////
+pub const PART_FLAGS_OFFSET                 : usize = 0x14;
+pub const PART_FLAGS_BOOTABLE               : u32 = 0x01;
+pub const PART_FLAGS_NO_MOUNT               : u32 = 0x02;
+pub const PART_DRIVE_NAME_OFFSET            : usize = 0x24;
+pub const PART_DRIVE_NAME_SIZE              : usize = 32;
+pub const PART_ENVIRONMENT_OFFSET           : usize = 0x80;
+
+// DosEnvec ///////////////////////////////////////////////////////////////////
+pub const DOS_ENVEC_SIZE                    : usize = 17;
+
+// Filesystem header block ////////////////////////////////////////////////////
+pub const FSHD_DOS_TYPE_OFFSET              : usize = 0x20;
+pub const FSHD_VERSION_OFFSET               : usize = 0x24;
+pub const FSHD_SEG_LIST_BLOCK_OFFSET        : usize = 0x48;
+
+// LoadSeg block //////////////////////////////////////////////////////////////
+pub const LSEG_DATA_OFFSET                  : usize = 0x14;
@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+use crate::block::Block;
+use crate::disk::*;
+use crate::errors::Error;
+
+use super::constants::*;
+
+
+/// Geometry and mount parameters of a partition, as found in the
+/// `pb_Environment` field of a partition block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DosEnvec {
+    pub table_size: u32,
+    pub size_block: u32,
+    pub sec_org: u32,
+    pub surfaces: u32,
+    pub sectors_per_block: u32,
+    pub blocks_per_track: u32,
+    pub reserved: u32,
+    pub pre_alloc: u32,
+    pub interleave: u32,
+    pub low_cyl: u32,
+    pub high_cyl: u32,
+    pub num_buffers: u32,
+    pub buf_mem_type: u32,
+    pub max_transfer: u32,
+    pub mask: u32,
+    pub boot_pri: i32,
+    pub dos_type: u32,
+}
+
+impl Default for DosEnvec {
+    fn default() -> Self {
+        Self {
+            table_size: DOS_ENVEC_SIZE as u32 - 1,
+            size_block: (BLOCK_SIZE/4) as u32,
+            sec_org: 0,
+            surfaces: 1,
+            sectors_per_block: 1,
+            blocks_per_track: 1,
+            reserved: 2,
+            pre_alloc: 0,
+            interleave: 0,
+            low_cyl: 0,
+            high_cyl: 0,
+            num_buffers: 30,
+            buf_mem_type: 0,
+            max_transfer: 0x00ff_ffff,
+            mask: 0x7fff_fffe,
+            boot_pri: 0,
+            dos_type: 0x444f_5301, // DOS\1
+        }
+    }
+}
+
+impl DosEnvec {
+    /// Returns the size of a sector in bytes, `size_block` being given in
+    /// longwords.
+    pub fn sector_size(&self) -> usize {
+        (self.size_block as usize).saturating_mul(4)
+    }
+
+    /// Returns the size of a filesystem block in bytes.
+    pub fn block_size(&self) -> usize {
+        self.sector_size().saturating_mul(self.sectors_per_block as usize)
+    }
+
+    /// Returns the number of sectors in a cylinder, tracks being counted in
+    /// sectors, or `None` if it overflows.
+    pub fn cylinder_sector_count(&self) -> Option<usize> {
+        (self.surfaces as usize).checked_mul(self.blocks_per_track as usize)
+    }
+
+    /// Returns the range of blocks of the partition on the disk, in disk
+    /// blocks of `BLOCK_SIZE` bytes whatever the sector size is.
+    /// Errors:
+    /// - When the geometry overflows or the cylinders are out of order.
+    pub fn block_range(&self) -> Result<Range<LBAAddress>, Error> {
+        let cylinder_size = self.cylinder_sector_count()
+            .and_then(|count| count.checked_mul(self.sector_size()));
+        let offset = |cylinder: usize| {
+            cylinder_size
+                .and_then(|size| cylinder.checked_mul(size))
+                .map(|size| size/BLOCK_SIZE)
+        };
+        let first = offset(self.low_cyl as usize);
+        let last = (self.high_cyl as usize).checked_add(1).and_then(offset);
+
+        match (first, last) {
+            (Some(first), Some(last)) if first <= last => Ok(first..last),
+            _ => Err(Error::RdbInvalidLayoutError),
+        }
+    }
+
+    pub(super) fn read(
+        block: &Block,
+        offset: usize,
+    ) -> Result<Self, Error> {
+        let v = block.read_u32_vector(offset, DOS_ENVEC_SIZE)?;
+
+        Ok(Self {
+            table_size: v[0],
+            size_block: v[1],
+            sec_org: v[2],
+            surfaces: v[3],
+            sectors_per_block: v[4],
+            blocks_per_track: v[5],
+            reserved: v[6],
+            pre_alloc: v[7],
+            interleave: v[8],
+            low_cyl: v[9],
+            high_cyl: v[10],
+            num_buffers: v[11],
+            buf_mem_type: v[12],
+            max_transfer: v[13],
+            mask: v[14],
+            boot_pri: v[15] as i32,
+            dos_type: v[16],
+        })
+    }
+
+    pub(super) fn write(
+        &self,
+        block: &mut Block,
+        offset: usize,
+    ) -> Result<(), Error> {
+        let v = [
+            self.table_size,
+            self.size_block,
+            self.sec_org,
+            self.surfaces,
+            self.sectors_per_block,
+            self.blocks_per_track,
+            self.reserved,
+            self.pre_alloc,
+            self.interleave,
+            self.low_cyl,
+            self.high_cyl,
+            self.num_buffers,
+            self.buf_mem_type,
+            self.max_transfer,
+            self.mask,
+            self.boot_pri as u32,
+            self.dos_type,
+        ];
+
+        for (i, value) in v.iter().copied().enumerate() {
+            block.write_u32(offset + 4*i, value)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use crate::block::Block;
+use crate::disk::*;
+use crate::errors::Error;
+
+use super::block::*;
+use super::constants::*;
+
+
+/// A filesystem stored in the RDB, along with its code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilesystemHeader {
+    pub address: LBAAddress,
+    pub dos_type: u32,
+    pub version: u32,
+    /// The filesystem code, as loaded from the LSEG blocks.
+    pub code: Vec<u8>,
+}
+
+impl FilesystemHeader {
+    pub(super) fn read(
+        block: &Block,
+    ) -> Result<Self, Error> {
+        let first = rdb_address(block.read_u32(FSHD_SEG_LIST_BLOCK_OFFSET)?);
+
+        let code = read_rdb_list(
+            block.disk.clone(),
+            first,
+            RDB_LOAD_SEG_BLOCK_ID,
+            |lseg_block| {
+                let summed_longs = lseg_block.read_u32(RDB_BLOCK_SUMMED_LONGS_OFFSET)? as usize;
+
+                lseg_block.read_u8_vector(
+                    LSEG_DATA_OFFSET,
+                    (4*summed_longs).saturating_sub(LSEG_DATA_OFFSET),
+                )
+            },
+        )?.concat();
+
+        Ok(Self {
+            address: block.address,
+            dos_type: block.read_u32(FSHD_DOS_TYPE_OFFSET)?,
+            version: block.read_u32(FSHD_VERSION_OFFSET)?,
+            code,
+        })
+    }
+}
@@ -0,0 +1,380 @@
+use crate::block::Block;
+use crate::disk::*;
+use crate::errors::Error;
+use crate::shared::*;
+
+use super::constants::*;
+use super::dos_envec::*;
+use super::rigid_disk_block::*;
+
+
+/******************************************************************************
+* PartitionLayout *************************************************************
+******************************************************************************/
+
+/// Describes a partition to be created by `RigidDiskBlockInitializer`.
+#[derive(Clone, Debug)]
+pub struct PartitionLayout {
+    name: String,
+    cylinder_count: Option<usize>,
+    dos_type: u32,
+    bootable: bool,
+    boot_priority: i32,
+}
+
+impl PartitionLayout {
+    /// Creates a partition layout, a `None` cylinder count meaning all the
+    /// remaining cylinders.
+    pub fn new(
+        name: &str,
+        cylinder_count: Option<usize>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            cylinder_count,
+            dos_type: DosEnvec::default().dos_type,
+            bootable: false,
+            boot_priority: 0,
+        }
+    }
+
+    pub fn with_dos_type(
+        &mut self,
+        dos_type: u32,
+    ) -> &mut Self {
+        self.dos_type = dos_type;
+        self
+    }
+
+    pub fn with_bootable(
+        &mut self,
+        bootable: bool,
+    ) -> &mut Self {
+        self.bootable = bootable;
+        self
+    }
+
+    pub fn with_boot_priority(
+        &mut self,
+        boot_priority: i32,
+    ) -> &mut Self {
+        self.boot_priority = boot_priority;
+        self
+    }
+}
+
+/******************************************************************************
+* RigidDiskBlockInitializer ***************************************************
+******************************************************************************/
+
+#[derive(Clone, Debug, Default)]
+pub struct RigidDiskBlockInitializer {
+    geometry: Option<DiskGeometry>,
+    partitions: Vec<PartitionLayout>,
+}
+
+impl RigidDiskBlockInitializer {
+    /// Sets the disk geometry, by default the disk is seen as 1 head and
+    /// 32 sectors per track.
+    pub fn with_geometry(
+        &mut self,
+        geometry: Option<DiskGeometry>,
+    ) -> &mut Self {
+        self.geometry = geometry;
+        self
+    }
+
+    /// Appends a partition, partitions are laid out in order after the
+    /// cylinders reserved for the RDB.
+    pub fn with_partition(
+        &mut self,
+        partition: &PartitionLayout,
+    ) -> &mut Self {
+        self.partitions.push(partition.clone());
+        self
+    }
+
+    fn layout(
+        &self,
+        geometry: &DiskGeometry,
+        lo_cylinder: usize,
+    ) -> Result<Vec<(usize, usize)>, Error> {
+        let mut cylinders = Vec::new();
+        let mut low = lo_cylinder;
+
+        for partition in self.partitions.iter() {
+            let count = partition.cylinder_count.unwrap_or(
+                geometry.cylinders.saturating_sub(low)
+            );
+
+            if count == 0 || low + count > geometry.cylinders {
+                return Err(Error::RdbInvalidLayoutError);
+            }
+
+            cylinders.push((low, low + count - 1));
+            low += count;
+        }
+
+        Ok(cylinders)
+    }
+
+    /// Writes the RDB and the partition blocks on the disk. Partitions are
+    /// left unformatted.
+    pub fn init(
+        &self,
+        disk: Shared<Disk>,
+    ) -> Result<RigidDiskBlock, Error> {
+        disk.borrow_mut().select_all_blocks();
+
+        let block_count = disk.borrow().block_count();
+        let geometry = self.geometry.unwrap_or_else(|| {
+            DiskGeometry::new(block_count/32, 1, 32)
+        });
+
+        if geometry.block_count() == 0 || geometry.block_count() > block_count {
+            return Err(Error::RdbInvalidLayoutError);
+        }
+
+        // the RDB and the partition blocks take the first cylinders
+        let cyl_blocks = geometry.heads*geometry.sectors;
+        let rdb_block_count = 1 + self.partitions.len();
+        let lo_cylinder = rdb_block_count.div_ceil(cyl_blocks);
+
+        let cylinders = self.layout(&geometry, lo_cylinder)?;
+
+        self.init_rigid_disk_block(disk.clone(), &geometry, lo_cylinder)?;
+
+        for (index, (partition, (low_cyl, high_cyl))) in self.partitions
+            .iter()
+            .zip(cylinders)
+            .enumerate() {
+            let address = 1 + index;
+            let next = if index + 1 < self.partitions.len() {
+                address as u32 + 1
+            } else {
+                RDB_END_OF_LIST
+            };
+
+            let mut block = Block::new(disk.clone(), address);
+
+            block.init_rdb_block(RDB_PARTITION_BLOCK_ID, RDB_BLOCK_SUMMED_LONGS)?;
+            block.write_u32(RDB_BLOCK_NEXT_OFFSET, next)?;
+            block.write_u32(
+                PART_FLAGS_OFFSET,
+                if partition.bootable { PART_FLAGS_BOOTABLE } else { 0 },
+            )?;
+            block.write_bcpl_string(
+                PART_DRIVE_NAME_OFFSET,
+                PART_DRIVE_NAME_SIZE,
+                &partition.name,
+            )?;
+
+            DosEnvec {
+                surfaces: geometry.heads as u32,
+                blocks_per_track: geometry.sectors as u32,
+                low_cyl: low_cyl as u32,
+                high_cyl: high_cyl as u32,
+                boot_pri: partition.boot_priority,
+                dos_type: partition.dos_type,
+                ..DosEnvec::default()
+            }.write(&mut block, PART_ENVIRONMENT_OFFSET)?;
+
+            block.write_rdb_checksum()?;
+        }
+
+        RigidDiskBlock::try_from_disk(disk)
+    }
+
+    fn init_rigid_disk_block(
+        &self,
+        disk: Shared<Disk>,
+        geometry: &DiskGeometry,
+        lo_cylinder: usize,
+    ) -> Result<(), Error> {
+        let cyl_blocks = geometry.heads*geometry.sectors;
+        let mut block = Block::new(disk, 0);
+
+        block.init_rdb_block(RDB_RIGID_DISK_BLOCK_ID, RDB_BLOCK_SUMMED_LONGS)?;
+
+        let partition_list = if self.partitions.is_empty() {
+            RDB_END_OF_LIST
+        } else {
+            1
+        };
+
+        for (offset, value) in [
+            (RDSK_BLOCK_BYTES_OFFSET, BLOCK_SIZE as u32),
+            (RDSK_BAD_BLOCK_LIST_OFFSET, RDB_END_OF_LIST),
+            (RDSK_PARTITION_LIST_OFFSET, partition_list),
+            (RDSK_FILESYSTEM_HEADER_LIST_OFFSET, RDB_END_OF_LIST),
+            (RDSK_DRIVE_INIT_OFFSET, RDB_END_OF_LIST),
+            (RDSK_CYLINDERS_OFFSET, geometry.cylinders as u32),
+            (RDSK_SECTORS_OFFSET, geometry.sectors as u32),
+            (RDSK_HEADS_OFFSET, geometry.heads as u32),
+            (RDSK_INTERLEAVE_OFFSET, 1),
+            (RDSK_PARK_OFFSET, geometry.cylinders as u32),
+            (RDSK_WRITE_PRE_COMP_OFFSET, geometry.cylinders as u32),
+            (RDSK_REDUCED_WRITE_OFFSET, geometry.cylinders as u32),
+            (RDSK_STEP_RATE_OFFSET, 3),
+            (RDSK_RDB_BLOCKS_LO_OFFSET, 0),
+            (RDSK_RDB_BLOCKS_HI_OFFSET, (lo_cylinder*cyl_blocks - 1) as u32),
+            (RDSK_LO_CYLINDER_OFFSET, lo_cylinder as u32),
+            (RDSK_HI_CYLINDER_OFFSET, geometry.cylinders as u32 - 1),
+            (RDSK_CYL_BLOCKS_OFFSET, cyl_blocks as u32),
+            (RDSK_HIGH_RDSK_BLOCK_OFFSET, self.partitions.len() as u32),
+        ] {
+            block.write_u32(offset, value)?;
+        }
+
+        for i in 0..RDSK_RESERVED1_SIZE {
+            block.write_u32(RDSK_RESERVED1_OFFSET + 4*i, RDB_END_OF_LIST)?;
+        }
+
+        block.write_rdb_checksum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::fs::*;
+    use super::*;
+
+    fn create_rdb_disk() -> (Shared<Disk>, RigidDiskBlock) {
+        let geometry = DiskGeometry::new(200, 2, 32);
+        let disk = shared(Disk::create(geometry.into()));
+
+        let rdb = RigidDiskBlockInitializer::default()
+            .with_geometry(Some(geometry))
+            .with_partition(PartitionLayout::new("DH0", Some(50)).with_bootable(true))
+            .with_partition(&PartitionLayout::new("DH1", None))
+            .init(disk.clone())
+            .unwrap();
+
+        (disk, rdb)
+    }
+
+    #[test]
+    fn init_is_ok() {
+        let (disk, rdb) = create_rdb_disk();
+
+        assert_eq!(rdb, RigidDiskBlock::try_from_disk(disk).unwrap());
+        assert_eq!(rdb.geometry, DiskGeometry::new(200, 2, 32));
+        assert_eq!(rdb.lo_cylinder, 1);
+        assert_eq!(rdb.partitions.len(), 2);
+
+        let dh0 = rdb.partition("dh0").unwrap();
+        let dh1 = rdb.partition("DH1").unwrap();
+
+        assert!(dh0.is_bootable());
+        assert!(!dh1.is_bootable());
+        assert_eq!(dh0.block_range().unwrap(), 64..3264);
+        assert_eq!(dh1.block_range().unwrap(), 3264..12800);
+        assert_eq!(dh1.dos_envec.dos_type, 0x444f5301);
+    }
+
+    #[test]
+    fn block_range_follows_block_size() {
+        let (disk, rdb) = create_rdb_disk();
+        let mut dh1 = rdb.partition("DH1").unwrap().clone();
+
+        // 1024 bytes sectors
+        dh1.dos_envec.size_block = 256;
+        assert_eq!(dh1.block_range().unwrap(), 6528..25600);
+        assert_eq!(
+            rdb.select_partition(disk.clone(), &dh1),
+            Err(Error::RdbUnsupportedBlockSizeError(1024)),
+        );
+
+        // 1024 bytes blocks made of two 512 bytes sectors
+        dh1.dos_envec.size_block = 128;
+        dh1.dos_envec.sectors_per_block = 2;
+        assert_eq!(dh1.block_range().unwrap(), 3264..12800);
+        assert_eq!(
+            rdb.select_partition(disk, &dh1),
+            Err(Error::RdbUnsupportedBlockSizeError(1024)),
+        );
+    }
+
+    #[test]
+    fn overflowing_geometry_fails() {
+        let (disk, rdb) = create_rdb_disk();
+        let mut dh1 = rdb.partition("DH1").unwrap().clone();
+
+        dh1.dos_envec.surfaces = u32::MAX;
+        dh1.dos_envec.blocks_per_track = u32::MAX;
+        dh1.dos_envec.high_cyl = u32::MAX;
+
+        assert_eq!(dh1.block_range(), Err(Error::RdbInvalidBlockError(dh1.address)));
+        assert_eq!(
+            rdb.select_partition(disk, &dh1),
+            Err(Error::RdbInvalidBlockError(dh1.address)),
+        );
+        assert!(dh1.to_string().contains('?'));
+
+        dh1.dos_envec.surfaces = 2;
+        dh1.dos_envec.blocks_per_track = 32;
+        dh1.dos_envec.low_cyl = 10;
+        dh1.dos_envec.high_cyl = 5;
+
+        assert!(dh1.block_range().is_err());
+    }
+
+    #[test]
+    fn invalid_layout_fails() {
+        let disk = shared(Disk::create(DiskType::Custom(6400)));
+
+        let res = RigidDiskBlockInitializer::default()
+            .with_geometry(Some(DiskGeometry::new(100, 2, 32)))
+            .with_partition(&PartitionLayout::new("DH0", Some(100)))
+            .init(disk);
+
+        assert_eq!(res.unwrap_err(), Error::RdbInvalidLayoutError);
+    }
+
+    #[test]
+    fn corrupted_partition_block_fails() {
+        let (disk, _) = create_rdb_disk();
+
+        disk.borrow_mut().block_mut(2).unwrap()[0x30] ^= 0xff;
+
+        assert_eq!(
+            RigidDiskBlock::try_from_disk(disk).unwrap_err(),
+            Error::RdbInvalidBlockError(2),
+        );
+    }
+
+    #[test]
+    fn open_partition_is_ok() {
+        let (disk, rdb) = create_rdb_disk();
+
+        for partition in rdb.partitions.iter() {
+            rdb.select_partition(disk.clone(), partition).unwrap();
+
+            AmigaDosFormater::default()
+                .with_filesystem_type(FilesystemType::FFS)
+                .format(disk.clone(), &partition.name)
+                .unwrap();
+        }
+
+        let dh1 = rdb.partition("DH1").unwrap();
+        let mut fs = rdb.open_partition(disk.clone(), dh1).unwrap();
+
+        fs.create_dir(Path::new("/dir")).unwrap();
+
+        // the RDB is left untouched
+        disk.borrow_mut().select_all_blocks();
+        assert_eq!(RigidDiskBlock::try_from_disk(disk.clone()).unwrap(), rdb);
+
+        let dh0 = rdb.partition("DH0").unwrap();
+        let fs = rdb.open_partition(disk.clone(), dh0).unwrap();
+
+        assert_eq!(fs.info().unwrap().volume_name, "DH0");
+        assert!(fs.metadata(Path::new("/dir")).is_err());
+
+        let fs = rdb.open_partition(disk.clone(), dh1).unwrap();
+
+        assert!(fs.metadata(Path::new("/dir")).is_ok());
+    }
+}
@@ -0,0 +1,19 @@
+//! Rigid Disk Block support.
+//!
+//! Hard disk images start with a RDB describing the disk geometry, its
+//! partitions and the filesystems needed to mount them. Each partition is
+//! a range of cylinders holding an AmigaDOS volume.
+
+mod block;
+mod constants;
+mod dos_envec;
+mod filesystem;
+mod init;
+mod partition;
+mod rigid_disk_block;
+
+pub use dos_envec::*;
+pub use filesystem::*;
+pub use init::*;
+pub use partition::*;
+pub use rigid_disk_block::*;
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::block::Block;
+use crate::disk::*;
+use crate::errors::Error;
+
+use super::constants::*;
+use super::dos_envec::*;
+
+
+/// Formats a DOS type the way AmigaDOS displays it, e.g. `DOS\1`.
+pub fn dos_type_to_string(dos_type: u32) -> String {
+    dos_type
+        .to_be_bytes()
+        .iter()
+        .map(|b| {
+            if b.is_ascii_graphic() {
+                (*b as char).to_string()
+            } else {
+                format!("\\{}", b)
+            }
+        })
+        .collect()
+}
+
+/// A partition of a hard disk, as described by a partition block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partition {
+    pub address: LBAAddress,
+    pub name: String,
+    pub flags: u32,
+    pub dos_envec: DosEnvec,
+}
+
+impl Partition {
+    pub fn is_bootable(&self) -> bool {
+        self.flags & PART_FLAGS_BOOTABLE != 0
+    }
+
+    pub fn is_automount(&self) -> bool {
+        self.flags & PART_FLAGS_NO_MOUNT == 0
+    }
+
+    /// Returns the range of blocks of the partition on the disk.
+    /// Errors:
+    /// - When the geometry of the partition block is invalid.
+    pub fn block_range(&self) -> Result<Range<LBAAddress>, Error> {
+        self.dos_envec.block_range()
+            .map_err(|_| Error::RdbInvalidBlockError(self.address))
+    }
+
+    pub(super) fn read(
+        block: &Block,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            address: block.address,
+            name: block.read_bcpl_string(PART_DRIVE_NAME_OFFSET, PART_DRIVE_NAME_SIZE)?,
+            flags: block.read_u32(PART_FLAGS_OFFSET)?,
+            dos_envec: DosEnvec::read(block, PART_ENVIRONMENT_OFFSET)?,
+        })
+    }
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (start, len) = match self.block_range() {
+            Ok(block_range) => (block_range.start.to_string(), block_range.len().to_string()),
+            Err(_) => (String::from("?"), String::from("?")),
+        };
+
+        write!(
+            f,
+            "{:<8} {:>6} {:>6} {:>10} {:>10} {}",
+            self.name,
+            self.dos_envec.low_cyl,
+            self.dos_envec.high_cyl,
+            start,
+            len,
+            dos_type_to_string(self.dos_envec.dos_type),
+        )?;
+
+        if self.is_bootable() {
+            write!(f, " bootable")?;
+        }
+
+        Ok(())
+    }
+}
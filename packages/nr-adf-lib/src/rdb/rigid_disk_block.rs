@@ -0,0 +1,136 @@
+use crate::block::Block;
+use crate::disk::*;
+use crate::errors::Error;
+use crate::fs::AmigaDos;
+use crate::shared::*;
+
+use super::block::*;
+use super::constants::*;
+use super::filesystem::*;
+use super::partition::*;
+
+
+/// Content of the Rigid Disk Block of a hard disk, with its partitions and
+/// filesystems.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RigidDiskBlock {
+    pub address: LBAAddress,
+    pub geometry: DiskGeometry,
+    pub flags: u32,
+    pub rdb_blocks_lo: LBAAddress,
+    pub rdb_blocks_hi: LBAAddress,
+    pub lo_cylinder: usize,
+    pub hi_cylinder: usize,
+    pub cyl_blocks: usize,
+    pub disk_vendor: String,
+    pub disk_product: String,
+    pub disk_revision: String,
+    pub partitions: Vec<Partition>,
+    pub filesystems: Vec<FilesystemHeader>,
+}
+
+impl RigidDiskBlock {
+    /// Tells if the disk starts with a Rigid Disk Block.
+    pub fn is_rdb_disk(disk: Shared<Disk>) -> bool {
+        Self::find(disk).is_some()
+    }
+
+    fn find(disk: Shared<Disk>) -> Option<LBAAddress> {
+        let block_count = disk.borrow().block_count();
+
+        (0..RDB_LOCATION_LIMIT.min(block_count)).find(|address| {
+            Block::new(disk.clone(), *address)
+                .check_rdb_block(RDB_RIGID_DISK_BLOCK_ID)
+                .is_ok()
+        })
+    }
+
+    /// Reads the Rigid Disk Block of the disk, which must give access to all
+    /// the blocks of the image.
+    pub fn try_from_disk(disk: Shared<Disk>) -> Result<Self, Error> {
+        let address = Self::find(disk.clone()).ok_or(Error::RdbNotFoundError)?;
+        let block = Block::new(disk.clone(), address);
+
+        let partitions = read_rdb_list(
+            disk.clone(),
+            rdb_address(block.read_u32(RDSK_PARTITION_LIST_OFFSET)?),
+            RDB_PARTITION_BLOCK_ID,
+            Partition::read,
+        )?;
+
+        let filesystems = read_rdb_list(
+            disk.clone(),
+            rdb_address(block.read_u32(RDSK_FILESYSTEM_HEADER_LIST_OFFSET)?),
+            RDB_FILESYSTEM_HEADER_BLOCK_ID,
+            FilesystemHeader::read,
+        )?;
+
+        Ok(Self {
+            address,
+            geometry: DiskGeometry::new(
+                block.read_u32(RDSK_CYLINDERS_OFFSET)? as usize,
+                block.read_u32(RDSK_HEADS_OFFSET)? as usize,
+                block.read_u32(RDSK_SECTORS_OFFSET)? as usize,
+            ),
+            flags: block.read_u32(RDSK_FLAGS_OFFSET)?,
+            rdb_blocks_lo: block.read_u32(RDSK_RDB_BLOCKS_LO_OFFSET)? as usize,
+            rdb_blocks_hi: block.read_u32(RDSK_RDB_BLOCKS_HI_OFFSET)? as usize,
+            lo_cylinder: block.read_u32(RDSK_LO_CYLINDER_OFFSET)? as usize,
+            hi_cylinder: block.read_u32(RDSK_HI_CYLINDER_OFFSET)? as usize,
+            cyl_blocks: block.read_u32(RDSK_CYL_BLOCKS_OFFSET)? as usize,
+            disk_vendor: block.read_rdb_string(
+                RDSK_DISK_VENDOR_OFFSET,
+                RDSK_DISK_VENDOR_SIZE,
+            )?,
+            disk_product: block.read_rdb_string(
+                RDSK_DISK_PRODUCT_OFFSET,
+                RDSK_DISK_PRODUCT_SIZE,
+            )?,
+            disk_revision: block.read_rdb_string(
+                RDSK_DISK_REVISION_OFFSET,
+                RDSK_DISK_REVISION_SIZE,
+            )?,
+            partitions,
+            filesystems,
+        })
+    }
+
+    /// Finds a partition given its name, case insensitively.
+    pub fn partition(&self, name: &str) -> Option<&Partition> {
+        self.partitions
+            .iter()
+            .find(|partition| partition.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Restricts the disk to the blocks of the given partition.
+    /// Errors:
+    /// - When the geometry of the partition is invalid.
+    /// - When the filesystem blocks of the partition are not 512 bytes long.
+    pub fn select_partition(
+        &self,
+        disk: Shared<Disk>,
+        partition: &Partition,
+    ) -> Result<(), Error> {
+        let block_range = partition.block_range()?;
+        let block_size = partition.dos_envec.block_size();
+
+        if block_size != BLOCK_SIZE {
+            return Err(Error::RdbUnsupportedBlockSizeError(block_size));
+        }
+
+        let mut disk = disk.borrow_mut();
+
+        disk.select_all_blocks();
+        disk.select_blocks(block_range)
+    }
+
+    /// Opens the filesystem of the given partition.
+    pub fn open_partition(
+        &self,
+        disk: Shared<Disk>,
+        partition: &Partition,
+    ) -> Result<AmigaDos, Error> {
+        self.select_partition(disk.clone(), partition)?;
+        AmigaDos::try_from(disk)
+    }
+}
@@ -0,0 +1,289 @@
+//! Shared ownership of disks and filesystem states.
+//!
+//! Filesystem handles such as `AmigaDos`, `File` or `DirIterator` share the
+//! disk they work on. By default it is shared through `Rc<RefCell<_>>`, at
+//! no cost but only within a thread. With the `sync` feature, `Arc<RwLock<_>>`
+//! is used instead so that handles are `Send + Sync`: many readers can
+//! access a disk at once while writers are serialised.
+//!
+//! As a filesystem operation borrows its state many times, the handles of a
+//! filesystem also share a `FsLock`, held for the whole of each operation:
+//! exclusively by operations changing the filesystem, shared by the others.
+
+#[cfg(not(feature = "sync"))]
+mod imp {
+    use std::cell::{
+        Ref,
+        RefCell,
+        RefMut,
+    };
+    use std::rc::Rc;
+
+    pub type Shared<T> = Rc<SharedCell<T>>;
+
+    #[derive(Debug, Default)]
+    pub struct SharedCell<T>(RefCell<T>);
+
+    impl<T> SharedCell<T> {
+        pub fn new(value: T) -> Self {
+            Self(RefCell::new(value))
+        }
+
+        #[inline]
+        pub fn borrow(&self) -> Ref<'_, T> {
+            self.0.borrow()
+        }
+
+        #[inline]
+        pub fn borrow_mut(&self) -> RefMut<'_, T> {
+            self.0.borrow_mut()
+        }
+
+        #[inline]
+        pub fn get_mut(&mut self) -> &mut T {
+            self.0.get_mut()
+        }
+    }
+
+    /// Bounds required by types held in shared states, none by default.
+    pub trait MaybeSync {}
+
+    impl<T: ?Sized> MaybeSync for T {}
+
+    /// Filesystem operations lock, operations can't overlap within a thread
+    /// so there is nothing to lock by default.
+    #[derive(Clone, Debug, Default)]
+    pub struct FsLock;
+
+    pub struct FsLockGuard;
+
+    impl FsLock {
+        pub fn new() -> Self {
+            Self
+        }
+
+        #[inline]
+        pub fn read(&self) -> FsLockGuard {
+            FsLockGuard
+        }
+
+        #[inline]
+        pub fn write(&self) -> FsLockGuard {
+            FsLockGuard
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+mod imp {
+    use std::sync::Arc;
+    use std::thread::{
+        self,
+        ThreadId,
+    };
+
+    use parking_lot::{
+        ArcRwLockReadGuard,
+        ArcRwLockWriteGuard,
+        Mutex,
+        RawRwLock,
+        RwLock,
+        RwLockReadGuard,
+        RwLockWriteGuard,
+    };
+
+    pub type Shared<T> = Arc<SharedCell<T>>;
+
+    #[derive(Debug, Default)]
+    pub struct SharedCell<T>(RwLock<T>);
+
+    impl<T> SharedCell<T> {
+        pub fn new(value: T) -> Self {
+            Self(RwLock::new(value))
+        }
+
+        // read locks are taken recursively by nested reads, which must not
+        // wait behind a pending writer
+        #[inline]
+        pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+            self.0.read_recursive()
+        }
+
+        #[inline]
+        pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write()
+        }
+
+        #[inline]
+        pub fn get_mut(&mut self) -> &mut T {
+            self.0.get_mut()
+        }
+    }
+
+    /// Bounds required by types held in shared states, to be sent and
+    /// shared across threads.
+    pub trait MaybeSync: Send + Sync {}
+
+    impl<T: ?Sized + Send + Sync> MaybeSync for T {}
+
+    #[derive(Debug, Default)]
+    struct FsLockState {
+        lock: Arc<RwLock<()>>,
+        // thread holding the lock exclusively, whose nested operations must
+        // not wait for themselves
+        writer: Mutex<Option<ThreadId>>,
+    }
+
+    /// Filesystem operations lock, shared by all the handles of a
+    /// filesystem.
+    /// The lock is reentrant: operations nested into an operation of the same
+    /// thread don't take it again.
+    #[derive(Clone, Debug, Default)]
+    pub struct FsLock(Arc<FsLockState>);
+
+    pub struct FsLockGuard {
+        state: Arc<FsLockState>,
+        _read_guard: Option<ArcRwLockReadGuard<RawRwLock, ()>>,
+        write_guard: Option<ArcRwLockWriteGuard<RawRwLock, ()>>,
+    }
+
+    impl FsLock {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        fn is_written_by_current_thread(&self) -> bool {
+            *self.0.writer.lock() == Some(thread::current().id())
+        }
+
+        fn guard(
+            &self,
+            read_guard: Option<ArcRwLockReadGuard<RawRwLock, ()>>,
+            write_guard: Option<ArcRwLockWriteGuard<RawRwLock, ()>>,
+        ) -> FsLockGuard {
+            FsLockGuard {
+                state: self.0.clone(),
+                _read_guard: read_guard,
+                write_guard,
+            }
+        }
+
+        /// Locks the filesystem for an operation reading it.
+        pub fn read(&self) -> FsLockGuard {
+            if self.is_written_by_current_thread() {
+                return self.guard(None, None);
+            }
+
+            self.guard(Some(self.0.lock.read_arc_recursive()), None)
+        }
+
+        /// Locks the filesystem for an operation changing it.
+        pub fn write(&self) -> FsLockGuard {
+            if self.is_written_by_current_thread() {
+                return self.guard(None, None);
+            }
+
+            let write_guard = self.0.lock.write_arc();
+
+            *self.0.writer.lock() = Some(thread::current().id());
+
+            self.guard(None, Some(write_guard))
+        }
+    }
+
+    impl Drop for FsLockGuard {
+        fn drop(&mut self) {
+            // the writer is cleared before the lock is released
+            if self.write_guard.is_some() {
+                *self.state.writer.lock() = None;
+            }
+        }
+    }
+}
+
+pub use imp::*;
+
+/// Wraps a value in a shared state.
+pub fn shared<T>(value: T) -> Shared<T> {
+    Shared::new(SharedCell::new(value))
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use std::thread;
+
+    use crate::disk::*;
+    use crate::fs::*;
+
+    use super::*;
+
+    fn is_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn handles_are_send_sync() {
+        is_send_sync::<Shared<Disk>>();
+        is_send_sync::<AmigaDos>();
+        is_send_sync::<File>();
+        is_send_sync::<DirIterator>();
+    }
+
+    #[test]
+    fn concurrent_reads_are_ok() {
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let mut fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        fs.create_dir("dir").unwrap();
+        fs.write("dir/file", b"hello").unwrap();
+
+        let fs = std::sync::Arc::new(fs);
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                let fs = fs.clone();
+
+                s.spawn(move || {
+                    assert_eq!(fs.read("dir/file").unwrap(), b"hello");
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn concurrent_writes_are_ok() {
+        const THREAD_COUNT: usize = 8;
+        const FILE_COUNT: usize = 40;
+
+        let disk = Disk::create(DiskType::DoubleDensity);
+        let fs = AmigaDosFormater::default()
+            .format(shared(disk), "TEST")
+            .unwrap();
+
+        let fs = std::sync::Arc::new(fs);
+        let barrier = std::sync::Barrier::new(THREAD_COUNT);
+        let names = |i| (0..FILE_COUNT).map(move |j| format!("file{i}_{j}"));
+
+        thread::scope(|s| {
+            for i in 0..THREAD_COUNT {
+                let fs = fs.clone();
+                let barrier = &barrier;
+
+                s.spawn(move || {
+                    barrier.wait();
+
+                    for name in names(i) {
+                        fs.write(&name, &name).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(fs.read_dir("/").unwrap().count(), THREAD_COUNT*FILE_COUNT);
+        assert!(fs.check().unwrap().is_empty());
+
+        for name in (0..THREAD_COUNT).flat_map(names) {
+            assert_eq!(fs.read(&name).unwrap(), name.as_bytes());
+        }
+    }
+}
@@ -1,4 +1,33 @@
-use nr_adf_lib::disk::DiskType;
+use std::fs;
+use std::io::{
+    ErrorKind,
+    Read,
+};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::disk::{
+    BLOCK_SIZE,
+    Disk,
+    DiskGeometry,
+    DiskType,
+};
+use nr_adf_lib::errors::Error;
+use nr_adf_lib::image::ImageFormat;
+use nr_adf_lib::rdb::{
+    Partition,
+    PartitionLayout,
+    RigidDiskBlock,
+};
+use nr_adf_lib::shared::{
+    shared,
+    Shared,
+};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
 pub enum ArgDiskType {
@@ -14,3 +43,146 @@ impl From<ArgDiskType> for DiskType {
         }
     }
 }
+
+/// Guess an image format from a file path extension.
+pub fn image_format_from_path(path: &Path) -> ImageFormat {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| ext.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Parse a disk geometry given as "<CYLINDERS>,<HEADS>,<SECTORS>".
+pub fn parse_geometry(s: &str) -> Result<DiskGeometry> {
+    let v = s
+        .split(',')
+        .map(|v| v.trim().parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match v.as_slice() {
+        [cylinders, heads, sectors] => {
+            Ok(DiskGeometry::new(*cylinders, *heads, *sectors))
+        },
+        _ => Err(anyhow!("expected <CYLINDERS>,<HEADS>,<SECTORS>")),
+    }
+}
+
+/// A partition given as "<NAME>[:<CYLINDERS>]", the last partition taking
+/// all the remaining cylinders when its size is omitted.
+#[derive(Clone)]
+pub struct ArgPartitionLayout(pub PartitionLayout);
+
+impl FromStr for ArgPartitionLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, cylinder_count) = match s.split_once(':') {
+            Some((name, cylinder_count)) => (name, Some(cylinder_count.parse()?)),
+            None => (s, None),
+        };
+
+        Ok(Self(PartitionLayout::new(name, cylinder_count)))
+    }
+}
+
+fn find_partition<'a>(
+    rdb: &'a RigidDiskBlock,
+    partition: &str,
+) -> Result<&'a Partition> {
+    rdb.partition(partition)
+        .or_else(|| {
+            partition
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| rdb.partitions.get(index))
+        })
+        .ok_or_else(|| anyhow!("partition '{}' not found", partition))
+}
+
+/// Restrict a disk to one of its partitions, given by name or index, if it
+/// is a hard disk image. Without partition, the first one is selected.
+pub fn select_partition(
+    disk: Shared<Disk>,
+    partition: Option<&str>,
+) -> Result<()> {
+    match RigidDiskBlock::try_from_disk(disk.clone()) {
+        Ok(rdb) => {
+            let partition = match partition {
+                Some(partition) => find_partition(&rdb, partition)?,
+                None => rdb.partitions.first().ok_or(anyhow!("no partition found"))?,
+            };
+
+            rdb.select_partition(disk, partition)?;
+            Ok(())
+        },
+        Err(Error::RdbNotFoundError) if partition.is_none() => Ok(()),
+        Err(Error::RdbNotFoundError) => Err(anyhow!("not a partitioned disk")),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Read an Amiga disk file, see `select_partition` for hard disk images.
+/// Raw images are read lazily from the file, compressed ones are
+/// decompressed in memory. Raw images which can't be written are opened
+/// read only, `write_disk` failing then.
+pub fn read_disk(
+    path: &Path,
+    partition: Option<&str>,
+) -> Result<Shared<Disk>> {
+    let mut header = Vec::new();
+
+    fs::File::open(path)?.take(BLOCK_SIZE as u64).read_to_end(&mut header)?;
+
+    let disk = match ImageFormat::detect(&header) {
+        ImageFormat::ADF => match Disk::open(path) {
+            Err(Error::DiskIoError(ErrorKind::PermissionDenied)) => Disk::open_read_only(path)?,
+            disk => disk?,
+        },
+        _ => Disk::try_create_with_data(fs::read(path)?)?,
+    };
+    let disk = shared(disk);
+
+    select_partition(disk.clone(), partition)?;
+
+    Ok(disk)
+}
+
+/// Save an Amiga disk read by `read_disk`, writing back only the modified
+/// blocks of a raw image.
+pub fn write_disk(
+    path: &Path,
+    disk: &Shared<Disk>,
+) -> Result<()> {
+    let mut disk = disk.borrow_mut();
+
+    if disk.is_file_backed() {
+        disk.flush()?;
+    } else {
+        fs::write(path, disk.encode()?)?;
+    }
+    Ok(())
+}
+
+/// Print data in hexadecimal and ASCII, 16 bytes per line, each line
+/// starting with the offset of its first byte, `offset` being the offset of
+/// the data on the disk.
+pub fn print_hex_dump(data: &[u8], offset: usize) {
+    for (index, line) in data.chunks(16).enumerate() {
+        let hex = line.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = line.iter()
+            .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' })
+            .collect::<String>();
+
+        println!("{:08x}  {:<47}  |{}|", offset + 16*index, hex, ascii);
+    }
+}
+
+/// Format the boot signature of a disk, non printable bytes being escaped.
+pub fn boot_signature_to_str(signature: &[u8]) -> String {
+    signature.iter()
+        .map(|b| std::ascii::escape_default(*b).to_string())
+        .collect()
+}
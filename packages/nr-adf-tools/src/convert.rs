@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    image_format_from_path,
+    read_disk,
+};
+
+
+/******************************************************************************
+ * Convert command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an input Amiga disk file (ADF, ADZ or DMS)
+    input_filepath: PathBuf,
+
+    /// Path to the output Amiga disk file
+    output_filepath: PathBuf,
+
+    /// Extract a partition of a hard disk image, by name or index
+    #[arg(long)]
+    partition: Option<String>,
+
+    /// Specify the output image format ["adf", "adz", "ext-adf"], guessed
+    /// from the output file extension by default
+    #[arg(long, short = 'i')]
+    image_format: Option<ImageFormat>,
+
+    /// Overwrite existing file
+    #[arg(long, short)]
+    force_overwrite: bool,
+}
+
+/******************************************************************************
+ * Convert command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    if args.output_filepath.exists() && !args.force_overwrite {
+        return Err(anyhow!("output file already exists!"));
+    }
+
+    let mut disk = if let Some(partition) = args.partition.as_deref() {
+        let disk = read_disk(&args.input_filepath, Some(partition))?;
+        let disk_data = disk.borrow().read_blocks()?;
+
+        Disk::try_create_with_data(disk_data)?
+    } else {
+        Disk::try_create_with_data(fs::read(&args.input_filepath)?)?
+    };
+
+    disk.set_image_format(args.image_format.unwrap_or_else(|| {
+        image_format_from_path(&args.output_filepath)
+    }));
+
+    fs::write(&args.output_filepath, disk.encode()?)?;
+
+    Ok(())
+}
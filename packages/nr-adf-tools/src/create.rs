@@ -6,9 +6,21 @@ use anyhow::{
     Result,
 };
 
-use nr_adf_lib::disk::Disk;
+use nr_adf_lib::disk::{
+    Disk,
+    DiskGeometry,
+    DiskType,
+};
+use nr_adf_lib::image::ImageFormat;
+use nr_adf_lib::rdb::RigidDiskBlockInitializer;
+use nr_adf_lib::shared::shared;
 
-use crate::cli_common::ArgDiskType;
+use crate::cli_common::{
+    ArgDiskType,
+    ArgPartitionLayout,
+    image_format_from_path,
+    parse_geometry,
+};
 
 /******************************************************************************
  * Create command run
@@ -22,18 +34,62 @@ pub struct Args {
     #[arg(long, short = 'F', default_value = "dd")]
     pub floppy_disk_type: ArgDiskType,
 
+    /// Specify a custom block count, to create a hard disk image
+    #[arg(long, short = 'b', conflicts_with = "floppy_disk_type")]
+    pub block_count: Option<usize>,
+
+    /// Specify a hard disk geometry as "<CYLINDERS>,<HEADS>,<SECTORS>"
+    #[arg(
+        long,
+        short = 'g',
+        value_parser = parse_geometry,
+        conflicts_with_all = ["floppy_disk_type", "block_count"],
+    )]
+    pub geometry: Option<DiskGeometry>,
+
+    /// Add a partition given as "<NAME>[:<CYLINDERS>]" and write a Rigid
+    /// Disk Block, can be repeated
+    #[arg(long, short = 'p')]
+    pub partition: Vec<ArgPartitionLayout>,
+
+    /// Specify the image format ["adf", "adz", "ext-adf"], guessed from the
+    /// output file extension by default
+    #[arg(long, short = 'i')]
+    pub image_format: Option<ImageFormat>,
+
     /// Overwrite existing file
     #[arg(long, short, default_value = "false")]
     pub force_overwrite: bool,
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk = Disk::create(args.floppy_disk_type.into());
-
     if args.output_file_path.exists() && !args.force_overwrite {
-        Err(anyhow!("output file already exists!"))
-    } else {
-        fs::write(&args.output_file_path, disk.data())?;
-        Ok(())
+        return Err(anyhow!("output file already exists!"));
     }
+
+    let disk_type = match (args.geometry, args.block_count) {
+        (Some(geometry), _) => geometry.into(),
+        (_, Some(block_count)) => DiskType::from_block_count(block_count),
+        _ => args.floppy_disk_type.into(),
+    };
+
+    let disk = shared(Disk::create(disk_type));
+
+    disk.borrow_mut().set_image_format(args.image_format.unwrap_or_else(|| {
+        image_format_from_path(&args.output_file_path)
+    }));
+
+    if !args.partition.is_empty() {
+        let mut initializer = RigidDiskBlockInitializer::default();
+
+        initializer.with_geometry(args.geometry);
+        for partition in args.partition.iter() {
+            initializer.with_partition(&partition.0);
+        }
+        initializer.init(disk.clone())?;
+    }
+
+    fs::write(&args.output_file_path, disk.borrow().encode()?)?;
+
+    Ok(())
 }
@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    print_hex_dump,
+    read_disk,
+};
+
+
+/******************************************************************************
+ * Dump track command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file, DOS or not
+    amiga_disk_filepath: PathBuf,
+
+    /// Track number, i.e. <CYLINDER>*2 + <HEAD> for a floppy disk
+    track: usize,
+
+    /// Dump the track encoded in AmigaDOS MFM, as read by the drive
+    #[arg(short, long)]
+    mfm: bool,
+
+    /// Save the raw track to a host file instead of printing it
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/******************************************************************************
+ * Dump track command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, None)?;
+    let disk = disk.borrow();
+    let data = if args.mfm {
+        disk.read_mfm_track(args.track)?
+    } else {
+        disk.read_track(args.track)?
+    };
+
+    if let Some(path) = args.output.as_ref() {
+        fs::write(path, data)?;
+    } else if args.mfm {
+        print_hex_dump(&data, 0);
+    } else {
+        print_hex_dump(&data, disk.track_blocks(args.track)?.start*BLOCK_SIZE);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Filenote command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Path to a file or a directory into the Amiga filesystem
+    amiga_input_file: PathBuf,
+
+    /// Comment to attach to the file, of at most 79 characters; the comment
+    /// is removed when omitted
+    comment: Option<String>,
+}
+
+/******************************************************************************
+ * Filenote command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
+
+    let comment = args.comment.as_deref().filter(|comment| !comment.is_empty());
+
+    fs.set_comment(&args.amiga_input_file, comment)?;
+
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}
@@ -1,12 +1,14 @@
-use std::cell::RefCell;
-use std::fs;
 use std::path::PathBuf;
-use std::rc::Rc;
 
 use anyhow::Result;
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
 
 /******************************************************************************
  * Format command run
@@ -16,6 +18,10 @@ pub struct Args {
     /// Disk file
     disk_file_path: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Volune name
     volume_name: String,
 
@@ -27,23 +33,43 @@ pub struct Args {
     #[arg(short = 'i', long = "international-mode", default_value = "off")]
     international_mode: InternationalMode,
 
+    /// Enable/Disable long file names ["on", "off"]
+    #[arg(short = 'l', long = "long-name-mode", default_value = "off")]
+    long_name_mode: LongNameMode,
+
     /// Specify the file system type
     #[arg(short = 't', long, default_value = "ofs")]
     filesystem_type: FilesystemType,
+
+    /// Specify the DOS type, from "DOS0" to "DOS7", instead of the file system
+    /// type and the modes
+    #[arg(short = 'd', long, conflicts_with_all = [
+        "cache_mode",
+        "international_mode",
+        "long_name_mode",
+        "filesystem_type",
+    ])]
+    dos_type: Option<DosType>,
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk = Disk::try_create_with_data(fs::read(&args.disk_file_path)?)?;
-
-    AmigaDosFormater::default()
-        .with_cache_mode(args.cache_mode)
-        .with_international_mode(args.international_mode)
-        .with_filesystem_type(args.filesystem_type)
-        .format(
-            Rc::new(RefCell::new(disk)),
-            &args.volume_name,
-        )?
-        .dump(&args.disk_file_path)?;
+    let disk = read_disk(&args.disk_file_path, args.partition.as_deref())?;
+
+    let mut formater = AmigaDosFormater::default();
+
+    if let Some(dos_type) = args.dos_type {
+        formater.with_dos_type(dos_type);
+    } else {
+        formater
+            .with_cache_mode(args.cache_mode)
+            .with_international_mode(args.international_mode)
+            .with_long_name_mode(args.long_name_mode)
+            .with_filesystem_type(args.filesystem_type);
+    }
+
+    formater.format(disk.clone(), &args.volume_name)?;
+
+    write_disk(&args.disk_file_path, &disk)?;
 
     Ok(())
 }
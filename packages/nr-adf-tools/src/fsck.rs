@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Fsck command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Repair the filesystem
+    #[arg(short, long)]
+    repair: bool,
+
+    /// Discard unreachable entries and lost data instead of saving them in
+    /// the 'lost+found' directory
+    #[arg(long, requires = "repair")]
+    no_lost_and_found: bool,
+}
+
+/******************************************************************************
+ * Fsck command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
+
+    if args.repair {
+        let mut options = RepairOptions::default();
+
+        if args.no_lost_and_found {
+            options.with_lost_and_found(None);
+        }
+
+        for action in fs.repair(&options)? {
+            println!("{}", action);
+        }
+
+        write_disk(&args.amiga_disk_filepath, &disk)?;
+    }
+
+    let findings = fs.check()?;
+
+    for finding in findings.iter() {
+        println!("{}", finding);
+    }
+
+    let error_count = findings
+        .iter()
+        .filter(|finding| finding.severity == CheckSeverity::Error)
+        .count();
+
+    if error_count > 0 {
+        Err(anyhow!("{} error(s) found", error_count))
+    } else {
+        Ok(())
+    }
+}
@@ -1,14 +1,21 @@
-use std::cell::RefCell;
 use std::fs;
 use std::path;
-use std::rc::Rc;
 use std::time::SystemTime;
 
 use anyhow::Result;
 
 use chrono::prelude::*;
 
+use nr_adf_lib::ext_adf::ExtendedAdf;
+use nr_adf_lib::flux::TrackReport;
+use nr_adf_lib::mfm::SectorStatus;
 use nr_adf_lib::prelude::*;
+use nr_adf_lib::rdb::RigidDiskBlock;
+
+use crate::cli_common::{
+    boot_signature_to_str,
+    select_partition,
+};
 
 
 /******************************************************************************
@@ -18,6 +25,10 @@ use nr_adf_lib::prelude::*;
 pub struct Args {
     /// Path to an Amiga disk file
     pub amiga_disk_filepath: path::PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
 }
 
 pub fn system_time_to_str(
@@ -26,19 +37,100 @@ pub fn system_time_to_str(
     DateTime::<Utc>::from(st).to_rfc2822()
 }
 
+fn print_partitions(rdb: &RigidDiskBlock) {
+    println!("Geometry: {} cylinders, {} heads, {} sectors",
+        rdb.geometry.cylinders,
+        rdb.geometry.heads,
+        rdb.geometry.sectors,
+    );
+    println!();
+    println!("{:<8} {:>6} {:>6} {:>10} {:>10} Type", "Name", "Low", "High", "Start", "Blocks");
+
+    for partition in rdb.partitions.iter() {
+        println!("{}", partition);
+    }
+
+    println!();
+}
+
+fn print_tracks(extended_adf: &ExtendedAdf) {
+    let tracks = extended_adf.tracks();
+
+    println!("Tracks: {}, {} raw",
+        tracks.len(),
+        tracks.iter().filter(|track| track.is_raw()).count(),
+    );
+
+    for (number, track) in tracks.iter().enumerate().filter(|(_, track)| !track.is_ok()) {
+        let errors = track.sectors()
+            .iter()
+            .enumerate()
+            .filter(|(_, status)| **status != SectorStatus::Ok)
+            .map(|(sector, status)| format!("sector {sector} {status}"))
+            .collect::<Vec<_>>();
+
+        println!("Track {:>3}: {}", number, errors.join(", "));
+    }
+
+    println!();
+}
+
+fn print_track_reports(reports: &[TrackReport]) {
+    println!("Tracks: {}, {} ok",
+        reports.len(),
+        reports.iter().filter(|report| report.is_ok()).count(),
+    );
+
+    for report in reports.iter().filter(|report| !report.is_ok()) {
+        println!("{}", report);
+    }
+
+    println!();
+}
+
 pub fn run(args: &Args) -> Result<()> {
     let disk_data = fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-
-    let fs: AmigaDos = Rc::new(RefCell::new(disk)).try_into()?;
+    let disk = shared(Disk::try_create_with_data(disk_data)?);
+
+    if let Some(extended_adf) = disk.borrow().extended_adf() {
+        print_tracks(extended_adf);
+    }
+
+    if let Some(reports) = disk.borrow().track_reports() {
+        print_track_reports(reports);
+    }
+
+    if let Ok(rdb) = RigidDiskBlock::try_from_disk(disk.clone()) {
+        print_partitions(&rdb);
+    }
+
+    select_partition(disk.clone(), args.partition.as_deref())?;
+
+    let fs = match AmigaDos::try_from(disk.clone()) {
+        Ok(fs) => fs,
+        Err(Error::NotADosDisk(signature)) => {
+            println!("Not a DOS disk, boot signature: \"{}\"",
+                boot_signature_to_str(&signature),
+            );
+            println!("Total: {:>8} {:>10}",
+                disk.borrow().block_count(),
+                disk.borrow().size(),
+            );
+            return Ok(());
+        },
+        Err(err) => return Err(err.into()),
+    };
     let fs_info = fs.info()?;
 
     println!("Volume name: {}", fs_info.volume_name);
-    println!("Volume type: {}, {}, {}",
+    println!("Volume type: {} ({}, {}, {}, {})",
+        fs_info.dos_type,
         fs_info.filesystem_type,
         fs_info.international_mode,
         fs_info.cache_mode,
+        fs_info.long_name_mode,
     );
+    println!("Boot code: {}", identify_boot_code(&fs.boot_code()?));
 
     println!();
 
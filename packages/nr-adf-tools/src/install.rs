@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Install command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Install the standard boot code of the given Workbench version ["1.3",
+    /// "2.0"] instead of the one matching the DOS type of the disk
+    #[arg(short = 'k', long, conflicts_with = "boot_code")]
+    kickstart: Option<StandardBootCode>,
+
+    /// Install a custom boot code read from a host file of at most 1012 bytes
+    #[arg(short = 'b', long)]
+    boot_code: Option<PathBuf>,
+
+    /// Save the current boot code to a host file instead of installing one
+    #[arg(short = 'x', long, conflicts_with_all = ["kickstart", "boot_code"])]
+    extract: Option<PathBuf>,
+}
+
+/******************************************************************************
+ * Install command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
+
+    if let Some(path) = args.extract.as_ref() {
+        let boot_code = fs.boot_code()?;
+
+        fs::write(path, boot_code)?;
+        println!("{}", identify_boot_code(&boot_code));
+        return Ok(());
+    }
+
+    if let Some(path) = args.boot_code.as_ref() {
+        fs.install_boot_code(&fs::read(path)?)?;
+    } else if let Some(kickstart) = args.kickstart {
+        fs.install_boot_code(&kickstart.boot_code())?;
+    } else {
+        fs.install_standard_boot_code()?;
+    }
+
+    println!("{}", identify_boot_code(&fs.boot_code()?));
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Link command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Target of the link
+    target: PathBuf,
+
+    /// Path to the link into the Amiga filesystem
+    link: PathBuf,
+
+    /// Create a soft link instead of a hard link
+    #[arg(short, long)]
+    symbolic: bool,
+}
+
+/******************************************************************************
+ * Link command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
+
+    if args.symbolic {
+        fs.symlink(&args.target, &args.link)?;
+    } else {
+        fs.hard_link(&args.target, &args.link)?;
+    }
+
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}
@@ -1,15 +1,14 @@
-use std::cell::RefCell;
-use std::fs;
 use std::path::{
     Path,
     PathBuf,
 };
-use std::rc::Rc;
 
 use anyhow::{anyhow, Result};
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::read_disk;
+
 /******************************************************************************
  * List command options
  *****************************************************************************/
@@ -18,12 +17,20 @@ pub struct Args {
     /// Path to an Amiga disk file
     amiga_disk_filepath: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Path to a file into the Amiga filesystem
     amiga_input_filepath: Option<PathBuf> ,
 
     /// Recursively list subdirectories encountered
     #[arg(short = 'r', long = "recurse")]
-    recursive: bool
+    recursive: bool,
+
+    /// Print the comment of each file on the following line
+    #[arg(short = 'c', long)]
+    comments: bool,
 }
 
 /******************************************************************************
@@ -38,10 +45,11 @@ fn list_directory(args: &Args, fs: &AmigaDos, path: &Path) -> Result<()> {
     for entry in fs.read_dir(path)? {
         match entry {
             Ok(entry) => {
-                if FileType::Dir == entry.file_type() {
+                // hard links may point to a parent directory
+                if FileType::Dir == entry.file_type() && !entry.metadata().is_hard_link() {
                     dirs.push(entry.path().into());
                 }
-                list_file(&entry.metadata())?;
+                list_file(args, fs, entry.path(), &entry.metadata())?;
             },
             Err(err) => {
                 return Err(anyhow!("{}", err));
@@ -59,15 +67,27 @@ fn list_directory(args: &Args, fs: &AmigaDos, path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn list_file(metadata: &Metadata) -> Result<()> {
-    println!("{}", metadata);
+fn list_file(
+    args: &Args,
+    fs: &AmigaDos,
+    path: &Path,
+    metadata: &Metadata,
+) -> Result<()> {
+    if metadata.is_symlink() {
+        println!("{} -> {}", metadata, fs.read_link(path)?.display());
+    } else {
+        println!("{}", metadata);
+    }
+
+    if let Some(comment) = metadata.comment().filter(|_| args.comments) {
+        println!(": {}", comment);
+    }
     Ok(())
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk_data = fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-    let fs = AmigaDos::try_from(Rc::new(RefCell::new(disk)))?;
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let fs = AmigaDos::try_from(disk)?;
 
     let path = if let Some(path) = args.amiga_input_filepath.as_ref() {
         path.clone()
@@ -75,16 +95,15 @@ pub fn run(args: &Args) -> Result<()> {
         PathBuf::from("/")
     };
 
-    let metadata = fs.metadata(&path)?;
+    let metadata = fs.symlink_metadata(&path)?;
 
     match metadata.file_type() {
-        FileType::File => {
-            list_file(&metadata)?;
+        FileType::File | FileType::Link => {
+            list_file(args, &fs, &path, &metadata)?;
         },
         FileType::Dir => {
             list_directory(args, &fs, &path)?;
         }
-        _ => {},
     }
     Ok(())
 }
@@ -1,43 +1,93 @@
 mod cli_common;
+mod sidecar;
 
 mod cat;
+mod convert;
 mod create;
+mod dump_track;
+mod filenote;
 mod format;
+mod fsck;
 mod info;
+mod install;
+mod ln;
 mod ls;
 mod mkdir;
+#[cfg(feature = "fuse")]
+mod mount;
+mod mv;
+mod pack;
+mod protect;
 mod read;
+mod read_blocks;
 mod rm;
+mod scan;
 mod touch;
+mod unpack;
 mod write;
+mod write_blocks;
 
 
 use clap::{Parser, Subcommand};
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Convert an Amiga disk file to another image format
+    Convert(convert::Args),
     /// Create a new Amiga disk file
     Create(create::Args),
+    /// Dump a track of a given Amiga disk file, DOS or not
+    DumpTrack(dump_track::Args),
+    /// Set or remove the comment of a file or a directory into a given
+    /// Amiga disk file
+    Filenote(filenote::Args),
     /// Format a given Amiga disk file
     Format(format::Args),
+    /// Check the consistency of a given Amiga disk file
+    Fsck(fsck::Args),
     /// Get info about a given Amiga disk file
     Info(info::Args),
+    /// Install or extract the boot code of a given Amiga disk file
+    Install(install::Args),
     /// Cat a file from a given Amiga disk file
     Cat(cat::Args),
+    /// Make links between files into a given Amiga disk file
+    #[command(visible_alias="ln")]
+    Link(ln::Args),
     /// List files from a given Amiga disk file
     #[command(visible_alias="ls")]
     List(ls::Args),
     /// Creates directories named as operands, in the order specified
     Mkdir(mkdir::Args),
+    /// Mount a given Amiga disk file through FUSE, until unmounted
+    #[cfg(feature = "fuse")]
+    Mount(mount::Args),
+    /// Rename or move a file or a directory into a given Amiga disk file
+    #[command(visible_alias="mv")]
+    Move(mv::Args),
+    /// Pack a host directory tree into an Amiga disk file, creating it if
+    /// needed
+    Pack(pack::Args),
+    /// Change the protection flags of a file or a directory into a given
+    /// Amiga disk file
+    Protect(protect::Args),
     /// Read a file from a given Amiga disk file
     Read(read::Args),
+    /// Read raw blocks from a given Amiga disk file, DOS or not
+    ReadBlocks(read_blocks::Args),
     /// Remove a file or a directory from a given Amiga disk file
     #[command(visible_alias="rm")]
     Remove(rm::Args),
+    /// Scan the boot code of Amiga disk files for viruses
+    Scan(scan::Args),
     /// Change file modification times
     Touch(touch::Args),
+    /// Unpack the whole tree of an Amiga disk file into a host directory
+    Unpack(unpack::Args),
     /// Write a file to a given Amiga disk location
     Write(write::Args),
+    /// Write raw blocks to a given Amiga disk file, DOS or not
+    WriteBlocks(write_blocks::Args),
 }
 
 #[derive(Parser)]
@@ -52,16 +102,31 @@ fn main() {
     let args = Args::parse();
 
     let res = match &args.command {
+        Commands::Convert(args) => convert::run(args),
         Commands::Create(args) => create::run(args),
+        Commands::DumpTrack(args) => dump_track::run(args),
+        Commands::Filenote(args) => filenote::run(args),
         Commands::Format(args) => format::run(args),
+        Commands::Fsck(args) => fsck::run(args),
         Commands::Info(args) => info::run(args),
+        Commands::Install(args) => install::run(args),
         Commands::Cat(args) => cat::run(args),
+        Commands::Link(args) => ln::run(args),
         Commands::List(args) => ls::run(args),
         Commands::Mkdir(args) => mkdir::run(args),
+        #[cfg(feature = "fuse")]
+        Commands::Mount(args) => mount::run(args),
+        Commands::Move(args) => mv::run(args),
+        Commands::Pack(args) => pack::run(args),
+        Commands::Protect(args) => protect::run(args),
         Commands::Read(args) => read::run(args),
+        Commands::ReadBlocks(args) => read_blocks::run(args),
         Commands::Remove(args) => rm::run(args),
+        Commands::Scan(args) => scan::run(args),
         Commands::Touch(args) => touch::run(args),
+        Commands::Unpack(args) => unpack::run(args),
         Commands::Write(args) => write::run(args),
+        Commands::WriteBlocks(args) => write_blocks::run(args),
     };
 
     if let Err(err) = res {
@@ -1,12 +1,14 @@
-use std::cell::RefCell;
-use std::fs;
 use std::path::PathBuf;
-use std::rc::Rc;
 
 use anyhow::Result;
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
 
 /******************************************************************************
  * Format command run
@@ -16,6 +18,10 @@ pub struct Args {
     /// Path to an Amiga disk file
     amiga_disk_filepath: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Path to a directory into the Amiga filesystem
     amiga_directory_filepath: PathBuf,
 
@@ -25,9 +31,8 @@ pub struct Args {
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk_data = fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-    let mut fs = AmigaDos::try_from(Rc::new(RefCell::new(disk)))?;
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
 
     if args.parent {
         fs.create_dir_all(&args.amiga_directory_filepath)?;
@@ -35,7 +40,7 @@ pub fn run(args: &Args) -> Result<()> {
         fs.create_dir(&args.amiga_directory_filepath)?;
     }
 
-    fs.dump(&args.amiga_disk_filepath)?;
+    write_disk(&args.amiga_disk_filepath, &disk)?;
 
     Ok(())
 }
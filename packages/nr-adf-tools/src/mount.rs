@@ -0,0 +1,966 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{
+    Read,
+    SeekFrom,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+use anyhow::Result;
+
+use fuser::{
+    FileAttr,
+    FileType as FuseFileType,
+    Filesystem,
+    FUSE_ROOT_ID,
+    MountOption,
+    ReplyAttr,
+    ReplyCreate,
+    ReplyData,
+    ReplyDirectory,
+    ReplyEmpty,
+    ReplyEntry,
+    ReplyOpen,
+    ReplyStatfs,
+    ReplyWrite,
+    Request,
+    TimeOrNow,
+};
+
+use libc::{
+    c_int,
+    EBADF,
+    EBUSY,
+    EEXIST,
+    EINVAL,
+    EIO,
+    EISDIR,
+    ELOOP,
+    ENAMETOOLONG,
+    ENOENT,
+    ENOSPC,
+    ENOTDIR,
+    ENOTEMPTY,
+    EROFS,
+    O_ACCMODE,
+    O_RDONLY,
+    O_RDWR,
+    O_TRUNC,
+    O_WRONLY,
+    RENAME_EXCHANGE,
+    RENAME_NOREPLACE,
+};
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+// how long the kernel may cache entries and attributes
+const TTL: Duration = Duration::from_secs(1);
+
+fn errno(err: Error) -> c_int {
+    match err {
+        Error::NotFoundError => ENOENT,
+        Error::AlreadyExists => EEXIST,
+        Error::NotADirectoryError => ENOTDIR,
+        Error::NotAFileError => EISDIR,
+        Error::NotEmptyError => ENOTEMPTY,
+        Error::NoSpaceLeft => ENOSPC,
+        Error::SoftLinkLoopError => ELOOP,
+        Error::InvalidNameLengthError(_) => ENAMETOOLONG,
+        Error::BadFileDescriptor |
+        Error::InvalidFileModeError => EBADF,
+        Error::NotASoftLinkError |
+        Error::InvalidNameError |
+        Error::InvalidPathError |
+        Error::InvalidRenameError |
+        Error::InvalidSeekPositionError => EINVAL,
+        _ => EIO,
+    }
+}
+
+fn io_errno(err: std::io::Error) -> c_int {
+    match err.into_inner().map(|err| err.downcast::<Error>()) {
+        Some(Ok(err)) => errno(*err),
+        _ => EIO,
+    }
+}
+
+/******************************************************************************
+ * Attributes
+ *****************************************************************************/
+// owner, group and other RWE bits, and their Unix counterpart
+const MODE_BITS: [(u32, u32); 9] = [
+    (0o400, 0x0000_0008),
+    (0o200, 0x0000_0004),
+    (0o100, 0x0000_0002),
+    (0o040, 0x0000_0800),
+    (0o020, 0x0000_0400),
+    (0o010, 0x0000_0200),
+    (0o004, 0x0000_8000),
+    (0o002, 0x0000_4000),
+    (0o001, 0x0000_2000),
+];
+
+// the owner RWED bits are set when the access is denied
+const OWNER_RWED_MASK: u32 = 0x0000_000f;
+
+/// Turns protection bits into a Unix mode, directories being searchable
+/// whenever they are readable.
+fn mode_from_permissions(permissions: Permissions, is_dir: bool) -> u16 {
+    let bits = permissions.bits() ^ OWNER_RWED_MASK;
+    let mut mode = MODE_BITS.iter()
+        .filter(|(_, bit)| bits & bit != 0)
+        .fold(0, |mode, (mode_bit, _)| mode | mode_bit);
+
+    if is_dir {
+        mode |= (mode & 0o444) >> 2;
+    }
+    mode as u16
+}
+
+/// Applies a Unix mode to protection bits, the HSPA and delete flags being
+/// left untouched.
+fn permissions_from_mode(permissions: Permissions, mode: u32) -> Permissions {
+    let mask = MODE_BITS.iter().fold(0, |mask, (_, bit)| mask | bit);
+    let bits = MODE_BITS.iter()
+        .filter(|(mode_bit, _)| mode & mode_bit != 0)
+        .fold((permissions.bits() ^ OWNER_RWED_MASK) & !mask, |bits, (_, bit)| bits | bit);
+
+    Permissions::from_bits(bits ^ OWNER_RWED_MASK)
+}
+
+fn fuse_file_type(file_type: FileType) -> FuseFileType {
+    match file_type {
+        FileType::Dir => FuseFileType::Directory,
+        FileType::File => FuseFileType::RegularFile,
+        FileType::Link => FuseFileType::Symlink,
+    }
+}
+
+/******************************************************************************
+ * Filesystem
+ *****************************************************************************/
+struct OpenFile {
+    ino: u64,
+    file: File,
+    // written since the file header was last updated
+    dirty: bool,
+}
+
+impl OpenFile {
+    fn flush(&mut self) -> Result<(), c_int> {
+        if self.dirty {
+            self.file.flush().map_err(io_errno)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+/// An AmigaDOS volume served through FUSE. Entries are numbered after their
+/// header block, the root directory excepted, and are reached by path as the
+/// library does.
+struct AdfFilesystem {
+    disk_path: PathBuf,
+    disk: Shared<Disk>,
+    fs: AmigaDos,
+    read_only: bool,
+    uid: u32,
+    gid: u32,
+    root_block_address: u64,
+    // paths of the entries the kernel knows about, by inode
+    paths: HashMap<u64, PathBuf>,
+    // open files, by handle
+    files: HashMap<u64, OpenFile>,
+    next_handle: u64,
+}
+
+impl AdfFilesystem {
+    fn path(&self, ino: u64) -> Result<PathBuf, c_int> {
+        self.paths.get(&ino).cloned().ok_or(ENOENT)
+    }
+
+    fn child_path(&self, parent: u64, name: &OsStr) -> Result<PathBuf, c_int> {
+        let name = name.to_str().ok_or(EINVAL)?;
+
+        Ok(self.path(parent)?.join(name))
+    }
+
+    fn inode(&self, metadata: &Metadata) -> u64 {
+        match metadata.header_block_address() as u64 {
+            addr if addr == self.root_block_address => FUSE_ROOT_ID,
+            addr => addr,
+        }
+    }
+
+    fn attr(&self, metadata: &Metadata) -> FileAttr {
+        let size = metadata.size() as u64;
+        let date = metadata.alteration_date();
+
+        FileAttr {
+            ino: self.inode(metadata),
+            size,
+            blocks: size.div_ceil(BLOCK_SIZE as u64),
+            atime: date,
+            mtime: date,
+            ctime: date,
+            crtime: date,
+            kind: fuse_file_type(metadata.file_type()),
+            perm: mode_from_permissions(metadata.permissions(), metadata.is_dir()),
+            nlink: if metadata.is_dir() { 2 } else { 1 },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE as u32,
+            flags: 0,
+        }
+    }
+
+    /// Returns the attributes of an entry, soft links not being followed,
+    /// and remembers its path.
+    fn entry(&mut self, path: PathBuf) -> Result<FileAttr, c_int> {
+        let metadata = self.fs.symlink_metadata(&path).map_err(errno)?;
+        let attr = self.attr(&metadata);
+
+        self.paths.insert(attr.ino, path);
+        Ok(attr)
+    }
+
+    fn open_file(&mut self, ino: u64, file: File) -> u64 {
+        let handle = self.next_handle;
+
+        self.next_handle += 1;
+        self.files.insert(handle, OpenFile { ino, file, dirty: false });
+        handle
+    }
+
+    fn open_handle(&mut self, ino: u64, fh: Option<u64>) -> Option<&mut OpenFile> {
+        let handle = fh
+            .filter(|fh| self.files.contains_key(fh))
+            .or_else(|| {
+                self.files.iter()
+                    .find(|(_, file)| file.ino == ino && file.dirty)
+                    .map(|(handle, _)| *handle)
+            })?;
+
+        self.files.get_mut(&handle)
+    }
+
+    fn is_open(&self, path: &Path) -> Result<bool, c_int> {
+        let metadata = self.fs.symlink_metadata(path).map_err(errno)?;
+        let ino = self.inode(&metadata);
+
+        Ok(self.files.values().any(|file| file.ino == ino))
+    }
+
+    fn flush_all(&mut self) -> Result<(), c_int> {
+        self.files.values_mut().try_for_each(OpenFile::flush)
+    }
+
+    fn check_writable(&self) -> Result<(), c_int> {
+        if self.read_only {
+            Err(EROFS)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set_size(
+        &mut self,
+        ino: u64,
+        fh: Option<u64>,
+        size: u64,
+    ) -> Result<(), c_int> {
+        if let Some(file) = self.open_handle(ino, fh) {
+            file.file.set_len(size as usize).map_err(errno)?;
+            file.dirty = false;
+            return Ok(());
+        }
+
+        let path = self.path(ino)?;
+
+        File::options()
+            .write(true)
+            .open(&self.fs, path)
+            .and_then(|mut file| file.set_len(size as usize))
+            .map_err(errno)
+    }
+
+    fn do_setattr(
+        &mut self,
+        ino: u64,
+        mode: Option<u32>,
+        size: Option<u64>,
+        mtime: Option<TimeOrNow>,
+        fh: Option<u64>,
+    ) -> Result<FileAttr, c_int> {
+        let path = self.path(ino)?;
+
+        if mode.is_some() || size.is_some() || mtime.is_some() {
+            self.check_writable()?;
+        }
+
+        if let Some(size) = size {
+            self.set_size(ino, fh, size)?;
+        }
+
+        if let Some(mode) = mode {
+            let permissions = self.fs.metadata(&path).map_err(errno)?.permissions();
+
+            self.fs.set_permissions(&path, permissions_from_mode(permissions, mode))
+                .map_err(errno)?;
+        }
+
+        if let Some(mtime) = mtime {
+            let date = match mtime {
+                TimeOrNow::SpecificTime(date) => date,
+                TimeOrNow::Now => SystemTime::now(),
+            };
+
+            // pending writes would update the date again once flushed
+            for file in self.files.values_mut().filter(|file| file.ino == ino) {
+                file.flush()?;
+            }
+
+            self.fs.set_modified(&path, &date).map_err(errno)?;
+        }
+
+        self.entry(path)
+    }
+
+    fn do_open(&mut self, ino: u64, flags: i32) -> Result<u64, c_int> {
+        let path = self.path(ino)?;
+        let (read, write) = match flags & O_ACCMODE {
+            O_RDONLY => (true, false),
+            O_WRONLY => (false, true),
+            O_RDWR => (true, true),
+            _ => return Err(EINVAL),
+        };
+
+        if write {
+            self.check_writable()?;
+        }
+
+        let file = File::options()
+            .read(read)
+            .write(write)
+            .truncate(flags & O_TRUNC != 0)
+            .open(&self.fs, path)
+            .map_err(errno)?;
+
+        Ok(self.open_file(ino, file))
+    }
+
+    fn do_read(&mut self, fh: u64, offset: i64, size: u32) -> Result<Vec<u8>, c_int> {
+        let file = self.files.get_mut(&fh).ok_or(EBADF)?;
+        let mut data = Vec::with_capacity(size as usize);
+
+        file.file.seek(SeekFrom::Start(offset as u64)).map_err(errno)?;
+        (&mut file.file)
+            .take(size as u64)
+            .read_to_end(&mut data)
+            .map_err(io_errno)?;
+
+        Ok(data)
+    }
+
+    fn do_write(&mut self, fh: u64, offset: i64, data: &[u8]) -> Result<u32, c_int> {
+        let file = self.files.get_mut(&fh).ok_or(EBADF)?;
+
+        file.file.seek(SeekFrom::Start(offset as u64)).map_err(errno)?;
+        file.file.write_all(data).map_err(io_errno)?;
+        file.dirty = true;
+
+        Ok(data.len() as u32)
+    }
+
+    fn do_create(
+        &mut self,
+        parent: u64,
+        name: &OsStr,
+        flags: i32,
+    ) -> Result<(FileAttr, u64), c_int> {
+        self.check_writable()?;
+
+        let path = self.child_path(parent, name)?;
+        let file = File::options()
+            .read(flags & O_ACCMODE != O_WRONLY)
+            .write(true)
+            .create_new(true)
+            .open(&self.fs, &path)
+            .map_err(errno)?;
+        let attr = self.entry(path)?;
+
+        Ok((attr, self.open_file(attr.ino, file)))
+    }
+
+    fn do_readdir(&mut self, ino: u64) -> Result<Vec<(u64, FuseFileType, String)>, c_int> {
+        let path = self.path(ino)?;
+        let mut entries = vec![
+            (ino, FuseFileType::Directory, String::from(".")),
+            (ino, FuseFileType::Directory, String::from("..")),
+        ];
+
+        for entry in self.fs.read_dir(&path).map_err(errno)? {
+            let entry = entry.map_err(errno)?;
+            let metadata = entry.metadata();
+
+            entries.push((
+                self.inode(&metadata),
+                fuse_file_type(metadata.file_type()),
+                entry.name().into(),
+            ));
+        }
+        Ok(entries)
+    }
+
+    fn do_remove(&mut self, parent: u64, name: &OsStr, dir: bool) -> Result<(), c_int> {
+        self.check_writable()?;
+
+        let path = self.child_path(parent, name)?;
+
+        // the blocks of an open file must not be reused
+        if self.is_open(&path)? {
+            return Err(EBUSY);
+        }
+
+        if dir {
+            self.fs.remove_dir(&path).map_err(errno)
+        } else {
+            self.fs.remove_file(&path).map_err(errno)
+        }
+    }
+
+    /// Renames an entry over an existing one. The replaced entry is moved
+    /// aside first and only removed once the rename succeeded, being restored
+    /// otherwise.
+    fn replace(&mut self, from: &Path, to: &Path, target: &Metadata) -> Result<(), c_int> {
+        // the blocks of an open file must not be reused
+        if self.is_open(to)? {
+            return Err(EBUSY);
+        }
+
+        if target.is_dir() && self.fs.read_dir(to).map_err(errno)?.next().is_some() {
+            return Err(ENOTEMPTY);
+        }
+
+        let parent = to.parent().ok_or(EINVAL)?;
+        let aside = (0..64)
+            .map(|index| parent.join(format!(".rename-{}-{}", target.header_block_address(), index)))
+            .find(|path| !self.fs.exists(path).unwrap_or(true))
+            .ok_or(EIO)?;
+
+        self.fs.rename(to, &aside).map_err(errno)?;
+
+        if let Err(err) = self.fs.rename(from, to) {
+            self.fs.rename(&aside, to).map_err(errno)?;
+            return Err(errno(err));
+        }
+
+        if target.is_dir() {
+            self.fs.remove_dir(&aside).map_err(errno)
+        } else {
+            self.fs.remove_file(&aside).map_err(errno)
+        }
+    }
+
+    fn do_rename(
+        &mut self,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        flags: u32,
+    ) -> Result<(), c_int> {
+        self.check_writable()?;
+
+        if flags & RENAME_EXCHANGE != 0 {
+            return Err(EINVAL);
+        }
+
+        let from = self.child_path(parent, name)?;
+        let to = self.child_path(newparent, newname)?;
+        let source = self.fs.symlink_metadata(&from).map_err(errno)?;
+
+        // the destination is replaced, unless it is the entry itself whose
+        // name only changes case
+        match self.fs.symlink_metadata(&to) {
+            Ok(target) if target.header_block_address() != source.header_block_address() => {
+                if flags & RENAME_NOREPLACE != 0 {
+                    return Err(EEXIST);
+                }
+                if target.is_dir() != source.is_dir() {
+                    return Err(if target.is_dir() { EISDIR } else { ENOTDIR });
+                }
+
+                self.replace(&from, &to, &target)?;
+            },
+            Ok(_) | Err(Error::NotFoundError) => {
+                self.fs.rename(&from, &to).map_err(errno)?;
+            },
+            Err(err) => return Err(errno(err)),
+        }
+
+        for path in self.paths.values_mut() {
+            if let Ok(suffix) = path.strip_prefix(&from) {
+                *path = to.join(suffix);
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_image(&mut self) -> Result<()> {
+        self.flush_all().map_err(std::io::Error::from_raw_os_error)?;
+
+        if !self.read_only {
+            write_disk(&self.disk_path, &self.disk)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes an open file and writes the image, so that the data reaches
+    /// the host filesystem before the volume is unmounted.
+    fn sync_file(&mut self, file: &mut OpenFile) -> Result<(), c_int> {
+        file.flush()?;
+
+        if !self.read_only {
+            write_disk(&self.disk_path, &self.disk).map_err(|err| {
+                eprintln!("Error: {}", err);
+                EIO
+            })?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! reply_with {
+    ($reply:expr, $res:expr, |$value:pat_param| $ok:expr) => {
+        match $res {
+            Ok($value) => $ok,
+            Err(err) => $reply.error(err),
+        }
+    };
+}
+
+impl Filesystem for AdfFilesystem {
+    fn destroy(&mut self) {
+        if let Err(err) = self.flush_image() {
+            eprintln!("Error: {}", err);
+        }
+    }
+
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let res = self.child_path(parent, name).and_then(|path| self.entry(path));
+
+        reply_with!(reply, res, |attr| reply.entry(&TTL, &attr, 0));
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        let res = self.path(ino).and_then(|path| self.entry(path));
+
+        reply_with!(reply, res, |attr| reply.attr(&TTL, &attr));
+    }
+
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        let res = self.do_setattr(ino, mode, size, mtime, fh);
+
+        reply_with!(reply, res, |attr| reply.attr(&TTL, &attr));
+    }
+
+    fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+        let res = self.path(ino)
+            .and_then(|path| self.fs.read_link(path).map_err(errno))
+            .and_then(|target| target.to_str().map(String::from).ok_or(EIO));
+
+        reply_with!(reply, res, |target| reply.data(target.as_bytes()));
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let res = self.check_writable()
+            .and_then(|_| self.child_path(parent, name))
+            .and_then(|path| {
+                self.fs.create_dir(&path).map_err(errno)?;
+                self.entry(path)
+            });
+
+        reply_with!(reply, res, |attr| reply.entry(&TTL, &attr, 0));
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let res = self.do_remove(parent, name, false);
+
+        reply_with!(reply, res, |_| reply.ok());
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let res = self.do_remove(parent, name, true);
+
+        reply_with!(reply, res, |_| reply.ok());
+    }
+
+    fn symlink(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        link_name: &OsStr,
+        target: &Path,
+        reply: ReplyEntry,
+    ) {
+        let res = self.check_writable()
+            .and_then(|_| self.child_path(parent, link_name))
+            .and_then(|path| {
+                self.fs.symlink(target, &path).map_err(errno)?;
+                self.entry(path)
+            });
+
+        reply_with!(reply, res, |attr| reply.entry(&TTL, &attr, 0));
+    }
+
+    fn rename(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        let res = self.do_rename(parent, name, newparent, newname, flags);
+
+        reply_with!(reply, res, |_| reply.ok());
+    }
+
+    fn link(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        newparent: u64,
+        newname: &OsStr,
+        reply: ReplyEntry,
+    ) {
+        let res = self.check_writable()
+            .and_then(|_| Ok((self.path(ino)?, self.child_path(newparent, newname)?)))
+            .and_then(|(existing, link)| {
+                self.fs.hard_link(existing, &link).map_err(errno)?;
+                self.entry(link)
+            });
+
+        reply_with!(reply, res, |attr| reply.entry(&TTL, &attr, 0));
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        let res = self.do_open(ino, flags);
+
+        reply_with!(reply, res, |fh| reply.opened(fh, 0));
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let res = self.do_read(fh, offset, size);
+
+        reply_with!(reply, res, |data| reply.data(&data));
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        let res = self.do_write(fh, offset, data);
+
+        reply_with!(reply, res, |size| reply.written(size));
+    }
+
+    fn flush(&mut self, _req: &Request<'_>, _ino: u64, fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        let res = self.files.get_mut(&fh).ok_or(EBADF).and_then(OpenFile::flush);
+
+        reply_with!(reply, res, |_| reply.ok());
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        let res = match self.files.remove(&fh) {
+            Some(mut file) => self.sync_file(&mut file),
+            None => Err(EBADF),
+        };
+
+        reply_with!(reply, res, |_| reply.ok());
+    }
+
+    fn fsync(&mut self, _req: &Request<'_>, _ino: u64, fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        // the file is taken out of the table while the image is written
+        let res = match self.files.remove(&fh) {
+            Some(mut file) => {
+                let res = self.sync_file(&mut file);
+
+                self.files.insert(fh, file);
+                res
+            },
+            None => Err(EBADF),
+        };
+
+        reply_with!(reply, res, |_| reply.ok());
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let entries = match self.do_readdir(ino) {
+            Ok(entries) => entries,
+            Err(err) => return reply.error(err),
+        };
+
+        for (index, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(ino, index as i64 + 1, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn statfs(&mut self, _req: &Request<'_>, _ino: u64, reply: ReplyStatfs) {
+        let res = self.fs.info().map_err(errno);
+
+        reply_with!(reply, res, |info| reply.statfs(
+            info.total_block_count as u64,
+            info.free_block_count as u64,
+            info.free_block_count as u64,
+            0,
+            0,
+            BLOCK_SIZE as u32,
+            if info.long_name_mode == LongNameMode::On { 107 } else { 30 },
+            BLOCK_SIZE as u32,
+        ));
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let res = self.do_create(parent, name, flags);
+
+        reply_with!(reply, res, |(attr, fh)| reply.created(&TTL, &attr, 0, fh, 0));
+    }
+}
+
+/******************************************************************************
+ * Mount command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Path to the mount point into the host filesystem
+    mount_point: PathBuf,
+
+    /// Mount the volume read only, as are DMS, SCP and IPF images
+    #[arg(short, long)]
+    read_only: bool,
+}
+
+/******************************************************************************
+ * Mount command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let read_only = args.read_only || disk.borrow().image_format().is_read_only();
+    let fs = AmigaDos::try_from(disk.clone())?;
+    let root_block_address = fs.metadata("/")?.header_block_address() as u64;
+
+    let filesystem = AdfFilesystem {
+        disk_path: args.amiga_disk_filepath.clone(),
+        disk,
+        fs,
+        read_only,
+        // SAFETY: these calls have no preconditions and can't fail
+        uid: unsafe { libc::getuid() },
+        gid: unsafe { libc::getgid() },
+        root_block_address,
+        paths: HashMap::from([(FUSE_ROOT_ID, PathBuf::from("/"))]),
+        files: HashMap::new(),
+        next_handle: 1,
+    };
+
+    let mut options = vec![
+        MountOption::FSName(args.amiga_disk_filepath.display().to_string()),
+        MountOption::Subtype("adf".into()),
+    ];
+
+    if read_only {
+        options.push(MountOption::RO);
+    }
+
+    // blocks until the volume is unmounted, e.g. by `fusermount -u`, the
+    // image being written back then
+    fuser::mount2(filesystem, &args.mount_point, &options)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // group and other read bits
+    const GROUP_READ: u32 = 0x0000_0800;
+    const OTHER_READ: u32 = 0x0000_8000;
+
+    fn init_filesystem() -> AdfFilesystem {
+        let disk = shared(Disk::create(DiskType::DoubleDensity));
+        let fs = AmigaDosFormater::default().format(disk.clone(), "TEST").unwrap();
+        let root_block_address = fs.metadata("/").unwrap().header_block_address() as u64;
+
+        AdfFilesystem {
+            disk_path: PathBuf::new(),
+            disk,
+            fs,
+            read_only: true,
+            uid: 0,
+            gid: 0,
+            root_block_address,
+            paths: HashMap::from([(FUSE_ROOT_ID, PathBuf::from("/"))]),
+            files: HashMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    #[test]
+    fn mode_from_permissions_is_ok() {
+        let read_only = Permissions::from_bits(0x0000_0004);
+        let shared = Permissions::from_bits(GROUP_READ | OTHER_READ);
+
+        assert_eq!(mode_from_permissions(Permissions::from_bits(0), false), 0o700);
+        assert_eq!(mode_from_permissions(Permissions::from_bits(0x0000_000f), false), 0o000);
+        assert_eq!(mode_from_permissions(read_only, false), 0o500);
+        assert_eq!(mode_from_permissions(shared, false), 0o744);
+        assert_eq!(mode_from_permissions(shared, true), 0o755);
+    }
+
+    #[test]
+    fn permissions_from_mode_is_ok() {
+        // archived, not deletable
+        let permissions = Permissions::from_bits(0x0000_0011);
+
+        assert_eq!(
+            permissions_from_mode(permissions, 0o644).bits(),
+            0x0000_0013 | GROUP_READ | OTHER_READ,
+        );
+        assert_eq!(permissions_from_mode(permissions, 0o700).bits(), 0x0000_0011);
+
+        for mode in [0o000, 0o123, 0o644, 0o755, 0o777] {
+            assert_eq!(mode_from_permissions(permissions_from_mode(permissions, mode), false) as u32, mode);
+        }
+    }
+
+    #[test]
+    fn failed_rename_keeps_destination() {
+        let mut filesystem = init_filesystem();
+
+        filesystem.read_only = false;
+        filesystem.fs.create_dir_all("/d/sub/e").unwrap();
+
+        let sub = filesystem.entry("/d/sub".into()).unwrap().ino;
+
+        // a directory can't be moved into itself
+        assert_eq!(
+            filesystem.do_rename(FUSE_ROOT_ID, "d".as_ref(), sub, "e".as_ref(), 0),
+            Err(EINVAL),
+        );
+        assert!(filesystem.fs.metadata("/d/sub/e").unwrap().is_dir());
+        assert_eq!(filesystem.fs.read_dir("/d/sub").unwrap().count(), 1);
+        assert!(filesystem.fs.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rename_replaces_destination() {
+        let mut filesystem = init_filesystem();
+
+        filesystem.read_only = false;
+        filesystem.fs.write("/a", b"a").unwrap();
+        filesystem.fs.write("/b", b"b").unwrap();
+        filesystem.do_rename(FUSE_ROOT_ID, "a".as_ref(), FUSE_ROOT_ID, "b".as_ref(), 0).unwrap();
+
+        assert_eq!(filesystem.fs.read("/b").unwrap(), b"a");
+        assert_eq!(filesystem.fs.read_dir("/").unwrap().count(), 1);
+        assert!(filesystem.fs.check().unwrap().is_empty());
+    }
+}
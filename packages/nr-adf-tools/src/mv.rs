@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Move command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Path to a file or a directory into the Amiga filesystem
+    amiga_source_filepath: PathBuf,
+
+    /// Destination path into the Amiga filesystem
+    amiga_target_filepath: PathBuf,
+}
+
+/******************************************************************************
+ * Move command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let fs = AmigaDos::try_from(disk.clone())?;
+
+    fs.rename(&args.amiga_source_filepath, &args.amiga_target_filepath)?;
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}
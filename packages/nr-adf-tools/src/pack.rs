@@ -0,0 +1,302 @@
+use std::fs;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    ArgDiskType,
+    image_format_from_path,
+    read_disk,
+    write_disk,
+};
+use crate::sidecar::{
+    SIDECAR_FILE_NAME,
+    Sidecar,
+    SidecarEntry,
+};
+
+
+/******************************************************************************
+ * Pack command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to a directory into the host filesystem
+    pub host_input_dirpath: PathBuf,
+
+    /// Path to an Amiga disk file, created and formatted when it doesn't
+    /// exist
+    pub amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Specify the disk type of a created disk
+    #[arg(long, short = 'F', default_value = "dd")]
+    pub floppy_disk_type: ArgDiskType,
+
+    /// Volume name of a created disk, the name of the host directory by
+    /// default
+    #[arg(long, short = 'n')]
+    pub volume_name: Option<String>,
+
+    /// Specify the file system type of a created disk
+    #[arg(short = 't', long, default_value = "ofs")]
+    pub filesystem_type: FilesystemType,
+
+    /// Specify the DOS type of a created disk, from "DOS0" to "DOS7",
+    /// instead of the file system type
+    #[arg(short = 'd', long, conflicts_with = "filesystem_type")]
+    pub dos_type: Option<DosType>,
+
+    /// If destination files already exist, force overwriting them
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/******************************************************************************
+ * Pack command run
+ *****************************************************************************/
+struct Packer<'a> {
+    args: &'a Args,
+    fs: &'a mut AmigaDos,
+    sidecar: Sidecar,
+}
+
+impl Packer<'_> {
+    fn pack_file(
+        &mut self,
+        host_path: &Path,
+        amiga_path: &Path,
+    ) -> Result<()> {
+        if self.fs.exists(amiga_path)? && !self.args.force {
+            return Err(anyhow!("{} already exists", amiga_path.display()));
+        }
+
+        self.fs.write(amiga_path, fs::read(host_path)?)?;
+        Ok(())
+    }
+
+    fn pack_soft_links(&mut self, path: &Path) -> Result<()> {
+        let soft_links = self.sidecar.soft_links()
+            .filter(|(link, _)| Path::new(link).parent() == Some(path))
+            .map(|(link, target)| (Path::new("/").join(link), PathBuf::from(target)))
+            .collect::<Vec<_>>();
+
+        for (link, target) in soft_links {
+            if self.args.force && self.fs.read_link(&link).is_ok() {
+                self.fs.remove_file(&link)?;
+            }
+
+            self.fs.symlink(target, link)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the content of a host directory, `path` being relative to the
+    /// root of the host directory tree and of the volume.
+    fn pack_dir(&mut self, path: &Path) -> Result<()> {
+        let host_dir_path = self.args.host_input_dirpath.join(path);
+        let mut entries = fs::read_dir(&host_dir_path)?.collect::<Result<Vec<_>, _>>()?;
+
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry.file_name()
+                .into_string()
+                .map_err(|name| anyhow!("invalid file name {}", name.to_string_lossy()))?;
+
+            // the sidecar file at the root is read as the metadata of the
+            // tree, unpack refusing entries which would collide with it
+            if path.as_os_str().is_empty() && name == SIDECAR_FILE_NAME {
+                continue;
+            }
+
+            let path = path.join(&name);
+            let key = path.to_str().ok_or(Error::InvalidPathError)?;
+            let amiga_path = Path::new("/").join(&path);
+            let metadata = fs::symlink_metadata(entry.path())?;
+
+            // host soft links are not followed, they could loop
+            if metadata.is_symlink() {
+                println!("Skipping symbolic link {}", entry.path().display());
+                continue;
+            }
+
+            if metadata.is_dir() {
+                self.fs.create_dir_all(&amiga_path)?;
+                self.pack_dir(&path)?;
+            } else {
+                self.pack_file(&entry.path(), &amiga_path)?;
+            }
+
+            if let Some(SidecarEntry::Entry { permissions, comment }) = self.sidecar.get(key) {
+                let (permissions, comment) = (*permissions, comment.clone());
+
+                self.fs.set_permissions(&amiga_path, permissions)?;
+                self.fs.set_comment(&amiga_path, comment.as_deref())?;
+            }
+
+            // the date of a directory is set once its content is written
+            self.fs.set_modified(&amiga_path, &metadata.modified()?)?;
+        }
+
+        self.pack_soft_links(path)
+    }
+}
+
+fn create_disk(args: &Args) -> Result<Shared<Disk>> {
+    let disk = shared(Disk::create(args.floppy_disk_type.into()));
+    let volume_name = match &args.volume_name {
+        Some(volume_name) => volume_name.clone(),
+        None => args.host_input_dirpath
+            .canonicalize()?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(String::from)
+            .ok_or(anyhow!("Invalid volume name"))?,
+    };
+
+    disk.borrow_mut().set_image_format(image_format_from_path(&args.amiga_disk_filepath));
+
+    let mut formater = AmigaDosFormater::default();
+
+    if let Some(dos_type) = args.dos_type {
+        formater.with_dos_type(dos_type);
+    } else {
+        formater.with_filesystem_type(args.filesystem_type);
+    }
+
+    formater.format(disk.clone(), &volume_name)?;
+
+    Ok(disk)
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    if !args.host_input_dirpath.is_dir() {
+        return Err(anyhow!("{} is not a directory", args.host_input_dirpath.display()));
+    }
+
+    let disk = if args.amiga_disk_filepath.exists() {
+        read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?
+    } else {
+        create_disk(args)?
+    };
+    let mut fs = AmigaDos::try_from(disk.clone())?;
+
+    let mut packer = Packer {
+        args,
+        fs: &mut fs,
+        sidecar: Sidecar::read(&args.host_input_dirpath)?,
+    };
+
+    packer.pack_dir(Path::new(""))?;
+
+    fs.set_modified("/", &fs::metadata(&args.host_input_dirpath)?.modified()?)?;
+
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unpack;
+
+    use super::*;
+
+    #[test]
+    fn pack_unpack_round_trip_is_ok() {
+        let temp = tempfile::tempdir().unwrap();
+        let input_path = temp.path().join("input");
+        let output_path = temp.path().join("output");
+        let image_path = temp.path().join("disk.adf");
+
+        fs::create_dir_all(input_path.join("dir/sub")).unwrap();
+        fs::write(input_path.join("file"), b"file content").unwrap();
+        fs::write(input_path.join("dir/sub/other"), vec![42; 3000]).unwrap();
+        fs::write(input_path.join(SIDECAR_FILE_NAME), concat!(
+            "----rwed--------\tdir\t\n",
+            "----r-e---------\tfile\ta\\\\comment\n",
+            "link\tlink\tdir/sub\n",
+        )).unwrap();
+
+        let args = Args {
+            host_input_dirpath: input_path.clone(),
+            amiga_disk_filepath: image_path.clone(),
+            partition: None,
+            floppy_disk_type: ArgDiskType::DD,
+            volume_name: Some("TEST".into()),
+            filesystem_type: FilesystemType::FFS,
+            dos_type: None,
+            force: false,
+        };
+
+        run(&args).unwrap();
+
+        let fs = AmigaDos::try_from(read_disk(&image_path, None).unwrap()).unwrap();
+
+        assert!(fs.check().unwrap().is_empty());
+        assert_eq!(fs.read("file").unwrap(), b"file content");
+        assert_eq!(fs.read_link("link").unwrap(), Path::new("dir/sub"));
+        assert_eq!(fs.metadata("file").unwrap().comment(), Some("a\\comment"));
+
+        unpack::run(&unpack::Args {
+            amiga_disk_filepath: image_path,
+            partition: None,
+            host_output_dirpath: output_path.clone(),
+            force: false,
+        }).unwrap();
+
+        assert_eq!(fs::read(output_path.join("file")).unwrap(), b"file content");
+        assert_eq!(fs::read(output_path.join("dir/sub/other")).unwrap(), vec![42; 3000]);
+        assert_eq!(
+            fs::read_to_string(output_path.join(SIDECAR_FILE_NAME)).unwrap(),
+            concat!(
+                "----rwed--------\tdir\t\n",
+                "----rwed--------\tdir/sub\t\n",
+                "----rwed--------\tdir/sub/other\t\n",
+                "----r-e---------\tfile\ta\\\\comment\n",
+                "link\tlink\tdir/sub\n",
+            ),
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn host_soft_links_are_not_followed() {
+        let temp = tempfile::tempdir().unwrap();
+        let input_path = temp.path().join("input");
+        let image_path = temp.path().join("disk.adf");
+
+        fs::create_dir_all(input_path.join("dir")).unwrap();
+        std::os::unix::fs::symlink("..", input_path.join("dir/loop")).unwrap();
+
+        let args = Args {
+            host_input_dirpath: input_path,
+            amiga_disk_filepath: image_path.clone(),
+            partition: None,
+            floppy_disk_type: ArgDiskType::DD,
+            volume_name: Some("TEST".into()),
+            filesystem_type: FilesystemType::OFS,
+            dos_type: None,
+            force: false,
+        };
+
+        run(&args).unwrap();
+
+        let fs = AmigaDos::try_from(read_disk(&image_path, None).unwrap()).unwrap();
+
+        assert!(fs.exists("dir").unwrap());
+        assert!(!fs.exists("dir/loop").unwrap());
+    }
+}
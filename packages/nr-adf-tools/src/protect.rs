@@ -0,0 +1,116 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Protect command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Path to a file or a directory into the Amiga filesystem
+    amiga_input_file: PathBuf,
+
+    /// Protection flags among "hsparwed", prefixed by '+' to add them or by
+    /// '-' to remove them, otherwise they replace the current ones
+    #[arg(allow_hyphen_values = true, default_value = "")]
+    flags: String,
+
+    /// Add the given flags to the current ones
+    #[arg(long, conflicts_with = "sub")]
+    add: bool,
+
+    /// Remove the given flags from the current ones
+    #[arg(long)]
+    sub: bool,
+
+    /// Change the protection of all the files and directories into the given
+    /// directory, recursively
+    #[arg(long, visible_alias = "recursive")]
+    all: bool,
+}
+
+/******************************************************************************
+ * Protect command run
+ *****************************************************************************/
+fn parse_protection_change(args: &Args) -> Result<ProtectionChange> {
+    let change = args.flags.parse::<ProtectionChange>()?;
+
+    Ok(match change {
+        ProtectionChange::Set(flags) if args.add => ProtectionChange::Add(flags),
+        ProtectionChange::Set(flags) if args.sub => ProtectionChange::Remove(flags),
+        change => change,
+    })
+}
+
+fn protect(
+    fs: &mut AmigaDos,
+    path: &Path,
+    change: ProtectionChange,
+) -> Result<()> {
+    let permissions = fs.metadata(path)?.permissions();
+
+    fs.set_permissions(path, change.apply(permissions))?;
+    Ok(())
+}
+
+fn protect_all(
+    fs: &mut AmigaDos,
+    path: &Path,
+    change: ProtectionChange,
+) -> Result<()> {
+    let entries = fs.read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+
+    for entry in entries {
+        let entry_path = entry.path();
+        let metadata = entry.metadata();
+
+        // soft links have no protection of their own, and the content of a
+        // hard linked directory is reached through its real entry
+        if metadata.is_symlink() {
+            continue;
+        }
+
+        protect(fs, entry_path, change)?;
+
+        if metadata.is_dir() && !metadata.is_hard_link() {
+            protect_all(fs, entry_path, change)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
+
+    let change = parse_protection_change(args)?;
+    let metadata = fs.metadata(&args.amiga_input_file)?;
+
+    if args.all && metadata.is_dir() {
+        protect_all(&mut fs, &args.amiga_input_file, change)?;
+    } else {
+        protect(&mut fs, &args.amiga_input_file, change)?;
+    }
+
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}
@@ -1,8 +1,6 @@
-use std::cell::RefCell;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
-use std::rc::Rc;
 
 use anyhow::{
     anyhow,
@@ -13,6 +11,8 @@ use promptly::prompt_default;
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::read_disk;
+
 
 fn get_output_filepath(args: &Args) -> Result<PathBuf> {
     if let Some(host_output_filepath) = &args.host_output_filepath {
@@ -56,6 +56,10 @@ pub struct Args {
     /// Path to an Amiga disk file
     pub amiga_disk_filepath: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Path to a file into the Amiga filesystem
     pub amiga_input_filepath: PathBuf,
 
@@ -68,10 +72,8 @@ pub struct Args {
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk_data = fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-
-    let fs: AmigaDos = Rc::new(RefCell::new(disk)).try_into()?;
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let fs = AmigaDos::try_from(disk)?;
     let data = fs.read(&args.amiga_input_filepath)?;
 
     if let Some(mut output) = get_output_file(args)? {
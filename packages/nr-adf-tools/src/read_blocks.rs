@@ -0,0 +1,52 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    print_hex_dump,
+    read_disk,
+};
+
+
+/******************************************************************************
+ * Read blocks command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file, DOS or not
+    amiga_disk_filepath: PathBuf,
+
+    /// Address of the first block to read
+    block: LBAAddress,
+
+    /// Number of blocks to read
+    #[arg(short, long, default_value_t = 1)]
+    count: usize,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    partition: Option<String>,
+
+    /// Save the raw blocks to a host file instead of printing them
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/******************************************************************************
+ * Read blocks command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let data = disk.borrow().read_blocks_at(args.block, args.count)?;
+
+    if let Some(path) = args.output.as_ref() {
+        fs::write(path, data)?;
+    } else {
+        print_hex_dump(&data, args.block*BLOCK_SIZE);
+    }
+
+    Ok(())
+}
@@ -1,10 +1,7 @@
-use std::cell::RefCell;
-use std::fs;
 use std::path::{
     Path,
     PathBuf,
 };
-use std::rc::Rc;
 
 use anyhow::{anyhow, Result};
 
@@ -12,6 +9,11 @@ use promptly::prompt_default;
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
 
 /******************************************************************************
  * Remove command options
@@ -21,6 +23,10 @@ pub struct Args {
     /// Path to an Amiga disk file
     amiga_disk_filepath: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Path to a file or a directory into the Amiga filesystem
     amiga_input_files: Vec<PathBuf>,
 
@@ -69,6 +75,14 @@ fn remove_dir(
     fs: &mut AmigaDos,
     path: &Path,
 ) -> Result<()> {
+    // a hard link is removed without its directory content
+    if fs.symlink_metadata(path)?.is_hard_link() {
+        if confirm_remove(args, path)? {
+            fs.remove_dir(path)?;
+        }
+        return Ok(());
+    }
+
     if confirm_examine(args, path)? {
         let entries = fs.read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
 
@@ -79,12 +93,9 @@ fn remove_dir(
                 FileType::Dir => {
                     remove_dir(args, fs, entry_path)?;
                 },
-                FileType::File => {
+                FileType::File | FileType::Link => {
                     remove_file(args, fs, entry_path)?;
                 },
-                _ => {
-                    return Err(anyhow!("Unsupported file type!"));
-                }
             }
         }
 
@@ -107,18 +118,17 @@ fn remove_file(
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk_data = fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-    let mut fs = AmigaDos::try_from(Rc::new(RefCell::new(disk)))?;
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut fs = AmigaDos::try_from(disk.clone())?;
 
     for input_filepath in args.amiga_input_files.iter() {
-        let metadata = fs.metadata(input_filepath)?;
+        let metadata = fs.symlink_metadata(input_filepath)?;
 
         match metadata.file_type() {
             FileType::Dir if args.recursive => {
                 remove_dir(args, &mut fs, input_filepath)?;
             },
-            FileType::File => {
+            FileType::File | FileType::Link => {
                 remove_file(args, &mut fs, input_filepath)?;
             },
             FileType::Dir => {
@@ -127,16 +137,10 @@ pub fn run(args: &Args) -> Result<()> {
                     input_filepath.to_str().ok_or(Error::InvalidPathError)?,
                 ));
             },
-            FileType::Link => {
-                return Err(anyhow!(
-                    "'{}' is a link",
-                    input_filepath.to_str().ok_or(Error::InvalidPathError)?,
-                ));
-            }
         }
     }
 
-    fs.dump(&args.amiga_disk_filepath)?;
+    write_disk(&args.amiga_disk_filepath, &disk)?;
 
     Ok(())
 }
@@ -0,0 +1,73 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Scan command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Paths to Amiga disk files
+    #[arg(required = true)]
+    amiga_disk_filepaths: Vec<PathBuf>,
+
+    /// Replace the boot code of infected disks by the standard one
+    #[arg(long)]
+    clean: bool,
+}
+
+/******************************************************************************
+ * Scan command run
+ *****************************************************************************/
+fn scan(args: &Args, path: &Path) -> Result<bool> {
+    // the boot block is read straight from the disk, so that damaged or
+    // non-DOS disks can be scanned as well
+    let disk = read_disk(path, None)?;
+    let scan = scan_disk_boot_code(&disk.borrow())?;
+
+    println!("{}: {}", path.display(), scan);
+
+    if args.clean && clean_disk_boot_code(disk.clone())? {
+        write_disk(path, &disk)?;
+        println!("{}: cleaned", path.display());
+        return Ok(false);
+    }
+
+    Ok(scan.is_infected())
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let mut infected = 0;
+    let mut failed = 0;
+
+    for path in args.amiga_disk_filepaths.iter() {
+        match scan(args, path) {
+            Ok(true) => infected += 1,
+            Ok(false) => (),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed += 1;
+            },
+        }
+    }
+
+    if infected > 0 || failed > 0 {
+        Err(anyhow!("{} infected disk(s), {} disk(s) not scanned", infected, failed))
+    } else {
+        Ok(())
+    }
+}
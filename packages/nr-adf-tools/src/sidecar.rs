@@ -0,0 +1,222 @@
+//! Sidecar file keeping the Amiga metadata a host directory tree can't
+//! represent: protection bits, comments and soft links.
+//!
+//! The file is written at the root of the host directory, one line per
+//! entry, fields being separated by tabs:
+//! - `<PERMISSIONS>\t<PATH>\t<COMMENT>` for files and directories, the
+//!   permissions being given as listed, e.g. `----rwed--------`,
+//! - `link\t<PATH>\t<TARGET>` for soft links.
+//!
+//! Paths are relative to the root of the volume. Backslashes, tabs and line
+//! breaks are escaped.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::prelude::*;
+
+
+pub const SIDECAR_FILE_NAME: &str = ".adf-metadata";
+
+const SOFT_LINK_TAG: &str = "link";
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            _ => return Err(anyhow!("invalid escape sequence in \"{}\"", s)),
+        }
+    }
+    Ok(unescaped)
+}
+
+/******************************************************************************
+ * Sidecar entries
+ *****************************************************************************/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SidecarEntry {
+    /// Protection bits and comment of a file or a directory.
+    Entry {
+        permissions: Permissions,
+        comment: Option<String>,
+    },
+    /// Target of a soft link, which has no host counterpart.
+    SoftLink {
+        target: String,
+    },
+}
+
+impl SidecarEntry {
+    fn to_line(&self, path: &str) -> String {
+        match self {
+            Self::Entry { permissions, comment } => format!("{}\t{}\t{}",
+                permissions,
+                escape(path),
+                escape(comment.as_deref().unwrap_or_default()),
+            ),
+            Self::SoftLink { target } => format!("{}\t{}\t{}",
+                SOFT_LINK_TAG,
+                escape(path),
+                escape(target),
+            ),
+        }
+    }
+
+    fn from_line(line: &str) -> Result<(String, Self)> {
+        let fields = line.split('\t').collect::<Vec<_>>();
+
+        let [kind, path, value] = fields.as_slice() else {
+            return Err(anyhow!("invalid metadata line \"{}\"", line));
+        };
+
+        let path = unescape(path)?;
+        let value = unescape(value)?;
+
+        let entry = if *kind == SOFT_LINK_TAG {
+            Self::SoftLink { target: value }
+        } else {
+            Self::Entry {
+                permissions: kind.parse()?,
+                comment: Some(value).filter(|comment| !comment.is_empty()),
+            }
+        };
+
+        Ok((path, entry))
+    }
+}
+
+/******************************************************************************
+ * Sidecar file
+ *****************************************************************************/
+/// The Amiga metadata of the entries of a host directory tree, by path.
+#[derive(Clone, Debug, Default)]
+pub struct Sidecar {
+    entries: BTreeMap<String, SidecarEntry>,
+}
+
+impl Sidecar {
+    /// Reads the sidecar file of a host directory, an empty sidecar being
+    /// returned when there is none.
+    pub fn read(dir: &Path) -> Result<Self> {
+        let content = match fs::read_to_string(dir.join(SIDECAR_FILE_NAME)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let entries = content.lines()
+            .filter(|line| !line.is_empty())
+            .map(SidecarEntry::from_line)
+            .collect::<Result<_>>()
+            .map_err(|err| anyhow!("{}: {}", dir.join(SIDECAR_FILE_NAME).display(), err))?;
+
+        Ok(Self { entries })
+    }
+
+    /// Writes the sidecar file of a host directory.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let content = self.entries.iter()
+            .map(|(path, entry)| entry.to_line(path) + "\n")
+            .collect::<String>();
+
+        fs::write(dir.join(SIDECAR_FILE_NAME), content)?;
+        Ok(())
+    }
+
+    pub fn get(&self, path: &str) -> Option<&SidecarEntry> {
+        self.entries.get(path)
+    }
+
+    pub fn insert(&mut self, path: &str, entry: SidecarEntry) {
+        self.entries.insert(path.into(), entry);
+    }
+
+    /// Returns the soft links, by path.
+    pub fn soft_links(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().filter_map(|(path, entry)| match entry {
+            SidecarEntry::SoftLink { target } => Some((path.as_str(), target.as_str())),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_is_ok() {
+        assert_eq!(escape("plain name"), "plain name");
+        assert_eq!(escape("a\\b\tc\nd\re"), "a\\\\b\\tc\\nd\\re");
+    }
+
+    #[test]
+    fn unescape_is_ok() {
+        for s in ["", "plain name", "a\\b\tc\nd\re", "\\t", "trailing\\"] {
+            assert_eq!(unescape(&escape(s)).unwrap(), s);
+        }
+
+        assert!(unescape("trailing\\").is_err());
+        assert!(unescape("unknown\\x").is_err());
+    }
+
+    #[test]
+    fn sidecar_lines_are_ok() {
+        let entries = [
+            ("dir/file", SidecarEntry::Entry {
+                permissions: "----rwed--------".parse().unwrap(),
+                comment: Some("with\ttab".into()),
+            }),
+            ("dir\nname", SidecarEntry::Entry {
+                permissions: "-s--r-e---------".parse().unwrap(),
+                comment: None,
+            }),
+            ("link", SidecarEntry::SoftLink {
+                target: "dir/file".into(),
+            }),
+        ];
+
+        for (path, entry) in entries {
+            let line = entry.to_line(path);
+
+            assert!(!line.contains('\n'));
+            assert_eq!(SidecarEntry::from_line(&line).unwrap(), (path.into(), entry));
+        }
+
+        assert!(SidecarEntry::from_line("----rwed--------\tpath").is_err());
+        assert!(SidecarEntry::from_line("bad\tpath\t").is_err());
+    }
+}
@@ -1,11 +1,9 @@
-use std::cell::RefCell;
 use std::fs;
 // use std::io::{
 //     Write,
 //     stdout,
 // };
 use std::path::PathBuf;
-use std::rc::Rc;
 use std::time::SystemTime;
 
 use anyhow::Result;
@@ -13,6 +11,11 @@ use chrono::DateTime;
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
 fn parse_time_value(args: &Args) -> Result<SystemTime> {
     if let Some(s) = &args.date_time {
         let dt = DateTime::parse_from_rfc3339(s)?;
@@ -30,6 +33,10 @@ pub struct Args {
     /// Path to an Amiga disk file
     amiga_disk_filepath: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Path to a file into the Amiga filesystem
     amiga_input_filepath: PathBuf,
 
@@ -43,10 +50,8 @@ pub struct Args {
 }
 
 pub fn run(args: &Args) -> Result<()> {
-    let disk_data = fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-
-    let fs = AmigaDos::try_from(Rc::new(RefCell::new(disk)))?;
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let fs = AmigaDos::try_from(disk.clone())?;
 
     let time = parse_time_value(args)?;
 
@@ -61,7 +66,7 @@ pub fn run(args: &Args) -> Result<()> {
         .open(&fs, &args.amiga_input_filepath)?
         .set_time(&time)?;
 
-    fs.dump(&args.amiga_disk_filepath)?;
+    write_disk(&args.amiga_disk_filepath, &disk)?;
 
     Ok(())
 }
@@ -0,0 +1,275 @@
+use std::fs;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use std::time::SystemTime;
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::read_disk;
+use crate::sidecar::{
+    SIDECAR_FILE_NAME,
+    Sidecar,
+    SidecarEntry,
+};
+
+
+/******************************************************************************
+ * Unpack command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file
+    pub amiga_disk_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
+    /// Path to a directory into the host filesystem, created if needed
+    pub host_output_dirpath: PathBuf,
+
+    /// If output files already exist, force overwriting them
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/******************************************************************************
+ * Unpack command run
+ *****************************************************************************/
+struct Unpacker<'a> {
+    args: &'a Args,
+    fs: &'a AmigaDos,
+    sidecar: Sidecar,
+}
+
+fn set_host_modified(path: &Path, time: SystemTime) -> Result<()> {
+    let file = if path.is_dir() {
+        fs::File::open(path)?
+    } else {
+        fs::File::options().write(true).open(path)?
+    };
+
+    file.set_modified(time)?;
+    Ok(())
+}
+
+/// Checks that the name of an entry read from the image is a plain file
+/// name, which can't lead out of the output directory once joined to it.
+fn check_entry_name(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(()),
+        _ => Err(anyhow!("invalid entry name \"{}\"", name.escape_default())),
+    }
+}
+
+impl Unpacker<'_> {
+    fn unpack_file(
+        &self,
+        amiga_path: &Path,
+        host_path: &Path,
+    ) -> Result<()> {
+        if host_path.exists() && !self.args.force {
+            return Err(anyhow!("{} already exists", host_path.display()));
+        }
+
+        fs::write(host_path, self.fs.read(amiga_path)?)?;
+        Ok(())
+    }
+
+    /// Extracts the content of an Amiga directory, `path` being relative to
+    /// the root of the volume.
+    fn unpack_dir(&mut self, path: &Path) -> Result<()> {
+        let amiga_dir_path = Path::new("/").join(path);
+        let entries = self.fs.read_dir(&amiga_dir_path)?.collect::<Result<Vec<_>, _>>()?;
+
+        for entry in entries {
+            check_entry_name(entry.name())?;
+
+            // the sidecar file is written at the root of the output
+            // directory, it would be overwritten by such an entry
+            if path.as_os_str().is_empty()
+            && entry.name().eq_ignore_ascii_case(SIDECAR_FILE_NAME) {
+                return Err(anyhow!("{} is reserved for the metadata file", entry.name()));
+            }
+
+            let path = path.join(entry.name());
+            let key = path.to_str().ok_or(Error::InvalidPathError)?;
+            let amiga_path = amiga_dir_path.join(entry.name());
+            let host_path = self.args.host_output_dirpath.join(&path);
+            let metadata = entry.metadata();
+
+            // soft links are only kept in the sidecar, their target being
+            // meaningless to the host
+            if metadata.is_symlink() {
+                let target = self.fs.read_link(&amiga_path)?;
+
+                self.sidecar.insert(key, SidecarEntry::SoftLink {
+                    target: target.to_string_lossy().into(),
+                });
+                continue;
+            }
+
+            // the content of a hard linked directory is reached through its
+            // real entry
+            if metadata.is_dir() && metadata.is_hard_link() {
+                println!("Skipping hard linked directory {}", key);
+                continue;
+            }
+
+            self.sidecar.insert(key, SidecarEntry::Entry {
+                permissions: metadata.permissions(),
+                comment: metadata.comment().map(String::from),
+            });
+
+            if metadata.is_dir() {
+                fs::create_dir_all(&host_path)?;
+                self.unpack_dir(&path)?;
+            } else {
+                self.unpack_file(&amiga_path, &host_path)?;
+            }
+
+            set_host_modified(&host_path, metadata.alteration_date())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let fs = AmigaDos::try_from(disk)?;
+
+    fs::create_dir_all(&args.host_output_dirpath)?;
+
+    let mut unpacker = Unpacker {
+        args,
+        fs: &fs,
+        sidecar: Sidecar::default(),
+    };
+
+    unpacker.unpack_dir(Path::new(""))?;
+    unpacker.sidecar.write(&args.host_output_dirpath)?;
+
+    set_host_modified(
+        &args.host_output_dirpath,
+        fs.metadata("/")?.alteration_date(),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use nr_adf_lib::disk::BLOCK_SIZE;
+
+    use super::*;
+
+    // offset of the name of an entry into its header block
+    const NAME_OFFSET: usize = BLOCK_SIZE - 80;
+    const CHECKSUM_OFFSET: usize = 20;
+
+    /// Renames an entry by writing its header block directly, as a crafted
+    /// image would do.
+    fn patch_entry_name(disk: &Shared<Disk>, addr: LBAAddress, name: &str) {
+        let mut disk = disk.borrow_mut();
+        let block = disk.block_mut(addr).unwrap();
+
+        block[NAME_OFFSET] = name.len() as u8;
+        block[NAME_OFFSET + 1..NAME_OFFSET + 1 + name.len()].copy_from_slice(name.as_bytes());
+        block[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4].fill(0);
+
+        let sum = block.chunks(4)
+            .map(|long| u32::from_be_bytes(long.try_into().unwrap()))
+            .fold(0u32, |sum, long| sum.wrapping_add(long));
+
+        block[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4]
+            .copy_from_slice(&sum.wrapping_neg().to_be_bytes());
+    }
+
+    #[test]
+    fn entry_names_are_checked() {
+        assert!(check_entry_name("file").is_ok());
+        assert!(check_entry_name("file.info").is_ok());
+        assert!(check_entry_name("..file").is_ok());
+
+        for name in ["", ".", "..", "a/b", "/", "a\\b", "..\\b"] {
+            assert!(check_entry_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn unpack_does_not_escape_output_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let disk = shared(Disk::create(DiskType::DoubleDensity));
+        let mut fs = AmigaDosFormater::default().format(disk.clone(), "TEST").unwrap();
+
+        fs.create_dir("up").unwrap();
+        fs.write("up/pwned.txt", b"pwned").unwrap();
+
+        let addr = fs.metadata("up").unwrap().header_block_address();
+
+        patch_entry_name(&disk, addr, "..");
+
+        let image_path = temp.path().join("disk.adf");
+        let output_path = temp.path().join("out");
+
+        std::fs::write(&image_path, disk.borrow().encode().unwrap()).unwrap();
+
+        let args = Args {
+            amiga_disk_filepath: image_path,
+            partition: None,
+            host_output_dirpath: output_path.clone(),
+            force: false,
+        };
+
+        assert!(run(&args).is_err());
+        assert!(!temp.path().join("pwned.txt").exists());
+        assert!(!output_path.join("pwned.txt").exists());
+    }
+
+    #[test]
+    fn unpack_refuses_sidecar_file_name() {
+        let temp = tempfile::tempdir().unwrap();
+        let disk = shared(Disk::create(DiskType::DoubleDensity));
+        let mut fs = AmigaDosFormater::default().format(disk.clone(), "TEST").unwrap();
+
+        fs.write(SIDECAR_FILE_NAME, b"file content").unwrap();
+        // only the root of the volume is reserved
+        fs.create_dir("dir").unwrap();
+        fs.write(Path::new("dir").join(SIDECAR_FILE_NAME), b"file content").unwrap();
+
+        let image_path = temp.path().join("disk.adf");
+        let output_path = temp.path().join("out");
+
+        std::fs::write(&image_path, disk.borrow().encode().unwrap()).unwrap();
+
+        let mut args = Args {
+            amiga_disk_filepath: image_path,
+            partition: None,
+            host_output_dirpath: output_path.clone(),
+            force: false,
+        };
+
+        assert!(run(&args).is_err());
+        assert!(!output_path.join(SIDECAR_FILE_NAME).exists());
+
+        fs.remove_file(SIDECAR_FILE_NAME).unwrap();
+        std::fs::write(&args.amiga_disk_filepath, disk.borrow().encode().unwrap()).unwrap();
+        args.force = true;
+
+        run(&args).unwrap();
+        assert_eq!(
+            std::fs::read(output_path.join("dir").join(SIDECAR_FILE_NAME)).unwrap(),
+            b"file content",
+        );
+    }
+}
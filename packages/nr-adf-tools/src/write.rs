@@ -1,6 +1,4 @@
-use std::cell::RefCell;
 use std::path::PathBuf;
-use std::rc::Rc;
 
 use anyhow::{
     anyhow,
@@ -11,6 +9,11 @@ use promptly::prompt_default;
 
 use nr_adf_lib::prelude::*;
 
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
 
 fn confirm_overwrite(args: &Args) -> Result<bool> {
     if args.force {
@@ -31,6 +34,10 @@ pub struct Args {
     /// Path to an Amiga disk file
     amiga_disk_filepath: PathBuf,
 
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    pub partition: Option<String>,
+
     /// Path to a file into the host filesystem
     host_input_filepath: PathBuf,
 
@@ -44,15 +51,14 @@ pub struct Args {
 
 pub fn run(args: &Args) -> Result<()> {
     let data = std::fs::read(&args.host_input_filepath)?;
-    let disk_data = std::fs::read(&args.amiga_disk_filepath)?;
-    let disk = Disk::try_create_with_data(disk_data)?;
-    let fs = AmigaDos::try_from(Rc::new(RefCell::new(disk)))?;
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let fs = AmigaDos::try_from(disk.clone())?;
 
     if !fs.exists(&args.amiga_output_filepath)? || confirm_overwrite(args)? {
         fs.write(&args.amiga_output_filepath, data)?;
     }
 
-    fs.dump(&args.amiga_disk_filepath)?;
+    write_disk(&args.amiga_disk_filepath, &disk)?;
 
     Ok(())
 }
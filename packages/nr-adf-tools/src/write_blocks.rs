@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use nr_adf_lib::prelude::*;
+
+use crate::cli_common::{
+    read_disk,
+    write_disk,
+};
+
+
+/******************************************************************************
+ * Write blocks command options
+ *****************************************************************************/
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path to an Amiga disk file, DOS or not
+    amiga_disk_filepath: PathBuf,
+
+    /// Address of the first block to write
+    block: LBAAddress,
+
+    /// Path to the host file to write, its last block being padded with
+    /// zeros
+    host_input_filepath: PathBuf,
+
+    /// Select a partition of a hard disk image, by name or index
+    #[arg(long)]
+    partition: Option<String>,
+}
+
+/******************************************************************************
+ * Write blocks command run
+ *****************************************************************************/
+pub fn run(args: &Args) -> Result<()> {
+    let disk = read_disk(&args.amiga_disk_filepath, args.partition.as_deref())?;
+    let mut data = fs::read(&args.host_input_filepath)?;
+
+    data.resize(data.len().next_multiple_of(BLOCK_SIZE), 0);
+    disk.borrow_mut().write_blocks_at(args.block, &data)?;
+
+    println!("{} block(s) written", data.len()/BLOCK_SIZE);
+    write_disk(&args.amiga_disk_filepath, &disk)?;
+
+    Ok(())
+}